@@ -0,0 +1,26 @@
+//! Feed arbitrary bytes to the IPC frame decoder, as a malicious or buggy
+//! peer might send them.
+//!
+//! Run with `cargo fuzz run decode_frame` from `crates/hotkey-manager`.
+
+#![no_main]
+
+use hotkey_manager::codec;
+use hotkey_manager::{IPCRequest, IPCResponse};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Decode frames back to back, as the server reads them
+    let mut rest = data;
+    while let Ok(Some((request, used))) = codec::decode_frame::<IPCRequest>(rest) {
+        // Anything that decodes must encode to a frame that decodes again
+        let frame = codec::encode(&request).expect("decoded requests encode");
+        let (again, _) = codec::decode_frame::<IPCRequest>(&frame)
+            .expect("encoded requests decode")
+            .expect("encoded frames are complete");
+        assert_eq!(again, request);
+        rest = &rest[used..];
+    }
+
+    let _ = codec::decode_frame::<IPCResponse>(data);
+});
@@ -0,0 +1,159 @@
+//! Tracking the frontmost application.
+//!
+//! The server observes NSWorkspace's notifications of applications being
+//! activated, and sends clients an `AppChanged` event whenever the frontmost
+//! application changes. On platforms other than macOS no application is
+//! ever reported.
+
+use std::cell::RefCell;
+
+/// An application that has come to the front
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct App {
+    /// Bundle identifier, such as `com.apple.Safari`. Empty for processes
+    /// without a bundle.
+    pub bundle_id: String,
+    /// Localized display name
+    pub name: String,
+}
+
+/// Detects changes to the frontmost application
+#[derive(Debug, Default)]
+pub(crate) struct AppWatcher {
+    current: Option<App>,
+}
+
+impl AppWatcher {
+    /// Check the frontmost application, returning it if it has changed
+    /// since the last check
+    fn check(&mut self) -> Option<App> {
+        self.update(frontmost())
+    }
+
+    fn update(&mut self, app: Option<App>) -> Option<App> {
+        // Keep the last known application while none is frontmost, such as
+        // while switching spaces
+        if app.is_none() || app == self.current {
+            return None;
+        }
+        self.current = app.clone();
+        app
+    }
+}
+
+/// Reports changes to the frontmost application until dropped
+pub(crate) struct AppObserver {
+    /// Identifies the observer to the notification center
+    #[cfg(target_os = "macos")]
+    token:
+        objc2::rc::Retained<objc2::runtime::ProtocolObject<dyn objc2::runtime::NSObjectProtocol>>,
+}
+
+impl AppObserver {
+    /// Call `on_change` with the frontmost application now, and again
+    /// whenever it changes.
+    ///
+    /// This must be called on the main thread, which runs the event loop,
+    /// and `on_change` is called on it too.
+    pub(crate) fn new(on_change: impl Fn(App) + 'static) -> Self {
+        let watcher = RefCell::new(AppWatcher::default());
+        let check = move || {
+            if let Some(app) = watcher.borrow_mut().check() {
+                on_change(app);
+            }
+        };
+        check();
+        Self::observe(check)
+    }
+
+    /// Call `check` whenever an application is activated
+    #[cfg(target_os = "macos")]
+    fn observe(check: impl Fn() + 'static) -> Self {
+        use block2::RcBlock;
+        use objc2_app_kit::{NSWorkspace, NSWorkspaceDidActivateApplicationNotification};
+        use objc2_foundation::NSNotification;
+        use std::ptr::NonNull;
+
+        let block = RcBlock::new(move |_: NonNull<NSNotification>| check());
+        // SAFETY: the notification name is a constant. Without a queue, the
+        // block runs on the thread posting the notification, which for
+        // NSWorkspace is the main thread.
+        let token = unsafe {
+            NSWorkspace::sharedWorkspace()
+                .notificationCenter()
+                .addObserverForName_object_queue_usingBlock(
+                    Some(NSWorkspaceDidActivateApplicationNotification),
+                    None,
+                    None,
+                    &block,
+                )
+        };
+        Self { token }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn observe(_check: impl Fn() + 'static) -> Self {
+        Self {}
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl Drop for AppObserver {
+    fn drop(&mut self) {
+        use objc2::runtime::AnyObject;
+        use objc2_app_kit::NSWorkspace;
+
+        let observer: &AnyObject = (*self.token).as_ref();
+        // SAFETY: the observer was added to this notification center
+        unsafe {
+            NSWorkspace::sharedWorkspace()
+                .notificationCenter()
+                .removeObserver(observer);
+        }
+    }
+}
+
+/// The frontmost application, according to NSWorkspace
+#[cfg(target_os = "macos")]
+fn frontmost() -> Option<App> {
+    use objc2::rc::autoreleasepool;
+    use objc2_app_kit::NSWorkspace;
+
+    autoreleasepool(|_| {
+        // SAFETY: these are read-only queries, made from the main thread that
+        // runs the event loop
+        let app = unsafe { NSWorkspace::sharedWorkspace().frontmostApplication() }?;
+        let (bundle_id, name) = unsafe { (app.bundleIdentifier(), app.localizedName()) };
+        Some(App {
+            bundle_id: bundle_id.map(|s| s.to_string()).unwrap_or_default(),
+            name: name.map(|s| s.to_string()).unwrap_or_default(),
+        })
+    })
+}
+
+#[cfg(not(target_os = "macos"))]
+fn frontmost() -> Option<App> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(name: &str) -> Option<App> {
+        Some(App {
+            bundle_id: format!("com.example.{name}"),
+            name: name.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_update() {
+        let mut watcher = AppWatcher::default();
+        assert_eq!(watcher.update(app("a")), app("a"));
+        assert_eq!(watcher.update(app("a")), None);
+        assert_eq!(watcher.update(None), None);
+        assert_eq!(watcher.update(app("a")), None);
+        assert_eq!(watcher.update(app("b")), app("b"));
+    }
+}
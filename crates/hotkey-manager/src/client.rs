@@ -1,8 +1,19 @@
 use crate::ipc::{IPCClient, IPCConnection};
-use crate::process::ProcessConfig;
-use crate::{Error, Result, ServerProcess, DEFAULT_SOCKET_PATH};
+use crate::pidfile::read_pid;
+use crate::process::{is_process_alive, ProcessConfig, DEFAULT_STARTUP_TIMEOUT};
+use crate::retry::{Backoff, RetryPolicy};
+use crate::socket;
+use crate::watchdog::{self, StateHook};
+use crate::{
+    default_socket_path, ConnectionHealth, ConnectionState, DisconnectPolicy, Error, Result,
+    ServerHealth, ServerProcess, SOCKET_ARG, SOCKET_ENV,
+};
 use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tokio::time::{sleep, timeout};
 use tracing::{debug, error, info, warn};
 
@@ -11,13 +22,46 @@ use tracing::{debug, error, info, warn};
 /// The client will attempt to connect to an existing server at the configured socket path.
 /// If no server is running and auto-spawn is configured, it will spawn a new server process.
 ///
+/// `Client` is its own builder: configure it with the `with_*` methods, then
+/// [`connect()`](Self::connect) returns the connected client.
+///
+/// ```no_run
+/// # async fn example() -> hotkey_manager::Result<()> {
+/// use hotkey_manager::{Client, RetryPolicy};
+/// use std::time::Duration;
+///
+/// let mut client = Client::new_with_socket("/tmp/my-app.sock")
+///     .with_auto_spawn_server()
+///     .with_retry_policy(RetryPolicy::fixed(Duration::from_millis(100), 10))
+///     .connect()
+///     .await?;
+/// client.connection()?.ping().await?;
+/// # Ok(())
+/// # }
+/// ```
+///
 /// # Server Spawning
 ///
 /// By default, the client will only connect to existing servers. To enable automatic
 /// server spawning, use one of these methods:
 ///
 /// - [`with_auto_spawn_server()`](Self::with_auto_spawn_server) - Uses the current executable with `--server` flag
+/// - [`with_auto_spawn_server_args()`](Self::with_auto_spawn_server_args) - Uses the current executable with custom arguments
 /// - [`with_server_command()`](Self::with_server_command) - Uses a custom command
+///
+/// A spawned server is told the client's socket path through the
+/// [`SOCKET_ENV`](crate::SOCKET_ENV) environment variable, which
+/// [`Server::new`](crate::Server::new) reads. Servers spawned with
+/// [`with_auto_spawn_server()`](Self::with_auto_spawn_server) also get it as a
+/// [`SOCKET_ARG`](crate::SOCKET_ARG) argument; use
+/// [`with_server_socket_arg()`](Self::with_server_socket_arg) to pass it to
+/// other commands.
+///
+/// The client knows a spawned server is ready once it passes a health check,
+/// or, for servers spawned with
+/// [`with_auto_spawn_server()`](Self::with_auto_spawn_server) or
+/// [`with_server_ready_handshake()`](Self::with_server_ready_handshake), once
+/// it creates the file named by [`READY_ENV`](crate::READY_ENV).
 pub struct Client {
     /// Socket path for IPC communication
     socket_path: String,
@@ -27,14 +71,36 @@ pub struct Client {
     server_startup_timeout: Duration,
     /// How long to wait for initial connection
     connection_timeout: Duration,
-    /// Number of connection attempts before giving up
-    max_connection_attempts: u32,
-    /// Delay between connection attempts
-    connection_retry_delay: Duration,
+    /// When to retry failed connection attempts
+    retry: Arc<dyn Backoff>,
+    /// How often to check the health of a spawned server, if at all
+    server_health_interval: Option<Duration>,
+    /// How long a spawned server has to exit after SIGTERM, if set
+    server_stop_timeout: Option<Duration>,
+    /// Flag to pass the socket path to a spawned server with, if set
+    server_socket_arg: Option<String>,
+    /// Whether a spawned server creates a ready file
+    server_ready_handshake: bool,
+    /// Whether to talk to a spawned server over a socket pair, rather than
+    /// a socket path
+    server_socketpair: bool,
     /// The spawned server process (if any)
     server: Option<ServerProcess>,
     /// The active IPC connection (if connected)
     connection: Option<IPCConnection>,
+    /// How often the watchdog pings the server, if at all
+    watchdog_interval: Option<Duration>,
+    /// Called when the connection state changes
+    state_hook: Option<StateHook>,
+    /// The last known health of the connection
+    health: Arc<Mutex<ConnectionHealth>>,
+    /// The running watchdog task, if any
+    watchdog: Option<JoinHandle<()>>,
+    /// The policy to ask the server for on connecting, if any
+    disconnect_policy: Option<DisconnectPolicy>,
+    /// Token of the session to resume on connecting, then of the current
+    /// session
+    session: Option<String>,
 }
 
 impl Default for Client {
@@ -45,31 +111,38 @@ impl Default for Client {
 
 impl Client {
     /// Create a new managed client with default configuration
+    ///
+    /// The client connects to the path in the [`SOCKET_ENV`] environment
+    /// variable if it is set, and to the
+    /// [user's socket](crate::user_socket_path) otherwise.
     pub fn new() -> Self {
         Self {
-            socket_path: DEFAULT_SOCKET_PATH.to_string(),
+            socket_path: default_socket_path(),
             server_config: None,
-            server_startup_timeout: Duration::from_millis(1000),
+            server_startup_timeout: DEFAULT_STARTUP_TIMEOUT,
             connection_timeout: Duration::from_secs(5),
-            max_connection_attempts: 5,
-            connection_retry_delay: Duration::from_millis(200),
+            retry: Arc::new(RetryPolicy::default()),
+            server_health_interval: None,
+            server_stop_timeout: None,
+            server_socket_arg: None,
+            server_ready_handshake: false,
+            server_socketpair: false,
             server: None,
             connection: None,
+            watchdog_interval: None,
+            state_hook: None,
+            health: Arc::new(Mutex::new(ConnectionHealth::new(
+                ConnectionState::Disconnected,
+            ))),
+            watchdog: None,
+            disconnect_policy: None,
+            session: None,
         }
     }
 
     /// Create a new managed client with the given socket path
     pub fn new_with_socket(socket_path: impl Into<String>) -> Self {
-        Self {
-            socket_path: socket_path.into(),
-            server_config: None,
-            server_startup_timeout: Duration::from_millis(1000),
-            connection_timeout: Duration::from_secs(5),
-            max_connection_attempts: 5,
-            connection_retry_delay: Duration::from_millis(200),
-            server: None,
-            connection: None,
-        }
+        Self::new().with_socket_path(socket_path)
     }
 
     /// Set the socket path
@@ -84,11 +157,30 @@ impl Client {
     /// This is equivalent to calling `with_server_command(current_exe, ["--server"])`.
     pub fn with_auto_spawn_server(mut self) -> Self {
         if let Ok(current_exe) = std::env::current_exe() {
-            self.server_config = Some(ProcessConfig::new(current_exe));
+            let mut config = ProcessConfig::new(current_exe);
+            config.socket_arg = Some(SOCKET_ARG.to_string());
+            config.ready_handshake = true;
+            self.server_config = Some(config);
         }
         self
     }
 
+    /// Enable automatic server spawning using the current executable with
+    /// custom arguments, for programs whose server mode isn't `--server`.
+    pub fn with_auto_spawn_server_args<I, A>(self, args: I) -> Self
+    where
+        I: IntoIterator<Item = A>,
+        A: AsRef<str>,
+    {
+        match std::env::current_exe() {
+            Ok(current_exe) => self.with_server_command(current_exe, args),
+            Err(e) => {
+                warn!("Can't find the current executable to spawn: {}", e);
+                self
+            }
+        }
+    }
+
     /// Set a custom server command for automatic spawning.
     ///
     /// Use this when you want to spawn a server with a specific command
@@ -122,26 +214,167 @@ impl Client {
         self
     }
 
-    /// Set the maximum number of connection attempts
-    pub fn with_max_connection_attempts(mut self, attempts: u32) -> Self {
-        self.max_connection_attempts = attempts;
+    /// Set when to retry failed connection attempts. The default is five
+    /// attempts, 200ms apart.
+    pub fn with_retry_policy(mut self, policy: impl Backoff + 'static) -> Self {
+        self.retry = Arc::new(policy);
+        self
+    }
+
+    /// Pass the socket path to a spawned server as an argument, following
+    /// `flag`, as well as in the environment.
+    pub fn with_server_socket_arg(mut self, flag: impl Into<String>) -> Self {
+        self.server_socket_arg = Some(flag.into());
+        self
+    }
+
+    /// Wait for a spawned server to create the file named by the
+    /// [`READY_ENV`](crate::READY_ENV) environment variable, rather than
+    /// polling its socket. Servers built on [`Server::new`](crate::Server::new)
+    /// do this.
+    pub fn with_server_ready_handshake(mut self) -> Self {
+        self.server_ready_handshake = true;
         self
     }
 
-    /// Set the delay between connection retry attempts
-    pub fn with_connection_retry_delay(mut self, delay: Duration) -> Self {
-        self.connection_retry_delay = delay;
+    /// Talk to a spawned server over a socket pair that it inherits, rather
+    /// than over a socket path, so that no path is involved at all. Servers
+    /// built on [`Server::new`](crate::Server::new) serve the socket named
+    /// by [`FD_ENV`](crate::FD_ENV).
+    ///
+    /// The client always spawns a server of its own, and is its only
+    /// client. The server exits when the client disconnects, and the
+    /// [watchdog](Self::with_watchdog) and server health checks, which
+    /// need a path to connect to, don't run.
+    pub fn with_server_socketpair(mut self) -> Self {
+        self.server_socketpair = true;
         self
     }
 
+    /// Periodically check the health of a spawned server, so that a wedged
+    /// server can be told apart from one that has exited. See
+    /// [`server_health`](Self::server_health).
+    pub fn with_server_health_interval(mut self, interval: Duration) -> Self {
+        self.server_health_interval = Some(interval);
+        self
+    }
+
+    /// Set how long a spawned server has to exit after being asked to stop,
+    /// before it is killed
+    pub fn with_server_stop_timeout(mut self, timeout: Duration) -> Self {
+        self.server_stop_timeout = Some(timeout);
+        self
+    }
+
+    /// Ping the server at an interval once connected, to track the health
+    /// of the connection. See [`health`](Self::health).
+    ///
+    /// The pings use a connection of their own.
+    pub fn with_watchdog(mut self, interval: Duration) -> Self {
+        self.watchdog_interval = Some(interval);
+        self
+    }
+
+    /// Call a function whenever the state of the connection changes.
+    ///
+    /// The function is called from an async task, so it should return
+    /// quickly. Without a [watchdog](Self::with_watchdog), it is only called
+    /// on connecting and disconnecting.
+    pub fn on_state_change<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(ConnectionState) + Send + Sync + 'static,
+    {
+        self.state_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Ask the server to apply a [`DisconnectPolicy`] on connecting, such as
+    /// keeping the bindings so that a restarted client can pick them up.
+    ///
+    /// Without one, the server's own policy applies.
+    pub fn with_disconnect_policy(mut self, policy: DisconnectPolicy) -> Self {
+        self.disconnect_policy = Some(policy);
+        self
+    }
+
+    /// Resume a session on connecting, using the token from a previous
+    /// client's [`session`](Self::session).
+    ///
+    /// The server keeps a session while its [`DisconnectPolicy`] keeps the
+    /// bindings, and delivers the events that arrived in the meantime. If
+    /// the session can't be resumed, the client starts a new one.
+    pub fn with_session(mut self, token: impl Into<String>) -> Self {
+        self.session = Some(token.into());
+        self
+    }
+
+    /// Get the token of the current session, for resuming it after a
+    /// restart with [`with_session`](Self::with_session).
+    ///
+    /// Only clients with a [disconnect policy](Self::with_disconnect_policy)
+    /// have a session.
+    pub fn session(&self) -> Option<&str> {
+        self.session.as_deref()
+    }
+
     /// Connect to the server, optionally spawning it first
-    pub async fn connect(mut self) -> Result<Self> {
+    pub async fn connect(self) -> Result<Self> {
+        let mut client = self.establish().await?;
+        if let Some(token) = client.session.take() {
+            match client.connection()?.resume(&token, None).await {
+                Ok(()) => {
+                    info!("Resumed session");
+                    client.session = Some(token);
+                }
+                Err(e) => warn!("Failed to resume session, starting a new one: {}", e),
+            }
+        }
+        if let Some(policy) = client.disconnect_policy {
+            client.session = Some(client.connection()?.set_disconnect_policy(policy).await?);
+        }
+        watchdog::update(
+            &client.health,
+            client.state_hook.as_ref(),
+            ConnectionHealth::new(ConnectionState::Connected),
+        );
+        if let (Some(interval), None, false) = (
+            client.watchdog_interval,
+            &client.watchdog,
+            client.server_socketpair,
+        ) {
+            client.watchdog = Some(tokio::spawn(watchdog::run(
+                client.socket_path.clone(),
+                interval,
+                client.health.clone(),
+                client.state_hook.clone(),
+            )));
+        }
+        Ok(client)
+    }
+
+    /// Get the last known health of the connection.
+    ///
+    /// Without a [watchdog](Self::with_watchdog), this only reflects whether
+    /// the client has connected.
+    pub fn health(&self) -> ConnectionHealth {
+        *self.health.lock().expect("health mutex poisoned")
+    }
+
+    /// Connect to an existing server, or spawn one and connect to it
+    async fn establish(mut self) -> Result<Self> {
         // Check if we're already connected
         if self.connection.is_some() {
             debug!("Already connected to server");
             return Ok(self);
         }
 
+        // A server on a socket pair is always our own
+        if self.server_socketpair {
+            if let Some(config) = self.spawn_config() {
+                return self.spawn_with_socketpair(config).await;
+            }
+        }
+
         // Try to connect to existing server first
         info!(
             "Attempting to connect to existing server at {}",
@@ -158,68 +391,40 @@ impl Client {
             }
         }
 
+        // A server started by an older release listens on the legacy socket
+        if let Some(legacy) = socket::legacy_fallback(&self.socket_path) {
+            match self.try_connect_to(legacy).await {
+                Ok(connection) => {
+                    info!("Connected to existing server at legacy socket {}", legacy);
+                    self.socket_path = legacy.to_string();
+                    self.connection = Some(connection);
+                    return Ok(self);
+                }
+                Err(e) => {
+                    debug!("Failed to connect to server at legacy socket: {}", e);
+                }
+            }
+        }
+
         // If we have server config, spawn the server
-        if let Some(server_config) = &self.server_config {
+        if let Some(config) = self.spawn_config() {
             info!("No existing server found, spawning new server");
 
-            let mut server = ServerProcess::new(server_config.clone());
+            let mut server = ServerProcess::new(config);
             server.start().await?;
 
-            // Try to connect with retries, polling for server readiness
-            debug!(
-                "Polling for server readiness (timeout: {:?})",
-                self.server_startup_timeout
-            );
-
-            let start_time = tokio::time::Instant::now();
-            let mut poll_interval = Duration::from_millis(10); // Start with fast polling
-            let connection = loop {
-                match self.try_connect().await {
-                    Ok(conn) => {
-                        let elapsed = start_time.elapsed();
-                        info!("Successfully connected to spawned server in {:?}", elapsed);
-                        break Some(conn);
-                    }
-                    Err(_) => {
-                        // Check if we've exceeded the startup timeout
-                        if start_time.elapsed() >= self.server_startup_timeout {
-                            debug!("Server startup timeout reached, trying with retries");
-                            break None;
-                        }
-                        // Server might not be ready yet, wait a bit and try again
-                        sleep(poll_interval).await;
-
-                        // Gradually increase polling interval to reduce CPU usage
-                        // but keep it reasonably fast for quick startup
-                        if poll_interval < Duration::from_millis(100) {
-                            poll_interval = poll_interval.saturating_add(Duration::from_millis(10));
-                        }
-                    }
-                }
-            };
-
-            match connection {
-                Some(conn) => {
+            match self.try_connect_with_retries().await {
+                Ok(conn) => {
+                    info!("Successfully connected to spawned server");
                     self.connection = Some(conn);
                     self.server = Some(server);
                     Ok(self)
                 }
-                None => {
-                    // If we couldn't connect during startup timeout, try with normal retries
-                    match self.try_connect_with_retries().await {
-                        Ok(conn) => {
-                            info!("Successfully connected to spawned server");
-                            self.connection = Some(conn);
-                            self.server = Some(server);
-                            Ok(self)
-                        }
-                        Err(e) => {
-                            error!("Failed to connect to spawned server: {}", e);
-                            // Stop the server since we can't connect
-                            server.stop().await?;
-                            Err(e)
-                        }
-                    }
+                Err(e) => {
+                    error!("Failed to connect to spawned server: {}", e);
+                    // Stop the server since we can't connect
+                    server.close().await?;
+                    Err(e)
                 }
             }
         } else {
@@ -230,46 +435,97 @@ impl Client {
         }
     }
 
+    /// The configuration to spawn a server with, if the client spawns one
+    fn spawn_config(&self) -> Option<ProcessConfig> {
+        let mut config = self.server_config.clone()?;
+        config.socket_path = PathBuf::from(&self.socket_path);
+        config
+            .env
+            .push((SOCKET_ENV.to_string(), self.socket_path.clone()));
+        if let Some(flag) = &self.server_socket_arg {
+            config.socket_arg = Some(flag.clone());
+        }
+        config.ready_handshake |= self.server_ready_handshake;
+        config.health_interval = self.server_health_interval;
+        if let Some(timeout) = self.server_stop_timeout {
+            config.stop_timeout = timeout;
+        }
+        config.startup_timeout = self.server_startup_timeout;
+        Some(config)
+    }
+
+    /// Spawn a server that inherits one end of a socket pair, and connect
+    /// to it over the other
+    async fn spawn_with_socketpair(mut self, config: ProcessConfig) -> Result<Self> {
+        info!("Spawning server on a socket pair");
+        let (ours, theirs) = std::os::unix::net::UnixStream::pair()?;
+        let mut server = ServerProcess::new(config.with_inherited_fd(theirs.into()));
+        server.start().await?;
+
+        ours.set_nonblocking(true)?;
+        let connection = IPCConnection::new(tokio::net::UnixStream::from_std(ours)?);
+        // The server answers once it is ready
+        let ping = match timeout(self.server_startup_timeout, connection.ping()).await {
+            Ok(result) => result,
+            Err(_) => Err(Error::Timeout {
+                operation: format!(
+                    "waiting {:?} for the server to become ready",
+                    self.server_startup_timeout
+                ),
+            }),
+        };
+        if let Err(e) = ping {
+            error!("Spawned server didn't answer on the socket pair: {}", e);
+            server.close().await?;
+            return Err(e);
+        }
+        info!("Connected to spawned server over a socket pair");
+        self.connection = Some(connection);
+        self.server = Some(server);
+        Ok(self)
+    }
+
     /// Try to connect to the server once
     async fn try_connect(&self) -> Result<IPCConnection> {
-        let client = IPCClient::new(&self.socket_path);
+        self.try_connect_to(&self.socket_path).await
+    }
+
+    /// Try to connect to a server on a socket path once
+    async fn try_connect_to(&self, socket_path: &str) -> Result<IPCConnection> {
+        let client = IPCClient::new(socket_path);
 
         match timeout(self.connection_timeout, client.connect()).await {
             Ok(Ok(connection)) => Ok(connection),
             Ok(Err(e)) => Err(e),
-            Err(_) => Err(Error::Ipc(format!(
-                "Connection timeout after {:?}",
-                self.connection_timeout
-            ))),
+            Err(_) => Err(Error::Timeout {
+                operation: format!("connecting after {:?}", self.connection_timeout),
+            }),
         }
     }
 
-    /// Try to connect with retries
+    /// Try to connect, retrying according to the retry policy
     async fn try_connect_with_retries(&self) -> Result<IPCConnection> {
-        let mut last_error = None;
-
-        for attempt in 1..=self.max_connection_attempts {
-            debug!(
-                "Connection attempt {}/{}",
-                attempt, self.max_connection_attempts
-            );
-
-            match self.try_connect().await {
+        let start = tokio::time::Instant::now();
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            debug!("Connection attempt {}", attempts);
+            let error = match self.try_connect().await {
                 Ok(connection) => return Ok(connection),
-                Err(e) => {
-                    warn!("Connection attempt {} failed: {}", attempt, e);
-                    last_error = Some(e);
-
-                    if attempt < self.max_connection_attempts {
-                        sleep(self.connection_retry_delay).await;
-                    }
+                Err(e) if !e.is_transient() => return Err(e),
+                Err(e) => e,
+            };
+            match self.retry.next_delay(attempts, start.elapsed()) {
+                Some(delay) => {
+                    warn!("Connection attempt {} failed: {}", attempts, error);
+                    sleep(delay).await;
+                }
+                None => {
+                    warn!("Giving up after {} connection attempts", attempts);
+                    return Err(error);
                 }
             }
         }
-
-        Err(last_error.unwrap_or_else(|| {
-            Error::Ipc("Failed to connect after all retry attempts".to_string())
-        }))
     }
 
     /// Get a reference to the connection
@@ -285,22 +541,51 @@ impl Client {
     }
 
     /// Disconnect from the server and optionally stop it
+    ///
+    /// This is safe to call more than once: later calls only do whatever
+    /// teardown is left. Both steps are attempted even if the first fails,
+    /// and the first error is returned.
     pub async fn disconnect(&mut self, stop_server: bool) -> Result<()> {
-        // Shutdown the connection
-        if let Some(mut connection) = self.connection.take() {
-            info!("Shutting down connection");
-            connection.shutdown().await?;
+        if let Some(watchdog) = self.watchdog.take() {
+            watchdog.abort();
         }
+        watchdog::update(
+            &self.health,
+            self.state_hook.as_ref(),
+            ConnectionHealth::new(ConnectionState::Disconnected),
+        );
+
+        // Shutdown the connection
+        let disconnected = match self.connection.take() {
+            Some(mut connection) => {
+                info!("Shutting down connection");
+                connection.shutdown().await
+            }
+            None => Ok(()),
+        };
 
         // Stop the server if requested and we spawned it
-        if stop_server {
-            if let Some(mut server) = self.server.take() {
+        let stopped = match self.server.take() {
+            Some(server) if stop_server => {
                 info!("Stopping managed server");
-                server.stop().await?;
+                server.close().await
             }
-        }
+            server => {
+                self.server = server;
+                Ok(())
+            }
+        };
 
-        Ok(())
+        disconnected.and(stopped)
+    }
+
+    /// Disconnect from the server and optionally stop it, consuming the
+    /// client.
+    ///
+    /// Prefer this to dropping a connected client, which can't shut the
+    /// connection down gracefully.
+    pub async fn close(mut self, stop_server: bool) -> Result<()> {
+        self.disconnect(stop_server).await
     }
 
     /// Get the PID of the spawned server process, if any.
@@ -310,19 +595,80 @@ impl Client {
     pub fn server_pid(&self) -> Option<u32> {
         self.server.as_ref().and_then(|s| s.pid())
     }
+
+    /// Get the health of the spawned server process, if any.
+    ///
+    /// Returns `None` if no server was spawned.
+    pub fn server_health(&self) -> Option<ServerHealth> {
+        self.server.as_ref().map(|s| s.health())
+    }
+
+    /// Get a channel that receives the exit status of the spawned server
+    /// process when it exits, so that its loss can be handled at once
+    /// rather than on the next failed request.
+    ///
+    /// Returns `None` if no server was spawned.
+    pub fn server_exit_events(&self) -> Option<mpsc::UnboundedReceiver<ExitStatus>> {
+        self.server.as_ref().map(|s| s.exit_events())
+    }
+
+    /// Check that a server is responding on the configured socket path, and
+    /// get the version it reports.
+    ///
+    /// This uses a connection of its own that only pings, so it doesn't
+    /// become the server's primary client, and neither connects nor spawns a
+    /// server for this client. Each step is limited by the connection
+    /// timeout.
+    pub async fn probe(&self) -> Result<Option<String>> {
+        let timed_out = |_| Error::Timeout {
+            operation: "waiting for the server".to_string(),
+        };
+        let connection = timeout(
+            self.connection_timeout,
+            IPCClient::new(&self.socket_path).connect(),
+        )
+        .await
+        .map_err(timed_out)??;
+        timeout(self.connection_timeout, connection.server_version())
+            .await
+            .map_err(timed_out)?
+    }
+
+    /// Discover the PID of a server running on the configured socket path.
+    ///
+    /// This reads the PID file written by a server started with
+    /// [`Server::with_pid_file`](crate::Server::with_pid_file), and does not
+    /// require a live connection. Returns `None` if there is no PID file, or if
+    /// the recorded process is no longer running.
+    pub fn discover(&self) -> Option<u32> {
+        let pid = read_pid(&self.socket_path)?;
+        if is_process_alive(pid) {
+            Some(pid)
+        } else {
+            debug!(
+                "Stale PID file for {}: process {} not running",
+                self.socket_path, pid
+            );
+            None
+        }
+    }
 }
 
 impl Drop for Client {
     fn drop(&mut self) {
+        if let Some(watchdog) = self.watchdog.take() {
+            watchdog.abort();
+        }
+
         // Clean disconnect on drop
         if self.is_connected() {
-            warn!("ManagedClient dropped while still connected");
+            warn!("Client dropped while still connected, use close() to shut down gracefully");
             // Can't do async in drop, so connection will close when dropped
         }
 
         // ServerProcess has its own drop implementation
         if self.server.is_some() {
-            warn!("ManagedClient dropped with running server");
+            warn!("Client dropped with running server, use close(true) to stop it gracefully");
         }
     }
 }
@@ -334,21 +680,64 @@ mod tests {
     #[test]
     fn test_client_builder() {
         let client = Client::new_with_socket("/test/socket.sock")
-            .with_max_connection_attempts(10)
+            .with_retry_policy(RetryPolicy::fixed(Duration::from_millis(500), 10))
             .with_server_startup_timeout(Duration::from_secs(2))
             .with_connection_timeout(Duration::from_secs(10))
-            .with_connection_retry_delay(Duration::from_millis(500));
+            .with_disconnect_policy(DisconnectPolicy::KeepForever)
+            .with_session("0123456789abcdef");
 
         assert_eq!(client.socket_path, "/test/socket.sock");
-        assert_eq!(client.max_connection_attempts, 10);
+        assert_eq!(
+            client.retry.next_delay(9, Duration::ZERO),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(client.retry.next_delay(10, Duration::ZERO), None);
         assert_eq!(client.server_startup_timeout, Duration::from_secs(2));
         assert_eq!(client.connection_timeout, Duration::from_secs(10));
-        assert_eq!(client.connection_retry_delay, Duration::from_millis(500));
+        assert_eq!(
+            client.disconnect_policy,
+            Some(DisconnectPolicy::KeepForever)
+        );
+        assert_eq!(client.session(), Some("0123456789abcdef"));
+    }
+
+    #[test]
+    fn test_auto_spawn_server_args() {
+        let client = Client::new().with_auto_spawn_server_args(["serve", "--quiet"]);
+        let config = client.server_config.as_ref().unwrap();
+        assert_eq!(config.executable, std::env::current_exe().unwrap());
+        assert_eq!(config.args, vec!["serve", "--quiet"]);
+        assert_eq!(config.socket_arg, None);
+
+        let client = Client::new().with_auto_spawn_server();
+        let config = client.server_config.as_ref().unwrap();
+        assert_eq!(config.socket_arg.as_deref(), Some(SOCKET_ARG));
+        assert!(config.ready_handshake);
     }
 
     #[test]
     fn test_client_default_socket_path() {
         let client = Client::new();
-        assert_eq!(client.socket_path, DEFAULT_SOCKET_PATH);
+        assert_eq!(client.socket_path, crate::user_socket_path());
+    }
+
+    #[tokio::test]
+    async fn test_probe_without_server() {
+        let client = Client::new_with_socket("/nonexistent/socket.sock");
+        assert!(client.probe().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_is_idempotent() {
+        let mut client = Client::new_with_socket("/nonexistent/socket.sock");
+        client.disconnect(true).await.unwrap();
+        client.disconnect(true).await.unwrap();
+        client.close(true).await.unwrap();
+    }
+
+    #[test]
+    fn test_client_discover_without_pid_file() {
+        let client = Client::new_with_socket("/nonexistent/socket.sock");
+        assert_eq!(client.discover(), None);
     }
 }
@@ -0,0 +1,152 @@
+//! Framing for the IPC protocol.
+//!
+//! Every message is JSON, prefixed with its length as a 4-byte big-endian
+//! integer. [`MessageCodec`] implements the framing for `tokio_util`'s
+//! `Framed` transports, which the server and client read and write through.
+//! The functions here only deal in bytes, so they can be tested and fuzzed
+//! without sockets.
+
+use crate::{Error, Result};
+use bytes::{Bytes, BytesMut};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+use tokio_util::codec::{Decoder, Encoder, LengthDelimitedCodec};
+
+/// Length of the header before each message
+pub const HEADER_LEN: usize = 4;
+
+/// Largest message either side accepts
+///
+/// Bounds the memory a peer can make the other allocate with a single
+/// header.
+pub const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// Frames incoming messages of type `D` and outgoing messages of type `E`
+///
+/// Decoding yields a result for each frame, so that a message that isn't
+/// valid JSON fails on its own, while the stream carries on with the next
+/// frame. Framing errors, such as a frame over [`MAX_FRAME_LEN`], end the
+/// stream, since there's no telling where the next frame starts.
+pub struct MessageCodec<D, E> {
+    frames: LengthDelimitedCodec,
+    messages: PhantomData<fn(E) -> D>,
+}
+
+impl<D, E> MessageCodec<D, E> {
+    /// Create a codec
+    pub fn new() -> Self {
+        Self {
+            frames: LengthDelimitedCodec::builder()
+                .length_field_length(HEADER_LEN)
+                .max_frame_length(MAX_FRAME_LEN)
+                .new_codec(),
+            messages: PhantomData,
+        }
+    }
+}
+
+impl<D, E> Default for MessageCodec<D, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D: DeserializeOwned, E> Decoder for MessageCodec<D, E> {
+    type Item = Result<D>;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Result<D>>> {
+        let frame = self.frames.decode(src).map_err(framing)?;
+        Ok(frame.map(|data| decode(&data)))
+    }
+}
+
+impl<D, E: Serialize> Encoder<&E> for MessageCodec<D, E> {
+    type Error = Error;
+
+    fn encode(&mut self, message: &E, dst: &mut BytesMut) -> Result<()> {
+        let data = serde_json::to_vec(message)?;
+        self.frames.encode(Bytes::from(data), dst).map_err(framing)
+    }
+}
+
+/// Map a framing failure, which is a protocol error unless the connection
+/// failed
+fn framing(err: std::io::Error) -> Error {
+    match err.kind() {
+        std::io::ErrorKind::InvalidData | std::io::ErrorKind::InvalidInput => {
+            Error::ProtocolError(err.to_string())
+        }
+        _ => Error::connection(err),
+    }
+}
+
+/// Encode a message as a frame, header included
+pub fn encode<T: Serialize>(message: &T) -> Result<Vec<u8>> {
+    let mut frame = BytesMut::new();
+    MessageCodec::<(), T>::new().encode(message, &mut frame)?;
+    Ok(frame.to_vec())
+}
+
+/// Decode the message in a frame, without its header
+pub fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T> {
+    Ok(serde_json::from_slice(data)?)
+}
+
+/// Decode the first frame in a buffer
+///
+/// Returns the message and the number of bytes it took up, or `None` if the
+/// buffer doesn't hold a whole frame yet.
+pub fn decode_frame<T: DeserializeOwned>(buf: &[u8]) -> Result<Option<(T, usize)>> {
+    let mut src = BytesMut::from(buf);
+    match MessageCodec::<T, ()>::new().decode(&mut src)? {
+        Some(message) => Ok(Some((message?, buf.len() - src.len()))),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::{IPCRequest, IPCResponse};
+    use crate::Key;
+
+    #[test]
+    fn test_round_trip() {
+        let request = IPCRequest::Rebind {
+            keys: vec![Key::parse("cmd+a").unwrap()],
+        };
+        let mut buf = encode(&request).unwrap();
+        let first = buf.len();
+        buf.extend(encode(&IPCRequest::Ping).unwrap());
+
+        let (decoded, used) = decode_frame::<IPCRequest>(&buf).unwrap().unwrap();
+        assert_eq!(decoded, request);
+        assert_eq!(used, first);
+        let (decoded, _) = decode_frame::<IPCRequest>(&buf[used..]).unwrap().unwrap();
+        assert_eq!(decoded, IPCRequest::Ping);
+
+        // Partial frames wait for more data
+        for end in 0..first {
+            assert!(decode_frame::<IPCRequest>(&buf[..end]).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn test_malformed() {
+        let oversized = ((MAX_FRAME_LEN + 1) as u32).to_be_bytes();
+        assert!(matches!(
+            decode_frame::<IPCRequest>(&oversized),
+            Err(Error::ProtocolError(_))
+        ));
+        assert!(encode(&"x".repeat(MAX_FRAME_LEN)).is_err());
+
+        let mut garbage = 3u32.to_be_bytes().to_vec();
+        garbage.extend_from_slice(b"{]}");
+        assert!(matches!(
+            decode_frame::<IPCResponse>(&garbage),
+            Err(Error::Serialization(_))
+        ));
+    }
+}
@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use thiserror::Error;
 
 /// The main error type for hotkey-manager operations
@@ -7,14 +8,73 @@ pub enum Error {
     #[error("Invalid key: {0}")]
     InvalidKey(String),
 
-    /// Error registering or managing hotkeys
+    /// Error managing hotkeys, or using the API in a way it doesn't allow
     #[error("Hotkey error: {0}")]
     HotkeyOperation(String),
 
-    /// Error in IPC communication
+    /// Another application or the system has already registered the key
+    #[error("{} is taken by {}", .0, .0.system_owner().unwrap_or("another application"))]
+    KeyInUseBySystem(crate::Key),
+
+    /// The system refused to register a key for another reason
+    #[error("Failed to register {key}: {source}")]
+    RegistrationFailed {
+        /// The key, in canonical form
+        key: crate::Key,
+        /// The failure reported by the system
+        #[source]
+        source: global_hotkey::Error,
+    },
+
+    /// The same key appears more than once in a batch of bindings
+    #[error("Key {key} is bound more than once, by {}", .identifiers.join(", "))]
+    DuplicateKey {
+        /// The key, in canonical form
+        key: crate::Key,
+        /// Identifiers of every binding for the key, in batch order
+        identifiers: Vec<String>,
+    },
+
+    /// A request failed on the server, which explained why, or the client
+    /// isn't connected
     #[error("IPC error: {0}")]
     Ipc(String),
 
+    /// No server is listening on the socket
+    #[error("No server is listening on {}: {source}", .path.display())]
+    ConnectionRefused {
+        /// Path of the socket
+        path: PathBuf,
+        /// The failure to connect
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The server closed the connection
+    #[error("Connection closed by the server")]
+    ConnectionClosed,
+
+    /// An operation didn't finish in time
+    #[error("Timed out {operation}")]
+    Timeout {
+        /// What was being waited for, such as "connecting to the server"
+        operation: String,
+    },
+
+    /// A server process couldn't be started
+    #[error("Failed to start server: {reason}")]
+    ServerSpawnFailed {
+        /// Why the server didn't start
+        reason: String,
+        /// The underlying failure, if any
+        #[source]
+        source: Option<std::io::Error>,
+    },
+
+    /// The server sent something the client didn't expect
+    #[error("Protocol error: {0}")]
+    ProtocolError(String),
+
     /// IO-related errors
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
@@ -22,6 +82,14 @@ pub enum Error {
     /// Serialization/deserialization errors
     #[error("Serialization error: {0}")]
     Serialization(String),
+
+    /// A config failed to load or validate, with every problem found
+    #[error("Invalid config: {}", .0.join("; "))]
+    InvalidConfig(Vec<String>),
+
+    /// Error installing or controlling a launchd service
+    #[error("Service error: {0}")]
+    Service(String),
 }
 
 /// Convenience type alias for Results using our Error type
@@ -34,8 +102,100 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+impl Error {
+    /// Whether the failure may go away by itself, so that retrying the
+    /// operation makes sense
+    ///
+    /// A server that isn't listening yet, has gone away, or is slow to
+    /// respond counts, while invalid requests and configs don't.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Error::ConnectionRefused { .. } | Error::ConnectionClosed | Error::Timeout { .. }
+        )
+    }
+
+    /// Map an IO error on an established connection, telling a connection
+    /// that the server closed apart from other failures
+    pub(crate) fn connection(err: std::io::Error) -> Self {
+        use std::io::ErrorKind;
+        match err.kind() {
+            ErrorKind::UnexpectedEof | ErrorKind::BrokenPipe | ErrorKind::ConnectionReset => {
+                Error::ConnectionClosed
+            }
+            _ => Error::Io(err),
+        }
+    }
+
+    /// Map a failure to register a key with the OS, telling keys that are
+    /// taken elsewhere apart from other failures
+    ///
+    /// macOS reports a taken key as a failed `RegisterEventHotKey` call, the
+    /// other platforms as already registered.
+    pub(crate) fn registration(err: global_hotkey::Error, key: &crate::Key) -> Self {
+        match err {
+            global_hotkey::Error::AlreadyRegistered(_) => Error::KeyInUseBySystem(key.canonical()),
+            global_hotkey::Error::FailedToRegister(message)
+                if message.starts_with("RegisterEventHotKey failed") =>
+            {
+                Error::KeyInUseBySystem(key.canonical())
+            }
+            source => Error::RegistrationFailed {
+                key: key.canonical(),
+                source,
+            },
+        }
+    }
+}
+
 impl From<global_hotkey::Error> for Error {
     fn from(err: global_hotkey::Error) -> Self {
         Error::HotkeyOperation(err.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Key;
+
+    #[test]
+    fn test_registration() {
+        let key = Key::parse("cmd+space").unwrap();
+        let err = Error::registration(
+            global_hotkey::Error::AlreadyRegistered(key.to_hotkey()),
+            &key,
+        );
+        assert!(matches!(&err, Error::KeyInUseBySystem(k) if *k == key));
+        assert_eq!(err.to_string(), "cmd+space is taken by Spotlight");
+
+        let key = Key::parse("ctrl+alt+k").unwrap();
+        let err = Error::registration(
+            global_hotkey::Error::FailedToRegister("RegisterEventHotKey failed for KeyK".into()),
+            &key,
+        );
+        assert_eq!(
+            err.to_string(),
+            "ctrl+alt+k is taken by another application"
+        );
+
+        let err = Error::registration(
+            global_hotkey::Error::FailedToRegister("Unknown scancode for F13".into()),
+            &key,
+        );
+        assert!(matches!(&err, Error::RegistrationFailed { key: k, .. } if *k == key));
+        assert!(std::error::Error::source(&err).is_some());
+        assert!(!err.is_transient());
+    }
+
+    #[test]
+    fn test_connection() {
+        use std::io::{Error as IoError, ErrorKind};
+        let err = Error::connection(IoError::from(ErrorKind::UnexpectedEof));
+        assert!(matches!(err, Error::ConnectionClosed));
+        assert!(err.is_transient());
+        let err = Error::connection(IoError::from(ErrorKind::PermissionDenied));
+        assert!(matches!(err, Error::Io(_)));
+        assert!(!err.is_transient());
+    }
+}
@@ -0,0 +1,69 @@
+//! Key handling hosted inside the server process.
+//!
+//! By default the server forwards every hotkey press to its client, and the
+//! client decides what to bind next. A [`Handler`] moves that logic into the
+//! server: it chooses the bindings, handles presses itself, and sends the
+//! client high-level events to render instead of raw key presses.
+
+use crate::ipc::IPCResponse;
+use crate::Key;
+use std::time::Duration;
+
+/// How often the server [polls](Handler::poll) a handler for events
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Key handling logic run by a server
+///
+/// The server binds the handler's [`keys`](Handler::keys) when a client
+/// connects, and again after every press it handles, so that a handler can
+/// change its bindings as its state changes.
+pub trait Handler: Send + 'static {
+    /// Keys that should be bound in the current state
+    fn keys(&self) -> Vec<Key>;
+
+    /// Handle a press of a bound key, returning events to send to the client
+    fn handle_key(&mut self, key: &Key) -> Vec<IPCResponse>;
+
+    /// How long to wait for the next key of a partly entered sequence, or
+    /// `None` if the handler isn't in the middle of one
+    ///
+    /// Checked after every press. If no key is pressed in time, the server
+    /// calls [`abort_chord`](Handler::abort_chord).
+    fn chord_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Give up on a partly entered sequence whose timeout passed, returning
+    /// events to send to the client, usually `ChordAborted`
+    ///
+    /// The server binds the new [`keys`](Handler::keys) afterwards.
+    fn abort_chord(&mut self) -> Vec<IPCResponse> {
+        Vec::new()
+    }
+
+    /// Events that arose in the background since the last call, such as
+    /// failures of commands started by earlier presses
+    ///
+    /// The server calls this a few times a second, and sends the events to
+    /// the client. It doesn't rebind keys afterwards.
+    fn poll(&mut self) -> Vec<IPCResponse> {
+        Vec::new()
+    }
+
+    /// Events describing the current state, sent when a client connects and
+    /// after a reload
+    fn snapshot(&self) -> Vec<IPCResponse> {
+        Vec::new()
+    }
+
+    /// Re-read the handler's config, keeping the current one if the new one
+    /// is invalid
+    ///
+    /// Returns every problem found on failure. The server binds the new
+    /// [`keys`](Handler::keys) after a successful reload.
+    fn reload(&mut self) -> Result<(), Vec<String>> {
+        Err(vec![
+            "This server's handler does not support reloading".to_string()
+        ])
+    }
+}
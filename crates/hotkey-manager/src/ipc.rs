@@ -8,7 +8,10 @@
 //! Key design decisions:
 //! - Hotkeys must be pre-configured before starting the server (no dynamic binding)
 //! - Communication uses Unix domain sockets with a simple length-prefixed protocol
-//! - Enforces single client/server relationship for simplicity and automatic cleanup
+//! - The server's lifetime is tied to a single primary client for automatic
+//!   cleanup, unless its [`DisconnectPolicy`] keeps the bindings for a new
+//!   primary client; further clients may connect while it runs to exchange
+//!   messages
 //! - Events are forwarded asynchronously to the connected client
 //!
 //! The IPC system is designed to solve the problem of running hotkey managers
@@ -16,19 +19,32 @@
 //! hotkey handling in the main thread can cause issues.
 
 use std::{
+    collections::{HashMap, HashSet, VecDeque},
     path::PathBuf,
-    sync::{Arc, Mutex},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
+use futures::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
-use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
-    net::{UnixListener, UnixStream},
+use tokio::net::{
+    unix::{OwnedReadHalf, OwnedWriteHalf},
+    UnixListener, UnixStream,
 };
+use tokio_util::codec::{Framed, FramedRead, FramedWrite};
 
 use crate::{
+    app::App,
+    codec::MessageCodec,
     error::{Error, Result},
-    manager::HotkeyManager,
+    handler::Handler,
+    manager::{check_duplicates, HotkeyEvent, HotkeyManager},
+    metrics::Metrics,
+    stats::HotkeyStats,
     Key,
 };
 use tracing::{debug, error, info, trace, warn};
@@ -38,11 +54,16 @@ use tracing::{debug, error, info, trace, warn};
 /// The IPC protocol is designed to be minimal and focused on querying
 /// hotkey state rather than dynamic configuration. Hotkeys must be
 /// configured when creating the HotkeyManager before starting the server.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum IPCRequest {
+    /// Check that the server is responsive. A connection that only pings
+    /// doesn't become the primary client, so health checks can run before
+    /// the primary client connects.
+    Ping,
     /// Request the server to shut down gracefully.
     /// In single-client mode, the server will also shut down when
-    /// the client disconnects without sending this command.
+    /// the client disconnects without sending this command. A
+    /// ShutdownComplete event follows the response once cleanup is done.
     Shutdown,
     /// Rebind all hotkeys, replacing the current configuration.
     /// This will first unbind all existing hotkeys, then bind the new ones.
@@ -51,6 +72,127 @@ pub enum IPCRequest {
         /// Vector of keys to bind
         keys: Vec<Key>,
     },
+    /// Change part of the current configuration: unbind some keys and bind
+    /// others, leaving every other binding registered. If any binding fails,
+    /// the change is rolled back.
+    UpdateBindings {
+        /// Keys to bind
+        bind: Vec<Key>,
+        /// Keys to unbind
+        unbind: Vec<Key>,
+    },
+    /// Bind a single hotkey alongside the current configuration, which
+    /// unbinds itself after a time limit, or after its first press if
+    /// `once` is set.
+    BindTemporary {
+        /// Key to bind
+        key: Key,
+        /// How long the binding lasts, in milliseconds
+        ttl_ms: u64,
+        /// Unbind after the first press
+        once: bool,
+    },
+    /// Bind keys as a named group alongside the current configuration. The
+    /// group can then be disabled, enabled and unbound as a unit.
+    BindGroup {
+        /// Name of the group
+        group: String,
+        /// Keys to add to the group
+        keys: Vec<Key>,
+    },
+    /// Temporarily unregister a group's keys. The response data is the
+    /// number of keys disabled.
+    DisableGroup {
+        /// Name of the group
+        group: String,
+    },
+    /// Register a disabled group's keys again. The response data is the
+    /// number of keys enabled.
+    EnableGroup {
+        /// Name of the group
+        group: String,
+    },
+    /// Remove every key in a group. The response data is the number of
+    /// keys unbound.
+    UnbindGroup {
+        /// Name of the group
+        group: String,
+    },
+    /// Invoke the callback bound to an identifier as if its hotkey was
+    /// pressed, producing a HotkeyTriggered event after the response.
+    /// Only honoured when the server allows test triggers.
+    Trigger {
+        /// Identifier of the bound hotkey
+        identifier: String,
+    },
+    /// Wait for the user to press any key combination and return it as
+    /// the response data. Existing bindings are suspended while waiting.
+    Capture {
+        /// How long to wait for a key press, in milliseconds
+        timeout_ms: u64,
+    },
+    /// Ask the server's [`Handler`] to re-read and validate its config,
+    /// then bind the keys for the new config. If the config is invalid the
+    /// old one stays active and the response lists every problem.
+    ReloadConfig,
+    /// Send a message to every other client subscribed to a topic. The
+    /// response data is the number of clients it was delivered to.
+    Publish {
+        /// Name of the topic
+        topic: String,
+        /// Arbitrary message content
+        payload: serde_json::Value,
+    },
+    /// Receive Published events for messages sent to a topic by other
+    /// clients.
+    Subscribe {
+        /// Name of the topic
+        topic: String,
+    },
+    /// Get how often each hotkey has been triggered since the server
+    /// started. The response data is a list of [`HotkeyStats`].
+    GetStats,
+    /// Get counters and gauges describing the server. The response data is
+    /// a [`Metrics`].
+    GetMetrics,
+    /// Choose what happens to the bindings if this client disconnects
+    /// without sending `Shutdown`, overriding the server's default for the
+    /// rest of the connection. Only the primary client may send it. The
+    /// response data is a token for resuming the session after a
+    /// disconnect.
+    SetDisconnectPolicy {
+        /// The policy to apply
+        policy: DisconnectPolicy,
+    },
+    /// Resume the session of a primary client that disconnected while the
+    /// bindings were kept, receiving the recent hotkey events it missed.
+    /// Must be the first request other than `Ping` on the connection, or
+    /// the previous session is discarded.
+    Resume {
+        /// The token from the previous session's `SetDisconnectPolicy`
+        token: String,
+        /// Sequence number of the last hotkey event the client received.
+        /// Without it, the server replays the events it didn't deliver.
+        #[serde(default)]
+        last_seq: Option<u64>,
+    },
+}
+
+impl IPCRequest {
+    /// Whether the request adds or removes bindings, which only the primary
+    /// client of a server without a handler may do
+    fn changes_bindings(&self) -> bool {
+        matches!(
+            self,
+            Self::Rebind { .. }
+                | Self::UpdateBindings { .. }
+                | Self::BindTemporary { .. }
+                | Self::BindGroup { .. }
+                | Self::DisableGroup { .. }
+                | Self::EnableGroup { .. }
+                | Self::UnbindGroup { .. }
+        )
+    }
 }
 
 /// Represents responses sent from the IPC server to clients.
@@ -67,22 +209,343 @@ pub enum IPCResponse {
     },
     /// Error response indicating the request failed.
     Error { message: String },
+    /// Error response to a ReloadConfig request, listing every problem with
+    /// the config. The previous config stays active.
+    InvalidConfig { errors: Vec<String> },
     /// Asynchronous event sent when a hotkey is triggered.
     HotkeyTriggered(Key),
+    /// Sent by a server with a [`Handler`] when a client connects and after
+    /// each key press, describing the active mode.
+    ModeChanged {
+        /// Names of the active modes, from outermost to current
+        modes: Vec<String>,
+        /// Bindings to display as (key, description) pairs
+        keys: Vec<(String, String)>,
+    },
+    /// Sent by a server with a [`Handler`] when a binding fires.
+    ActionExecuted {
+        /// The key that was pressed
+        key: Key,
+        /// Description of the binding
+        desc: String,
+    },
+    /// Sent by a server with a [`Handler`] when a key starts or continues a
+    /// sequence binding, like the `ctrl+x` of `ctrl+x ctrl+s`.
+    ChordPending {
+        /// The keys of the sequence pressed so far
+        prefix: Vec<Key>,
+        /// The keys that can come next
+        remaining: Vec<Key>,
+    },
+    /// Sent by a server with a [`Handler`] when it gives up on a partly
+    /// entered sequence, because the next key didn't come in time.
+    ChordAborted,
+    /// A message for the user from a server with a [`Handler`], or a
+    /// warning about a failure in the server's hotkey listener.
+    Message {
+        /// The message text
+        text: String,
+        /// Whether the message reports a problem
+        warning: bool,
+    },
+    /// Asynchronous event carrying a message published by another client to
+    /// a subscribed topic.
+    Published {
+        /// Name of the topic
+        topic: String,
+        /// The message content
+        payload: serde_json::Value,
+    },
+    /// Asynchronous event sent when a different application comes to the
+    /// front, and when the primary client connects.
+    AppChanged {
+        /// Bundle identifier, such as `com.apple.Safari`. Empty for
+        /// processes without a bundle.
+        bundle_id: String,
+        /// Display name of the application
+        name: String,
+    },
+    /// A hotkey event with its sequence number, which increases with every
+    /// event. [`IPCConnection`] unwraps these, dropping events it has
+    /// already received, so clients only see the inner event.
+    Sequenced {
+        /// Sequence number of the event
+        seq: u64,
+        /// The event
+        event: Box<IPCResponse>,
+    },
+    /// Sent after the response to a Shutdown request, once the server has
+    /// finished cleaning up. For the primary client, every hotkey has been
+    /// unbound and the socket file removed. For other clients, only their
+    /// connection ends.
+    ShutdownComplete,
+}
+
+impl From<App> for IPCResponse {
+    fn from(app: App) -> Self {
+        IPCResponse::AppChanged {
+            bundle_id: app.bundle_id,
+            name: app.name,
+        }
+    }
+}
+
+/// What the server does with its bindings when the primary client
+/// disconnects without sending `Shutdown`.
+///
+/// Keeping the bindings lets a client restart without losing them: the next
+/// client to connect becomes the primary client, and receives their events.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DisconnectPolicy {
+    /// Unbind every hotkey and shut down
+    #[default]
+    Unbind,
+    /// Keep the bindings, and shut down if no client connects within the
+    /// given time
+    Keep(Duration),
+    /// Keep the bindings, and wait for a client indefinitely
+    KeepForever,
+}
+
+impl FromStr for DisconnectPolicy {
+    type Err = Error;
+
+    /// Parse `unbind`, `keep` (indefinitely), or a number of seconds to keep
+    /// the bindings for.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "unbind" => Ok(Self::Unbind),
+            "keep" => Ok(Self::KeepForever),
+            secs => secs
+                .parse()
+                .map(|secs| Self::Keep(Duration::from_secs(secs)))
+                .map_err(|_| {
+                    Error::InvalidConfig(vec![format!(
+                    "Invalid disconnect policy '{s}': expected unbind, keep, or a number of seconds"
+                )])
+                }),
+        }
+    }
+}
+
+/// How a connection to the server ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ending {
+    /// The client sent nothing but `Ping` requests, or never took over as
+    /// the primary client
+    OnlyPinged,
+    /// The client sent `Shutdown`
+    Shutdown,
+    /// The client closed the connection
+    Disconnected,
+}
+
+/// How long [`IPCConnection::shutdown`] waits for the server to finish
+/// cleaning up
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Number of recent hotkey events kept for replay
+const REPLAY_CAPACITY: usize = 64;
+
+/// How old an undelivered hotkey event can be and still be replayed to a new
+/// primary client. A resumed session gets every event kept.
+const REPLAY_WINDOW: Duration = Duration::from_secs(5);
+
+/// A handler shared between the server's tasks
+type SharedHandler = Arc<Mutex<Box<dyn Handler>>>;
+
+/// Where events for the primary client go
+#[derive(Default)]
+pub(crate) struct EventSink {
+    /// The channel to the primary client, while one is connected
+    sender: Option<tokio::sync::mpsc::UnboundedSender<IPCResponse>>,
+    /// Recent hotkey events, for a client that missed them
+    replay: Replay,
+}
+
+/// The events for the primary client, shared between the server's tasks
+type EventSender = Arc<Mutex<EventSink>>;
+
+/// Recent hotkey events, numbered in the order they fired
+#[derive(Default)]
+struct Replay {
+    /// Sequence number of the last event
+    last: u64,
+    /// Sequence number of the last event written to a client
+    delivered: u64,
+    /// The most recent events, oldest first, with when they fired
+    events: VecDeque<(u64, Instant, IPCResponse)>,
+}
+
+impl Replay {
+    /// Number an event and keep it, returning the sequenced event
+    fn record(&mut self, event: IPCResponse) -> IPCResponse {
+        self.last += 1;
+        if self.events.len() == REPLAY_CAPACITY {
+            self.events.pop_front();
+        }
+        self.events
+            .push_back((self.last, Instant::now(), event.clone()));
+        IPCResponse::Sequenced {
+            seq: self.last,
+            event: Box::new(event),
+        }
+    }
+
+    /// The kept events after sequence number `after`, leaving out those
+    /// older than `max_age` if given
+    fn since(&self, after: u64, max_age: Option<Duration>) -> Vec<IPCResponse> {
+        self.events
+            .iter()
+            .filter(|(seq, at, _)| *seq > after && max_age.is_none_or(|age| at.elapsed() <= age))
+            .map(|(seq, _, event)| IPCResponse::Sequenced {
+                seq: *seq,
+                event: Box::new(event.clone()),
+            })
+            .collect()
+    }
+}
+
+/// Reports changes of the frontmost application to the primary client
+#[derive(Clone)]
+pub(crate) struct AppNotifier {
+    event_sender: EventSender,
+    current: Arc<Mutex<Option<App>>>,
+}
+
+impl AppNotifier {
+    /// Record the new frontmost application and tell the primary client
+    pub(crate) fn notify(&self, app: App) {
+        *self.current.lock().expect("current app mutex poisoned") = Some(app.clone());
+        if let Some(sender) = &self
+            .event_sender
+            .lock()
+            .expect("event_sender mutex poisoned")
+            .sender
+        {
+            let _ = sender.send(app.into());
+        }
+    }
+}
+
+/// Event channels of the clients subscribed to each topic
+type Topics = Arc<Mutex<HashMap<String, Vec<tokio::sync::mpsc::UnboundedSender<IPCResponse>>>>>;
+
+/// Server state shared by every client connection
+#[derive(Clone)]
+struct Shared {
+    manager: Arc<HotkeyManager>,
+    event_sender: EventSender,
+    allow_test_triggers: bool,
+    handler: Option<SharedHandler>,
+    topics: Topics,
+    current_app: Arc<Mutex<Option<App>>>,
+    /// The socket file to remove on teardown, unless serving an inherited
+    /// socket
+    socket_path: Option<PathBuf>,
+    /// Whether [`teardown`] has run
+    torn_down: Arc<AtomicBool>,
+    /// The policy for when the current primary client disconnects
+    disconnect_policy: Arc<Mutex<DisconnectPolicy>>,
+    /// The primary client's session. Lock `event_sender` first when taking
+    /// both.
+    session: Arc<Mutex<Session>>,
+}
+
+/// The primary client's session, which a client reconnecting after a
+/// disconnect can resume
+#[derive(Default)]
+struct Session {
+    /// Token identifying the session, once the client has asked for one
+    token: Option<String>,
+    /// Whether the client disconnected, leaving the session to resume
+    resumable: bool,
+}
+
+/// Generate a token identifying a session
+fn session_token() -> String {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    if let Ok(elapsed) = SystemTime::now().duration_since(UNIX_EPOCH) {
+        hasher.write_u128(elapsed.as_nanos());
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+/// Make `events` the channel for the primary client's events, first
+/// replaying the hotkey events that weren't delivered.
+///
+/// A client resuming the previous session gets every kept event after
+/// `last_seq`, or after the last one delivered. Otherwise, only recent
+/// events are replayed, and the previous session is discarded. Returns the
+/// sequence number of the last event delivered before.
+fn attach(
+    shared: &Shared,
+    events: &tokio::sync::mpsc::UnboundedSender<IPCResponse>,
+    resume: bool,
+    last_seq: Option<u64>,
+) -> u64 {
+    let mut sink = shared
+        .event_sender
+        .lock()
+        .expect("event_sender mutex poisoned");
+    let mut session = shared.session.lock().expect("session mutex poisoned");
+    let delivered = sink.replay.delivered;
+    let missed = if resume {
+        sink.replay.since(last_seq.unwrap_or(delivered), None)
+    } else {
+        if session.resumable {
+            info!("Discarding the previous client's session");
+        }
+        session.token = None;
+        sink.replay.since(delivered, Some(REPLAY_WINDOW))
+    };
+    session.resumable = false;
+    if !missed.is_empty() {
+        info!("Replaying {} missed hotkey events", missed.len());
+    }
+    for event in missed {
+        let _ = events.send(event);
+    }
+    sink.sender = Some(events.clone());
+    delivered
+}
+
+/// Keep the primary client's session for it to resume, if it has one
+fn suspend_session(shared: &Shared) {
+    let mut session = shared.session.lock().expect("session mutex poisoned");
+    if session.token.is_some() {
+        session.resumable = true;
+        debug!("Keeping the session for the client to resume");
+    }
 }
 
 /// IPC server that manages hotkey operations for a single client.
 ///
-/// The server runs in a separate process and communicates with one client
-/// via Unix domain socket. It maintains a pre-configured HotkeyManager
-/// and forwards hotkey events to the connected client.
+/// The server runs in a separate process and communicates with one primary
+/// client via Unix domain socket. It maintains a pre-configured
+/// HotkeyManager and forwards hotkey events to the primary client. Other
+/// clients may connect while it runs, to publish and subscribe to messages
+/// or reload the config, but can't change the bindings.
 ///
-/// The server automatically shuts down when the client disconnects,
-/// ensuring clean process management.
+/// The server automatically shuts down when the primary client disconnects,
+/// ensuring clean process management, unless its [`DisconnectPolicy`] keeps
+/// the bindings for a new primary client.
 pub(crate) struct IPCServer {
     socket_path: PathBuf,
     manager: Arc<HotkeyManager>,
-    event_sender: Arc<Mutex<Option<tokio::sync::mpsc::UnboundedSender<IPCResponse>>>>,
+    event_sender: EventSender,
+    allow_test_triggers: bool,
+    handler: Option<SharedHandler>,
+    current_app: Arc<Mutex<Option<App>>>,
+    ready_file: Option<PathBuf>,
+    disconnect_policy: DisconnectPolicy,
+    /// A connected socket to serve instead of listening on the path
+    inherited: Option<std::os::unix::net::UnixStream>,
+    /// Address to serve metrics over HTTP on, if any
+    #[cfg(feature = "metrics-http")]
+    metrics_address: Option<std::net::SocketAddr>,
 }
 
 impl IPCServer {
@@ -93,37 +556,361 @@ impl IPCServer {
     /// the server, as dynamic binding is not supported through IPC.
     pub(crate) fn new(socket_path: impl Into<PathBuf>, manager: HotkeyManager) -> Self {
         let socket_path = socket_path.into();
-        let event_sender = Arc::new(Mutex::new(None));
+        let event_sender: EventSender = Arc::new(Mutex::new(EventSink::default()));
+
+        // Surface listener failures to the primary client
+        let error_sender = event_sender.clone();
+        manager.set_error_handler(move |failure| {
+            if let Some(sender) = &error_sender
+                .lock()
+                .expect("event_sender mutex poisoned")
+                .sender
+            {
+                let _ = sender.send(IPCResponse::Message {
+                    text: failure.to_string(),
+                    warning: true,
+                });
+            }
+        });
 
         Self {
             socket_path,
             manager: Arc::new(manager),
             event_sender,
+            allow_test_triggers: false,
+            handler: None,
+            current_app: Arc::new(Mutex::new(None)),
+            ready_file: None,
+            disconnect_policy: DisconnectPolicy::default(),
+            inherited: None,
+            #[cfg(feature = "metrics-http")]
+            metrics_address: None,
+        }
+    }
+
+    /// Serve the client on the other end of a connected socket, rather than
+    /// listening on the socket path.
+    pub(crate) fn with_inherited_stream(
+        mut self,
+        stream: Option<std::os::unix::net::UnixStream>,
+    ) -> Self {
+        self.inherited = stream;
+        self
+    }
+
+    /// Serve metrics over HTTP on `address` while the server runs.
+    #[cfg(feature = "metrics-http")]
+    pub(crate) fn with_metrics_address(mut self, address: Option<std::net::SocketAddr>) -> Self {
+        self.metrics_address = address;
+        self
+    }
+
+    /// Decide what happens to the bindings when a primary client
+    /// disconnects without sending `Shutdown`, unless it chose a policy
+    /// itself.
+    pub(crate) fn with_disconnect_policy(mut self, policy: DisconnectPolicy) -> Self {
+        self.disconnect_policy = policy;
+        self
+    }
+
+    /// Create a file at `path` once the socket is listening.
+    pub(crate) fn with_ready_file(mut self, path: Option<PathBuf>) -> Self {
+        self.ready_file = path;
+        self
+    }
+
+    /// Accept `Trigger` requests, which simulate hotkey presses.
+    pub(crate) fn with_test_triggers(mut self, enabled: bool) -> Self {
+        self.allow_test_triggers = enabled;
+        self
+    }
+
+    /// Handle hotkey presses in the server with the given handler, which
+    /// also decides what is bound.
+    pub(crate) fn with_handler(mut self, handler: Box<dyn Handler>) -> Self {
+        self.handler = Some(Arc::new(Mutex::new(handler)));
+        self
+    }
+
+    /// Bind keys that forward their presses to the primary client, before
+    /// any client has connected. Every key is tried, and the error lists
+    /// those that failed.
+    pub(crate) fn bind_forwarded(&self, bindings: &[(String, Key)]) -> Result<()> {
+        let callback = create_event_forwarder(self.event_sender.clone());
+        let failed: Vec<String> = self
+            .manager
+            .bind_multiple(bindings, callback)?
+            .into_iter()
+            .zip(bindings)
+            .filter_map(|(result, (identifier, _))| {
+                result.err().map(|e| format!("{identifier}: {e}"))
+            })
+            .collect();
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::HotkeyOperation(format!(
+                "Failed to bind keys: {}",
+                failed.join("; ")
+            )))
+        }
+    }
+
+    /// A notifier for reporting frontmost application changes, which can be
+    /// used from other threads.
+    pub(crate) fn app_notifier(&self) -> AppNotifier {
+        AppNotifier {
+            event_sender: self.event_sender.clone(),
+            current: self.current_app.clone(),
         }
     }
 
-    /// Run the IPC server, accepting a single client connection.
+    /// Run the IPC server, serving the first client to connect as the
+    /// primary client. Connections that only ping the server, such as
+    /// health checks, are not taken as the primary client.
     ///
     /// This method will block until the server shuts down. The server
-    /// exits when the client disconnects.
+    /// exits when the primary client sends `Shutdown`, or disconnects and
+    /// the [`DisconnectPolicy`] doesn't keep the bindings. Otherwise, the
+    /// next client to connect becomes the primary client.
     ///
     /// The server automatically removes any existing socket file at the path
-    /// before binding to ensure a clean start.
-    pub async fn run(self) -> Result<()> {
+    /// before binding to ensure a clean start, and creates its ready file, if
+    /// it has one, once it is listening.
+    ///
+    /// With an [inherited socket](Self::with_inherited_stream), the client on
+    /// its other end is the only client, and the server exits when it
+    /// disconnects.
+    pub async fn run(mut self) -> Result<()> {
+        if let Some(stream) = self.inherited.take() {
+            return self.run_inherited(stream).await;
+        }
+
         // Remove socket file if it exists
         let _ = std::fs::remove_file(&self.socket_path);
 
-        let listener = UnixListener::bind(&self.socket_path)?;
+        let listener = Arc::new(UnixListener::bind(&self.socket_path)?);
+        if let Some(path) = &self.ready_file {
+            match std::fs::write(path, format!("{}\n", std::process::id())) {
+                Ok(()) => debug!("Wrote ready file: {}", path.display()),
+                Err(e) => warn!("Failed to write ready file {}: {}", path.display(), e),
+            }
+        }
+        let shared = self.shared(Some(self.socket_path.clone()));
+
+        #[cfg(feature = "metrics-http")]
+        let metrics_server = self.serve_metrics();
+
+        // When the bindings are kept for a new primary client, the time to
+        // give up waiting for one
+        let mut deadline = None;
+        let result = loop {
+            // Accept the primary connection
+            let accepted = match deadline {
+                Some(deadline) => {
+                    match tokio::time::timeout_at(deadline, listener.accept()).await {
+                        Ok(accepted) => accepted,
+                        Err(_) => {
+                            info!("No client connected in time");
+                            break Ok(());
+                        }
+                    }
+                }
+                None => listener.accept().await,
+            };
+            let (stream, _) = match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => break Err(e.into()),
+            };
+            info!("Client connected");
+
+            // Each primary client starts with the server's policy
+            *shared
+                .disconnect_policy
+                .lock()
+                .expect("disconnect policy mutex poisoned") = self.disconnect_policy;
+
+            // Serve any further clients in the background
+            let acceptor = tokio::spawn(accept_secondary(listener.clone(), shared.clone()));
+
+            let result = handle_client(stream, shared.clone(), true).await;
+            acceptor.abort();
+            if result.is_err() {
+                shared.manager.collector().ipc_error();
+            }
+            match result {
+                // A health check, rather than the primary client
+                Ok(Ending::OnlyPinged) => {
+                    debug!("Client only pinged, waiting for the primary client")
+                }
+                Ok(Ending::Shutdown) => break Ok(()),
+                result => {
+                    info!("Client disconnected");
+                    let policy = *shared
+                        .disconnect_policy
+                        .lock()
+                        .expect("disconnect policy mutex poisoned");
+                    match (policy, result) {
+                        (DisconnectPolicy::Unbind, result) => break result.map(|_| ()),
+                        (policy, result) => {
+                            if let Err(e) = result {
+                                warn!("Client connection failed: {}", e);
+                            }
+                            suspend_session(&shared);
+                            deadline = match policy {
+                                DisconnectPolicy::Keep(grace) => {
+                                    info!("Keeping bindings for {:?}, waiting for a client", grace);
+                                    Some(tokio::time::Instant::now() + grace)
+                                }
+                                _ => {
+                                    info!("Keeping bindings, waiting for a client");
+                                    None
+                                }
+                            };
+                        }
+                    }
+                }
+            }
+        };
+
+        #[cfg(feature = "metrics-http")]
+        if let Some(metrics_server) = metrics_server {
+            metrics_server.abort();
+        }
+        teardown(&shared);
+        drop(listener);
+        info!("Closed socket");
+        result
+    }
+
+    /// Serve the client on the other end of an inherited socket until it
+    /// disconnects
+    async fn run_inherited(self, stream: std::os::unix::net::UnixStream) -> Result<()> {
+        stream.set_nonblocking(true)?;
+        let stream = UnixStream::from_std(stream)?;
+        info!("Serving inherited socket");
+        let shared = self.shared(None);
 
-        // Accept single connection and handle it
-        let (stream, _) = listener.accept().await?;
-        let manager = self.manager.clone();
-        let event_sender = self.event_sender.clone();
+        #[cfg(feature = "metrics-http")]
+        let metrics_server = self.serve_metrics();
 
-        info!("Client connected");
-        handle_client(stream, manager, event_sender).await?;
+        let result = handle_client(stream, shared.clone(), true).await;
+        if result.is_err() {
+            shared.manager.collector().ipc_error();
+        }
         info!("Client disconnected");
-        Ok(())
+
+        #[cfg(feature = "metrics-http")]
+        if let Some(metrics_server) = metrics_server {
+            metrics_server.abort();
+        }
+        teardown(&shared);
+        result.map(|_| ())
+    }
+
+    /// State shared by the connections, removing the socket file at
+    /// `socket_path` on teardown if there is one
+    fn shared(&self, socket_path: Option<PathBuf>) -> Shared {
+        Shared {
+            manager: self.manager.clone(),
+            event_sender: self.event_sender.clone(),
+            allow_test_triggers: self.allow_test_triggers,
+            handler: self.handler.clone(),
+            topics: Arc::new(Mutex::new(HashMap::new())),
+            current_app: self.current_app.clone(),
+            socket_path,
+            torn_down: Arc::new(AtomicBool::new(false)),
+            disconnect_policy: Arc::new(Mutex::new(self.disconnect_policy)),
+            session: Arc::new(Mutex::new(Session::default())),
+        }
+    }
+
+    /// Start serving metrics over HTTP, if there is an address to serve them
+    /// on
+    #[cfg(feature = "metrics-http")]
+    fn serve_metrics(&self) -> Option<tokio::task::JoinHandle<()>> {
+        self.metrics_address.map(|address| {
+            let manager = self.manager.clone();
+            tokio::spawn(async move {
+                if let Err(e) = crate::metrics::serve(address, move || manager.metrics()).await {
+                    error!("Metrics server failed: {}", e);
+                }
+            })
+        })
+    }
+}
+
+/// Release what the server holds, the first time it is called: unbind every
+/// hotkey, and remove the socket file so that no new clients connect.
+fn teardown(shared: &Shared) {
+    if shared.torn_down.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    info!("Tearing down server");
+    if let Err(e) = shared.manager.unbind_all() {
+        warn!("Failed to unbind hotkeys during teardown: {}", e);
+    }
+    let Some(socket_path) = &shared.socket_path else {
+        return;
+    };
+    match std::fs::remove_file(socket_path) {
+        Ok(()) => info!("Removed socket file {}", socket_path.display()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            debug!("Socket file {} already removed", socket_path.display())
+        }
+        Err(e) => warn!(
+            "Failed to remove socket file {}: {}",
+            socket_path.display(),
+            e
+        ),
+    }
+}
+
+/// Frames requests in and responses out, on the server side
+type ServerCodec = MessageCodec<IPCRequest, IPCResponse>;
+
+/// Frames responses in and requests out, on the client side
+type ClientCodec = MessageCodec<IPCResponse, IPCRequest>;
+
+/// The writing half of a client connection, shared by the request loop and
+/// the event forwarder
+type ResponseWriter = tokio::sync::Mutex<FramedWrite<OwnedWriteHalf, ServerCodec>>;
+
+/// Write a length-prefixed message to a client.
+async fn write_message(writer: &ResponseWriter, message: &IPCResponse) -> Result<()> {
+    writer.lock().await.send(message).await
+}
+
+/// Classify an IO failure on a framed connection, telling a connection that
+/// the peer closed apart from other failures
+fn connection_error(err: Error) -> Error {
+    match err {
+        Error::Io(e) => Error::connection(e),
+        err => err,
+    }
+}
+
+/// Serve secondary clients until the task is aborted or accepting fails.
+async fn accept_secondary(listener: Arc<UnixListener>, shared: Shared) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let shared = shared.clone();
+                tokio::spawn(async move {
+                    info!("Secondary client connected");
+                    let manager = shared.manager.clone();
+                    if let Err(e) = handle_client(stream, shared, false).await {
+                        debug!("Secondary client error: {:?}", e);
+                        manager.collector().ipc_error();
+                    }
+                    info!("Secondary client disconnected");
+                });
+            }
+            Err(e) => {
+                error!("Failed to accept connection: {:?}", e);
+                break;
+            }
+        }
     }
 }
 
@@ -134,176 +921,760 @@ impl IPCServer {
 /// - Forwards hotkey events to the client
 /// - Cleans up when the client disconnects
 ///
+/// With a handler, key presses are passed to the handler rather than
+/// forwarded, and the client receives the events it returns instead.
+///
+/// Only the primary client receives hotkey and application events. Secondary
+/// clients receive just the messages published to topics they subscribe to.
+///
 /// Uses a simple length-prefixed binary protocol for message framing.
-async fn handle_client(
-    stream: UnixStream,
-    manager: Arc<HotkeyManager>,
-    event_sender: Arc<Mutex<Option<tokio::sync::mpsc::UnboundedSender<IPCResponse>>>>,
-) -> Result<()> {
+///
+/// Returns how the connection ended.
+async fn handle_client(stream: UnixStream, shared: Shared, primary: bool) -> Result<Ending> {
     debug!("handle_client: Starting client handler");
     let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel();
     trace!("handle_client: Created event channel");
-    *event_sender.lock().expect("event_sender mutex poisoned") = Some(event_tx.clone());
-    debug!("handle_client: Set event sender in shared state");
+    let manager = shared.manager.clone();
+    let event_sender = shared.event_sender.clone();
+    // A primary client takes over the events right away, unless there is a
+    // session it may resume, which its first request decides
+    let mut attached = None;
+    let resumable = shared
+        .session
+        .lock()
+        .expect("session mutex poisoned")
+        .resumable;
+    if primary && !resumable {
+        attached = Some(attach(&shared, &event_tx, false, None));
+        debug!("handle_client: Set event sender in shared state");
+    }
+    let handler = if primary {
+        shared.handler.clone()
+    } else {
+        None
+    };
 
     let (reader, writer) = stream.into_split();
-    let reader = Arc::new(tokio::sync::Mutex::new(reader));
-    let writer = Arc::new(tokio::sync::Mutex::new(writer));
+    let mut requests: FramedRead<OwnedReadHalf, ServerCodec> =
+        FramedRead::new(reader, ServerCodec::new());
+    let writer: Arc<ResponseWriter> = Arc::new(tokio::sync::Mutex::new(FramedWrite::new(
+        writer,
+        ServerCodec::new(),
+    )));
+
+    // Bind the handler's keys and describe its state to the new client
+    if let Some(handler) = &handler {
+        let (keys, snapshot) = {
+            let handler = handler.lock().expect("handler mutex poisoned");
+            (handler.keys(), handler.snapshot())
+        };
+        if let IPCResponse::Error { message } = rebind(&manager, &keys, &event_sender) {
+            error!("{message}");
+        }
+        for event in snapshot {
+            let _ = event_tx.send(event);
+        }
+    }
+
+    // Tell the primary client which application is in front
+    if primary {
+        let app = shared
+            .current_app
+            .lock()
+            .expect("current app mutex poisoned")
+            .clone();
+        if let Some(app) = app {
+            let _ = event_tx.send(app.into());
+        }
+    }
 
     // Spawn task to forward events to client
     let writer_clone = writer.clone();
+    let hosted = handler
+        .clone()
+        .map(|handler| (handler, manager.clone(), event_sender.clone()));
+    let sink = event_sender.clone();
+    let metrics_manager = manager.clone();
     tokio::spawn(async move {
         info!("Event forwarding task started");
-        while let Some(event) = event_rx.recv().await {
-            debug!("Event forwarding task received event: {:?}", event);
-            let data = match serde_json::to_vec(&event) {
-                Ok(d) => d,
-                Err(e) => {
-                    error!("Failed to serialize event: {:?}", e);
-                    continue;
+        // When a hosted handler gives up on a partly entered sequence
+        let mut chord_deadline: Option<tokio::time::Instant> = None;
+        // Picks up events a hosted handler raises between presses
+        let mut poll = tokio::time::interval(crate::handler::POLL_INTERVAL);
+        poll.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        'events: loop {
+            let (seq, events) = tokio::select! {
+                event = event_rx.recv() => {
+                    let Some(event) = event else {
+                        break;
+                    };
+                    debug!("Event forwarding task received event: {:?}", event);
+                    let seq = match &event {
+                        IPCResponse::Sequenced { seq, .. } => Some(*seq),
+                        _ => None,
+                    };
+                    let events = match (&hosted, event) {
+                        (
+                            Some((handler, manager, event_sender)),
+                            IPCResponse::Sequenced { event, .. },
+                        ) => match *event {
+                            IPCResponse::HotkeyTriggered(key) => {
+                                let (events, timeout) =
+                                    update_hosted(handler, manager, event_sender, |handler| {
+                                        handler.handle_key(&key)
+                                    });
+                                chord_deadline =
+                                    timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+                                events
+                            }
+                            event => vec![event],
+                        },
+                        (_, event) => vec![event],
+                    };
+                    (seq, events)
+                }
+                () = tokio::time::sleep_until(
+                    chord_deadline.unwrap_or_else(tokio::time::Instant::now)
+                ), if chord_deadline.is_some() => {
+                    chord_deadline = None;
+                    let Some((handler, manager, event_sender)) = &hosted else {
+                        continue;
+                    };
+                    debug!("Chord timed out");
+                    let (events, _) = update_hosted(handler, manager, event_sender, |handler| {
+                        handler.abort_chord()
+                    });
+                    (None, events)
+                }
+                _ = poll.tick(), if hosted.is_some() => {
+                    let Some((handler, _, _)) = &hosted else {
+                        continue;
+                    };
+                    let events = handler.lock().expect("handler mutex poisoned").poll();
+                    (None, events)
                 }
             };
-            let len_bytes = (data.len() as u32).to_be_bytes();
-            let mut writer = writer_clone.lock().await;
-            trace!("Sending event to client, data len: {}", data.len());
-            if let Err(e) = writer.write_all(&len_bytes).await {
-                error!("Failed to write event length: {:?}", e);
-                break;
-            }
-            if let Err(e) = writer.write_all(&data).await {
-                error!("Failed to write event data: {:?}", e);
-                break;
+            for event in events {
+                trace!("Sending event to client: {:?}", event);
+                match write_message(&writer_clone, &event).await {
+                    Ok(()) => {}
+                    Err(e @ Error::Serialization(_)) => {
+                        error!("Failed to encode event: {:?}", e);
+                        continue;
+                    }
+                    Err(e) => {
+                        error!("Failed to write event: {:?}", e);
+                        break 'events;
+                    }
+                }
+                metrics_manager.collector().event_delivered();
+                trace!("Event sent to client successfully");
             }
-            if let Err(e) = writer.flush().await {
-                error!("Failed to flush event data: {:?}", e);
-                break;
+            if let Some(seq) = seq {
+                let mut sink = sink.lock().expect("event_sender mutex poisoned");
+                sink.replay.delivered = sink.replay.delivered.max(seq);
             }
-            trace!("Event sent to client successfully");
         }
         info!("Event forwarding task ended");
     });
 
+    let mut only_pinged = None;
+    let mut ending = Ending::Disconnected;
     loop {
-        // Read message length
-        let mut len_bytes = [0u8; 4];
-        {
-            let mut reader = reader.lock().await;
-            match reader.read_exact(&mut len_bytes).await {
-                Ok(_) => {}
-                Err(_) => break,
+        // A frame that's too large leaves no way to find the next one, so
+        // it ends the connection, while a request that doesn't decode is
+        // answered with an error, and the connection carries on with the
+        // next frame
+        let request = match requests.next().await {
+            Some(Ok(Ok(request))) => request,
+            Some(Err(e @ Error::ProtocolError(_))) => return Err(e),
+            Some(Err(_)) | None => break,
+            Some(Ok(Err(e))) => {
+                warn!("Received malformed request: {}", e);
+                manager.collector().ipc_error();
+                write_message(
+                    &writer,
+                    &IPCResponse::Error {
+                        message: format!("Malformed request: {e}"),
+                    },
+                )
+                .await?;
+                continue;
             }
-        }
-
-        let len = u32::from_be_bytes(len_bytes) as usize;
-
-        // Read message data
-        let mut data = vec![0u8; len];
-        {
-            let mut reader = reader.lock().await;
-            reader.read_exact(&mut data).await?;
-        }
-
-        let request: IPCRequest = serde_json::from_slice(&data)?;
+        };
         debug!("Received request: {:?}", request);
+        let is_ping = matches!(request, IPCRequest::Ping);
+        only_pinged = Some(only_pinged.unwrap_or(true) && is_ping);
+        if primary && attached.is_none() && !is_ping {
+            let (resume, last_seq) = match &request {
+                IPCRequest::Resume { token, last_seq } => (
+                    shared
+                        .session
+                        .lock()
+                        .expect("session mutex poisoned")
+                        .token
+                        .as_ref()
+                        == Some(token),
+                    *last_seq,
+                ),
+                _ => (false, None),
+            };
+            attached = Some(attach(&shared, &event_tx, resume, last_seq));
+        }
         let is_shutdown = matches!(request, IPCRequest::Shutdown);
-        let response = handle_request(&manager, request, &event_sender).await;
+        let trigger = match &request {
+            IPCRequest::Trigger { identifier } => Some(identifier.clone()),
+            _ => None,
+        };
+        let is_reload = matches!(request, IPCRequest::ReloadConfig);
+        let response = handle_request(&shared, request, &event_tx, primary).await;
         trace!("Generated response: {:?}", response);
+        if matches!(
+            response,
+            IPCResponse::Error { .. } | IPCResponse::InvalidConfig { .. }
+        ) {
+            manager.collector().ipc_error();
+        }
 
         // Send response
-        let response_data = serde_json::to_vec(&response)?;
-        let response_len = (response_data.len() as u32).to_be_bytes();
+        write_message(&writer, &response).await?;
+
+        // Fire simulated hotkeys only after the response, so the resulting
+        // event never arrives ahead of it
+        if let (Some(identifier), IPCResponse::Success { .. }) = (trigger, &response) {
+            manager.trigger(&identifier);
+        }
+
+        // Likewise, describe a reloaded handler's new state to the primary
+        // client after the response
+        if let (true, Some(handler), IPCResponse::Success { .. }) =
+            (is_reload, &shared.handler, &response)
         {
-            let mut writer = writer.lock().await;
-            writer.write_all(&response_len).await?;
-            writer.write_all(&response_data).await?;
-            writer.flush().await?;
+            let snapshot = handler.lock().expect("handler mutex poisoned").snapshot();
+            if let Some(sender) = &event_sender
+                .lock()
+                .expect("event_sender mutex poisoned")
+                .sender
+            {
+                for event in snapshot {
+                    let _ = sender.send(event);
+                }
+            }
         }
 
         if is_shutdown {
+            if primary {
+                teardown(&shared);
+            }
+            write_message(&writer, &IPCResponse::ShutdownComplete).await?;
+            ending = Ending::Shutdown;
             break;
         }
     }
 
-    // Clear event sender
-    *event_sender.lock().expect("event_sender mutex poisoned") = None;
+    // Clear event sender. Events written to a client that only pinged were
+    // never read, so leave them for the next client to replay.
+    if let Some(delivered) = attached {
+        let mut sink = event_sender.lock().expect("event_sender mutex poisoned");
+        sink.sender = None;
+        if only_pinged == Some(true) {
+            sink.replay.delivered = delivered;
+        }
+    }
 
-    Ok(())
+    if only_pinged == Some(true) || (primary && attached.is_none()) {
+        ending = Ending::OnlyPinged;
+    }
+    Ok(ending)
 }
 
 /// Process an individual IPC request and generate the appropriate response.
 ///
 /// This function handles the business logic for each request type,
-/// interfacing with the HotkeyManager to query state.
+/// interfacing with the HotkeyManager to query state. `events` is the
+/// requesting client's event channel.
 async fn handle_request(
-    manager: &Arc<HotkeyManager>,
+    shared: &Shared,
     request: IPCRequest,
-    event_sender: &Arc<Mutex<Option<tokio::sync::mpsc::UnboundedSender<IPCResponse>>>>,
+    events: &tokio::sync::mpsc::UnboundedSender<IPCResponse>,
+    primary: bool,
 ) -> IPCResponse {
+    let Shared {
+        manager,
+        event_sender,
+        allow_test_triggers,
+        handler,
+        topics,
+        disconnect_policy,
+        session,
+        ..
+    } = shared;
+    let hosted = handler.is_some();
     match request {
+        IPCRequest::Ping => IPCResponse::Success {
+            message: "Pong".to_string(),
+            data: Some(serde_json::Value::String(crate::VERSION.to_string())),
+        },
+
         IPCRequest::Shutdown => IPCResponse::Success {
             message: "Shutting down".to_string(),
             data: None,
         },
 
+        ref request if request.changes_bindings() && hosted => IPCResponse::Error {
+            message: "Bindings are managed by the server's handler".to_string(),
+        },
+
+        ref request if request.changes_bindings() && !primary => IPCResponse::Error {
+            message: "Only the primary client can change bindings".to_string(),
+        },
+
         IPCRequest::Rebind { keys } => {
             info!("Processing Rebind request with {} keys", keys.len());
-            // First unbind all existing hotkeys
-            if let Err(e) = manager.unbind_all() {
-                return IPCResponse::Error {
-                    message: format!("Failed to unbind existing hotkeys: {e}"),
-                };
+            rebind(manager, &keys, event_sender)
+        }
+
+        IPCRequest::UpdateBindings { bind, unbind } => {
+            info!(
+                "Processing UpdateBindings request, binding {} and unbinding {} keys",
+                bind.len(),
+                unbind.len()
+            );
+            update_bindings(manager, &bind, &unbind, event_sender)
+        }
+
+        IPCRequest::BindTemporary { key, ttl_ms, once } => {
+            info!("Processing BindTemporary request for '{}'", key);
+            let ttl = std::time::Duration::from_millis(ttl_ms);
+            let callback = create_event_forwarder(event_sender.clone());
+            let key = key.canonical();
+            match manager.bind_temporary(key.canonical_string(), key.clone(), ttl, once, callback) {
+                Ok(_) => IPCResponse::Success {
+                    message: format!("Bound {key} for {ttl_ms} ms"),
+                    data: None,
+                },
+                Err(e) => IPCResponse::Error {
+                    message: format!("Failed to bind {key}: {e}"),
+                },
             }
+        }
 
-            // Create a mapping from identifier to Key
-            let mut key_map = std::collections::HashMap::new();
-            let key_pairs: Vec<(String, Key)> = keys
-                .iter()
-                .map(|key| {
-                    let identifier = key.to_string();
-                    key_map.insert(identifier.clone(), key.clone());
-                    (identifier, key.clone())
+        IPCRequest::BindGroup { group, keys } => {
+            info!("Binding {} keys in group '{}'", keys.len(), group);
+            let key_pairs = canonical_pairs(&keys);
+            let callback = create_event_forwarder(event_sender.clone());
+            let results = match manager.bind_group(&group, &key_pairs, callback) {
+                Ok(results) => results,
+                Err(e) => {
+                    return IPCResponse::Error {
+                        message: e.to_string(),
+                    }
+                }
+            };
+            let failed: Vec<String> = results
+                .into_iter()
+                .zip(&key_pairs)
+                .filter_map(|(result, (identifier, _))| {
+                    result.err().map(|e| format!("{identifier}: {e}"))
                 })
                 .collect();
+            if failed.is_empty() {
+                IPCResponse::Success {
+                    message: format!("Bound {} keys in group {group}", keys.len()),
+                    data: None,
+                }
+            } else {
+                IPCResponse::Error {
+                    message: format!(
+                        "Failed to bind keys in group {group}: {}",
+                        failed.join("; ")
+                    ),
+                }
+            }
+        }
 
-            // Use the existing event sender for creating callbacks
-            debug!("Creating event forwarder with existing event sender");
-            let callback = create_event_forwarder_with_key_map(event_sender.clone(), key_map);
+        IPCRequest::DisableGroup { group } => {
+            group_response("Disabled", &group, manager.disable_group(&group))
+        }
 
-            // Bind all the new hotkeys
-            debug!("Binding {} new hotkeys", keys.len());
-            let results = manager.bind_multiple(&key_pairs, callback);
+        IPCRequest::EnableGroup { group } => {
+            group_response("Enabled", &group, manager.enable_group(&group))
+        }
 
-            // Check if any bindings failed
-            let mut failed_bindings = Vec::new();
-            let mut successful_count = 0;
+        IPCRequest::UnbindGroup { group } => {
+            group_response("Unbound", &group, manager.unbind_group(&group))
+        }
 
-            for (idx, result) in results.iter().enumerate() {
-                match result {
-                    Ok(_) => successful_count += 1,
-                    Err(e) => failed_bindings.push((key_pairs[idx].0.clone(), e.to_string())),
+        IPCRequest::ReloadConfig => {
+            let Some(handler) = handler else {
+                return IPCResponse::Error {
+                    message: "This server has no config to reload".to_string(),
+                };
+            };
+            info!("Processing ReloadConfig request");
+            let result = {
+                let mut handler = handler.lock().expect("handler mutex poisoned");
+                handler.reload().map(|()| handler.keys())
+            };
+            match result {
+                Ok(keys) => match rebind(manager, &keys, event_sender) {
+                    IPCResponse::Success { .. } => IPCResponse::Success {
+                        message: "Config reloaded".to_string(),
+                        data: None,
+                    },
+                    response => response,
+                },
+                Err(errors) => {
+                    warn!("Config reload failed: {}", errors.join("; "));
+                    IPCResponse::InvalidConfig { errors }
                 }
             }
+        }
+
+        IPCRequest::Publish { topic, payload } => {
+            let mut topics = topics.lock().expect("topics mutex poisoned");
+            let delivered = match topics.get_mut(&topic) {
+                Some(subscribers) => {
+                    // Drop subscribers whose connection has closed
+                    subscribers.retain(|s| !s.is_closed());
+                    let mut delivered = 0;
+                    for subscriber in subscribers.iter().filter(|s| !s.same_channel(events)) {
+                        let event = IPCResponse::Published {
+                            topic: topic.clone(),
+                            payload: payload.clone(),
+                        };
+                        if subscriber.send(event).is_ok() {
+                            delivered += 1;
+                        }
+                    }
+                    delivered
+                }
+                None => 0,
+            };
+            debug!("Published to '{}' for {} clients", topic, delivered);
+            IPCResponse::Success {
+                message: format!("Delivered to {delivered} clients"),
+                data: Some(serde_json::Value::from(delivered)),
+            }
+        }
+
+        IPCRequest::Subscribe { topic } => {
+            let mut topics = topics.lock().expect("topics mutex poisoned");
+            let subscribers = topics.entry(topic.clone()).or_default();
+            if !subscribers.iter().any(|s| s.same_channel(events)) {
+                subscribers.push(events.clone());
+            }
+            IPCResponse::Success {
+                message: format!("Subscribed to {topic}"),
+                data: None,
+            }
+        }
+
+        IPCRequest::Capture { timeout_ms } => {
+            info!("Processing Capture request ({} ms)", timeout_ms);
+            let timeout = std::time::Duration::from_millis(timeout_ms);
+            // Capturing blocks until a key is pressed, so wait for it on a
+            // thread of its own
+            let manager = manager.clone();
+            let captured = tokio::task::spawn_blocking(move || manager.capture(timeout))
+                .await
+                .unwrap_or_else(|e| {
+                    Err(Error::HotkeyOperation(format!("Capture task failed: {e}")))
+                });
+            match captured {
+                Ok(Some(key)) => IPCResponse::Success {
+                    message: format!("Captured {key}"),
+                    data: serde_json::to_value(&key).ok(),
+                },
+                Ok(None) => IPCResponse::Success {
+                    message: "No key pressed".to_string(),
+                    data: None,
+                },
+                Err(e) => IPCResponse::Error {
+                    message: format!("Failed to capture key: {e}"),
+                },
+            }
+        }
+
+        IPCRequest::GetStats => {
+            let stats = manager.stats();
+            IPCResponse::Success {
+                message: format!("Statistics for {} hotkeys", stats.len()),
+                data: serde_json::to_value(&stats).ok(),
+            }
+        }
+
+        IPCRequest::GetMetrics => IPCResponse::Success {
+            message: "Metrics".to_string(),
+            data: serde_json::to_value(manager.metrics()).ok(),
+        },
+
+        IPCRequest::SetDisconnectPolicy { policy } => {
+            if !primary {
+                return IPCResponse::Error {
+                    message: "Only the primary client can set the disconnect policy".to_string(),
+                };
+            }
+            info!("Disconnect policy set to {:?}", policy);
+            *disconnect_policy
+                .lock()
+                .expect("disconnect policy mutex poisoned") = policy;
+            let token = session
+                .lock()
+                .expect("session mutex poisoned")
+                .token
+                .get_or_insert_with(session_token)
+                .clone();
+            IPCResponse::Success {
+                message: format!("Disconnect policy set to {policy:?}"),
+                data: Some(serde_json::Value::String(token)),
+            }
+        }
 
-            if failed_bindings.is_empty() {
+        IPCRequest::Resume { token, .. } => {
+            let current = session
+                .lock()
+                .expect("session mutex poisoned")
+                .token
+                .clone();
+            if !primary {
+                IPCResponse::Error {
+                    message: "Only the primary client can resume a session".to_string(),
+                }
+            } else if current.as_ref() == Some(&token) {
                 IPCResponse::Success {
-                    message: format!("Successfully bound {successful_count} hotkeys"),
+                    message: "Session resumed".to_string(),
                     data: None,
                 }
             } else {
-                // If any failed, unbind all to maintain atomicity
-                let _ = manager.unbind_all();
                 IPCResponse::Error {
-                    message: format!(
-                        "Failed to bind {} hotkeys: {:?}",
-                        failed_bindings.len(),
-                        failed_bindings
-                    ),
+                    message: "Unknown or expired session".to_string(),
+                }
+            }
+        }
+
+        IPCRequest::Trigger { identifier } => {
+            if !*allow_test_triggers {
+                IPCResponse::Error {
+                    message: "Test triggers are not enabled on this server".to_string(),
+                }
+            } else if !manager.is_bound(&identifier) {
+                IPCResponse::Error {
+                    message: format!("No hotkey bound for identifier: {identifier}"),
+                }
+            } else {
+                IPCResponse::Success {
+                    message: format!("Triggering {identifier}"),
+                    data: None,
                 }
             }
         }
     }
 }
 
+/// Pair each key, in canonical form, with its identifier.
+fn canonical_pairs(keys: &[Key]) -> Vec<(String, Key)> {
+    keys.iter()
+        .map(Key::canonical)
+        .map(|key| (key.canonical_string(), key))
+        .collect()
+}
+
+/// The response to a group operation, with the number of keys affected as
+/// data.
+fn group_response(verb: &str, group: &str, result: Result<usize>) -> IPCResponse {
+    match result {
+        Ok(count) => IPCResponse::Success {
+            message: format!("{verb} {count} keys in group {group}"),
+            data: Some(serde_json::Value::from(count)),
+        },
+        Err(e) => IPCResponse::Error {
+            message: format!("Failed to update group {group}: {e}"),
+        },
+    }
+}
+
+/// Replace all bindings with the given keys, forwarding their presses as
+/// events. Keys that were already bound stay registered throughout.
+///
+/// The operation is atomic - if any binding fails, all are unbound.
+fn rebind(manager: &HotkeyManager, keys: &[Key], event_sender: &EventSender) -> IPCResponse {
+    let key_pairs = canonical_pairs(keys);
+
+    // Reject a batch with duplicates before touching the current bindings
+    if let Err(e) = check_duplicates(&key_pairs) {
+        return IPCResponse::Error {
+            message: e.to_string(),
+        };
+    }
+
+    // Use the existing event sender for creating callbacks
+    debug!("Creating event forwarder with existing event sender");
+    let callback = create_event_forwarder(event_sender.clone());
+
+    // Swap in the new hotkeys, leaving keys bound before and after, such as
+    // global keys present in every mode, registered throughout
+    debug!("Rebinding to {} hotkeys", key_pairs.len());
+    let results = match manager.rebind_multiple(&key_pairs, callback) {
+        Ok(results) => results,
+        Err(e) => {
+            return IPCResponse::Error {
+                message: format!("Failed to replace existing hotkeys: {e}"),
+            }
+        }
+    };
+
+    // Check if any bindings failed
+    let mut failed_bindings = Vec::new();
+    let mut successful_count = 0;
+
+    for (idx, result) in results.iter().enumerate() {
+        match result {
+            Ok(_) => successful_count += 1,
+            Err(e @ Error::KeyInUseBySystem(_)) => failed_bindings.push(e.to_string()),
+            Err(e) => failed_bindings.push(format!("{}: {e}", key_pairs[idx].0)),
+        }
+    }
+
+    if failed_bindings.is_empty() {
+        manager.collector().rebind();
+        IPCResponse::Success {
+            message: format!("Successfully bound {successful_count} hotkeys"),
+            data: None,
+        }
+    } else {
+        // If any failed, unbind all to maintain atomicity
+        let _ = manager.unbind_all();
+        IPCResponse::Error {
+            message: format!(
+                "Failed to bind {} hotkeys: {}",
+                failed_bindings.len(),
+                failed_bindings.join("; ")
+            ),
+        }
+    }
+}
+
+/// Bind some keys and unbind others, forwarding presses of the new keys as
+/// events, and leaving every other binding in place.
+///
+/// If any key fails to bind, the keys bound are unbound again and the keys
+/// unbound are bound again.
+fn update_bindings(
+    manager: &HotkeyManager,
+    bind: &[Key],
+    unbind: &[Key],
+    event_sender: &EventSender,
+) -> IPCResponse {
+    let key_pairs = canonical_pairs(bind);
+    if let Err(e) = check_duplicates(&key_pairs) {
+        return IPCResponse::Error {
+            message: e.to_string(),
+        };
+    }
+
+    // Unbind first, so that a key can move between the lists
+    let unbound = match manager.unbind_keys(unbind) {
+        Ok(count) => count,
+        Err(e) => {
+            return IPCResponse::Error {
+                message: format!("Failed to unbind hotkeys: {e}"),
+            }
+        }
+    };
+
+    let callback = create_event_forwarder(event_sender.clone());
+    let results = match manager.bind_multiple(&key_pairs, callback.clone()) {
+        Ok(results) => results,
+        Err(e) => {
+            return IPCResponse::Error {
+                message: e.to_string(),
+            }
+        }
+    };
+    let mut bound = Vec::new();
+    let mut failed = Vec::new();
+    for (result, (identifier, key)) in results.into_iter().zip(&key_pairs) {
+        match result {
+            Ok(_) => bound.push(key.clone()),
+            Err(e @ Error::KeyInUseBySystem(_)) => failed.push(e.to_string()),
+            Err(e) => failed.push(format!("{identifier}: {e}")),
+        }
+    }
+
+    if failed.is_empty() {
+        manager.collector().rebind();
+        return IPCResponse::Success {
+            message: format!("Bound {} and unbound {unbound} hotkeys", bound.len()),
+            data: None,
+        };
+    }
+
+    // Roll back to the previous bindings
+    if let Err(e) = manager.unbind_keys(&bound) {
+        warn!("Failed to roll back new bindings: {}", e);
+    }
+    if let Ok(results) = manager.bind_multiple(&canonical_pairs(unbind), callback) {
+        for e in results.into_iter().filter_map(|result| result.err()) {
+            warn!("Failed to restore a binding: {}", e);
+        }
+    }
+    IPCResponse::Error {
+        message: format!(
+            "Failed to bind {} hotkeys: {}",
+            failed.len(),
+            failed.join("; ")
+        ),
+    }
+}
+
+/// Let a handler process a key press or the end of a chord timeout, then
+/// bind the keys for its new state.
+///
+/// Returns the events to send to the client, and how long to wait for the
+/// next key of a sequence, if the handler is in the middle of one.
+fn update_hosted(
+    handler: &Mutex<Box<dyn Handler>>,
+    manager: &HotkeyManager,
+    event_sender: &EventSender,
+    update: impl FnOnce(&mut dyn Handler) -> Vec<IPCResponse>,
+) -> (Vec<IPCResponse>, Option<Duration>) {
+    let (mut events, keys, timeout) = {
+        let mut handler = handler.lock().expect("handler mutex poisoned");
+        let events = update(handler.as_mut());
+        (events, handler.keys(), handler.chord_timeout())
+    };
+    if let IPCResponse::Error { message } = rebind(manager, &keys, event_sender) {
+        events.push(IPCResponse::Message {
+            text: message,
+            warning: true,
+        });
+    }
+    (events, timeout)
+}
+
+/// The keys to bind and the keys to unbind to get from `old` to `new`
+fn diff_keys(old: &[Key], new: &[Key]) -> (Vec<Key>, Vec<Key>) {
+    let old_keys: HashSet<&Key> = old.iter().collect();
+    let new_keys: HashSet<&Key> = new.iter().collect();
+    let bind = new
+        .iter()
+        .filter(|key| !old_keys.contains(key))
+        .cloned()
+        .collect();
+    let unbind = old
+        .iter()
+        .filter(|key| !new_keys.contains(key))
+        .cloned()
+        .collect();
+    (bind, unbind)
+}
+
+/// The error for a response that doesn't fit the request
+fn unexpected(response: IPCResponse) -> Error {
+    Error::ProtocolError(format!("Unexpected response: {response:?}"))
+}
+
 /// IPC client for connecting to a hotkey manager server.
 ///
 /// The client connects to a server via Unix domain socket and can
@@ -328,74 +1699,449 @@ impl IPCClient {
     /// and events. The server must be running and listening on the socket
     /// path for this to succeed.
     pub async fn connect(&self) -> Result<IPCConnection> {
-        let stream = UnixStream::connect(&self.socket_path).await?;
-        Ok(IPCConnection { stream })
+        let stream = UnixStream::connect(&self.socket_path)
+            .await
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::NotFound => {
+                    Error::ConnectionRefused {
+                        path: self.socket_path.clone(),
+                        source: e,
+                    }
+                }
+                _ => Error::Io(e),
+            })?;
+        Ok(IPCConnection::new(stream))
     }
 }
 
+/// A request waiting to be sent by a connection's task, with where its
+/// reply goes
+type Outgoing = (
+    IPCRequest,
+    tokio::sync::oneshot::Sender<Result<IPCResponse>>,
+);
+
 /// An active connection to an IPC server.
 ///
 /// This struct provides methods to interact with the server, including
 /// querying hotkey state and receiving events. All communication is
 /// asynchronous and uses a length-prefixed binary protocol.
+///
+/// A task owns the socket. Request methods take `&self`, so several requests
+/// can be in flight at once, for instance with `tokio::join!`. The server
+/// answers requests in the order it receives them, which is how the task
+/// matches replies to requests. Everything else the server sends, such as
+/// hotkey events, is read with [`recv_event`](Self::recv_event).
+///
+/// Waiting is cancel safe: requests and [`recv_event`](Self::recv_event)
+/// can be raced against timeouts without corrupting the stream. The reply to
+/// a request that was given up on is discarded when it arrives.
 pub struct IPCConnection {
-    stream: UnixStream,
+    /// Requests for the task to send
+    outgoing: tokio::sync::mpsc::UnboundedSender<Outgoing>,
+    /// Messages other than replies, from the task
+    events: tokio::sync::mpsc::UnboundedReceiver<Result<IPCResponse>>,
+    /// Sequence number of the last hotkey event received
+    last_seq: Arc<Mutex<Option<u64>>>,
+    /// How long to wait for the reply to each request
+    request_timeout: Option<Duration>,
 }
 
 impl IPCConnection {
-    /// Send a request to the server using the length-prefixed protocol.
-    ///
-    /// Messages are encoded as JSON and prefixed with a 4-byte big-endian
-    /// length header for proper framing over the stream connection.
-    async fn send_request(&mut self, request: &IPCRequest) -> Result<()> {
-        let data = serde_json::to_vec(request)?;
-        let len_bytes = (data.len() as u32).to_be_bytes();
-        self.stream.write_all(&len_bytes).await?;
-        self.stream.write_all(&data).await?;
-        self.stream.flush().await?;
-        Ok(())
-    }
-
-    /// Receive a response from the server using the length-prefixed protocol.
+    /// Wrap a stream connected to a server, starting the task that owns it
+    pub(crate) fn new(stream: UnixStream) -> Self {
+        let (outgoing, requests) = tokio::sync::mpsc::unbounded_channel();
+        let (event_tx, events) = tokio::sync::mpsc::unbounded_channel();
+        let last_seq = Arc::new(Mutex::new(None));
+        tokio::spawn(drive_connection(
+            Framed::new(stream, ClientCodec::new()),
+            requests,
+            event_tx,
+            last_seq.clone(),
+        ));
+        Self {
+            outgoing,
+            events,
+            last_seq,
+            request_timeout: None,
+        }
+    }
+
+    /// Give up on requests that the server hasn't replied to within
+    /// `timeout`, with a [`Timeout`](Error::Timeout) error (default: wait
+    /// indefinitely).
     ///
-    /// Reads the 4-byte length header first, then reads exactly that many
-    /// bytes and decodes the JSON response.
-    async fn recv_response(&mut self) -> Result<IPCResponse> {
-        let mut len_bytes = [0u8; 4];
-        self.stream.read_exact(&mut len_bytes).await?;
-        let len = u32::from_be_bytes(len_bytes) as usize;
+    /// A capture may take as long as its own timeout on top of this.
+    pub fn with_request_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.set_request_timeout(timeout);
+        self
+    }
+
+    /// Set how long to wait for replies, as with
+    /// [`with_request_timeout`](Self::with_request_timeout).
+    pub fn set_request_timeout(&mut self, timeout: Option<Duration>) {
+        self.request_timeout = timeout;
+    }
 
-        let mut data = vec![0u8; len];
-        self.stream.read_exact(&mut data).await?;
+    /// Send a request and wait for the reply, within the request timeout.
+    async fn request(&self, request: IPCRequest) -> Result<IPCResponse> {
+        self.request_within(request, Duration::ZERO).await
+    }
+
+    /// Send a request and wait for the reply, allowing `extra` time on top
+    /// of the request timeout.
+    async fn request_within(&self, request: IPCRequest, extra: Duration) -> Result<IPCResponse> {
+        let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+        self.outgoing
+            .send((request, reply_tx))
+            .map_err(|_| Error::ConnectionClosed)?;
+        let reply = async { reply_rx.await.unwrap_or(Err(Error::ConnectionClosed)) };
+        match self.request_timeout.map(|limit| limit + extra) {
+            Some(limit) => {
+                tokio::time::timeout(limit, reply)
+                    .await
+                    .map_err(|_| Error::Timeout {
+                        operation: format!("waiting {limit:?} for a reply from the server"),
+                    })?
+            }
+            None => reply.await,
+        }
+    }
 
-        let response: IPCResponse = serde_json::from_slice(&data)?;
-        Ok(response)
+    /// Get the sequence number of the last hotkey event received, for
+    /// resuming a session without receiving it again.
+    pub fn last_sequence(&self) -> Option<u64> {
+        *self.last_seq.lock().expect("last_seq mutex poisoned")
     }
 
-    /// Send a shutdown request to the server.
+    /// Send a shutdown request to the server, and wait for it to finish
+    /// cleaning up.
     ///
     /// This requests a graceful shutdown of the server. In single-client mode,
     /// the server will also shut down automatically when the client disconnects,
     /// but sending an explicit shutdown is recommended for clean termination.
+    /// Events that arrive before the acknowledgment are discarded. Servers
+    /// that predate the acknowledgment are done when they close the
+    /// connection.
     pub async fn shutdown(&mut self) -> Result<()> {
-        self.send_request(&IPCRequest::Shutdown).await?;
-        Ok(())
+        let acknowledged = async {
+            match self.request(IPCRequest::Shutdown).await {
+                Ok(IPCResponse::Error { message }) => return Err(Error::Ipc(message)),
+                Ok(_) => {}
+                Err(Error::ConnectionClosed) => return Ok(()),
+                Err(e) => return Err(e),
+            }
+            loop {
+                match self.recv_event().await {
+                    Ok(IPCResponse::ShutdownComplete) => return Ok(()),
+                    Ok(other) => trace!("Discarding message during shutdown: {:?}", other),
+                    Err(Error::ConnectionClosed) => return Ok(()),
+                    Err(e) => return Err(e),
+                }
+            }
+        };
+        tokio::time::timeout(SHUTDOWN_TIMEOUT, acknowledged)
+            .await
+            .map_err(|_| Error::Timeout {
+                operation: "waiting for the server to shut down".to_string(),
+            })?
+    }
+
+    /// Check that the server is responsive.
+    pub async fn ping(&self) -> Result<()> {
+        match self.request(IPCRequest::Ping).await? {
+            IPCResponse::Success { .. } => Ok(()),
+            IPCResponse::Error { message } => Err(Error::Ipc(message)),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Ping the server and get the version it reports.
+    ///
+    /// Returns `None` for servers that predate version reporting.
+    pub async fn server_version(&self) -> Result<Option<String>> {
+        match self.request(IPCRequest::Ping).await? {
+            IPCResponse::Success { data, .. } => {
+                Ok(data.and_then(|data| data.as_str().map(String::from)))
+            }
+            IPCResponse::Error { message } => Err(Error::Ipc(message)),
+            other => Err(unexpected(other)),
+        }
     }
 
     /// Rebind all hotkeys, replacing the current configuration.
     ///
     /// This operation is atomic - if any binding fails, all existing hotkeys
     /// are restored.
-    pub async fn rebind(&mut self, keys: &[Key]) -> Result<()> {
-        self.send_request(&IPCRequest::Rebind {
-            keys: keys.to_vec(),
+    pub async fn rebind(&self, keys: &[Key]) -> Result<()> {
+        match self
+            .request(IPCRequest::Rebind {
+                keys: keys.to_vec(),
+            })
+            .await?
+        {
+            IPCResponse::Success { .. } => Ok(()),
+            IPCResponse::Error { message } => Err(Error::Ipc(message)),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Change the bindings from `old` to `new`, unbinding only the keys that
+    /// are no longer wanted and binding only those that are new.
+    ///
+    /// Keys in both stay registered with the OS throughout, so moving
+    /// between modes that share most of their keys doesn't drop and
+    /// re-register them. `old` should be the keys last bound, with
+    /// [`rebind`](Self::rebind) or this method. If any key fails to bind,
+    /// the bindings are left as they were.
+    pub async fn rebind_diff(&self, old: &[Key], new: &[Key]) -> Result<()> {
+        let (bind, unbind) = diff_keys(old, new);
+        if bind.is_empty() && unbind.is_empty() {
+            return Ok(());
+        }
+        match self
+            .request(IPCRequest::UpdateBindings { bind, unbind })
+            .await?
+        {
+            IPCResponse::Success { .. } => Ok(()),
+            IPCResponse::Error { message } => Err(Error::Ipc(message)),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Bind a key in addition to the current configuration until `ttl` has
+    /// passed, or until its first press if `once` is set.
+    ///
+    /// Presses are delivered as HotkeyTriggered events, like any other
+    /// binding. A rebind also removes temporary bindings.
+    pub async fn bind_temporary(
+        &self,
+        key: &Key,
+        ttl: std::time::Duration,
+        once: bool,
+    ) -> Result<()> {
+        match self
+            .request(IPCRequest::BindTemporary {
+                key: key.clone(),
+                ttl_ms: ttl.as_millis() as u64,
+                once,
+            })
+            .await?
+        {
+            IPCResponse::Success { .. } => Ok(()),
+            IPCResponse::Error { message } => Err(Error::Ipc(message)),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Bind keys as a named group, alongside the current configuration.
+    ///
+    /// Presses are delivered as HotkeyTriggered events. A rebind also
+    /// removes groups.
+    pub async fn bind_group(&self, group: &str, keys: &[Key]) -> Result<()> {
+        match self
+            .request(IPCRequest::BindGroup {
+                group: group.to_string(),
+                keys: keys.to_vec(),
+            })
+            .await?
+        {
+            IPCResponse::Success { .. } => Ok(()),
+            IPCResponse::Error { message } => Err(Error::Ipc(message)),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Temporarily unregister the keys in a group, returning how many were
+    /// disabled.
+    pub async fn disable_group(&self, group: &str) -> Result<usize> {
+        self.group_request(IPCRequest::DisableGroup {
+            group: group.to_string(),
+        })
+        .await
+    }
+
+    /// Register the keys in a disabled group again, returning how many were
+    /// enabled.
+    pub async fn enable_group(&self, group: &str) -> Result<usize> {
+        self.group_request(IPCRequest::EnableGroup {
+            group: group.to_string(),
         })
-        .await?;
+        .await
+    }
+
+    /// Remove every key in a group, returning how many were unbound.
+    pub async fn unbind_group(&self, group: &str) -> Result<usize> {
+        self.group_request(IPCRequest::UnbindGroup {
+            group: group.to_string(),
+        })
+        .await
+    }
+
+    async fn group_request(&self, request: IPCRequest) -> Result<usize> {
+        match self.request(request).await? {
+            IPCResponse::Success {
+                data: Some(data), ..
+            } => Ok(serde_json::from_value(data)?),
+            IPCResponse::Error { message } => Err(Error::Ipc(message)),
+            other => Err(unexpected(other)),
+        }
+    }
 
-        match self.recv_response().await? {
+    /// Simulate a press of a bound hotkey.
+    ///
+    /// The server must have been started with test triggers enabled. On
+    /// success, a HotkeyTriggered event for the key follows and can be read
+    /// with [`recv_event`](Self::recv_event).
+    pub async fn trigger(&self, identifier: &str) -> Result<()> {
+        match self
+            .request(IPCRequest::Trigger {
+                identifier: identifier.to_string(),
+            })
+            .await?
+        {
             IPCResponse::Success { .. } => Ok(()),
             IPCResponse::Error { message } => Err(Error::Ipc(message)),
-            _ => Err(Error::Ipc("Unexpected response".to_string())),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Wait for the user to press a key combination and return it.
+    ///
+    /// The server's current bindings are suspended while waiting, so the
+    /// press is not delivered as a HotkeyTriggered event. Returns `None` if
+    /// no key is pressed within the timeout.
+    pub async fn capture(&self, timeout: std::time::Duration) -> Result<Option<Key>> {
+        let request = IPCRequest::Capture {
+            timeout_ms: timeout.as_millis() as u64,
+        };
+        match self.request_within(request, timeout).await? {
+            IPCResponse::Success { data: None, .. } => Ok(None),
+            IPCResponse::Success {
+                data: Some(data), ..
+            } => Ok(Some(serde_json::from_value(data)?)),
+            IPCResponse::Error { message } => Err(Error::Ipc(message)),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Get how often each hotkey has been triggered since the server
+    /// started, most triggered first.
+    pub async fn get_stats(&self) -> Result<Vec<HotkeyStats>> {
+        match self.request(IPCRequest::GetStats).await? {
+            IPCResponse::Success {
+                data: Some(data), ..
+            } => Ok(serde_json::from_value(data)?),
+            IPCResponse::Error { message } => Err(Error::Ipc(message)),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Choose what the server does with the bindings if this connection
+    /// closes without a [`shutdown`](Self::shutdown), for the rest of the
+    /// connection.
+    ///
+    /// Only the primary client may set it. Returns a token that a later
+    /// connection can [`resume`](Self::resume) the session with.
+    pub async fn set_disconnect_policy(&self, policy: DisconnectPolicy) -> Result<String> {
+        match self
+            .request(IPCRequest::SetDisconnectPolicy { policy })
+            .await?
+        {
+            IPCResponse::Success {
+                data: Some(serde_json::Value::String(token)),
+                ..
+            } => Ok(token),
+            IPCResponse::Error { message } => Err(Error::Ipc(message)),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Resume the session of a previous connection, which disconnected
+    /// while the server kept its bindings. The recent hotkey events after
+    /// `last_seq`, the previous connection's
+    /// [`last_sequence`](Self::last_sequence), are delivered next. Without
+    /// it, the events the server didn't deliver are.
+    ///
+    /// This must be the first request other than a ping, as any other
+    /// request starts a new session.
+    pub async fn resume(&mut self, token: &str, last_seq: Option<u64>) -> Result<()> {
+        {
+            let mut last = self.last_seq.lock().expect("last_seq mutex poisoned");
+            *last = (*last).max(last_seq);
+        }
+        match self
+            .request(IPCRequest::Resume {
+                token: token.to_string(),
+                last_seq,
+            })
+            .await?
+        {
+            IPCResponse::Success { .. } => Ok(()),
+            IPCResponse::Error { message } => Err(Error::Ipc(message)),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Get counters and gauges describing the server, for monitoring.
+    pub async fn get_metrics(&self) -> Result<Metrics> {
+        match self.request(IPCRequest::GetMetrics).await? {
+            IPCResponse::Success {
+                data: Some(data), ..
+            } => Ok(serde_json::from_value(data)?),
+            IPCResponse::Error { message } => Err(Error::Ipc(message)),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Ask the server to reload its config.
+    ///
+    /// Only servers with a [`Handler`] have a config to reload. If the new
+    /// config is invalid, the old one stays active and the error is
+    /// [`Error::InvalidConfig`] listing every problem.
+    pub async fn reload_config(&self) -> Result<()> {
+        match self.request(IPCRequest::ReloadConfig).await? {
+            IPCResponse::Success { .. } => Ok(()),
+            IPCResponse::InvalidConfig { errors } => Err(Error::InvalidConfig(errors)),
+            IPCResponse::Error { message } => Err(Error::Ipc(message)),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Send a message to every other client subscribed to a topic.
+    ///
+    /// Returns the number of clients it was delivered to.
+    pub async fn publish(&self, topic: &str, payload: serde_json::Value) -> Result<usize> {
+        match self
+            .request(IPCRequest::Publish {
+                topic: topic.to_string(),
+                payload,
+            })
+            .await?
+        {
+            IPCResponse::Success { data, .. } => Ok(data
+                .and_then(|d| d.as_u64())
+                .map(|n| n as usize)
+                .unwrap_or(0)),
+            IPCResponse::Error { message } => Err(Error::Ipc(message)),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Subscribe to messages published to a topic by other clients.
+    ///
+    /// Messages arrive as Published events, read with
+    /// [`recv_event`](Self::recv_event).
+    pub async fn subscribe(&self, topic: &str) -> Result<()> {
+        match self
+            .request(IPCRequest::Subscribe {
+                topic: topic.to_string(),
+            })
+            .await?
+        {
+            IPCResponse::Success { .. } => Ok(()),
+            IPCResponse::Error { message } => Err(Error::Ipc(message)),
+            other => Err(unexpected(other)),
         }
     }
 
@@ -408,8 +2154,94 @@ impl IPCConnection {
     /// For typical request-response patterns, this is called internally
     /// by the request methods. Call this directly when waiting for
     /// asynchronous hotkey events.
+    ///
+    /// This method is cancel safe: if it is used in `select!` or under a
+    /// timeout and another branch completes first, no message is lost.
     pub async fn recv_event(&mut self) -> Result<IPCResponse> {
-        self.recv_response().await
+        self.events
+            .recv()
+            .await
+            .unwrap_or(Err(Error::ConnectionClosed))
+    }
+}
+
+/// Whether a message from the server is the reply to a request
+fn is_reply(response: &IPCResponse) -> bool {
+    matches!(
+        response,
+        IPCResponse::Success { .. } | IPCResponse::Error { .. } | IPCResponse::InvalidConfig { .. }
+    )
+}
+
+/// Own a client's socket: send requests as they come, hand each reply to
+/// the oldest request still waiting, and pass everything else on as events.
+///
+/// Sequenced events are unwrapped, skipping those already received. Ends
+/// when the connection closes, or the [`IPCConnection`] is dropped.
+async fn drive_connection(
+    mut stream: Framed<UnixStream, ClientCodec>,
+    mut requests: tokio::sync::mpsc::UnboundedReceiver<Outgoing>,
+    events: tokio::sync::mpsc::UnboundedSender<Result<IPCResponse>>,
+    last_seq: Arc<Mutex<Option<u64>>>,
+) {
+    let mut waiting = VecDeque::new();
+    loop {
+        tokio::select! {
+            request = requests.recv() => {
+                let Some((request, reply)) = request else {
+                    break;
+                };
+                match stream.send(&request).await.map_err(connection_error) {
+                    Ok(()) => waiting.push_back(reply),
+                    Err(e) => {
+                        let _ = reply.send(Err(e));
+                    }
+                }
+            }
+            message = stream.next() => {
+                let message = match message {
+                    Some(Ok(message)) => message,
+                    Some(Err(e)) => {
+                        let _ = events.send(Err(connection_error(e)));
+                        break;
+                    }
+                    None => {
+                        let _ = events.send(Err(Error::ConnectionClosed));
+                        break;
+                    }
+                };
+                let event = match message {
+                    Ok(IPCResponse::Sequenced { seq, event }) => {
+                        let mut last = last_seq.lock().expect("last_seq mutex poisoned");
+                        if last.is_some_and(|last| seq <= last) {
+                            trace!("Skipping hotkey event {} already received", seq);
+                            continue;
+                        }
+                        *last = Some(seq);
+                        Ok(*event)
+                    }
+                    Ok(response) if is_reply(&response) => {
+                        match waiting.pop_front() {
+                            Some(reply) => {
+                                if let Err(response) = reply.send(Ok(response)) {
+                                    trace!("Discarding reply to an abandoned request: {:?}", response);
+                                }
+                            }
+                            None => {
+                                let _ = events.send(Ok(response));
+                            }
+                        }
+                        continue;
+                    }
+                    message => message,
+                };
+                let _ = events.send(event);
+            }
+        }
+    }
+    // Requests still waiting won't get a reply
+    for reply in waiting {
+        let _ = reply.send(Err(Error::ConnectionClosed));
     }
 }
 
@@ -422,32 +2254,208 @@ impl IPCConnection {
 /// Use this with the event_sender from an IPCServer to bridge hotkey
 /// events to the IPC client. The callback is thread-safe and can be cloned
 /// for multiple hotkeys.
-pub(crate) fn create_event_forwarder_with_key_map(
-    event_sender: Arc<Mutex<Option<tokio::sync::mpsc::UnboundedSender<IPCResponse>>>>,
-    key_map: std::collections::HashMap<String, Key>,
-) -> impl Fn(&str) + Send + Sync + Clone + 'static {
-    let key_map = Arc::new(key_map);
-    move |identifier| {
-        trace!("Event forwarder called for identifier: '{}'", identifier);
-        if let Some(sender) = event_sender
-            .lock()
-            .expect("event_sender mutex poisoned")
-            .as_ref()
-        {
-            if let Some(key) = key_map.get(identifier) {
-                debug!("Sending HotkeyTriggered event for key: '{}'", key);
-                match sender.send(IPCResponse::HotkeyTriggered(key.clone())) {
-                    Ok(_) => trace!("HotkeyTriggered event sent successfully"),
-                    Err(e) => error!("Failed to send HotkeyTriggered event: {:?}", e),
-                }
-            } else {
-                error!("No key found in map for identifier: '{}'", identifier);
+pub(crate) fn create_event_forwarder(
+    event_sender: EventSender,
+) -> impl Fn(&HotkeyEvent) + Send + Sync + Clone + 'static {
+    move |event| {
+        trace!(
+            "Event forwarder called for identifier: '{}' (id: {}, state: {:?}, {:?} after the event)",
+            event.identifier,
+            event.id,
+            event.state,
+            event.timestamp.elapsed()
+        );
+        let mut sink = event_sender.lock().expect("event_sender mutex poisoned");
+        let sequenced = sink
+            .replay
+            .record(IPCResponse::HotkeyTriggered(event.key.clone()));
+        if let Some(sender) = &sink.sender {
+            debug!("Sending HotkeyTriggered event for key: '{}'", event.key);
+            match sender.send(sequenced) {
+                Ok(_) => trace!("HotkeyTriggered event sent successfully"),
+                Err(e) => error!("Failed to send HotkeyTriggered event: {:?}", e),
             }
         } else {
             warn!(
-                "No event sender available to forward hotkey event for identifier: '{}'",
-                identifier
+                "No client connected to forward hotkey event for identifier: '{}', keeping it for replay",
+                event.identifier
             );
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triggered(key: &str) -> IPCResponse {
+        IPCResponse::HotkeyTriggered(Key::parse(key).unwrap())
+    }
+
+    fn seqs(events: &[IPCResponse]) -> Vec<u64> {
+        events
+            .iter()
+            .map(|event| match event {
+                IPCResponse::Sequenced { seq, .. } => *seq,
+                other => panic!("unexpected event: {other:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_replay() {
+        let mut replay = Replay::default();
+        for _ in 0..REPLAY_CAPACITY + 2 {
+            replay.record(triggered("cmd+a"));
+        }
+        // Only the newest events are kept
+        let kept = replay.since(0, None);
+        assert_eq!(kept.len(), REPLAY_CAPACITY);
+        assert_eq!(seqs(&kept)[0], 3);
+        assert_eq!(seqs(&replay.since(64, None)), vec![65, 66]);
+        assert!(replay.since(66, None).is_empty());
+        assert_eq!(replay.since(64, Some(REPLAY_WINDOW)).len(), 2);
+        assert!(replay.since(64, Some(Duration::ZERO)).is_empty());
+    }
+
+    #[test]
+    fn test_diff_keys() {
+        let keys =
+            |keys: &[&str]| -> Vec<Key> { keys.iter().map(|k| Key::parse(k).unwrap()).collect() };
+        let (bind, unbind) = diff_keys(
+            &keys(&["a", "ctrl+b", "esc"]),
+            &keys(&["control+b", "esc", "c"]),
+        );
+        assert_eq!(bind, keys(&["c"]));
+        assert_eq!(unbind, keys(&["a"]));
+
+        let (bind, unbind) = diff_keys(&keys(&["a"]), &keys(&["a"]));
+        assert!(bind.is_empty() && unbind.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_skip_received_events() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let (_, writer) = server.into_split();
+        let writer = tokio::sync::Mutex::new(FramedWrite::new(writer, ServerCodec::new()));
+        let mut replay = Replay::default();
+        let first = replay.record(triggered("cmd+a"));
+        let second = replay.record(triggered("cmd+b"));
+        for event in [&first, &first, &second] {
+            write_message(&writer, event).await.unwrap();
+        }
+        write_message(&writer, &IPCResponse::ShutdownComplete)
+            .await
+            .unwrap();
+
+        let mut connection = IPCConnection::new(client);
+        assert!(matches!(
+            connection.recv_event().await.unwrap(),
+            IPCResponse::HotkeyTriggered(key) if key == Key::parse("cmd+a").unwrap()
+        ));
+        // The repeated event is skipped
+        assert!(matches!(
+            connection.recv_event().await.unwrap(),
+            IPCResponse::HotkeyTriggered(key) if key == Key::parse("cmd+b").unwrap()
+        ));
+        assert_eq!(connection.last_sequence(), Some(2));
+        assert!(matches!(
+            connection.recv_event().await.unwrap(),
+            IPCResponse::ShutdownComplete
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        use tokio::io::AsyncWriteExt;
+
+        let (client, server) = UnixStream::pair().unwrap();
+        let (reader, writer) = server.into_split();
+        let mut requests = FramedRead::new(reader, ServerCodec::new());
+        let writer = tokio::sync::Mutex::new(FramedWrite::new(writer, ServerCodec::new()));
+        let mut connection =
+            IPCConnection::new(client).with_request_timeout(Some(Duration::from_millis(50)));
+
+        // The server doesn't reply in time
+        let err = connection.ping().await.unwrap_err();
+        assert!(matches!(err, Error::Timeout { .. }));
+        assert!(requests.next().await.unwrap().unwrap().is_ok());
+
+        // The late reply is discarded, and the next request gets its own
+        let late = IPCResponse::Success {
+            message: "pong".to_string(),
+            data: None,
+        };
+        write_message(&writer, &late).await.unwrap();
+        let server = async {
+            assert!(requests.next().await.unwrap().unwrap().is_ok());
+            let version = IPCResponse::Success {
+                message: "pong".to_string(),
+                data: Some(serde_json::json!("1.0")),
+            };
+            write_message(&writer, &version).await.unwrap();
+        };
+        let (version, ()) = tokio::join!(connection.server_version(), server);
+        assert_eq!(version.unwrap(), Some("1.0".to_string()));
+
+        // A partly received message survives a cancelled receive
+        let frame = crate::codec::encode(&triggered("cmd+a")).unwrap();
+        let (head, tail) = frame.split_at(frame.len() / 2);
+        let mut raw = writer.into_inner().into_inner();
+        raw.write_all(head).await.unwrap();
+        let waited = tokio::time::timeout(Duration::from_millis(20), connection.recv_event()).await;
+        assert!(waited.is_err());
+        raw.write_all(tail).await.unwrap();
+        assert!(matches!(
+            connection.recv_event().await.unwrap(),
+            IPCResponse::HotkeyTriggered(key) if key == Key::parse("cmd+a").unwrap()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_requests() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let (reader, writer) = server.into_split();
+        let mut requests = FramedRead::new(reader, ServerCodec::new());
+        let writer = tokio::sync::Mutex::new(FramedWrite::new(writer, ServerCodec::new()));
+        let mut connection = IPCConnection::new(client);
+
+        // Both requests are sent before either is answered, with an event in
+        // between the replies
+        let server = async {
+            let mut received = Vec::new();
+            for _ in 0..2 {
+                received.push(requests.next().await.unwrap().unwrap().unwrap());
+            }
+            let pong = IPCResponse::Success {
+                message: "pong".to_string(),
+                data: Some(serde_json::json!("1.0")),
+            };
+            let stats = IPCResponse::Success {
+                message: "stats".to_string(),
+                data: Some(serde_json::json!([])),
+            };
+            write_message(&writer, &pong).await.unwrap();
+            write_message(&writer, &triggered("cmd+a")).await.unwrap();
+            write_message(&writer, &stats).await.unwrap();
+            received
+        };
+        let (version, stats, received) =
+            tokio::join!(connection.server_version(), connection.get_stats(), server);
+        assert_eq!(received, vec![IPCRequest::Ping, IPCRequest::GetStats]);
+        assert_eq!(version.unwrap(), Some("1.0".to_string()));
+        assert!(stats.unwrap().is_empty());
+        assert!(matches!(
+            connection.recv_event().await.unwrap(),
+            IPCResponse::HotkeyTriggered(key) if key == Key::parse("cmd+a").unwrap()
+        ));
+
+        // Closing the connection fails requests still waiting
+        drop(requests);
+        drop(writer);
+        assert!(matches!(
+            connection.ping().await,
+            Err(Error::ConnectionClosed | Error::ConnectionRefused { .. })
+        ));
+    }
+}
@@ -1,13 +1,35 @@
+//! Key definitions, and the names they are parsed from.
+//!
+//! Besides the built-in names, [`register_alias`] adds names of your own,
+//! such as the names of keys in another language.
+
 use crate::error::{Error, Result};
 use global_hotkey::hotkey::{Code, HotKey, Modifiers};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
+use std::sync::{OnceLock, RwLock};
+
+/// The modifiers that keys can be bound with
+const SUPPORTED_MODIFIERS: Modifiers = Modifiers::CONTROL
+    .union(Modifiers::ALT)
+    .union(Modifiers::SHIFT)
+    .union(Modifiers::SUPER);
 
 /// A unified key definition that can be parsed, serialized, and converted to HotKey
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+///
+/// Keys compare and hash by their [canonical form](Key::canonical), so
+/// different spellings of the same chord are equal. Modifiers serialize as
+/// an array of names, such as `["ctrl", "shift"]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Key {
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "modifier_names"
+    )]
     pub modifiers: Option<Modifiers>,
     pub code: Code,
 }
@@ -25,7 +47,17 @@ impl Key {
     /// - "ctrl+a" (with modifiers)
     /// - "cmd+shift+a" (multiple modifiers)
     /// - "control+alt+delete" (alternative names)
+    /// - "A" or "cmd+?" (shifted characters, meaning "shift+a" and
+    ///   "shift+cmd+slash")
+    ///
+    /// Shifted characters follow the US layout. Use [`Key::parse_with`] to
+    /// read them as their unshifted keys instead.
     pub fn parse(s: &str) -> Result<Self> {
+        Key::parse_with(s, &ParseOptions::default())
+    }
+
+    /// Parse a key from a string representation, with options
+    pub fn parse_with(s: &str, options: &ParseOptions) -> Result<Self> {
         // Split by '+' to separate modifiers and key
         let parts: Vec<&str> = s.split('+').map(|p| p.trim()).collect();
 
@@ -38,8 +70,18 @@ impl Key {
         let key_part = parts.last().unwrap();
         let modifier_parts = &parts[..parts.len() - 1];
 
-        // Parse the key code
-        let code = parse_code(key_part)?;
+        // Parse the key code, with any modifiers that come with it, from a
+        // character typed with shift or an alias
+        let (code, implied) = match parse_shifted(key_part) {
+            Some(code) if options.shifted_characters => (code, Some(Modifiers::SHIFT)),
+            _ => match parse_builtin_code(key_part) {
+                Ok(code) => (code, None),
+                Err(err) => {
+                    let key = options.alias(key_part).ok_or(err)?;
+                    (key.code, key.modifiers)
+                }
+            },
+        };
 
         // Parse modifiers
         let modifiers = if modifier_parts.is_empty() {
@@ -47,28 +89,122 @@ impl Key {
         } else {
             let mut mods = Modifiers::empty();
             for part in modifier_parts {
-                match part.to_lowercase().as_str() {
-                    "ctrl" | "control" => mods |= Modifiers::CONTROL,
-                    "alt" | "option" => mods |= Modifiers::ALT,
-                    "shift" => mods |= Modifiers::SHIFT,
-                    "cmd" | "command" | "super" | "win" | "windows" | "meta" => {
-                        mods |= Modifiers::SUPER
-                    }
-                    _ => return Err(Error::InvalidKey(format!("Unknown modifier: {part}"))),
-                }
+                mods |= parse_modifier(part)
+                    .ok_or_else(|| Error::InvalidKey(format!("Unknown modifier: {part}")))?;
             }
             Some(mods)
         };
+        let modifiers = match (modifiers, implied) {
+            (None, None) => None,
+            (mods, implied) => Some(mods.unwrap_or_default() | implied.unwrap_or_default()),
+        };
 
         Ok(Key { code, modifiers })
     }
 
+    /// All supported key codes combined with every combination of modifiers
+    ///
+    /// Used to listen for an arbitrary key press when capturing a shortcut.
+    pub(crate) fn all() -> Vec<Self> {
+        let modifiers = [
+            Modifiers::CONTROL,
+            Modifiers::ALT,
+            Modifiers::SHIFT,
+            Modifiers::SUPER,
+        ];
+        let mut keys = Vec::new();
+        for mask in 0..(1 << modifiers.len()) {
+            let mods = modifiers
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| mask & (1 << i) != 0)
+                .fold(Modifiers::empty(), |acc, (_, m)| acc | *m);
+            for code in CODES {
+                keys.push(Key::from(HotKey::new(Some(mods), *code)));
+            }
+        }
+        keys
+    }
+
+    /// The canonical form of this key
+    ///
+    /// `meta` becomes `cmd`, modifiers that can't be bound are dropped, and
+    /// an empty modifier set becomes `None`.
+    pub fn canonical(&self) -> Key {
+        let modifiers = self.modifiers.map(|mods| {
+            let mut canonical = mods & SUPPORTED_MODIFIERS;
+            if mods.contains(Modifiers::META) {
+                canonical |= Modifiers::SUPER;
+            }
+            canonical
+        });
+        Key {
+            modifiers: modifiers.filter(|mods| !mods.is_empty()),
+            code: self.code,
+        }
+    }
+
+    /// The identifier of this key, the same for every spelling of the chord
+    ///
+    /// Modifiers come in a fixed order, so `"shift+cmd+A"` and
+    /// `"command+shift+a"` are both `"shift+cmd+a"`. The server uses this to
+    /// identify keys, and it parses back to an equal key.
+    pub fn canonical_string(&self) -> String {
+        self.canonical().to_string()
+    }
+
+    /// The macOS feature that uses this key by default, if any
+    ///
+    /// Used to explain why a key can't be bound. Users can reassign these
+    /// shortcuts in System Settings, so this is a likely owner, not a
+    /// certain one.
+    pub fn system_owner(&self) -> Option<&'static str> {
+        let owner = match self.canonical_string().as_str() {
+            "cmd+space" => "Spotlight",
+            "alt+cmd+space" => "Finder search",
+            "ctrl+space" | "ctrl+alt+space" => "input source switching",
+            "cmd+tab" | "shift+cmd+tab" => "the application switcher",
+            "alt+cmd+escape" => "Force Quit",
+            "shift+cmd+3" | "shift+cmd+4" | "shift+cmd+5" => "screenshots",
+            "ctrl+up" => "Mission Control",
+            "ctrl+down" => "application windows",
+            "ctrl+left" | "ctrl+right" => "switching spaces",
+            "ctrl+cmd+q" => "Lock Screen",
+            "shift+cmd+q" => "Log Out",
+            _ => return None,
+        };
+        Some(owner)
+    }
+
     /// Convert this Key to a global_hotkey HotKey
     pub fn to_hotkey(&self) -> HotKey {
         HotKey::new(self.modifiers, self.code)
     }
 }
 
+impl PartialEq for Key {
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b) = (self.canonical(), other.canonical());
+        a.code == b.code && a.modifiers == b.modifiers
+    }
+}
+
+impl Eq for Key {}
+
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let key = self.canonical();
+        key.code.hash(state);
+        key.modifiers.hash(state);
+    }
+}
+
+/// Whether an identifier names a binding, also accepting any spelling of a
+/// key for bindings identified by a key's canonical string
+pub(crate) fn identifier_matches(bound: &str, identifier: &str) -> bool {
+    bound == identifier || Key::parse(identifier).is_ok_and(|key| key.canonical_string() == bound)
+}
+
 impl From<Key> for HotKey {
     fn from(key: Key) -> Self {
         key.to_hotkey()
@@ -123,6 +259,167 @@ impl TryFrom<String> for Key {
     }
 }
 
+/// Options for [`Key::parse_with`]
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    shifted_characters: bool,
+    aliases: Option<AliasRegistry>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            shifted_characters: true,
+            aliases: None,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Set whether uppercase letters and shifted symbols, such as `"A"` and
+    /// `"?"`, add shift to their key (default: true)
+    ///
+    /// When disabled, uppercase letters are the same as lowercase ones, and
+    /// shifted symbols are invalid.
+    pub fn with_shifted_characters(mut self, enabled: bool) -> Self {
+        self.shifted_characters = enabled;
+        self
+    }
+
+    /// Resolve aliases in `aliases`, before those in the process-wide
+    /// registry
+    pub fn with_aliases(mut self, aliases: AliasRegistry) -> Self {
+        self.aliases = Some(aliases);
+        self
+    }
+
+    /// The chord an alias names
+    fn alias(&self, name: &str) -> Option<Key> {
+        if let Some(key) = self.aliases.as_ref().and_then(|a| a.get(name)) {
+            return Some(key.clone());
+        }
+        global_aliases()
+            .read()
+            .expect("aliases lock poisoned")
+            .get(name)
+            .cloned()
+    }
+}
+
+/// Parse a character typed with shift on a US layout into its key
+fn parse_shifted(s: &str) -> Option<Code> {
+    let mut chars = s.chars();
+    let (Some(c), None) = (chars.next(), chars.next()) else {
+        return None;
+    };
+    if c.is_ascii_uppercase() {
+        return parse_builtin_code(&c.to_string()).ok();
+    }
+    let code = match c {
+        '~' => Code::Backquote,
+        '!' => Code::Digit1,
+        '@' => Code::Digit2,
+        '#' => Code::Digit3,
+        '$' => Code::Digit4,
+        '%' => Code::Digit5,
+        '^' => Code::Digit6,
+        '&' => Code::Digit7,
+        '*' => Code::Digit8,
+        '(' => Code::Digit9,
+        ')' => Code::Digit0,
+        '_' => Code::Minus,
+        '{' => Code::BracketLeft,
+        '}' => Code::BracketRight,
+        '|' => Code::Backslash,
+        ':' => Code::Semicolon,
+        '"' => Code::Quote,
+        '<' => Code::Comma,
+        '>' => Code::Period,
+        '?' => Code::Slash,
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Parse the name of a modifier that keys can be bound with
+fn parse_modifier(name: &str) -> Option<Modifiers> {
+    match name.to_lowercase().as_str() {
+        "ctrl" | "control" => Some(Modifiers::CONTROL),
+        "alt" | "option" => Some(Modifiers::ALT),
+        "shift" => Some(Modifiers::SHIFT),
+        "cmd" | "command" | "super" | "win" | "windows" | "meta" => Some(Modifiers::SUPER),
+        _ => None,
+    }
+}
+
+/// Serialize modifiers as an array of names, in the same order as
+/// [`Key`]'s display form.
+///
+/// Modifiers that keys can't be bound with, such as `caps_lock`, keep the
+/// name of their flag. Deserializing also accepts the forms that earlier
+/// versions wrote: the flag bits as a number, or flag names joined by `|`.
+mod modifier_names {
+    use super::{parse_modifier, Modifiers, SUPPORTED_MODIFIERS};
+    use serde::de::Error as _;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        modifiers: &Option<Modifiers>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let Some(mods) = modifiers else {
+            return serializer.serialize_none();
+        };
+        let mut names: Vec<String> = [
+            (Modifiers::CONTROL, "ctrl"),
+            (Modifiers::ALT, "alt"),
+            (Modifiers::SHIFT, "shift"),
+            (Modifiers::SUPER, "cmd"),
+        ]
+        .into_iter()
+        .filter(|(flag, _)| mods.contains(*flag))
+        .map(|(_, name)| name.to_string())
+        .collect();
+        names.extend(
+            mods.difference(SUPPORTED_MODIFIERS)
+                .iter_names()
+                .map(|(name, _)| name.to_lowercase()),
+        );
+        serializer.collect_seq(names)
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Names(Vec<String>),
+        Bits(u32),
+        Text(String),
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Modifiers>, D::Error> {
+        let names = match Option::<Repr>::deserialize(deserializer)? {
+            None => return Ok(None),
+            Some(Repr::Bits(bits)) => return Ok(Some(Modifiers::from_bits_retain(bits))),
+            Some(Repr::Names(names)) => names,
+            Some(Repr::Text(text)) => text
+                .split('|')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect(),
+        };
+        let mut mods = Modifiers::empty();
+        for name in names {
+            // The flag name covers META, which parse_modifier folds into SUPER
+            mods |= Modifiers::from_name(&name.to_uppercase())
+                .or_else(|| parse_modifier(&name))
+                .ok_or_else(|| D::Error::custom(format!("Unknown modifier: {name}")))?;
+        }
+        Ok(Some(mods))
+    }
+}
+
 impl fmt::Display for Key {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut parts = Vec::new();
@@ -155,8 +452,256 @@ impl FromStr for Key {
     }
 }
 
-/// Parse a key code from a string
-fn parse_code(s: &str) -> Result<Code> {
+/// Every key code that can be parsed and displayed
+pub(crate) const CODES: &[Code] = &[
+    // Letters
+    Code::KeyA,
+    Code::KeyB,
+    Code::KeyC,
+    Code::KeyD,
+    Code::KeyE,
+    Code::KeyF,
+    Code::KeyG,
+    Code::KeyH,
+    Code::KeyI,
+    Code::KeyJ,
+    Code::KeyK,
+    Code::KeyL,
+    Code::KeyM,
+    Code::KeyN,
+    Code::KeyO,
+    Code::KeyP,
+    Code::KeyQ,
+    Code::KeyR,
+    Code::KeyS,
+    Code::KeyT,
+    Code::KeyU,
+    Code::KeyV,
+    Code::KeyW,
+    Code::KeyX,
+    Code::KeyY,
+    Code::KeyZ,
+    // Numbers
+    Code::Digit0,
+    Code::Digit1,
+    Code::Digit2,
+    Code::Digit3,
+    Code::Digit4,
+    Code::Digit5,
+    Code::Digit6,
+    Code::Digit7,
+    Code::Digit8,
+    Code::Digit9,
+    // Function keys
+    Code::F1,
+    Code::F2,
+    Code::F3,
+    Code::F4,
+    Code::F5,
+    Code::F6,
+    Code::F7,
+    Code::F8,
+    Code::F9,
+    Code::F10,
+    Code::F11,
+    Code::F12,
+    // Navigation, editing and punctuation
+    Code::Escape,
+    Code::Space,
+    Code::Enter,
+    Code::Tab,
+    Code::Backspace,
+    Code::Delete,
+    Code::Insert,
+    Code::Home,
+    Code::End,
+    Code::PageUp,
+    Code::PageDown,
+    Code::ArrowLeft,
+    Code::ArrowRight,
+    Code::ArrowUp,
+    Code::ArrowDown,
+    Code::Minus,
+    Code::Equal,
+    Code::BracketLeft,
+    Code::BracketRight,
+    Code::Backslash,
+    Code::Semicolon,
+    Code::Quote,
+    Code::Comma,
+    Code::Period,
+    Code::Slash,
+    Code::Backquote,
+];
+
+/// Alternative names for keys, on top of those [`parse_builtin_code`] knows
+const BUILTIN_ALIASES: &[(&str, Code)] = &[
+    ("entrée", Code::Enter),
+    ("échap", Code::Escape),
+    ("suppr", Code::Delete),
+    ("espace", Code::Space),
+];
+
+/// A table of custom names for keys and chords
+///
+/// An alias can name a single key, like `"eingabe"` for enter, or a whole
+/// chord, like `"launcher"` for `cmd+space`. Modifiers written before an
+/// alias add to its chord, so `"shift+launcher"` is `shift+cmd+space`.
+///
+/// [`Key::parse`] resolves aliases in the process-wide registry, which
+/// [`AliasRegistry::register`] and [`register_alias`] add to. A registry can
+/// also be passed to a single parse with [`ParseOptions::with_aliases`].
+///
+/// Registries serialize as a map from alias to chord, so configs can define
+/// their own:
+///
+/// ```
+/// use hotkey_manager::key::AliasRegistry;
+/// use hotkey_manager::{Key, ParseOptions};
+///
+/// let aliases: AliasRegistry = serde_json::from_str(r#"{"launcher": "cmd+space"}"#).unwrap();
+/// let options = ParseOptions::default().with_aliases(aliases);
+/// let key = Key::parse_with("shift+launcher", &options).unwrap();
+/// assert_eq!(key.to_string(), "shift+cmd+space");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    try_from = "BTreeMap<String, String>",
+    into = "BTreeMap<String, String>"
+)]
+pub struct AliasRegistry {
+    aliases: BTreeMap<String, Key>,
+}
+
+impl AliasRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A copy of the process-wide registry
+    pub fn global() -> Self {
+        global_aliases()
+            .read()
+            .expect("aliases lock poisoned")
+            .clone()
+    }
+
+    /// Add an alias for the chord `target`, replacing any alias of the same
+    /// name
+    ///
+    /// Names are case-insensitive. The target can use built-in names and
+    /// aliases in the process-wide registry, but not those in this one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `target` isn't a valid key, or `alias` is empty,
+    /// contains `+`, or is already a built-in name.
+    pub fn insert(&mut self, alias: &str, target: &str) -> Result<()> {
+        let alias = alias.trim().to_lowercase();
+        if alias.is_empty() || alias.contains('+') || parse_builtin_code(&alias).is_ok() {
+            return Err(Error::InvalidKey(format!("Invalid alias: {alias:?}")));
+        }
+        let key = Key::parse(target)?;
+        self.aliases.insert(alias, key);
+        Ok(())
+    }
+
+    /// Add an alias, as with [`AliasRegistry::insert`]
+    pub fn with_alias(mut self, alias: &str, target: &str) -> Result<Self> {
+        self.insert(alias, target)?;
+        Ok(self)
+    }
+
+    /// Remove an alias, returning the chord it named
+    pub fn remove(&mut self, alias: &str) -> Option<Key> {
+        self.aliases.remove(&alias.trim().to_lowercase())
+    }
+
+    /// The chord an alias names
+    pub fn get(&self, alias: &str) -> Option<&Key> {
+        self.aliases.get(&alias.trim().to_lowercase())
+    }
+
+    /// Iterate over the aliases and the chords they name, in order of name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Key)> {
+        self.aliases
+            .iter()
+            .map(|(alias, key)| (alias.as_str(), key))
+    }
+
+    /// Whether the registry has no aliases
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+
+    /// Add these aliases to the process-wide registry, replacing any of the
+    /// same names
+    pub fn register(&self) {
+        let mut global = global_aliases().write().expect("aliases lock poisoned");
+        for (alias, key) in &self.aliases {
+            global.aliases.insert(alias.clone(), key.clone());
+        }
+    }
+}
+
+impl TryFrom<BTreeMap<String, String>> for AliasRegistry {
+    type Error = Error;
+
+    fn try_from(aliases: BTreeMap<String, String>) -> Result<Self> {
+        let mut registry = AliasRegistry::new();
+        for (alias, target) in &aliases {
+            registry.insert(alias, target)?;
+        }
+        Ok(registry)
+    }
+}
+
+impl From<AliasRegistry> for BTreeMap<String, String> {
+    fn from(registry: AliasRegistry) -> Self {
+        registry
+            .aliases
+            .into_iter()
+            .map(|(alias, key)| (alias, key.canonical_string()))
+            .collect()
+    }
+}
+
+/// The process-wide alias registry
+fn global_aliases() -> &'static RwLock<AliasRegistry> {
+    static ALIASES: OnceLock<RwLock<AliasRegistry>> = OnceLock::new();
+    ALIASES.get_or_init(|| {
+        let aliases = BUILTIN_ALIASES
+            .iter()
+            .map(|(alias, code)| (alias.to_string(), Key::new(*code, None)))
+            .collect();
+        RwLock::new(AliasRegistry { aliases })
+    })
+}
+
+/// Add a name that parses as the key or chord `target`, for this process
+///
+/// Names are case-insensitive. Registering a name again replaces its target.
+/// Keys still display with their built-in names.
+///
+/// ```
+/// use hotkey_manager::{key, Key};
+///
+/// key::register_alias("eingabe", "enter").unwrap();
+/// assert_eq!(Key::parse("cmd+Eingabe").unwrap().to_string(), "cmd+enter");
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if `target` isn't a valid key, or `alias` is empty,
+/// contains `+`, or is already a built-in name.
+pub fn register_alias(alias: &str, target: &str) -> Result<()> {
+    AliasRegistry::new().with_alias(alias, target)?.register();
+    Ok(())
+}
+
+/// Parse a key code from one of its built-in names
+fn parse_builtin_code(s: &str) -> Result<Code> {
     match s.to_lowercase().as_str() {
         // Letters
         "a" => Ok(Code::KeyA),
@@ -214,7 +759,7 @@ fn parse_code(s: &str) -> Result<Code> {
 
         // Special keys
         "escape" | "esc" => Ok(Code::Escape),
-        "space" | " " => Ok(Code::Space),
+        "space" | "spacebar" | " " => Ok(Code::Space),
         "enter" | "return" => Ok(Code::Enter),
         "tab" => Ok(Code::Tab),
         "backspace" => Ok(Code::Backspace),
@@ -224,6 +769,8 @@ fn parse_code(s: &str) -> Result<Code> {
         "end" => Ok(Code::End),
         "pageup" | "page_up" | "pgup" => Ok(Code::PageUp),
         "pagedown" | "page_down" | "pgdn" => Ok(Code::PageDown),
+        "capslock" | "caps_lock" | "caps" => Ok(Code::CapsLock),
+        "numlock" | "num_lock" => Ok(Code::NumLock),
 
         // Arrow keys
         "left" | "arrowleft" => Ok(Code::ArrowLeft),
@@ -317,6 +864,8 @@ fn format_code(code: &Code) -> &'static str {
         Code::End => "end",
         Code::PageUp => "pageup",
         Code::PageDown => "pagedown",
+        Code::CapsLock => "capslock",
+        Code::NumLock => "numlock",
 
         // Arrow keys
         Code::ArrowLeft => "left",
@@ -393,6 +942,33 @@ mod tests {
         assert_eq!(key1, key2);
     }
 
+    #[test]
+    fn test_canonical() {
+        let spellings = ["shift+cmd+A", "command+shift+a", "SHIFT+super+a"];
+        for spelling in spellings {
+            let key = Key::parse(spelling).unwrap();
+            assert_eq!(key.canonical_string(), "shift+cmd+a");
+            assert_eq!(Key::parse(&key.canonical_string()).unwrap(), key);
+        }
+
+        // Empty, meta and unsupported modifiers don't make a different key
+        let plain = Key::parse("a").unwrap();
+        let empty = Key::new(Code::KeyA, Some(Modifiers::empty()));
+        let locked = Key::new(Code::KeyA, Some(Modifiers::CAPS_LOCK));
+        assert_eq!(empty, plain);
+        assert_eq!(locked, plain);
+        assert_eq!(locked.canonical().modifiers, None);
+        assert_eq!(locked.canonical_string(), "a");
+        let meta = Key::new(Code::KeyA, Some(Modifiers::META));
+        assert_eq!(meta, Key::parse("cmd+a").unwrap());
+        let set: std::collections::HashSet<Key> = [plain, empty, locked].into_iter().collect();
+        assert_eq!(set.len(), 1);
+
+        assert!(identifier_matches("shift+cmd+a", "cmd+shift+a"));
+        assert!(identifier_matches("launch", "launch"));
+        assert!(!identifier_matches("A", "a"));
+    }
+
     #[test]
     fn test_display() {
         let key = Key::parse("ctrl+a").unwrap();
@@ -450,6 +1026,46 @@ mod tests {
         assert_eq!(key, deserialized);
     }
 
+    #[test]
+    fn test_serialize_modifier_names() {
+        let key = Key::parse("cmd+shift+ctrl+a").unwrap();
+        let json = serde_json::to_value(&key).unwrap();
+        assert_eq!(
+            json["modifiers"],
+            serde_json::json!(["ctrl", "shift", "cmd"])
+        );
+        assert_eq!(serde_json::from_value::<Key>(json).unwrap(), key);
+
+        let locked = Key::new(Code::KeyA, Some(Modifiers::ALT | Modifiers::CAPS_LOCK));
+        let json = serde_json::to_value(&locked).unwrap();
+        assert_eq!(json["modifiers"], serde_json::json!(["alt", "caps_lock"]));
+        let deserialized: Key = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized.modifiers, locked.modifiers);
+
+        let bare = serde_json::to_value(Key::parse("a").unwrap()).unwrap();
+        assert!(bare.get("modifiers").is_none());
+        let bare: Key = serde_json::from_value(bare).unwrap();
+        assert_eq!(bare.modifiers, None);
+    }
+
+    #[test]
+    fn test_deserialize_legacy_modifiers() {
+        let expected = Some(Modifiers::CONTROL | Modifiers::SHIFT);
+        let bits = (Modifiers::CONTROL | Modifiers::SHIFT).bits();
+        let key: Key =
+            serde_json::from_str(&format!(r#"{{"modifiers":{bits},"code":"KeyA"}}"#)).unwrap();
+        assert_eq!(key.modifiers, expected);
+
+        let key: Key =
+            serde_json::from_str(r#"{"modifiers":"CONTROL | SHIFT","code":"KeyA"}"#).unwrap();
+        assert_eq!(key.modifiers, expected);
+
+        let key: Key = serde_json::from_str(r#"{"modifiers":null,"code":"KeyA"}"#).unwrap();
+        assert_eq!(key.modifiers, None);
+
+        assert!(serde_json::from_str::<Key>(r#"{"modifiers":["bogus"],"code":"KeyA"}"#).is_err());
+    }
+
     #[test]
     fn test_from_str() {
         let key: Key = "ctrl+a".parse().unwrap();
@@ -457,6 +1073,114 @@ mod tests {
         assert_eq!(key.modifiers, Some(Modifiers::CONTROL));
     }
 
+    #[test]
+    fn test_all() {
+        let keys = Key::all();
+        assert_eq!(keys.len(), CODES.len() * 16);
+        assert!(keys.contains(&Key::parse("a").unwrap()));
+        assert!(keys.contains(&Key::parse("ctrl+alt+shift+cmd+f12").unwrap()));
+
+        // Every code round-trips through its display name
+        for code in CODES {
+            let key = Key::new(*code, None);
+            assert_eq!(Key::parse(&key.to_string()).unwrap(), key);
+        }
+    }
+
+    #[test]
+    fn test_parse_shifted() {
+        let cases = [
+            ("A", "shift+a"),
+            ("?", "shift+slash"),
+            ("cmd+?", "shift+cmd+slash"),
+            ("shift+A", "shift+a"),
+            ("ctrl+!", "ctrl+shift+1"),
+            ("{", "shift+bracketleft"),
+            ("F1", "f1"),
+            ("Tab", "tab"),
+        ];
+        for (spelling, expected) in cases {
+            let key = Key::parse(spelling).unwrap();
+            assert_eq!(key.to_string(), expected, "{spelling}");
+        }
+
+        let literal = ParseOptions::default().with_shifted_characters(false);
+        assert_eq!(
+            Key::parse_with("A", &literal).unwrap(),
+            Key::parse("a").unwrap()
+        );
+        assert!(Key::parse_with("?", &literal).is_err());
+    }
+
+    #[test]
+    fn test_aliases() {
+        let cases = [
+            ("return", "enter"),
+            ("spacebar", "space"),
+            ("pgup", "pageup"),
+            ("caps", "capslock"),
+            ("CapsLock", "capslock"),
+            ("numlock", "numlock"),
+            ("ctrl+Entrée", "ctrl+enter"),
+            ("suppr", "delete"),
+        ];
+        for (spelling, expected) in cases {
+            let key = Key::parse(spelling).unwrap();
+            assert_eq!(key.to_string(), expected, "{spelling}");
+            assert_eq!(Key::parse(expected).unwrap(), key);
+        }
+
+        assert!(Key::parse("ctrl+abbrechen").is_err());
+        register_alias("Abbrechen", "esc").unwrap();
+        assert_eq!(
+            Key::parse("ctrl+abbrechen").unwrap().to_string(),
+            "ctrl+escape"
+        );
+
+        assert!(register_alias("a", "b").is_err());
+        assert!(register_alias("x", "unknown").is_err());
+        assert!(register_alias("ctrl+x", "a").is_err());
+        assert!(register_alias(" ", "a").is_err());
+    }
+
+    #[test]
+    fn test_alias_registry() {
+        let aliases = AliasRegistry::new()
+            .with_alias("Launcher", "cmd+space")
+            .unwrap()
+            .with_alias("go", "ctrl+G")
+            .unwrap();
+        assert_eq!(
+            aliases.get("launcher"),
+            Some(&Key::parse("cmd+space").unwrap())
+        );
+
+        let options = ParseOptions::default().with_aliases(aliases.clone());
+        let parse = |s| Key::parse_with(s, &options).unwrap().to_string();
+        assert_eq!(parse("launcher"), "cmd+space");
+        assert_eq!(parse("shift+launcher"), "shift+cmd+space");
+        assert_eq!(parse("go"), "ctrl+shift+g");
+        assert_eq!(parse("suppr"), "delete");
+        assert!(Key::parse("ctrl+launcher").is_err());
+
+        let json = serde_json::to_string(&aliases).unwrap();
+        assert_eq!(json, r#"{"go":"ctrl+shift+g","launcher":"cmd+space"}"#);
+        assert_eq!(
+            serde_json::from_str::<AliasRegistry>(&json).unwrap(),
+            aliases
+        );
+        assert!(serde_json::from_str::<AliasRegistry>(r#"{"x":"bogus"}"#).is_err());
+
+        let mut global = AliasRegistry::new()
+            .with_alias("spotlight", "cmd+space")
+            .unwrap();
+        global.register();
+        assert_eq!(Key::parse("spotlight").unwrap().to_string(), "cmd+space");
+        assert!(AliasRegistry::global().get("spotlight").is_some());
+        assert!(global.remove("Spotlight").is_some());
+        assert!(global.is_empty());
+    }
+
     #[test]
     fn test_parse_errors() {
         assert!(Key::parse("").is_err());
@@ -0,0 +1,253 @@
+//! LaunchAgent management for starting programs at login on macOS.
+//!
+//! A [`LaunchAgent`] describes a per-user launchd job. It can be written to
+//! and removed from `~/Library/LaunchAgents`, and loaded or unloaded with
+//! `launchctl`.
+
+use crate::{Error, Result};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use tracing::{debug, info};
+
+/// A per-user launchd job definition
+#[derive(Debug, Clone)]
+pub struct LaunchAgent {
+    label: String,
+    program_args: Vec<String>,
+    env: Vec<(String, String)>,
+    keep_alive: bool,
+    log_path: Option<PathBuf>,
+    dir: Option<PathBuf>,
+}
+
+impl LaunchAgent {
+    /// Create a job with the given label that runs a program at login
+    pub fn new(label: impl Into<String>, program: impl Into<PathBuf>) -> Self {
+        Self {
+            label: label.into(),
+            program_args: vec![program.into().to_string_lossy().into_owned()],
+            env: Vec::new(),
+            keep_alive: false,
+            log_path: None,
+            dir: None,
+        }
+    }
+
+    /// Add arguments to pass to the program
+    pub fn with_args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.program_args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Set an environment variable for the program
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// Restart the program whenever it exits
+    pub fn with_keep_alive(mut self, keep_alive: bool) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    /// Send the program's stdout and stderr to a file
+    pub fn with_log_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.log_path = Some(path.into());
+        self
+    }
+
+    /// Store the plist in a directory other than `~/Library/LaunchAgents`
+    pub fn with_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = Some(dir.into());
+        self
+    }
+
+    /// The job's label
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Path of the plist file for this job
+    pub fn plist_path(&self) -> Result<PathBuf> {
+        let dir = match &self.dir {
+            Some(dir) => dir.clone(),
+            None => {
+                let home = std::env::var_os("HOME").ok_or_else(|| {
+                    Error::Service("HOME environment variable is not set".to_string())
+                })?;
+                PathBuf::from(home).join("Library/LaunchAgents")
+            }
+        };
+        Ok(dir.join(format!("{}.plist", self.label)))
+    }
+
+    /// Render the job as a launchd property list
+    pub fn to_plist(&self) -> String {
+        let mut out = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" ",
+            "\"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n",
+            "<plist version=\"1.0\">\n",
+            "<dict>\n",
+        ));
+        push_entry(&mut out, "Label", &string(&self.label));
+        let args: String = self
+            .program_args
+            .iter()
+            .map(|arg| format!("\t\t{}\n", string(arg)))
+            .collect();
+        push_entry(
+            &mut out,
+            "ProgramArguments",
+            &format!("<array>\n{args}\t</array>"),
+        );
+        if !self.env.is_empty() {
+            let vars: String = self
+                .env
+                .iter()
+                .map(|(k, v)| format!("\t\t<key>{}</key>\n\t\t{}\n", escape(k), string(v)))
+                .collect();
+            push_entry(
+                &mut out,
+                "EnvironmentVariables",
+                &format!("<dict>\n{vars}\t</dict>"),
+            );
+        }
+        push_entry(&mut out, "RunAtLoad", "<true/>");
+        if self.keep_alive {
+            push_entry(&mut out, "KeepAlive", "<true/>");
+        }
+        if let Some(log_path) = &self.log_path {
+            let log_path = string(&log_path.to_string_lossy());
+            push_entry(&mut out, "StandardOutPath", &log_path);
+            push_entry(&mut out, "StandardErrorPath", &log_path);
+        }
+        out.push_str("</dict>\n</plist>\n");
+        out
+    }
+
+    /// Whether the job's plist file exists
+    pub fn is_installed(&self) -> bool {
+        self.plist_path().map(|p| p.exists()).unwrap_or(false)
+    }
+
+    /// Write the job's plist file
+    ///
+    /// Returns `true` if the file was created or its contents changed, and
+    /// `false` if an identical file was already present.
+    pub fn write(&self) -> Result<bool> {
+        let path = self.plist_path()?;
+        let plist = self.to_plist();
+        if fs::read_to_string(&path).is_ok_and(|existing| existing == plist) {
+            return Ok(false);
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, plist)?;
+        info!("Wrote LaunchAgent: {}", path.display());
+        Ok(true)
+    }
+
+    /// Remove the job's plist file
+    ///
+    /// Returns `true` if the file existed.
+    pub fn remove(&self) -> Result<bool> {
+        let path = self.plist_path()?;
+        match fs::remove_file(&path) {
+            Ok(()) => {
+                info!("Removed LaunchAgent: {}", path.display());
+                Ok(true)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Load the job into launchd, starting it
+    pub fn load(&self) -> Result<()> {
+        launchctl(&["load", "-w", &self.plist_path()?.to_string_lossy()])
+    }
+
+    /// Unload the job from launchd, stopping it
+    pub fn unload(&self) -> Result<()> {
+        launchctl(&["unload", &self.plist_path()?.to_string_lossy()])
+    }
+}
+
+/// Run `launchctl` with the given arguments
+fn launchctl(args: &[&str]) -> Result<()> {
+    debug!("Running launchctl {}", args.join(" "));
+    let output = Command::new("launchctl").args(args).output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(Error::Service(format!(
+            "launchctl {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+fn push_entry(out: &mut String, key: &str, value: &str) {
+    out.push_str(&format!("\t<key>{key}</key>\n\t{value}\n"));
+}
+
+fn string(s: &str) -> String {
+    format!("<string>{}</string>", escape(s))
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_plist() {
+        let agent = LaunchAgent::new("com.example.test", "/usr/local/bin/hotki")
+            .with_args(["--server"])
+            .with_env("HOTKEY_SOCKET", "/tmp/a&b.sock")
+            .with_keep_alive(true)
+            .with_log_path("/tmp/test.log");
+        let plist = agent.to_plist();
+        assert!(plist.contains("<string>com.example.test</string>"));
+        assert!(plist.contains(
+            "<array>\n\t\t<string>/usr/local/bin/hotki</string>\n\t\t<string>--server</string>\n\t</array>"
+        ));
+        assert!(plist.contains("<key>HOTKEY_SOCKET</key>\n\t\t<string>/tmp/a&amp;b.sock</string>"));
+        assert!(plist.contains("<key>KeepAlive</key>\n\t<true/>"));
+        assert!(plist.contains("<key>StandardErrorPath</key>\n\t<string>/tmp/test.log</string>"));
+
+        let plain = LaunchAgent::new("com.example.test", "/bin/true").to_plist();
+        assert!(!plain.contains("KeepAlive"));
+        assert!(!plain.contains("EnvironmentVariables"));
+    }
+
+    #[test]
+    fn test_write_and_remove() {
+        let dir =
+            std::env::temp_dir().join(format!("hotkey-manager-launchd-{}", std::process::id()));
+        let agent = LaunchAgent::new("com.example.test", "/bin/true").with_dir(&dir);
+        assert!(!agent.is_installed());
+
+        assert!(agent.write().unwrap());
+        assert!(agent.is_installed());
+        assert!(!agent.write().unwrap());
+
+        let changed = agent.clone().with_args(["--flag"]);
+        assert!(changed.write().unwrap());
+
+        assert!(agent.remove().unwrap());
+        assert!(!agent.is_installed());
+        assert!(!agent.remove().unwrap());
+        let _ = fs::remove_dir(&dir);
+    }
+}
@@ -3,21 +3,76 @@
 //! This crate provides a high-level interface for managing global hotkeys with callbacks.
 //! It handles hotkey registration, event listening, and callback execution in a thread-safe manner.
 
-/// Default socket path for IPC communication
-pub const DEFAULT_SOCKET_PATH: &str = "/tmp/hotkey-manager.sock";
+/// Socket path shared by all users in releases before per-user sockets.
+/// See [`user_socket_path`] for the default now.
+pub const LEGACY_SOCKET_PATH: &str = "/tmp/hotkey-manager.sock";
 
+/// Socket path used by default before per-user sockets
+#[deprecated(note = "use default_socket_path()")]
+pub const DEFAULT_SOCKET_PATH: &str = LEGACY_SOCKET_PATH;
+
+/// Environment variable naming the socket path that servers listen on and
+/// clients connect to, when none is given. Clients also set it for the
+/// servers they spawn.
+pub const SOCKET_ENV: &str = "HOTKEY_SOCKET";
+
+/// Command line flag that the bundled binaries take a socket path with.
+/// Clients pass it to servers spawned with
+/// [`Client::with_auto_spawn_server`].
+pub const SOCKET_ARG: &str = "--socket";
+
+/// Environment variable naming a file the server creates once it is
+/// listening. Clients set it for the servers they spawn, to know when they
+/// can connect.
+pub const READY_ENV: &str = "HOTKEY_READY_FILE";
+
+/// Environment variable naming a file descriptor the server inherits: one
+/// end of a connected socket, which it serves instead of listening on a
+/// path. Clients set it for servers spawned with
+/// [`Client::with_server_socketpair`].
+pub const FD_ENV: &str = "HOTKEY_FD";
+
+/// Version of this crate. Servers report it in response to pings, so
+/// clients can detect a server from a different release.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+mod app;
 mod client;
+pub mod codec;
 mod error;
+mod handler;
 mod ipc;
-mod key;
+pub mod key;
+mod launchd;
 mod manager;
+mod metrics;
+mod pidfile;
 mod process;
+mod retry;
+mod sequence;
 mod server;
+mod socket;
+mod stats;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+mod watchdog;
 
 // Re-export the main types from modules
 pub use client::Client;
 pub use error::{Error, Result};
-pub use ipc::{IPCConnection, IPCResponse};
-pub use key::Key;
-pub use process::ServerProcess;
-pub use server::Server;
+pub use handler::Handler;
+pub use ipc::{DisconnectPolicy, IPCConnection, IPCRequest, IPCResponse};
+pub use key::{AliasRegistry, Key, ParseOptions};
+pub use launchd::LaunchAgent;
+pub use metrics::Metrics;
+pub use pidfile::pid_file_path;
+pub use process::{ServerHealth, ServerProcess};
+pub use retry::{Backoff, Delay, RetryPolicy};
+pub use sequence::Sequences;
+pub use server::{run_server, run_server_on, run_server_with, Server};
+pub use socket::{default_socket_path, user_socket_path};
+pub use stats::HotkeyStats;
+pub use watchdog::{ConnectionHealth, ConnectionState};
+
+#[cfg(feature = "proptest")]
+pub use proptest;
@@ -1,44 +1,334 @@
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::key::identifier_matches;
+use crate::metrics::{Collector, Metrics};
+use crate::stats::{HotkeyStats, Stats};
 use crate::Key;
-use global_hotkey::{hotkey::HotKey, GlobalHotKeyEvent, GlobalHotKeyManager};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use global_hotkey::{hotkey::HotKey, GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use std::collections::{BTreeSet, HashMap};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 use tracing::{debug, error, info, trace, warn};
 
-/// Type alias for hotkey callbacks that receive the identifier
-type HotkeyCallback = Arc<dyn Fn(&str) + Send + Sync>;
+/// Context passed to a hotkey callback
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HotkeyEvent<'a> {
+    /// The unique ID of the registered hotkey, as returned by `bind`
+    pub id: u32,
+    /// User-provided identifier for the hotkey
+    pub identifier: &'a str,
+    /// The key combination that was pressed
+    pub key: &'a Key,
+    /// Whether the key was pressed or released
+    pub state: HotKeyState,
+    /// When the event was received
+    pub timestamp: Instant,
+}
+
+/// Type alias for hotkey callbacks that receive the event context
+type HotkeyCallback = Arc<dyn Fn(&HotkeyEvent) + Send + Sync>;
+
+/// Adapts a callback that only takes the hotkey identifier, the signature
+/// used before callbacks received a [`HotkeyEvent`].
+#[allow(dead_code)]
+pub(crate) fn identifier_callback<F>(callback: F) -> impl Fn(&HotkeyEvent) + Send + Sync + Clone
+where
+    F: Fn(&str) + Send + Sync + Clone + 'static,
+{
+    move |event| callback(event.identifier)
+}
+
+/// A failure in the hotkey listener thread
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub(crate) enum ListenerError {
+    /// Receiving events from the system failed. The listener stops after
+    /// this, since no more events can arrive.
+    #[error("Failed to receive hotkey events: {0}")]
+    Receive(String),
+    /// The bindings lock was poisoned by a thread that panicked
+    #[error("Hotkey bindings are unavailable after a panic")]
+    Poisoned,
+    /// An event arrived for a hotkey ID that isn't bound
+    #[error("Received an event for unknown hotkey id {0}")]
+    UnknownId(u32),
+}
+
+/// Type alias for the listener failure handler
+type ErrorHandler = Arc<dyn Fn(&ListenerError) + Send + Sync>;
+
+/// Call the error handler, if one is set
+fn report(handler: &Mutex<Option<ErrorHandler>>, failure: &ListenerError) {
+    // Clone the handler out so it can replace itself
+    let handler = handler
+        .lock()
+        .expect("error handler mutex poisoned")
+        .clone();
+    if let Some(handler) = handler {
+        handler(failure);
+    }
+}
+
+/// A callback invocation waiting to run
+struct Job {
+    callback: HotkeyCallback,
+    id: u32,
+    identifier: String,
+    key: Key,
+    state: HotKeyState,
+    timestamp: Instant,
+}
+
+impl Job {
+    /// Run the callback, logging rather than propagating a panic
+    fn run(self) {
+        trace!("About to call callback for '{}'", self.identifier);
+        let event = HotkeyEvent {
+            id: self.id,
+            identifier: &self.identifier,
+            key: &self.key,
+            state: self.state,
+            timestamp: self.timestamp,
+        };
+        match panic::catch_unwind(AssertUnwindSafe(|| (self.callback)(&event))) {
+            Ok(()) => trace!("Callback completed for '{}'", self.identifier),
+            Err(payload) => error!(
+                "Callback for '{}' panicked: {}",
+                self.identifier,
+                panic_message(payload.as_ref())
+            ),
+        }
+    }
+}
+
+/// The message of a panic payload, if it has one
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// Runs callbacks on a worker thread, so that a slow or panicking callback
+/// can't stall or kill the listener thread.
+///
+/// Callbacks run one at a time, in the order of their presses, since
+/// forwarded events must reach clients in that order. The time from each
+/// press to its callback finishing is recorded in `metrics`.
+#[derive(Clone)]
+struct Executor {
+    jobs: mpsc::Sender<Job>,
+}
+
+impl Executor {
+    fn new(metrics: Arc<Collector>) -> Self {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        std::thread::Builder::new()
+            .name("hotkey-callback".to_string())
+            .spawn(move || {
+                for job in receiver {
+                    let pressed = job.timestamp;
+                    job.run();
+                    metrics.callback_finished(pressed.elapsed());
+                }
+            })
+            .expect("failed to spawn callback worker");
+        Self { jobs }
+    }
+
+    /// Queue a callback to run on a worker
+    fn submit(&self, job: Job) {
+        if self.jobs.send(job).is_err() {
+            error!("Callback workers have stopped, dropping hotkey event");
+        }
+    }
+}
+
+/// Registers hotkeys with the system, recording how long each call takes
+struct Registrar {
+    /// `None` in tests, where every call succeeds without touching the system
+    manager: Option<GlobalHotKeyManager>,
+    metrics: Arc<Collector>,
+}
+
+impl Registrar {
+    fn register(&self, hotkey: HotKey) -> global_hotkey::Result<()> {
+        let start = Instant::now();
+        let result = match &self.manager {
+            Some(manager) => manager.register(hotkey),
+            None => Ok(()),
+        };
+        self.metrics.registered(start.elapsed());
+        result
+    }
+
+    fn unregister(&self, hotkey: HotKey) -> global_hotkey::Result<()> {
+        let start = Instant::now();
+        let result = match &self.manager {
+            Some(manager) => manager.unregister(hotkey),
+            None => Ok(()),
+        };
+        self.metrics.unregistered(start.elapsed());
+        result
+    }
+}
+
+/// Deadlines of temporary hotkeys, earliest first
+#[derive(Default)]
+struct Deadlines {
+    queue: BTreeSet<(Instant, u32)>,
+    /// Set when the manager is dropped, to stop the timer thread
+    stopped: bool,
+}
+
+/// Tracks when temporary hotkeys expire, for a single timer thread to
+/// unbind them
+#[derive(Default)]
+struct Expiry {
+    deadlines: Mutex<Deadlines>,
+    /// Wakes the timer thread when the earliest deadline may have changed
+    changed: Condvar,
+}
+
+impl Expiry {
+    /// Schedules the hotkey with an ID to expire at a deadline
+    fn schedule(&self, id: u32, at: Instant) {
+        let mut deadlines = self.deadlines.lock().expect("deadlines mutex poisoned");
+        deadlines.queue.insert((at, id));
+        self.changed.notify_one();
+    }
+
+    /// Cancels an entry's deadline, if it has one
+    fn cancel(&self, entry: &HotkeyEntry) {
+        if let Some(at) = entry.expires {
+            let mut deadlines = self.deadlines.lock().expect("deadlines mutex poisoned");
+            deadlines.queue.remove(&(at, entry.hotkey.id()));
+        }
+    }
+
+    /// Stops the timer thread
+    fn stop(&self) {
+        self.deadlines
+            .lock()
+            .expect("deadlines mutex poisoned")
+            .stopped = true;
+        self.changed.notify_one();
+    }
+
+    /// Waits for the earliest deadline to pass and returns it with its ID,
+    /// or `None` once stopped.
+    fn next(&self) -> Option<(Instant, u32)> {
+        let mut deadlines = self.deadlines.lock().expect("deadlines mutex poisoned");
+        loop {
+            if deadlines.stopped {
+                return None;
+            }
+            let now = Instant::now();
+            deadlines = match deadlines.queue.first().copied() {
+                Some((at, _)) if at <= now => return deadlines.queue.pop_first(),
+                Some((at, _)) => {
+                    self.changed
+                        .wait_timeout(deadlines, at - now)
+                        .expect("deadlines mutex poisoned")
+                        .0
+                }
+                None => self
+                    .changed
+                    .wait(deadlines)
+                    .expect("deadlines mutex poisoned"),
+            };
+        }
+    }
+}
 
 /// Represents a registered hotkey with its metadata
 struct HotkeyEntry {
     /// The actual hotkey combination
     hotkey: HotKey,
+    /// The key the hotkey was registered for
+    key: Key,
     /// User-provided identifier for this hotkey
     identifier: String,
     /// Callback function to execute when the hotkey is pressed
     callback: HotkeyCallback,
+    /// Unbind the hotkey after its first press
+    once: bool,
+    /// Name of the group the hotkey belongs to, if any
+    group: Option<String>,
+    /// When a temporary hotkey is unbound
+    expires: Option<Instant>,
 }
 
 /// A manager for global hotkeys that handles registration and callback execution.
 pub(crate) struct HotkeyManager {
-    manager: GlobalHotKeyManager,
+    /// Shared with the listener and timer threads, which unbind temporary
+    /// hotkeys
+    manager: Arc<Registrar>,
     hotkeys: Arc<Mutex<HashMap<u32, HotkeyEntry>>>,
+    /// Deadlines of temporary hotkeys. Locked after `hotkeys` when both are
+    /// needed.
+    expiry: Arc<Expiry>,
+    /// Set while [`capture`](Self::capture) has the bindings suspended.
+    /// Only changed with `hotkeys` locked.
+    capturing: Arc<AtomicBool>,
+    /// Entries of disabled groups, by group name. These are unregistered
+    /// from the system, so they may overlap with active bindings. When both
+    /// are needed, `hotkeys` is always locked first.
+    disabled: Mutex<HashMap<String, Vec<HotkeyEntry>>>,
+    executor: Executor,
+    /// Trigger counts, which outlive individual bindings
+    stats: Arc<Mutex<Stats>>,
+    /// Counters for monitoring, shared with the IPC layer
+    metrics: Arc<Collector>,
+    /// Called by the listener thread when it hits a failure
+    error_handler: Arc<Mutex<Option<ErrorHandler>>>,
 }
 
 impl HotkeyManager {
     /// Creates a new `HotkeyManager` instance.
     ///
-    /// This will spawn a background thread to listen for hotkey events.
+    /// This will spawn a background thread to listen for hotkey events, and
+    /// a worker thread to run callbacks in order.
     ///
     /// # Errors
     ///
     /// Returns an error if the underlying global hotkey manager fails to initialize.
     pub(crate) fn new() -> Result<Self> {
         trace!("Creating new HotkeyManager");
-        let manager = GlobalHotKeyManager::new()?;
+        let system = GlobalHotKeyManager::new()?;
         debug!("GlobalHotKeyManager created successfully");
+        Ok(Self::start(Some(system)))
+    }
+
+    /// Creates a manager that binds hotkeys without registering them with
+    /// the system, so its bookkeeping can be tested anywhere.
+    #[cfg(test)]
+    fn detached() -> Self {
+        Self::start(None)
+    }
+
+    /// Spawns the listener and worker threads around a system manager.
+    fn start(system: Option<GlobalHotKeyManager>) -> Self {
+        let metrics = Arc::new(Collector::default());
+        let manager = Arc::new(Registrar {
+            manager: system,
+            metrics: metrics.clone(),
+        });
+        let listener_manager = manager.clone();
 
         let hotkeys = Arc::new(Mutex::new(HashMap::<u32, HotkeyEntry>::new()));
         let hotkeys_clone = hotkeys.clone();
+        let expiry = Arc::new(Expiry::default());
+        let listener_expiry = expiry.clone();
+        let capturing = Arc::new(AtomicBool::new(false));
+        let listener_capturing = capturing.clone();
+        let executor = Executor::new(metrics.clone());
+        let listener_executor = executor.clone();
+        let stats = Arc::new(Mutex::new(Stats::default()));
+        let listener_stats = stats.clone();
+        let error_handler: Arc<Mutex<Option<ErrorHandler>>> = Arc::new(Mutex::new(None));
+        let listener_error_handler = error_handler.clone();
 
         // Spawn a thread to listen for hotkey events
         std::thread::spawn(move || {
@@ -62,8 +352,10 @@ impl HotkeyManager {
                         if event.state == global_hotkey::HotKeyState::Pressed {
                             debug!("Hotkey pressed event detected for id={}", event.id);
 
-                            match hotkeys_clone.lock() {
-                                Ok(hotkeys) => {
+                            // Reported once the lock is released, so the
+                            // handler may use the manager
+                            let failure = match hotkeys_clone.lock() {
+                                Ok(mut hotkeys) => {
                                     trace!(
                                         "Successfully acquired hotkeys lock, checking {} entries",
                                         hotkeys.len()
@@ -74,18 +366,44 @@ impl HotkeyManager {
                                             "Triggering callback for identifier: '{}'",
                                             entry.identifier
                                         );
-                                        trace!("About to call callback for '{}'", entry.identifier);
-                                        (entry.callback)(&entry.identifier);
-                                        trace!("Callback completed for '{}'", entry.identifier);
+                                        // Captured presses aren't triggers
+                                        if !listener_capturing.load(Ordering::SeqCst) {
+                                            listener_stats
+                                                .lock()
+                                                .expect("stats mutex poisoned")
+                                                .record(&entry.identifier, SystemTime::now());
+                                        }
+                                        listener_executor.submit(Job {
+                                            callback: entry.callback.clone(),
+                                            id: event.id,
+                                            identifier: entry.identifier.clone(),
+                                            key: entry.key.clone(),
+                                            state: event.state,
+                                            timestamp: Instant::now(),
+                                        });
+                                        if entry.once {
+                                            remove(
+                                                &listener_manager,
+                                                &listener_expiry,
+                                                &mut hotkeys,
+                                                event.id,
+                                            );
+                                        }
+                                        None
                                     } else {
                                         warn!("No hotkey entry found for id: {} (available IDs: {:?})", 
                                               event.id,
                                               hotkeys.keys().collect::<Vec<_>>());
+                                        Some(ListenerError::UnknownId(event.id))
                                     }
                                 }
                                 Err(e) => {
                                     error!("Failed to acquire hotkeys lock: {:?}", e);
+                                    Some(ListenerError::Poisoned)
                                 }
+                            };
+                            if let Some(failure) = failure {
+                                report(&listener_error_handler, &failure);
                             }
                         } else {
                             trace!("Ignoring hotkey event with state: {:?}", event.state);
@@ -94,14 +412,76 @@ impl HotkeyManager {
                     Err(e) => {
                         error!("Error receiving hotkey event: {:?}", e);
                         trace!("Receiver error details: {:?}", e);
+                        // The receiver only fails once the channel has
+                        // disconnected, so no further events can arrive
+                        report(
+                            &listener_error_handler,
+                            &ListenerError::Receive(e.to_string()),
+                        );
+                        break;
                     }
                 }
             }
         });
 
-        let result = Self { manager, hotkeys };
+        // Spawn a thread to unbind temporary hotkeys as they expire
+        let timer_manager = manager.clone();
+        let timer_hotkeys = hotkeys.clone();
+        let timer_expiry = expiry.clone();
+        std::thread::spawn(move || {
+            while let Some((at, id)) = timer_expiry.next() {
+                let mut hotkeys = timer_hotkeys.lock().expect("hotkeys mutex poisoned");
+                // Only matches if the hotkey hasn't been unbound and bound
+                // again since the deadline was set
+                if hotkeys
+                    .get(&id)
+                    .is_some_and(|entry| entry.expires == Some(at))
+                {
+                    remove(&timer_manager, &timer_expiry, &mut hotkeys, id);
+                }
+            }
+            trace!("Hotkey timer thread stopped");
+        });
+
+        let result = Self {
+            manager,
+            hotkeys,
+            expiry,
+            capturing,
+            disabled: Mutex::new(HashMap::new()),
+            executor,
+            stats,
+            metrics,
+            error_handler,
+        };
         info!("HotkeyManager initialized successfully");
-        Ok(result)
+        result
+    }
+
+    /// Sets a function to call when the listener thread hits a failure,
+    /// such as an event for an unknown hotkey, replacing any previous one.
+    ///
+    /// Failures are always logged as well. The handler runs on the listener
+    /// thread, so it should return quickly.
+    pub(crate) fn set_error_handler<F>(&self, handler: F)
+    where
+        F: Fn(&ListenerError) + Send + Sync + 'static,
+    {
+        *self
+            .error_handler
+            .lock()
+            .expect("error handler mutex poisoned") = Some(Arc::new(handler));
+    }
+
+    /// Fails if a capture has the bindings suspended, since they are
+    /// restored over any change once it finishes. Call with `hotkeys` locked.
+    fn check_not_capturing(&self) -> Result<()> {
+        if self.capturing.load(Ordering::SeqCst) {
+            return Err(Error::HotkeyOperation(
+                "Bindings can't change while a key is being captured".to_string(),
+            ));
+        }
+        Ok(())
     }
 
     /// Binds a new hotkey with a callback function.
@@ -110,7 +490,7 @@ impl HotkeyManager {
     ///
     /// * `identifier` - A string identifier for this hotkey
     /// * `key` - The key combination to bind
-    /// * `callback` - The function to call when the hotkey is pressed (receives the identifier)
+    /// * `callback` - The function to call when the hotkey is pressed (receives the event context)
     ///
     /// # Returns
     ///
@@ -126,11 +506,82 @@ impl HotkeyManager {
         callback: F,
     ) -> Result<u32>
     where
-        F: Fn(&str) + Send + Sync + 'static,
+        F: Fn(&HotkeyEvent) + Send + Sync + 'static,
     {
-        let key = key.into();
+        self.insert(
+            identifier.into(),
+            key.into(),
+            Arc::new(callback),
+            false,
+            None,
+        )
+    }
+
+    /// Binds a hotkey that unbinds itself once `ttl` has passed, or after
+    /// its first press if `once` is set.
+    ///
+    /// Useful for transient keys, such as a confirmation prompt. If the
+    /// hotkey is rebound before it expires, the new binding is kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the hotkey registration fails.
+    pub(crate) fn bind_temporary<F>(
+        &self,
+        identifier: impl Into<String>,
+        key: impl Into<Key>,
+        ttl: Duration,
+        once: bool,
+        callback: F,
+    ) -> Result<u32>
+    where
+        F: Fn(&HotkeyEvent) + Send + Sync + 'static,
+    {
+        let mut hotkeys = self.hotkeys.lock().expect("hotkeys mutex poisoned");
+        let id = self.insert_into(
+            &mut hotkeys,
+            identifier.into(),
+            key.into(),
+            Arc::new(callback),
+            once,
+            None,
+        )?;
+        // A TTL too long to represent never expires
+        if let Some(at) = Instant::now().checked_add(ttl) {
+            debug!("Hotkey id {} expires in {:?}", id, ttl);
+            if let Some(entry) = hotkeys.get_mut(&id) {
+                entry.expires = Some(at);
+            }
+            self.expiry.schedule(id, at);
+        }
+        Ok(id)
+    }
+
+    /// Registers a hotkey with the system and stores its entry.
+    fn insert(
+        &self,
+        identifier: String,
+        key: Key,
+        callback: HotkeyCallback,
+        once: bool,
+        group: Option<String>,
+    ) -> Result<u32> {
+        let mut hotkeys = self.hotkeys.lock().expect("hotkeys mutex poisoned");
+        self.insert_into(&mut hotkeys, identifier, key, callback, once, group)
+    }
+
+    /// Registers a hotkey with the system and stores its entry in
+    /// `hotkeys`, which the caller has locked.
+    fn insert_into(
+        &self,
+        hotkeys: &mut HashMap<u32, HotkeyEntry>,
+        identifier: String,
+        key: Key,
+        callback: HotkeyCallback,
+        once: bool,
+        group: Option<String>,
+    ) -> Result<u32> {
         let hotkey = key.to_hotkey();
-        let identifier = identifier.into();
         debug!(
             "Binding hotkey '{}': {:?} with id {}",
             identifier,
@@ -138,24 +589,38 @@ impl HotkeyManager {
             hotkey.id()
         );
         trace!("Key details: {:?}", key);
+        self.check_not_capturing()?;
+
+        // A key we have bound already would otherwise be reported as taken
+        // by another application
+        if let Some(entry) = hotkeys.get(&hotkey.id()) {
+            return Err(Error::HotkeyOperation(format!(
+                "{key} is already bound as '{}'",
+                entry.identifier
+            )));
+        }
 
         // Register with the system
         trace!("Registering hotkey with system...");
-        self.manager.register(hotkey)?;
+        self.manager
+            .register(hotkey)
+            .map_err(|e| Error::registration(e, &key))?;
         info!(
             "Successfully registered hotkey '{}' with system",
             identifier
         );
 
         // Store the hotkey entry
-        trace!("Acquiring hotkeys lock...");
-        let mut hotkeys = self.hotkeys.lock().expect("hotkeys mutex poisoned");
         let id = hotkey.id();
         trace!("Hotkey ID from hotkey.id(): {}", id);
         let entry = HotkeyEntry {
             hotkey,
+            key,
             identifier: identifier.clone(),
-            callback: Arc::new(callback),
+            callback,
+            once,
+            group,
+            expires: None,
         };
         hotkeys.insert(id, entry);
         debug!("Stored hotkey entry for '{}' with id {}", identifier, id);
@@ -168,6 +633,380 @@ impl HotkeyManager {
         Ok(id)
     }
 
+    /// Queues the callback bound to an identifier as if its hotkey was pressed.
+    ///
+    /// Returns `false` if no hotkey is bound with the identifier.
+    pub(crate) fn trigger(&self, identifier: &str) -> bool {
+        let job = {
+            let mut hotkeys = self.hotkeys.lock().expect("hotkeys mutex poisoned");
+            let found = hotkeys
+                .iter()
+                .find(|(_, entry)| identifier_matches(&entry.identifier, identifier))
+                .map(|(id, entry)| {
+                    let job = Job {
+                        callback: entry.callback.clone(),
+                        id: *id,
+                        identifier: entry.identifier.clone(),
+                        key: entry.key.clone(),
+                        state: HotKeyState::Pressed,
+                        timestamp: Instant::now(),
+                    };
+                    (job, entry.once)
+                });
+            if let Some((job, true)) = &found {
+                remove(&self.manager, &self.expiry, &mut hotkeys, job.id);
+            }
+            found.map(|(job, _)| job)
+        };
+        match job {
+            Some(job) => {
+                info!("Simulating hotkey press for identifier: '{}'", identifier);
+                if !self.capturing.load(Ordering::SeqCst) {
+                    self.stats
+                        .lock()
+                        .expect("stats mutex poisoned")
+                        .record(&job.identifier, SystemTime::now());
+                }
+                self.executor.submit(job);
+                true
+            }
+            None => {
+                warn!("Cannot trigger unbound identifier: '{}'", identifier);
+                false
+            }
+        }
+    }
+
+    /// Waits for the user to press any key combination and returns it.
+    ///
+    /// All current bindings are suspended while every supported combination
+    /// is registered, then restored once a key is pressed or the timeout
+    /// expires. Combinations that the system refuses to register (for
+    /// example, ones reserved by other applications) cannot be captured.
+    ///
+    /// Binding changes fail while a capture runs, rather than being undone
+    /// when the bindings are restored. Presses captured aren't counted in
+    /// [`stats`](Self::stats), and temporary hotkeys that expire meanwhile
+    /// aren't restored.
+    ///
+    /// Returns `None` if no key was pressed within the timeout.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if another capture is running, or naming every
+    /// previous binding that couldn't be restored. The rest are restored
+    /// regardless.
+    pub(crate) fn capture(&self, timeout: Duration) -> Result<Option<Key>> {
+        debug!("Capturing next key press (timeout: {:?})", timeout);
+        let (tx, rx) = mpsc::channel::<Key>();
+        let callback: HotkeyCallback = Arc::new(move |event: &HotkeyEvent| {
+            let _ = tx.send(event.key.clone());
+        });
+
+        // Suspend the current bindings and listen for everything
+        let saved: Vec<HotkeyEntry> = {
+            let mut hotkeys = self.hotkeys.lock().expect("hotkeys mutex poisoned");
+            if self.capturing.swap(true, Ordering::SeqCst) {
+                return Err(Error::HotkeyOperation(
+                    "A key is already being captured".to_string(),
+                ));
+            }
+            let saved: Vec<HotkeyEntry> = hotkeys.drain().map(|(_, entry)| entry).collect();
+            for entry in &saved {
+                if let Err(e) = self.manager.unregister(entry.hotkey) {
+                    warn!("Failed to suspend hotkey '{}': {}", entry.identifier, e);
+                }
+            }
+            let mut registered = 0;
+            for key in Key::all() {
+                let hotkey = key.to_hotkey();
+                if self.manager.register(hotkey).is_ok() {
+                    hotkeys.insert(
+                        hotkey.id(),
+                        HotkeyEntry {
+                            hotkey,
+                            identifier: key.canonical_string(),
+                            key,
+                            callback: callback.clone(),
+                            once: false,
+                            group: None,
+                            expires: None,
+                        },
+                    );
+                    registered += 1;
+                }
+            }
+            debug!("Registered {} hotkeys for capture", registered);
+            saved
+        };
+
+        let captured = rx.recv_timeout(timeout).ok();
+
+        // Restore the previous bindings
+        let mut hotkeys = self.hotkeys.lock().expect("hotkeys mutex poisoned");
+        for (_, entry) in hotkeys.drain() {
+            if let Err(e) = self.manager.unregister(entry.hotkey) {
+                trace!(
+                    "Failed to unregister capture hotkey '{}': {}",
+                    entry.identifier,
+                    e
+                );
+            }
+        }
+        let now = Instant::now();
+        let mut failed = Vec::new();
+        for entry in saved {
+            // The timer thread couldn't unbind these while they were suspended
+            if entry.expires.is_some_and(|at| at <= now) {
+                debug!(
+                    "Temporary hotkey '{}' expired while capturing",
+                    entry.identifier
+                );
+                self.expiry.cancel(&entry);
+                continue;
+            }
+            match self.manager.register(entry.hotkey) {
+                Ok(()) => {
+                    hotkeys.insert(entry.hotkey.id(), entry);
+                }
+                Err(e) => {
+                    warn!("Failed to restore hotkey '{}': {}", entry.identifier, e);
+                    self.expiry.cancel(&entry);
+                    failed.push(Error::registration(e, &entry.key).to_string());
+                }
+            }
+        }
+        self.capturing.store(false, Ordering::SeqCst);
+        if !failed.is_empty() {
+            return Err(Error::HotkeyOperation(format!(
+                "Failed to restore {} hotkeys after capturing: {}",
+                failed.len(),
+                failed.join("; ")
+            )));
+        }
+
+        match captured {
+            Some(key) => {
+                info!("Captured key press: '{}'", key);
+                Ok(Some(key))
+            }
+            None => {
+                debug!("No key pressed within capture timeout");
+                Ok(None)
+            }
+        }
+    }
+
+    /// Returns trigger statistics for every identifier that has been
+    /// triggered, most triggered first.
+    pub(crate) fn stats(&self) -> Vec<HotkeyStats> {
+        self.stats.lock().expect("stats mutex poisoned").all()
+    }
+
+    /// Returns the counters recorded so far, with the number of hotkeys bound.
+    pub(crate) fn metrics(&self) -> Metrics {
+        let active = self.hotkeys.lock().expect("hotkeys mutex poisoned").len();
+        self.metrics.snapshot(active)
+    }
+
+    /// Returns the collector for recording metrics outside the manager.
+    pub(crate) fn collector(&self) -> &Collector {
+        &self.metrics
+    }
+
+    /// Returns whether a hotkey is bound with the given identifier.
+    pub(crate) fn is_bound(&self, identifier: &str) -> bool {
+        self.hotkeys
+            .lock()
+            .expect("hotkeys mutex poisoned")
+            .values()
+            .any(|entry| identifier_matches(&entry.identifier, identifier))
+    }
+
+    /// Binds hotkeys as a named group with a single callback, so that they
+    /// can later be disabled, enabled or unbound together.
+    ///
+    /// Binding into a disabled group adds the hotkeys to it without
+    /// enabling the group.
+    ///
+    /// # Returns
+    ///
+    /// Returns a vector of results, one for each hotkey binding attempt.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::DuplicateKey`] without binding anything if a key
+    /// appears more than once.
+    pub(crate) fn bind_group<F, K>(
+        &self,
+        group: &str,
+        hotkeys: &[(impl Into<String> + Clone, K)],
+        callback: F,
+    ) -> Result<Vec<Result<u32>>>
+    where
+        F: Fn(&HotkeyEvent) + Send + Sync + 'static + Clone,
+        K: Into<Key> + Clone,
+    {
+        check_duplicates(hotkeys)?;
+        let mut bound = self.hotkeys.lock().expect("hotkeys mutex poisoned");
+        self.check_not_capturing()?;
+        let mut disabled = self.disabled.lock().expect("disabled mutex poisoned");
+        Ok(hotkeys
+            .iter()
+            .map(|(identifier, key)| {
+                let identifier = identifier.clone().into();
+                let key = key.clone().into();
+                let callback: HotkeyCallback = Arc::new(callback.clone());
+                match disabled.get_mut(group) {
+                    Some(entries) => {
+                        let hotkey = key.to_hotkey();
+                        entries.push(HotkeyEntry {
+                            hotkey,
+                            key,
+                            identifier,
+                            callback,
+                            once: false,
+                            group: Some(group.to_string()),
+                            expires: None,
+                        });
+                        Ok(hotkey.id())
+                    }
+                    None => self.insert_into(
+                        &mut bound,
+                        identifier,
+                        key,
+                        callback,
+                        false,
+                        Some(group.to_string()),
+                    ),
+                }
+            })
+            .collect())
+    }
+
+    /// Unregisters a group's hotkeys from the system, keeping them so that
+    /// the group can be enabled again.
+    ///
+    /// Returns the number of hotkeys disabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a hotkey fails to unregister.
+    pub(crate) fn disable_group(&self, group: &str) -> Result<usize> {
+        let mut hotkeys = self.hotkeys.lock().expect("hotkeys mutex poisoned");
+        self.check_not_capturing()?;
+        let mut disabled = self.disabled.lock().expect("disabled mutex poisoned");
+        let ids = group_ids(&hotkeys, group);
+        let stash = disabled.entry(group.to_string()).or_default();
+        for id in &ids {
+            if let Some(entry) = hotkeys.remove(id) {
+                let result = self.manager.unregister(entry.hotkey);
+                stash.push(entry);
+                result?;
+            }
+        }
+        info!("Disabled {} hotkeys in group '{}'", ids.len(), group);
+        Ok(ids.len())
+    }
+
+    /// Registers a disabled group's hotkeys with the system again.
+    ///
+    /// Returns the number of hotkeys enabled, which is zero if the group is
+    /// not disabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a hotkey fails to register, for instance because
+    /// the same key has been bound since the group was disabled. Hotkeys
+    /// that could not be registered stay disabled.
+    pub(crate) fn enable_group(&self, group: &str) -> Result<usize> {
+        let mut hotkeys = self.hotkeys.lock().expect("hotkeys mutex poisoned");
+        self.check_not_capturing()?;
+        let mut disabled = self.disabled.lock().expect("disabled mutex poisoned");
+        let Some(mut stash) = disabled.remove(group) else {
+            return Ok(0);
+        };
+        let mut count = 0;
+        while let Some(entry) = stash.pop() {
+            let id = entry.hotkey.id();
+            let result = if hotkeys.contains_key(&id) {
+                Err(Error::HotkeyOperation(format!(
+                    "{} is already bound",
+                    entry.key
+                )))
+            } else {
+                self.manager
+                    .register(entry.hotkey)
+                    .map_err(|e| Error::registration(e, &entry.key))
+            };
+            if let Err(e) = result {
+                stash.push(entry);
+                disabled.insert(group.to_string(), stash);
+                return Err(e);
+            }
+            hotkeys.insert(id, entry);
+            count += 1;
+        }
+        info!("Enabled {} hotkeys in group '{}'", count, group);
+        Ok(count)
+    }
+
+    /// Unbinds every hotkey in a group, whether or not it is enabled.
+    ///
+    /// Returns the number of hotkeys unbound.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a hotkey fails to unregister.
+    pub(crate) fn unbind_group(&self, group: &str) -> Result<usize> {
+        let mut hotkeys = self.hotkeys.lock().expect("hotkeys mutex poisoned");
+        self.check_not_capturing()?;
+        let disabled = self
+            .disabled
+            .lock()
+            .expect("disabled mutex poisoned")
+            .remove(group)
+            .map_or(0, |entries| entries.len());
+        let ids = group_ids(&hotkeys, group);
+        for id in &ids {
+            if let Some(entry) = hotkeys.remove(id) {
+                self.manager.unregister(entry.hotkey)?;
+            }
+        }
+        info!(
+            "Unbound {} hotkeys in group '{}'",
+            ids.len() + disabled,
+            group
+        );
+        Ok(ids.len() + disabled)
+    }
+
+    /// Unbinds the hotkeys for some keys, leaving the rest bound. Keys that
+    /// aren't bound, or are bound in a group, are skipped.
+    ///
+    /// Returns the number of hotkeys unbound.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a hotkey fails to unregister.
+    pub(crate) fn unbind_keys(&self, keys: &[Key]) -> Result<usize> {
+        let mut hotkeys = self.hotkeys.lock().expect("hotkeys mutex poisoned");
+        self.check_not_capturing()?;
+        let mut count = 0;
+        for key in keys {
+            let id = key.to_hotkey().id();
+            if hotkeys.get(&id).is_some_and(|entry| entry.group.is_none()) {
+                if let Some(entry) = hotkeys.remove(&id) {
+                    self.expiry.cancel(&entry);
+                    self.manager.unregister(entry.hotkey)?;
+                    count += 1;
+                }
+            }
+        }
+        debug!("Unbound {} of {} hotkeys", count, keys.len());
+        Ok(count)
+    }
+
     /// Unbinds all registered hotkeys.
     ///
     /// # Errors
@@ -176,41 +1015,185 @@ impl HotkeyManager {
     pub(crate) fn unbind_all(&self) -> Result<()> {
         debug!("Unbinding all hotkeys");
         let mut hotkeys = self.hotkeys.lock().expect("hotkeys mutex poisoned");
+        self.check_not_capturing()?;
         let count = hotkeys.len();
         trace!("Found {} hotkeys to unbind", count);
 
         for (id, entry) in hotkeys.drain() {
             trace!("Unregistering hotkey '{}' (id: {})", entry.identifier, id);
+            self.expiry.cancel(&entry);
             self.manager.unregister(entry.hotkey)?;
         }
+        self.disabled
+            .lock()
+            .expect("disabled mutex poisoned")
+            .clear();
 
         info!("Successfully unbound all {} hotkeys", count);
         Ok(())
     }
 
-    /// Convenience method to bind multiple hotkeys with a single callback that receives the identifier.
+    /// Replaces all registered hotkeys with `hotkeys`, like
+    /// [`unbind_all`](Self::unbind_all) followed by
+    /// [`bind_multiple`](Self::bind_multiple).
+    ///
+    /// Keys bound both before and after, outside a group, stay registered
+    /// with the system throughout, taking the new identifier and callback,
+    /// so that their presses aren't missed while the rest are swapped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::DuplicateKey`] without changing anything if a key
+    /// appears more than once, or an error if a hotkey fails to unregister.
+    pub(crate) fn rebind_multiple<F, K>(
+        &self,
+        hotkeys: &[(impl Into<String> + Clone, K)],
+        callback: F,
+    ) -> Result<Vec<Result<u32>>>
+    where
+        F: Fn(&HotkeyEvent) + Send + Sync + 'static + Clone,
+        K: Into<Key> + Clone,
+    {
+        check_duplicates(hotkeys)?;
+        let wanted: HashMap<u32, usize> = hotkeys
+            .iter()
+            .enumerate()
+            .map(|(idx, (_, key))| (key.clone().into().to_hotkey().id(), idx))
+            .collect();
+
+        let mut kept = HashMap::new();
+        {
+            let mut bound = self.hotkeys.lock().expect("hotkeys mutex poisoned");
+            self.check_not_capturing()?;
+            for (&id, entry) in bound.iter_mut() {
+                let Some(&idx) = wanted.get(&id) else {
+                    continue;
+                };
+                if entry.group.is_some() {
+                    continue;
+                }
+                let (identifier, key) = &hotkeys[idx];
+                self.expiry.cancel(entry);
+                entry.identifier = identifier.clone().into();
+                entry.key = key.clone().into();
+                entry.callback = Arc::new(callback.clone());
+                entry.once = false;
+                entry.expires = None;
+                kept.insert(idx, id);
+            }
+            let stale: Vec<u32> = bound
+                .keys()
+                .filter(|id| !wanted.get(id).is_some_and(|idx| kept.contains_key(idx)))
+                .copied()
+                .collect();
+            for id in stale {
+                if let Some(entry) = bound.remove(&id) {
+                    trace!("Unregistering hotkey '{}' (id: {})", entry.identifier, id);
+                    self.expiry.cancel(&entry);
+                    self.manager.unregister(entry.hotkey)?;
+                }
+            }
+        }
+        self.disabled
+            .lock()
+            .expect("disabled mutex poisoned")
+            .clear();
+        debug!(
+            "Kept {} of {} hotkeys registered while rebinding",
+            kept.len(),
+            hotkeys.len()
+        );
+
+        Ok(hotkeys
+            .iter()
+            .enumerate()
+            .map(|(idx, (identifier, key))| match kept.get(&idx) {
+                Some(&id) => Ok(id),
+                None => self.bind(identifier.clone(), key.clone(), callback.clone()),
+            })
+            .collect())
+    }
+
+    /// Convenience method to bind multiple hotkeys with a single callback that receives the event context.
     ///
     /// # Arguments
     ///
     /// * `hotkeys` - A slice of tuples containing (identifier, key)
-    /// * `callback` - The function to call when any hotkey is pressed (receives the identifier)
+    /// * `callback` - The function to call when any hotkey is pressed (receives the event context)
     ///
     /// # Returns
     ///
     /// Returns a vector of results, one for each hotkey binding attempt.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::DuplicateKey`] without binding anything if a key
+    /// appears more than once.
     pub(crate) fn bind_multiple<F, K>(
         &self,
         hotkeys: &[(impl Into<String> + Clone, K)],
         callback: F,
-    ) -> Vec<Result<u32>>
+    ) -> Result<Vec<Result<u32>>>
     where
-        F: Fn(&str) + Send + Sync + 'static + Clone,
+        F: Fn(&HotkeyEvent) + Send + Sync + 'static + Clone,
         K: Into<Key> + Clone,
     {
-        hotkeys
+        check_duplicates(hotkeys)?;
+        self.check_not_capturing()?;
+        Ok(hotkeys
             .iter()
             .map(|(id, key)| self.bind(id.clone(), key.clone(), callback.clone()))
-            .collect()
+            .collect())
+    }
+}
+
+/// Checks that no key appears more than once in a batch of bindings,
+/// comparing keys in canonical form.
+///
+/// # Errors
+///
+/// Returns [`Error::DuplicateKey`] for the first key that appears more than
+/// once, naming every binding for it.
+pub(crate) fn check_duplicates<K>(hotkeys: &[(impl Into<String> + Clone, K)]) -> Result<()>
+where
+    K: Into<Key> + Clone,
+{
+    let mut seen: HashMap<Key, Vec<String>> = HashMap::new();
+    let mut order = Vec::new();
+    for (identifier, key) in hotkeys {
+        let key = key.clone().into().canonical();
+        let identifiers = seen.entry(key.clone()).or_default();
+        if identifiers.is_empty() {
+            order.push(key);
+        }
+        identifiers.push(identifier.clone().into());
+    }
+    for key in order {
+        if seen[&key].len() > 1 {
+            let identifiers = seen.remove(&key).unwrap_or_default();
+            return Err(Error::DuplicateKey { key, identifiers });
+        }
+    }
+    Ok(())
+}
+
+/// IDs of the active hotkeys in a group
+fn group_ids(hotkeys: &HashMap<u32, HotkeyEntry>, group: &str) -> Vec<u32> {
+    hotkeys
+        .iter()
+        .filter(|(_, entry)| entry.group.as_deref() == Some(group))
+        .map(|(id, _)| *id)
+        .collect()
+}
+
+/// Unbinds a single hotkey, logging rather than returning a failure
+fn remove(manager: &Registrar, expiry: &Expiry, hotkeys: &mut HashMap<u32, HotkeyEntry>, id: u32) {
+    if let Some(entry) = hotkeys.remove(&id) {
+        expiry.cancel(&entry);
+        match manager.unregister(entry.hotkey) {
+            Ok(()) => info!("Unbound temporary hotkey '{}'", entry.identifier),
+            Err(e) => warn!("Failed to unbind hotkey '{}': {}", entry.identifier, e),
+        }
     }
 }
 
@@ -221,5 +1204,254 @@ impl Drop for HotkeyManager {
         if let Err(e) = self.unbind_all() {
             error!("Failed to unbind all hotkeys during drop: {:?}", e);
         }
+        self.expiry.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_duplicates() {
+        let key = |s: &str| Key::parse(s).unwrap();
+        let unique = [("a", key("a")), ("b", key("cmd+a"))];
+        assert!(check_duplicates(&unique).is_ok());
+
+        let batch = [
+            ("one", key("cmd+shift+a")),
+            ("two", key("b")),
+            ("three", key("shift+command+A")),
+        ];
+        match check_duplicates(&batch) {
+            Err(Error::DuplicateKey {
+                key: dup,
+                identifiers,
+            }) => {
+                assert_eq!(dup, key("shift+cmd+a"));
+                assert_eq!(identifiers, vec!["one", "three"]);
+            }
+            other => panic!("expected a duplicate key error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_identifier_callback() {
+        let (tx, rx) = mpsc::channel();
+        let callback = identifier_callback(move |identifier: &str| {
+            let _ = tx.send(identifier.to_string());
+        });
+        let key = Key::parse("cmd+a").unwrap();
+        callback(&HotkeyEvent {
+            id: 1,
+            identifier: "select-all",
+            key: &key,
+            state: HotKeyState::Pressed,
+            timestamp: Instant::now(),
+        });
+        assert_eq!(rx.try_recv().unwrap(), "select-all");
+    }
+
+    #[test]
+    fn test_trigger_records_bound_identifier() {
+        let manager = HotkeyManager::detached();
+        let (tx, rx) = mpsc::channel();
+        manager
+            .bind("cmd+a", Key::parse("cmd+a").unwrap(), move |event| {
+                let _ = tx.send(event.identifier.to_string());
+            })
+            .unwrap();
+
+        assert!(manager.trigger("meta+a"));
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), "cmd+a");
+        let stats = manager.stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].identifier, "cmd+a");
+        assert_eq!(stats[0].count, 1);
+    }
+
+    /// Waits up to a few seconds for a condition to hold
+    fn eventually(condition: impl Fn() -> bool) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if condition() {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        condition()
+    }
+
+    #[test]
+    fn test_bind_temporary_expires() {
+        let manager = HotkeyManager::detached();
+        let short = manager
+            .bind_temporary(
+                "short",
+                Key::parse("a").unwrap(),
+                Duration::from_millis(20),
+                false,
+                |_| {},
+            )
+            .unwrap();
+        manager
+            .bind_temporary(
+                "long",
+                Key::parse("b").unwrap(),
+                Duration::from_secs(3600),
+                false,
+                |_| {},
+            )
+            .unwrap();
+
+        assert!(eventually(|| !manager.is_bound("short")));
+        assert!(manager.is_bound("long"));
+        let deadlines = manager.expiry.deadlines.lock().unwrap();
+        assert!(deadlines.queue.iter().all(|&(_, id)| id != short));
+        assert_eq!(deadlines.queue.len(), 1);
+    }
+
+    #[test]
+    fn test_bind_temporary_cancelled() {
+        let manager = HotkeyManager::detached();
+        let a = Key::parse("a").unwrap();
+        manager
+            .bind_temporary("a", a.clone(), Duration::from_secs(3600), false, |_| {})
+            .unwrap();
+        assert_eq!(manager.unbind_keys(&[a]).unwrap(), 1);
+        assert!(manager.expiry.deadlines.lock().unwrap().queue.is_empty());
+
+        // Rebinding the key keeps it past the original deadline
+        manager
+            .bind_temporary(
+                "b",
+                Key::parse("b").unwrap(),
+                Duration::from_millis(20),
+                false,
+                |_| {},
+            )
+            .unwrap();
+        manager
+            .rebind_multiple(&[("b", Key::parse("b").unwrap())], |_| {})
+            .unwrap();
+        assert!(manager.expiry.deadlines.lock().unwrap().queue.is_empty());
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(manager.is_bound("b"));
+    }
+
+    #[test]
+    fn test_capture_keeps_bindings() {
+        let manager = Arc::new(HotkeyManager::detached());
+        manager.bind("a", Key::parse("a").unwrap(), |_| {}).unwrap();
+        manager
+            .bind_temporary(
+                "t",
+                Key::parse("t").unwrap(),
+                Duration::from_millis(20),
+                false,
+                |_| {},
+            )
+            .unwrap();
+        let capture = {
+            let manager = manager.clone();
+            std::thread::spawn(move || manager.capture(Duration::from_secs(5)))
+        };
+        assert!(eventually(|| manager.capturing.load(Ordering::SeqCst)));
+
+        // Changes would be undone by the restore, so they fail instead
+        let b = Key::parse("b").unwrap();
+        assert!(manager
+            .rebind_multiple(&[("b", b.clone())], |_| {})
+            .is_err());
+        assert!(manager.unbind_all().is_err());
+        assert!(manager.capture(Duration::ZERO).is_err());
+
+        // Let the temporary hotkey expire, then press a key to capture
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(manager.trigger("x"));
+        let captured = capture.join().unwrap().unwrap();
+        assert_eq!(captured, Some(Key::parse("x").unwrap()));
+        assert!(manager.stats().is_empty());
+        assert!(manager.is_bound("a"));
+        assert!(!manager.is_bound("t"));
+
+        // A rebind once the capture is done survives
+        manager.rebind_multiple(&[("b", b)], |_| {}).unwrap();
+        assert!(manager.is_bound("b"));
+        assert!(!manager.is_bound("a"));
+    }
+
+    /// A press of `a` with an identifier, to run on an executor
+    fn job(identifier: &str, callback: HotkeyCallback) -> Job {
+        Job {
+            callback,
+            id: 1,
+            identifier: identifier.to_string(),
+            key: Key::parse("a").unwrap(),
+            state: HotKeyState::Pressed,
+            timestamp: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn test_executor_isolates_panics() {
+        let executor = Executor::new(Arc::default());
+        let (tx, rx) = mpsc::channel();
+        executor.submit(job("bad", Arc::new(|_| panic!("callback failed"))));
+        executor.submit(job(
+            "good",
+            Arc::new(move |event| {
+                let _ = tx.send(event.identifier.to_string());
+            }),
+        ));
+        assert_eq!(rx.recv_timeout(Duration::from_secs(2)).unwrap(), "good");
+    }
+
+    #[test]
+    fn test_executor_keeps_order() {
+        let executor = Executor::new(Arc::default());
+        let (tx, rx) = mpsc::channel();
+        let callback: HotkeyCallback = Arc::new(move |event| {
+            // The first press takes longest, so a later one would overtake
+            // it if callbacks ran concurrently
+            if event.identifier == "0" {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            let _ = tx.send(event.identifier.to_string());
+        });
+        for n in 0..10 {
+            executor.submit(job(&n.to_string(), callback.clone()));
+        }
+        let order: Vec<String> = (0..10)
+            .map(|_| rx.recv_timeout(Duration::from_secs(2)).unwrap())
+            .collect();
+        let expected: Vec<String> = (0..10).map(|n| n.to_string()).collect();
+        assert_eq!(order, expected);
+    }
+
+    #[test]
+    fn test_report() {
+        let handler = Mutex::new(None);
+        report(&handler, &ListenerError::Poisoned);
+
+        let (tx, rx) = mpsc::channel();
+        let callback: ErrorHandler = Arc::new(move |failure: &ListenerError| {
+            let _ = tx.send(failure.clone());
+        });
+        *handler.lock().unwrap() = Some(callback);
+        report(&handler, &ListenerError::UnknownId(7));
+        assert_eq!(rx.try_recv().unwrap(), ListenerError::UnknownId(7));
+        assert_eq!(
+            ListenerError::UnknownId(7).to_string(),
+            "Received an event for unknown hotkey id 7"
+        );
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let payload = panic::catch_unwind(|| panic!("static")).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "static");
+        let payload = panic::catch_unwind(|| panic!("formatted {}", 1)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "formatted 1");
     }
 }
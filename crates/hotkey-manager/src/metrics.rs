@@ -0,0 +1,368 @@
+//! Counters and gauges for monitoring a long-running server.
+//!
+//! The manager and IPC layer record into a [`Collector`], and clients read a
+//! [`Metrics`] snapshot with a `GetMetrics` request. With the `metrics-http`
+//! feature, the server can also expose them over HTTP in the Prometheus text
+//! format.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A snapshot of a server's metrics
+///
+/// Counters start at zero when the server starts.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metrics {
+    /// Events written to clients
+    pub events_delivered: u64,
+    /// Times the bindings were replaced
+    pub rebinds: u64,
+    /// Hotkeys currently bound
+    pub active_bindings: u64,
+    /// Requests that failed, and connections that ended in an error
+    pub ipc_errors: u64,
+    /// Hotkey callbacks run
+    pub callbacks: u64,
+    /// Total time from a press to its callback finishing, in microseconds
+    pub callback_latency_us_total: u64,
+    /// Longest time from a press to its callback finishing, in microseconds
+    pub callback_latency_us_max: u64,
+    /// Hotkeys registered with the system
+    #[serde(default)]
+    pub registrations: u64,
+    /// Median time to register a hotkey with the system, in microseconds
+    #[serde(default)]
+    pub register_latency_us_p50: u64,
+    /// 95th percentile time to register a hotkey with the system, in
+    /// microseconds
+    #[serde(default)]
+    pub register_latency_us_p95: u64,
+    /// Hotkeys unregistered from the system
+    #[serde(default)]
+    pub unregistrations: u64,
+    /// Median time to unregister a hotkey from the system, in microseconds
+    #[serde(default)]
+    pub unregister_latency_us_p50: u64,
+    /// 95th percentile time to unregister a hotkey from the system, in
+    /// microseconds
+    #[serde(default)]
+    pub unregister_latency_us_p95: u64,
+}
+
+impl Metrics {
+    /// Render the metrics in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let metrics = [
+            (
+                "events_delivered_total",
+                "counter",
+                "Events written to clients",
+                self.events_delivered,
+            ),
+            (
+                "rebinds_total",
+                "counter",
+                "Times the bindings were replaced",
+                self.rebinds,
+            ),
+            (
+                "active_bindings",
+                "gauge",
+                "Hotkeys currently bound",
+                self.active_bindings,
+            ),
+            (
+                "ipc_errors_total",
+                "counter",
+                "Failed requests and connections",
+                self.ipc_errors,
+            ),
+            (
+                "callbacks_total",
+                "counter",
+                "Hotkey callbacks run",
+                self.callbacks,
+            ),
+            (
+                "callback_latency_microseconds_total",
+                "counter",
+                "Total time from a press to its callback finishing",
+                self.callback_latency_us_total,
+            ),
+            (
+                "callback_latency_microseconds_max",
+                "gauge",
+                "Longest time from a press to its callback finishing",
+                self.callback_latency_us_max,
+            ),
+            (
+                "registrations_total",
+                "counter",
+                "Hotkeys registered with the system",
+                self.registrations,
+            ),
+            (
+                "register_latency_microseconds_p50",
+                "gauge",
+                "Median time to register a hotkey with the system",
+                self.register_latency_us_p50,
+            ),
+            (
+                "register_latency_microseconds_p95",
+                "gauge",
+                "95th percentile time to register a hotkey with the system",
+                self.register_latency_us_p95,
+            ),
+            (
+                "unregistrations_total",
+                "counter",
+                "Hotkeys unregistered from the system",
+                self.unregistrations,
+            ),
+            (
+                "unregister_latency_microseconds_p50",
+                "gauge",
+                "Median time to unregister a hotkey from the system",
+                self.unregister_latency_us_p50,
+            ),
+            (
+                "unregister_latency_microseconds_p95",
+                "gauge",
+                "95th percentile time to unregister a hotkey from the system",
+                self.unregister_latency_us_p95,
+            ),
+        ];
+        let mut text = String::new();
+        for (name, kind, help, value) in metrics {
+            let _ = writeln!(text, "# HELP hotkey_manager_{name} {help}");
+            let _ = writeln!(text, "# TYPE hotkey_manager_{name} {kind}");
+            let _ = writeln!(text, "hotkey_manager_{name} {value}");
+        }
+        text
+    }
+}
+
+/// Number of recent timings that latency percentiles are computed over
+const LATENCY_SAMPLES: usize = 1024;
+
+/// The most recent timings of an operation, in microseconds
+#[derive(Debug, Default)]
+struct Latencies {
+    count: u64,
+    recent: VecDeque<u64>,
+}
+
+impl Latencies {
+    fn record(&mut self, latency: Duration) {
+        if self.recent.len() == LATENCY_SAMPLES {
+            self.recent.pop_front();
+        }
+        self.recent
+            .push_back(u64::try_from(latency.as_micros()).unwrap_or(u64::MAX));
+        self.count += 1;
+    }
+
+    /// The timing that `percent` percent of recent timings are no longer
+    /// than, or zero without any
+    fn percentile(&self, percent: usize) -> u64 {
+        let mut sorted: Vec<u64> = self.recent.iter().copied().collect();
+        sorted.sort_unstable();
+        // Nearest rank
+        let rank = (sorted.len() * percent).div_ceil(100);
+        sorted
+            .get(rank.saturating_sub(1))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Records metrics from any thread
+#[derive(Debug, Default)]
+pub(crate) struct Collector {
+    events_delivered: AtomicU64,
+    rebinds: AtomicU64,
+    ipc_errors: AtomicU64,
+    callbacks: AtomicU64,
+    callback_latency_us_total: AtomicU64,
+    callback_latency_us_max: AtomicU64,
+    register_latency: Mutex<Latencies>,
+    unregister_latency: Mutex<Latencies>,
+}
+
+impl Collector {
+    /// Record that an event was written to a client
+    pub(crate) fn event_delivered(&self) {
+        self.events_delivered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record that the bindings were replaced
+    pub(crate) fn rebind(&self) {
+        self.rebinds.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a failed request or connection
+    pub(crate) fn ipc_error(&self) {
+        self.ipc_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record that a callback finished, `latency` after its press
+    pub(crate) fn callback_finished(&self, latency: Duration) {
+        let us = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+        self.callbacks.fetch_add(1, Ordering::Relaxed);
+        self.callback_latency_us_total
+            .fetch_add(us, Ordering::Relaxed);
+        self.callback_latency_us_max
+            .fetch_max(us, Ordering::Relaxed);
+    }
+
+    /// Record that registering a hotkey with the system took `latency`
+    pub(crate) fn registered(&self, latency: Duration) {
+        self.register_latency
+            .lock()
+            .expect("latency mutex poisoned")
+            .record(latency);
+    }
+
+    /// Record that unregistering a hotkey from the system took `latency`
+    pub(crate) fn unregistered(&self, latency: Duration) {
+        self.unregister_latency
+            .lock()
+            .expect("latency mutex poisoned")
+            .record(latency);
+    }
+
+    /// The current metrics, with the number of hotkeys bound
+    pub(crate) fn snapshot(&self, active_bindings: usize) -> Metrics {
+        let register = self
+            .register_latency
+            .lock()
+            .expect("latency mutex poisoned");
+        let unregister = self
+            .unregister_latency
+            .lock()
+            .expect("latency mutex poisoned");
+        Metrics {
+            events_delivered: self.events_delivered.load(Ordering::Relaxed),
+            rebinds: self.rebinds.load(Ordering::Relaxed),
+            active_bindings: active_bindings as u64,
+            ipc_errors: self.ipc_errors.load(Ordering::Relaxed),
+            callbacks: self.callbacks.load(Ordering::Relaxed),
+            callback_latency_us_total: self.callback_latency_us_total.load(Ordering::Relaxed),
+            callback_latency_us_max: self.callback_latency_us_max.load(Ordering::Relaxed),
+            registrations: register.count,
+            register_latency_us_p50: register.percentile(50),
+            register_latency_us_p95: register.percentile(95),
+            unregistrations: unregister.count,
+            unregister_latency_us_p50: unregister.percentile(50),
+            unregister_latency_us_p95: unregister.percentile(95),
+        }
+    }
+}
+
+/// Serve metrics in the Prometheus text format at `/metrics` over HTTP,
+/// until the task is aborted or accepting fails
+#[cfg(feature = "metrics-http")]
+pub(crate) async fn serve<F>(address: std::net::SocketAddr, metrics: F) -> crate::Result<()>
+where
+    F: Fn() -> Metrics + Send + Sync + 'static,
+{
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tracing::{debug, info};
+
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    info!("Serving metrics on http://{}/metrics", address);
+    let metrics = std::sync::Arc::new(metrics);
+    loop {
+        let (mut stream, peer) = listener.accept().await?;
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            // Only the request line matters
+            let mut request = [0u8; 1024];
+            let len = match stream.read(&mut request).await {
+                Ok(len) => len,
+                Err(e) => {
+                    debug!("Failed to read metrics request from {}: {}", peer, e);
+                    return;
+                }
+            };
+            let request = String::from_utf8_lossy(&request[..len]);
+            let (status, body) = if request.starts_with("GET /metrics ") {
+                ("200 OK", metrics().to_prometheus())
+            } else {
+                ("404 Not Found", "Not found\n".to_string())
+            };
+            let response = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            if let Err(e) = stream.write_all(response.as_bytes()).await {
+                debug!("Failed to write metrics response to {}: {}", peer, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collector() {
+        let collector = Collector::default();
+        collector.event_delivered();
+        collector.event_delivered();
+        collector.rebind();
+        collector.ipc_error();
+        collector.callback_finished(Duration::from_micros(300));
+        collector.callback_finished(Duration::from_micros(100));
+        for us in 1..=100 {
+            collector.registered(Duration::from_micros(us));
+        }
+        collector.unregistered(Duration::from_micros(40));
+        let metrics = collector.snapshot(3);
+        assert_eq!(
+            metrics,
+            Metrics {
+                events_delivered: 2,
+                rebinds: 1,
+                active_bindings: 3,
+                ipc_errors: 1,
+                callbacks: 2,
+                callback_latency_us_total: 400,
+                callback_latency_us_max: 300,
+                registrations: 100,
+                register_latency_us_p50: 50,
+                register_latency_us_p95: 95,
+                unregistrations: 1,
+                unregister_latency_us_p50: 40,
+                unregister_latency_us_p95: 40,
+            }
+        );
+
+        let text = metrics.to_prometheus();
+        assert!(text.contains("# TYPE hotkey_manager_active_bindings gauge\n"));
+        assert!(text.contains("\nhotkey_manager_events_delivered_total 2\n"));
+        assert!(text.contains("\nhotkey_manager_register_latency_microseconds_p95 95\n"));
+        assert!(text.ends_with("hotkey_manager_unregister_latency_microseconds_p95 40\n"));
+    }
+
+    #[test]
+    fn test_latency_window() {
+        let mut latencies = Latencies::default();
+        assert_eq!(latencies.percentile(50), 0);
+
+        // Only the most recent timings count towards percentiles
+        for _ in 0..LATENCY_SAMPLES {
+            latencies.record(Duration::from_millis(5));
+        }
+        for _ in 0..LATENCY_SAMPLES {
+            latencies.record(Duration::from_micros(10));
+        }
+        assert_eq!(latencies.count, 2 * LATENCY_SAMPLES as u64);
+        assert_eq!(latencies.percentile(95), 10);
+    }
+}
@@ -0,0 +1,88 @@
+//! PID file support for locating a running server.
+//!
+//! The server can optionally record its process ID in a file next to its
+//! socket (`<socket>.pid`). Tooling that has no live connection can read this
+//! file to find, query, or signal the server process.
+
+use crate::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// Compute the PID file path for a socket path.
+///
+/// The PID file lives next to the socket, with `.pid` appended to the full
+/// socket file name.
+pub fn pid_file_path(socket_path: impl AsRef<Path>) -> PathBuf {
+    let mut path = socket_path.as_ref().as_os_str().to_owned();
+    path.push(".pid");
+    PathBuf::from(path)
+}
+
+/// A PID file written by the server. The file is removed when the value is
+/// dropped or explicitly removed.
+pub(crate) struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// Write the current process ID to the PID file for the given socket path.
+    pub(crate) fn create(socket_path: impl AsRef<Path>) -> Result<Self> {
+        let path = pid_file_path(socket_path);
+        fs::write(&path, format!("{}\n", std::process::id()))?;
+        debug!("Wrote PID file: {}", path.display());
+        Ok(Self { path })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("Failed to remove PID file {}: {}", self.path.display(), e);
+        } else {
+            debug!("Removed PID file: {}", self.path.display());
+        }
+    }
+}
+
+/// Read a PID from the PID file for the given socket path.
+///
+/// Returns `None` if the file does not exist or does not contain a valid PID.
+pub(crate) fn read_pid(socket_path: impl AsRef<Path>) -> Option<u32> {
+    let path = pid_file_path(socket_path);
+    let contents = fs::read_to_string(&path).ok()?;
+    match contents.trim().parse() {
+        Ok(pid) => Some(pid),
+        Err(_) => {
+            warn!("Invalid PID file contents in {}", path.display());
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pid_file_path() {
+        assert_eq!(
+            pid_file_path("/tmp/hotkey-manager.sock"),
+            PathBuf::from("/tmp/hotkey-manager.sock.pid")
+        );
+    }
+
+    #[test]
+    fn test_pid_file_roundtrip() {
+        let socket_path = std::env::temp_dir().join(format!(
+            "hotkey-manager-pidfile-test-{}.sock",
+            std::process::id()
+        ));
+        let pid_file = PidFile::create(&socket_path).unwrap();
+        assert_eq!(read_pid(&socket_path), Some(std::process::id()));
+
+        drop(pid_file);
+        assert!(!pid_file_path(&socket_path).exists());
+        assert_eq!(read_pid(&socket_path), None);
+    }
+}
@@ -1,14 +1,101 @@
-use crate::{Error, Result};
-use std::path::PathBuf;
+use crate::ipc::IPCClient;
+use crate::{default_socket_path, Error, Result, FD_ENV, READY_ENV};
+use std::fmt;
+use std::future::Future;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::ExitStatus;
 use std::process::{Child, Command};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
 use std::time::Duration;
-use tokio::time::sleep;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout, Instant};
 use tracing::{debug, error, info, warn};
 
-/// Default delay to wait for server startup
-pub(crate) const DEFAULT_STARTUP_DELAY: Duration = Duration::from_millis(500);
+/// Default time to wait for the server to become ready after spawning
+pub(crate) const DEFAULT_STARTUP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Default time to wait for the server to exit after SIGTERM, before
+/// killing it
+pub(crate) const DEFAULT_STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long the default health check waits for the server to respond
+const PING_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How often `start` runs the health check while waiting for readiness
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// The future returned by a health check
+type HealthFuture = Pin<Box<dyn Future<Output = bool> + Send>>;
+
+/// A check of whether the server listening on a socket path is responsive
+#[derive(Clone)]
+pub(crate) struct HealthCheck(Arc<dyn Fn(PathBuf) -> HealthFuture + Send + Sync>);
+
+impl HealthCheck {
+    /// Create a health check from an async function of the socket path
+    pub fn new<F, Fut>(check: F) -> Self
+    where
+        F: Fn(PathBuf) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        Self(Arc::new(move |path| Box::pin(check(path))))
+    }
+
+    /// Connect to the socket and ping the server
+    pub fn ping() -> Self {
+        Self::new(|path| async move {
+            let ping = async {
+                let connection = IPCClient::new(path).connect().await?;
+                connection.ping().await
+            };
+            matches!(timeout(PING_TIMEOUT, ping).await, Ok(Ok(())))
+        })
+    }
+
+    async fn check(&self, socket_path: &Path) -> bool {
+        (self.0)(socket_path.to_path_buf()).await
+    }
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self {
+        Self::ping()
+    }
+}
+
+impl fmt::Debug for HealthCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("HealthCheck")
+    }
+}
+
+/// The health of a server process, as last seen by its monitor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ServerHealth {
+    /// The process is running and its health check passes
+    Healthy,
+    /// The process is running, but its health check fails
+    Wedged,
+    /// The process has exited
+    Dead,
+}
+
+impl ServerHealth {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Self::Healthy,
+            1 => Self::Wedged,
+            _ => Self::Dead,
+        }
+    }
+}
 
 /// Configuration for launching a hotkey server process
 #[derive(Debug, Clone)]
@@ -19,10 +106,29 @@ pub(crate) struct ProcessConfig {
     pub args: Vec<String>,
     /// Environment variables to set
     pub env: Vec<(String, String)>,
-    /// How long to wait after spawning before considering it "started"
-    pub startup_delay: Duration,
+    /// How long to wait after spawning for the health check to pass
+    pub startup_timeout: Duration,
     /// Whether to inherit the parent's environment
     pub inherit_env: bool,
+    /// Socket path the server listens on, passed to the health check
+    pub socket_path: PathBuf,
+    /// Flag to pass `socket_path` to the server with, if it takes one
+    pub socket_arg: Option<String>,
+    /// Whether the server creates the file named by [`READY_ENV`] once it is
+    /// listening. If so, `start` waits for the file instead of running the
+    /// health check.
+    pub ready_handshake: bool,
+    /// Decides whether the server is ready, and later whether it is healthy
+    pub health_check: HealthCheck,
+    /// How often to check the health of the running server, if at all
+    pub health_interval: Option<Duration>,
+    /// How long to wait for the server to exit after SIGTERM, before
+    /// killing it
+    pub stop_timeout: Duration,
+    /// One end of a connected socket for the server to serve, instead of
+    /// listening on `socket_path`. Passed to the next server started, and
+    /// then closed.
+    pub inherited_fd: Option<Arc<OwnedFd>>,
 }
 
 impl ProcessConfig {
@@ -32,35 +138,88 @@ impl ProcessConfig {
             executable: executable.into(),
             args: vec!["--server".to_string()],
             env: Vec::new(),
-            startup_delay: DEFAULT_STARTUP_DELAY,
+            startup_timeout: DEFAULT_STARTUP_TIMEOUT,
             inherit_env: true,
+            socket_path: PathBuf::from(default_socket_path()),
+            socket_arg: None,
+            ready_handshake: false,
+            health_check: HealthCheck::default(),
+            health_interval: None,
+            stop_timeout: DEFAULT_STOP_TIMEOUT,
+            inherited_fd: None,
         }
     }
+
+    /// Pass `fd`, one end of a connected socket, to the server, which
+    /// serves it instead of listening on a path. The server learns the
+    /// descriptor from the [`FD_ENV`] environment variable.
+    ///
+    /// The server is taken to be ready once it has been spawned, since it
+    /// has no socket path to check.
+    pub fn with_inherited_fd(mut self, fd: OwnedFd) -> Self {
+        self.inherited_fd = Some(Arc::new(fd));
+        self
+    }
+}
+
+/// The ready file used for a server on a socket path: the socket path with
+/// `.ready` appended
+fn ready_file_path(socket_path: &Path) -> PathBuf {
+    let mut path = socket_path.as_os_str().to_owned();
+    path.push(".ready");
+    PathBuf::from(path)
+}
+
+/// How a server process was stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Stopped {
+    /// There was no process, or it had already exited
+    NotRunning,
+    /// The process exited after SIGTERM
+    Terminated,
+    /// The process ignored SIGTERM, and was killed
+    Killed,
 }
 
 /// A managed server process for hotkey handling
+///
+/// Shut the server down with [`close`](Self::close), which gives it a
+/// chance to exit cleanly. Dropping a running `ServerProcess` kills the
+/// server immediately: `Drop` can't wait for a graceful exit without
+/// blocking, which is never safe from inside an async runtime.
 pub struct ServerProcess {
-    child: Option<Child>,
+    /// PID of the most recently started process
+    pid: Option<u32>,
+    /// The exit status of that process, once it has exited
+    exited: Option<watch::Receiver<Option<ExitStatus>>>,
+    /// Channels to send the exit status of the next process to exit
+    exit_listeners: Arc<Mutex<Vec<mpsc::UnboundedSender<ExitStatus>>>>,
     config: ProcessConfig,
-    is_running: Arc<AtomicBool>,
+    /// The last [`ServerHealth`] seen by the monitor
+    health: Arc<AtomicU8>,
+    monitor: Option<JoinHandle<()>>,
 }
 
 impl ServerProcess {
     /// Create a new server process with the given configuration
     pub(crate) fn new(config: ProcessConfig) -> Self {
         Self {
-            child: None,
+            pid: None,
+            exited: None,
+            exit_listeners: Arc::new(Mutex::new(Vec::new())),
             config,
-            is_running: Arc::new(AtomicBool::new(false)),
+            health: Arc::new(AtomicU8::new(ServerHealth::Dead as u8)),
+            monitor: None,
         }
     }
 
     /// Start the server process
     pub(crate) async fn start(&mut self) -> Result<()> {
         if self.is_running() {
-            return Err(Error::HotkeyOperation(
-                "Server is already running".to_string(),
-            ));
+            return Err(Error::ServerSpawnFailed {
+                reason: "Server is already running".to_string(),
+                source: None,
+            });
         }
 
         info!("Starting server process: {:?}", self.config.executable);
@@ -72,6 +231,9 @@ impl ServerProcess {
         for arg in &self.config.args {
             command.arg(arg);
         }
+        if let Some(flag) = &self.config.socket_arg {
+            command.arg(flag).arg(&self.config.socket_path);
+        }
 
         // Configure environment
         if !self.config.inherit_env {
@@ -82,95 +244,358 @@ impl ServerProcess {
             command.env(key, value);
         }
 
+        let inherited_fd = self.config.inherited_fd.take();
+        if let Some(fd) = &inherited_fd {
+            let fd = fd.as_raw_fd();
+            command.env(FD_ENV, fd.to_string());
+            // SAFETY: fcntl is async-signal-safe, and the closure touches
+            // nothing else between fork and exec
+            unsafe {
+                command.pre_exec(move || {
+                    // Let the descriptor survive exec in the child only
+                    let flags = libc::fcntl(fd, libc::F_GETFD);
+                    if flags < 0 || libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC) < 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        }
+
+        let ready_file = (self.config.ready_handshake && inherited_fd.is_none())
+            .then(|| ready_file_path(&self.config.socket_path));
+        if let Some(path) = &ready_file {
+            // A file left by an earlier server would say we're ready at once
+            let _ = std::fs::remove_file(path);
+            command.env(READY_ENV, path);
+        }
+
+        // Run the server in its own process group, so that stopping it also
+        // stops anything it spawned
+        command.process_group(0);
+
         // Spawn the process
-        let child = command.spawn().map_err(Error::Io)?;
+        let child = command.spawn().map_err(|e| Error::ServerSpawnFailed {
+            reason: format!("Failed to run {:?}", self.config.executable),
+            source: Some(e),
+        })?;
 
         let pid = child.id();
         info!("Server process spawned with PID: {}", pid);
+        self.watch(child);
 
-        self.child = Some(child);
-        self.is_running.store(true, Ordering::SeqCst);
+        // The child has its own copy of an inherited socket. Closing ours
+        // lets the other end see the server exit.
+        let inherited = inherited_fd.is_some();
+        drop(inherited_fd);
 
-        // Wait for startup
-        debug!("Waiting {:?} for server startup", self.config.startup_delay);
-        sleep(self.config.startup_delay).await;
+        // Wait for the server to create its ready file, or to pass its health
+        // check
+        debug!(
+            "Waiting up to {:?} for server readiness",
+            self.config.startup_timeout
+        );
+        let deadline = Instant::now() + self.config.startup_timeout;
+        loop {
+            if !self.is_running() {
+                return Err(Error::ServerSpawnFailed {
+                    reason: "Server process died during startup".to_string(),
+                    source: None,
+                });
+            }
+            let ready = match &ready_file {
+                Some(path) => path.exists(),
+                // Requests on the socket wait until the server reads them
+                None if inherited => true,
+                None => {
+                    self.config
+                        .health_check
+                        .check(&self.config.socket_path)
+                        .await
+                }
+            };
+            if ready {
+                break;
+            }
+            if Instant::now() >= deadline {
+                return Err(Error::Timeout {
+                    operation: format!(
+                        "waiting {:?} for the server to become ready",
+                        self.config.startup_timeout
+                    ),
+                });
+            }
+            sleep(READY_POLL_INTERVAL).await;
+        }
+        if let Some(path) = &ready_file {
+            let _ = std::fs::remove_file(path);
+        }
+        info!("Server process is ready");
+        self.health
+            .store(ServerHealth::Healthy as u8, Ordering::SeqCst);
 
-        // Check if process is still running
-        if !self.is_running() {
-            return Err(Error::HotkeyOperation(
-                "Server process died during startup".to_string(),
-            ));
+        // Health checks connect to the socket path, which a server with an
+        // inherited socket doesn't listen on
+        if let (Some(interval), Some(exited), false) =
+            (self.config.health_interval, &self.exited, inherited)
+        {
+            self.monitor = Some(tokio::spawn(monitor(
+                pid,
+                exited.clone(),
+                self.config.clone(),
+                interval,
+                self.health.clone(),
+            )));
         }
 
         Ok(())
     }
 
-    /// Stop the server process
-    pub(crate) async fn stop(&mut self) -> Result<()> {
-        if let Some(mut child) = self.child.take() {
-            info!("Stopping server process");
+    /// Wait for a child to exit on a background thread, which reaps it and
+    /// reports its exit status.
+    fn watch(&mut self, mut child: Child) {
+        let pid = child.id();
+        let (sender, receiver) = watch::channel(None);
+        let listeners = self.exit_listeners.clone();
+        let spawned = thread::Builder::new()
+            .name(format!("server-{pid}-exit"))
+            .spawn(move || {
+                let status = match child.wait() {
+                    Ok(status) => status,
+                    Err(e) => {
+                        error!("Failed to wait for server process {}: {}", pid, e);
+                        return;
+                    }
+                };
+                info!("Server process {} exited with status: {}", pid, status);
+                // Hold the listeners while publishing, so that a listener is
+                // either notified here or sees the status when it subscribes
+                let mut listeners = listeners.lock().expect("exit listeners mutex poisoned");
+                let _ = sender.send(Some(status));
+                for listener in listeners.drain(..) {
+                    let _ = listener.send(status);
+                }
+            });
+        if let Err(e) = spawned {
+            error!("Failed to watch server process {}: {}", pid, e);
+        }
+        self.pid = Some(pid);
+        self.exited = Some(receiver);
+    }
+
+    /// The exit status of the server process, if it has exited
+    fn exit_status(&self) -> Option<ExitStatus> {
+        self.exited.as_ref().and_then(|exited| *exited.borrow())
+    }
+
+    /// Wait for the server process to exit, returning its exit status.
+    ///
+    /// Resolves immediately if the process has already exited, and to `None`
+    /// if it was never started or its exit status couldn't be read.
+    pub fn wait_exited(&self) -> impl Future<Output = Option<ExitStatus>> + Send + 'static {
+        let exited = self.exited.clone();
+        async move {
+            let mut exited = exited?;
+            let status = *exited.wait_for(Option::is_some).await.ok()?;
+            status
+        }
+    }
 
-            // Try graceful termination first
-            if let Err(e) = child.kill() {
-                error!("Failed to kill server process: {}", e);
-                return Err(Error::Io(e));
+    /// Get a channel that receives the exit status of the server process
+    /// when it exits.
+    ///
+    /// If the process has already exited, its status is available at once.
+    pub fn exit_events(&self) -> mpsc::UnboundedReceiver<ExitStatus> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let mut listeners = self
+            .exit_listeners
+            .lock()
+            .expect("exit listeners mutex poisoned");
+        match self.exit_status() {
+            Some(status) => {
+                let _ = sender.send(status);
             }
+            None => listeners.push(sender),
+        }
+        receiver
+    }
 
-            // Wait for the process to exit
-            match child.wait() {
-                Ok(status) => {
-                    info!("Server process exited with status: {:?}", status);
-                }
-                Err(e) => {
-                    warn!("Failed to wait for server process: {}", e);
-                }
+    /// The health of the server, as of the last check by the monitor.
+    ///
+    /// Without a health interval, the server is only checked at startup,
+    /// so this reports a healthy server until its process exits.
+    pub fn health(&self) -> ServerHealth {
+        if !self.is_running() {
+            return ServerHealth::Dead;
+        }
+        ServerHealth::from_u8(self.health.load(Ordering::SeqCst))
+    }
+
+    /// Stop the server process.
+    ///
+    /// The server is sent SIGTERM so it can clean up, and killed if it
+    /// hasn't exited once the stop timeout has passed.
+    pub(crate) async fn stop(&mut self) -> Result<Stopped> {
+        if let Some(monitor) = self.monitor.take() {
+            monitor.abort();
+        }
+        // The server's group is named by its PID
+        let Some(group) = self.pid.take() else {
+            return Ok(Stopped::NotRunning);
+        };
+        info!("Stopping server process");
+
+        if self.exit_status().is_some() {
+            debug!("Server process had already exited");
+            signal_group(group, libc::SIGKILL);
+            return Ok(Stopped::NotRunning);
+        }
+
+        // Try graceful termination first
+        let exited = self.wait_exited();
+        let stopped = if signal_group(group, libc::SIGTERM)
+            && timeout(self.config.stop_timeout, self.wait_exited())
+                .await
+                .is_ok()
+        {
+            // Don't leave behind anything the server spawned
+            signal_group(group, libc::SIGKILL);
+            Stopped::Terminated
+        } else {
+            warn!(
+                "Server process did not exit within {:?}, killing it",
+                self.config.stop_timeout
+            );
+            if !signal_group(group, libc::SIGKILL) {
+                error!("Failed to kill server process {}", group);
+                return Err(Error::HotkeyOperation(format!(
+                    "Failed to kill server process {group}"
+                )));
             }
+            Stopped::Killed
+        };
 
-            self.is_running.store(false, Ordering::SeqCst);
+        // Wait for the process to be reaped
+        if timeout(self.config.stop_timeout, exited).await.is_err() {
+            warn!("Server process {} was not reaped after being killed", group);
         }
+        info!("Server process stopped ({:?})", stopped);
 
-        Ok(())
+        Ok(stopped)
     }
 
-    /// Check if the server process is running
-    pub(crate) fn is_running(&self) -> bool {
-        if let Some(child) = self.child.as_ref() {
-            // Try to get the process status without waiting
-            match std::process::Command::new("kill")
-                .args(["-0", &child.id().to_string()])
-                .output()
-            {
-                Ok(output) => {
-                    let is_running = output.status.success();
-                    self.is_running.store(is_running, Ordering::SeqCst);
-                    is_running
-                }
-                Err(_) => {
-                    // If we can't check, assume it's not running
-                    self.is_running.store(false, Ordering::SeqCst);
-                    false
-                }
-            }
-        } else {
-            false
+    /// Gracefully stop the server process, if it is running, and release
+    /// it.
+    ///
+    /// This is the async counterpart of dropping the `ServerProcess`, and
+    /// should be preferred wherever an `.await` is possible.
+    pub async fn close(mut self) -> Result<()> {
+        self.stop().await.map(|_| ())
+    }
+
+    /// Check if the server process is running.
+    ///
+    /// This doesn't block or spawn anything: the status is kept up to date
+    /// by the thread that waits for the process to exit, so it is cheap to
+    /// call from async code.
+    pub fn is_running(&self) -> bool {
+        let (Some(pid), Some(exited)) = (self.pid, &self.exited) else {
+            return false;
+        };
+        if exited.borrow().is_some() {
+            return false;
+        }
+        if exited.has_changed().is_err() {
+            // The watcher has stopped without an exit status, so ask the
+            // system instead
+            return is_process_alive(pid);
         }
+        true
     }
 
     /// Get the process ID if running
     pub fn pid(&self) -> Option<u32> {
-        self.child.as_ref().map(|c| c.id())
+        self.pid.filter(|_| self.is_running())
+    }
+}
+
+/// Periodically check the health of a running server, until its process
+/// exits.
+async fn monitor(
+    pid: u32,
+    exited: watch::Receiver<Option<ExitStatus>>,
+    config: ProcessConfig,
+    interval: Duration,
+    health: Arc<AtomicU8>,
+) {
+    loop {
+        sleep(interval).await;
+        let status = if exited.borrow().is_some() {
+            ServerHealth::Dead
+        } else if config.health_check.check(&config.socket_path).await {
+            ServerHealth::Healthy
+        } else {
+            ServerHealth::Wedged
+        };
+        let previous = ServerHealth::from_u8(health.swap(status as u8, Ordering::SeqCst));
+        if status != previous {
+            match status {
+                ServerHealth::Healthy => info!("Server process {} is healthy again", pid),
+                ServerHealth::Wedged => warn!("Server process {} is not responding", pid),
+                ServerHealth::Dead => warn!("Server process {} has exited", pid),
+            }
+        }
+        if status == ServerHealth::Dead {
+            break;
+        }
+    }
+}
+
+/// Send a signal, such as `SIGTERM`, to every process in a process group,
+/// returning whether any process received it.
+fn signal_group(group: u32, signal: libc::c_int) -> bool {
+    let Ok(group) = libc::pid_t::try_from(group) else {
+        return false;
+    };
+    // SAFETY: killpg has no memory safety requirements
+    if unsafe { libc::killpg(group, signal) } == 0 {
+        return true;
+    }
+    let e = std::io::Error::last_os_error();
+    // A group with no processes left has nothing to signal
+    if e.raw_os_error() != Some(libc::ESRCH) {
+        warn!("Failed to send signal {} to group {}: {}", signal, group, e);
+    }
+    false
+}
+
+/// Check whether a process with the given PID exists.
+///
+/// If the check itself fails, the process is assumed not to be running.
+pub(crate) fn is_process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // Signal 0 checks that the process exists without signalling it
+    // SAFETY: kill has no memory safety requirements
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return true;
     }
+    // The process exists, but belongs to someone else
+    std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
 }
 
 impl Drop for ServerProcess {
     fn drop(&mut self) {
+        if let Some(monitor) = self.monitor.take() {
+            monitor.abort();
+        }
         if self.is_running() {
-            warn!("ServerProcess dropped while still running, attempting to stop");
-            // Always use synchronous kill to avoid runtime issues
-            if let Some(mut child) = self.child.take() {
-                let _ = child.kill();
-                let _ = child.wait();
-                self.is_running.store(false, Ordering::SeqCst);
+            warn!("ServerProcess dropped while still running, use close() to stop it gracefully");
+            // Kill synchronously, which is safe whether or not we are inside
+            // an async runtime. The watcher thread reaps the process.
+            if let Some(group) = self.pid.take() {
+                signal_group(group, libc::SIGKILL);
             }
         }
     }
@@ -187,7 +612,169 @@ mod tests {
         assert_eq!(config.executable, PathBuf::from("/usr/bin/test"));
         assert_eq!(config.args, vec!["--server"]);
         assert_eq!(config.env, Vec::<(String, String)>::new());
-        assert_eq!(config.startup_delay, DEFAULT_STARTUP_DELAY);
+        assert_eq!(config.startup_timeout, DEFAULT_STARTUP_TIMEOUT);
         assert!(config.inherit_env);
+        assert_eq!(config.socket_path, PathBuf::from(crate::user_socket_path()));
+        assert_eq!(config.socket_arg, None);
+        assert!(!config.ready_handshake);
+        assert_eq!(config.health_interval, None);
+        assert_eq!(config.stop_timeout, DEFAULT_STOP_TIMEOUT);
+    }
+
+    #[tokio::test]
+    async fn test_stop() {
+        let mut server = ServerProcess::new(ProcessConfig::new("sleep"));
+        server.watch(
+            Command::new("sleep")
+                .arg("10")
+                .process_group(0)
+                .spawn()
+                .unwrap(),
+        );
+        assert_eq!(server.stop().await.unwrap(), Stopped::Terminated);
+        assert_eq!(server.stop().await.unwrap(), Stopped::NotRunning);
+
+        // A process that ignores SIGTERM is killed once the timeout passes
+        let mut config = ProcessConfig::new("sh");
+        config.stop_timeout = Duration::from_millis(200);
+        let mut server = ServerProcess::new(config);
+        let child = Command::new("sh")
+            .args(["-c", "trap '' TERM; sleep 10"])
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        server.watch(child);
+        // Give the shell time to install its trap
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(server.stop().await.unwrap(), Stopped::Killed);
+    }
+
+    #[tokio::test]
+    async fn test_stop_kills_group() {
+        use std::io::{BufRead, BufReader};
+        use std::process::Stdio;
+
+        let mut server = ServerProcess::new(ProcessConfig::new("sh"));
+        let mut child = Command::new("sh")
+            .args(["-c", "sleep 10 & echo $!; wait"])
+            .stdout(Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let grandchild: u32 = line.trim().parse().unwrap();
+        server.watch(child);
+
+        // The orphaned grandchild may linger as a zombie until it is reaped
+        let exited = |pid: u32| {
+            let output = Command::new("ps")
+                .args(["-o", "stat=", "-p", &pid.to_string()])
+                .output()
+                .unwrap();
+            let stat = String::from_utf8_lossy(&output.stdout);
+            stat.trim().is_empty() || stat.trim().starts_with('Z')
+        };
+        assert!(!exited(grandchild));
+        assert_eq!(server.stop().await.unwrap(), Stopped::Terminated);
+        sleep(Duration::from_millis(100)).await;
+        assert!(exited(grandchild));
+    }
+
+    #[tokio::test]
+    async fn test_drop_in_runtime() {
+        let mut server = ServerProcess::new(ProcessConfig::new("sleep"));
+        server.watch(
+            Command::new("sleep")
+                .arg("10")
+                .process_group(0)
+                .spawn()
+                .unwrap(),
+        );
+        let exited = server.wait_exited();
+        drop(server);
+        assert!(exited.await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_exit_notification() {
+        let mut server = ServerProcess::new(ProcessConfig::new("sh"));
+        assert_eq!(server.wait_exited().await, None);
+
+        let mut events = server.exit_events();
+        server.watch(Command::new("sh").args(["-c", "exit 3"]).spawn().unwrap());
+        let status = server.wait_exited().await.unwrap();
+        assert_eq!(status.code(), Some(3));
+        assert_eq!(events.recv().await, Some(status));
+        assert!(!server.is_running());
+
+        // Subscribing after the exit still reports it
+        assert_eq!(server.exit_events().recv().await, Some(status));
+    }
+
+    #[tokio::test]
+    async fn test_ready_handshake() {
+        let mut config = ProcessConfig::new("sh");
+        config.args = vec![
+            "-c".to_string(),
+            "sleep 0.1; touch \"$HOTKEY_READY_FILE\"; sleep 10".to_string(),
+        ];
+        config.socket_path = std::env::temp_dir().join("hotkey-manager-ready-test.sock");
+        config.ready_handshake = true;
+        // The health check would never pass, so only the ready file can
+        config.health_check = HealthCheck::new(|_| async { false });
+        let ready_file = ready_file_path(&config.socket_path);
+
+        let mut server = ServerProcess::new(config);
+        server.start().await.unwrap();
+        assert!(!ready_file.exists());
+        assert_eq!(server.stop().await.unwrap(), Stopped::Terminated);
+    }
+
+    #[tokio::test]
+    async fn test_inherited_fd() {
+        use std::io::{ErrorKind, Read};
+        use std::os::unix::net::UnixStream;
+
+        let (mut ours, theirs) = UnixStream::pair().unwrap();
+        let mut config = ProcessConfig::new("sh").with_inherited_fd(theirs.into());
+        config.args = vec!["-c".to_string(), "sleep 10".to_string()];
+        // Neither could succeed without a socket path to listen on
+        config.ready_handshake = true;
+        config.health_check = HealthCheck::new(|_| async { false });
+
+        let mut server = ServerProcess::new(config);
+        server.start().await.unwrap();
+
+        // Only the server holds the other end, so the socket stays open until
+        // it exits
+        ours.set_read_timeout(Some(Duration::from_millis(100)))
+            .unwrap();
+        let mut buf = [0; 1];
+        let err = ours.read(&mut buf).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::WouldBlock | ErrorKind::TimedOut
+        ));
+        assert_eq!(server.stop().await.unwrap(), Stopped::Terminated);
+        ours.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(ours.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_is_process_alive() {
+        assert!(is_process_alive(std::process::id()));
+        let mut child = Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        assert!(!is_process_alive(pid));
+    }
+
+    #[tokio::test]
+    async fn test_ping_without_server() {
+        let path = std::env::temp_dir().join("hotkey-manager-no-server.sock");
+        assert!(!HealthCheck::ping().check(&path).await);
     }
 }
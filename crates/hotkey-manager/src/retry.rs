@@ -0,0 +1,167 @@
+//! Retry policies for connecting to a server.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Decides whether, and how soon, to retry a failed attempt.
+///
+/// Implement this to plug a custom strategy into
+/// [`Client::with_retry_policy`](crate::Client::with_retry_policy).
+pub trait Backoff: Send + Sync {
+    /// The delay before the next attempt, given the number of attempts made
+    /// so far and the time elapsed since the first one. Returns `None` to
+    /// give up.
+    fn next_delay(&self, attempts: u32, elapsed: Duration) -> Option<Duration>;
+}
+
+/// How the delay between attempts grows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Delay {
+    /// The same delay before every retry
+    Fixed(Duration),
+    /// A delay that doubles after every attempt, up to a maximum
+    Exponential {
+        /// Delay before the first retry
+        initial: Duration,
+        /// Longest delay between retries
+        max: Duration,
+    },
+}
+
+/// A standard retry policy, with limits on attempts and elapsed time
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// How the delay between attempts grows
+    pub delay: Delay,
+    /// Give up after this many attempts
+    pub max_attempts: Option<u32>,
+    /// Give up once this much time has passed since the first attempt
+    pub max_elapsed: Option<Duration>,
+    /// Randomly shorten each delay by up to this fraction (0.0 to 1.0), so
+    /// that clients don't retry in lockstep
+    pub jitter: f64,
+}
+
+impl RetryPolicy {
+    /// Retry after the same delay each time, for at most `max_attempts`
+    /// attempts in total
+    pub fn fixed(delay: Duration, max_attempts: u32) -> Self {
+        Self {
+            delay: Delay::Fixed(delay),
+            max_attempts: Some(max_attempts),
+            max_elapsed: None,
+            jitter: 0.0,
+        }
+    }
+
+    /// Retry with a delay that doubles from `initial` up to `max`, without
+    /// limit. Combine with [`with_max_elapsed`](Self::with_max_elapsed) or
+    /// [`with_max_attempts`](Self::with_max_attempts).
+    pub fn exponential(initial: Duration, max: Duration) -> Self {
+        Self {
+            delay: Delay::Exponential { initial, max },
+            max_attempts: None,
+            max_elapsed: None,
+            jitter: 0.0,
+        }
+    }
+
+    /// Give up after this many attempts
+    pub fn with_max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = Some(attempts);
+        self
+    }
+
+    /// Give up once this much time has passed since the first attempt
+    pub fn with_max_elapsed(mut self, elapsed: Duration) -> Self {
+        self.max_elapsed = Some(elapsed);
+        self
+    }
+
+    /// Randomly shorten each delay by up to a fraction of it
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// The delay after a number of attempts, before jitter
+    fn base_delay(&self, attempts: u32) -> Duration {
+        match self.delay {
+            Delay::Fixed(delay) => delay,
+            Delay::Exponential { initial, max } => {
+                let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+                initial.saturating_mul(factor).min(max)
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Five attempts, 200ms apart
+    fn default() -> Self {
+        Self::fixed(Duration::from_millis(200), 5)
+    }
+}
+
+impl Backoff for RetryPolicy {
+    fn next_delay(&self, attempts: u32, elapsed: Duration) -> Option<Duration> {
+        if self.max_attempts.is_some_and(|max| attempts >= max) {
+            return None;
+        }
+        let mut delay = self.base_delay(attempts);
+        if self.jitter > 0.0 {
+            delay = delay.mul_f64(1.0 - self.jitter * random_fraction());
+        }
+        if let Some(max) = self.max_elapsed {
+            // Don't wait past the deadline
+            let remaining = max.checked_sub(elapsed)?;
+            delay = delay.min(remaining);
+        }
+        Some(delay)
+    }
+}
+
+/// A random number in `[0, 1)`, good enough for jitter
+fn random_fraction() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn test_fixed() {
+        let policy = RetryPolicy::fixed(100 * MS, 3);
+        assert_eq!(policy.next_delay(1, Duration::ZERO), Some(100 * MS));
+        assert_eq!(policy.next_delay(2, Duration::ZERO), Some(100 * MS));
+        assert_eq!(policy.next_delay(3, Duration::ZERO), None);
+    }
+
+    #[test]
+    fn test_exponential() {
+        let policy = RetryPolicy::exponential(10 * MS, 50 * MS).with_max_elapsed(100 * MS);
+        let delays: Vec<_> = (1..=4)
+            .map(|n| policy.next_delay(n, Duration::ZERO))
+            .collect();
+        assert_eq!(
+            delays,
+            vec![Some(10 * MS), Some(20 * MS), Some(40 * MS), Some(50 * MS)]
+        );
+        assert_eq!(policy.next_delay(5, 80 * MS), Some(20 * MS));
+        assert_eq!(policy.next_delay(5, 120 * MS), None);
+    }
+
+    #[test]
+    fn test_jitter() {
+        let policy = RetryPolicy::fixed(100 * MS, 10).with_jitter(0.5);
+        for attempt in 1..10 {
+            let delay = policy.next_delay(attempt, Duration::ZERO).unwrap();
+            assert!(delay > 50 * MS && delay <= 100 * MS);
+        }
+    }
+}
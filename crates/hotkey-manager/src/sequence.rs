@@ -0,0 +1,212 @@
+//! Bindings for sequences of keys.
+//!
+//! [`Sequences`] is a [`Handler`] for bindings like `ctrl+x ctrl+s`, which
+//! fire after several keys pressed one after another. It binds the first key
+//! of every sequence, then only the keys that can follow what has been
+//! pressed so far. Clients get a `ChordPending` event after each key of an
+//! unfinished sequence, so they can show the prefix, and `ChordAborted` if
+//! the next key doesn't come in time.
+
+use crate::ipc::IPCResponse;
+use crate::{Handler, Key};
+use std::time::Duration;
+
+/// How long [`Sequences`] waits for the next key by default
+const DEFAULT_CHORD_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A [`Handler`] binding sequences of keys
+///
+/// A finished sequence produces an `ActionExecuted` event with the last key
+/// and the sequence's description. A sequence that is a prefix of another
+/// one fires as soon as it is complete, so the longer one can't be entered.
+#[derive(Debug, Clone)]
+pub struct Sequences {
+    bindings: Vec<(Vec<Key>, String)>,
+    prefix: Vec<Key>,
+    timeout: Duration,
+}
+
+impl Default for Sequences {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sequences {
+    /// Create a handler without bindings
+    pub fn new() -> Self {
+        Self {
+            bindings: Vec::new(),
+            prefix: Vec::new(),
+            timeout: DEFAULT_CHORD_TIMEOUT,
+        }
+    }
+
+    /// Bind a sequence of keys. Empty sequences are ignored.
+    pub fn with_sequence(
+        mut self,
+        keys: impl IntoIterator<Item = Key>,
+        desc: impl Into<String>,
+    ) -> Self {
+        let keys: Vec<Key> = keys.into_iter().collect();
+        if !keys.is_empty() {
+            self.bindings.push((keys, desc.into()));
+        }
+        self
+    }
+
+    /// How long to wait for each key after the first (default: 1 second)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The keys of the current sequence pressed so far
+    pub fn prefix(&self) -> &[Key] {
+        &self.prefix
+    }
+
+    /// The keys that can follow the prefix, in the order they were bound
+    fn next_keys(&self) -> Vec<Key> {
+        let mut keys: Vec<Key> = Vec::new();
+        for (sequence, _) in &self.bindings {
+            if let Some(key) = sequence
+                .strip_prefix(self.prefix.as_slice())
+                .and_then(|rest| rest.first())
+            {
+                if !keys.contains(key) {
+                    keys.push(key.clone());
+                }
+            }
+        }
+        keys
+    }
+}
+
+impl Handler for Sequences {
+    fn keys(&self) -> Vec<Key> {
+        self.next_keys()
+    }
+
+    fn handle_key(&mut self, key: &Key) -> Vec<IPCResponse> {
+        self.prefix.push(key.clone());
+        if let Some((_, desc)) = self
+            .bindings
+            .iter()
+            .find(|(sequence, _)| *sequence == self.prefix)
+        {
+            let desc = desc.clone();
+            self.prefix.clear();
+            return vec![IPCResponse::ActionExecuted {
+                key: key.clone(),
+                desc,
+            }];
+        }
+        let remaining = self.next_keys();
+        if remaining.is_empty() {
+            // Only bound keys should arrive, but a key that continues no
+            // sequence ends the current one
+            self.prefix.clear();
+            return vec![IPCResponse::ChordAborted];
+        }
+        vec![IPCResponse::ChordPending {
+            prefix: self.prefix.clone(),
+            remaining,
+        }]
+    }
+
+    fn chord_timeout(&self) -> Option<Duration> {
+        (!self.prefix.is_empty()).then_some(self.timeout)
+    }
+
+    fn abort_chord(&mut self) -> Vec<IPCResponse> {
+        if self.prefix.is_empty() {
+            return Vec::new();
+        }
+        self.prefix.clear();
+        vec![IPCResponse::ChordAborted]
+    }
+
+    fn snapshot(&self) -> Vec<IPCResponse> {
+        if self.prefix.is_empty() {
+            return Vec::new();
+        }
+        vec![IPCResponse::ChordPending {
+            prefix: self.prefix.clone(),
+            remaining: self.next_keys(),
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(s: &str) -> Vec<Key> {
+        s.split_whitespace()
+            .map(|k| Key::parse(k).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_sequences() {
+        let mut handler = Sequences::new()
+            .with_sequence(keys("ctrl+x ctrl+s"), "Save")
+            .with_sequence(keys("ctrl+x ctrl+c"), "Quit")
+            .with_sequence(keys("ctrl+x 4 f"), "Find file")
+            .with_sequence(keys("cmd+k"), "Command")
+            .with_sequence(Vec::new(), "Nothing")
+            .with_timeout(Duration::from_millis(500));
+        assert_eq!(handler.keys(), keys("ctrl+x cmd+k"));
+        assert_eq!(handler.chord_timeout(), None);
+        assert!(handler.snapshot().is_empty());
+
+        let events = handler.handle_key(&keys("ctrl+x")[0]);
+        assert!(matches!(
+            events.as_slice(),
+            [IPCResponse::ChordPending { prefix, remaining }]
+                if *prefix == keys("ctrl+x") && *remaining == keys("ctrl+s ctrl+c 4")
+        ));
+        assert_eq!(handler.keys(), keys("ctrl+s ctrl+c 4"));
+        assert_eq!(handler.chord_timeout(), Some(Duration::from_millis(500)));
+        assert_eq!(handler.snapshot().len(), 1);
+
+        handler.handle_key(&keys("4")[0]);
+        assert_eq!(handler.prefix(), keys("ctrl+x 4").as_slice());
+        let events = handler.handle_key(&keys("f")[0]);
+        assert!(matches!(
+            events.as_slice(),
+            [IPCResponse::ActionExecuted { desc, .. }] if desc == "Find file"
+        ));
+        assert!(handler.prefix().is_empty());
+        assert_eq!(handler.keys(), keys("ctrl+x cmd+k"));
+
+        // Single keys fire at once
+        assert!(matches!(
+            handler.handle_key(&keys("cmd+k")[0]).as_slice(),
+            [IPCResponse::ActionExecuted { desc, .. }] if desc == "Command"
+        ));
+    }
+
+    #[test]
+    fn test_abort() {
+        let mut handler = Sequences::new().with_sequence(keys("ctrl+x ctrl+s"), "Save");
+        assert!(handler.abort_chord().is_empty());
+
+        handler.handle_key(&keys("ctrl+x")[0]);
+        assert!(matches!(
+            handler.abort_chord().as_slice(),
+            [IPCResponse::ChordAborted]
+        ));
+        assert_eq!(handler.chord_timeout(), None);
+        assert_eq!(handler.keys(), keys("ctrl+x"));
+
+        // A key that continues no sequence ends the current one
+        handler.handle_key(&keys("ctrl+x")[0]);
+        assert!(matches!(
+            handler.handle_key(&keys("q")[0]).as_slice(),
+            [IPCResponse::ChordAborted]
+        ));
+        assert!(handler.prefix().is_empty());
+    }
+}
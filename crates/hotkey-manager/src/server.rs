@@ -1,18 +1,43 @@
+use crate::app::AppObserver;
+use crate::handler::Handler;
 use crate::ipc::IPCServer;
 use crate::manager::HotkeyManager;
-use crate::{Error, Result, DEFAULT_SOCKET_PATH};
+use crate::pidfile::PidFile;
+use crate::socket::inherited_socket;
+use crate::{default_socket_path, DisconnectPolicy, Error, Key, Result, READY_ENV};
+use std::collections::BTreeMap;
+use std::os::fd::OwnedFd;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
 use tao::event::Event;
-use tao::event_loop::{ControlFlow, EventLoop};
+use tao::event_loop::{ControlFlow, EventLoopBuilder};
 #[cfg(target_os = "macos")]
 use tao::platform::macos::{ActivationPolicy, EventLoopExtMacOS};
-use tracing::{debug, error, info, trace};
+use tracing::{debug, error, info, trace, warn};
 
 /// A hotkey server that manages the event loop and IPC communication
 pub struct Server {
     socket_path: String,
+    /// Whether to write a PID file next to the socket
+    pid_file: bool,
+    /// Whether clients may simulate hotkey presses
+    allow_test_triggers: bool,
+    /// Key handling logic hosted in the server, if any
+    handler: Option<Box<dyn Handler>>,
+    /// Keys to bind at startup, by identifier
+    bindings: Vec<(String, Key)>,
+    /// File to create once the socket is listening, if any
+    ready_file: Option<PathBuf>,
+    /// What happens to the bindings when the client disconnects
+    disconnect_policy: DisconnectPolicy,
+    /// A connected socket to serve instead of listening on `socket_path`
+    inherited_fd: Option<OwnedFd>,
+    /// Address to serve metrics over HTTP on, if any
+    #[cfg(feature = "metrics-http")]
+    metrics_address: Option<std::net::SocketAddr>,
 }
 
 impl Default for Server {
@@ -23,35 +48,169 @@ impl Default for Server {
 
 impl Server {
     /// Create a new hotkey server with default configuration
+    ///
+    /// The server listens on the path in the
+    /// [`SOCKET_ENV`](crate::SOCKET_ENV) environment variable if it is set,
+    /// as it is for servers spawned by a [`Client`](crate::Client), and on
+    /// the [user's socket](crate::user_socket_path) otherwise.
+    /// Likewise, it creates the ready file named by [`READY_ENV`], if set.
+    /// When it runs, it serves the socket named by
+    /// [`FD_ENV`](crate::FD_ENV), if set, instead of listening on a path.
     pub fn new() -> Self {
         Self {
-            socket_path: DEFAULT_SOCKET_PATH.to_string(),
+            socket_path: default_socket_path(),
+            pid_file: false,
+            allow_test_triggers: false,
+            handler: None,
+            bindings: Vec::new(),
+            ready_file: std::env::var_os(READY_ENV)
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+            disconnect_policy: DisconnectPolicy::default(),
+            inherited_fd: None,
+            #[cfg(feature = "metrics-http")]
+            metrics_address: None,
         }
     }
 
+    /// Create a server that binds the keys in a config file at startup.
+    ///
+    /// The file is a JSON object mapping identifiers to key strings, such as
+    /// `{"launcher": "cmd+space"}`. Every key is checked, and the error lists
+    /// all the problems found.
+    pub fn from_config_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            Error::InvalidConfig(vec![format!("Failed to read {}: {e}", path.display())])
+        })?;
+        Ok(Self::new().with_bindings(parse_bindings(&text)?))
+    }
+
     /// Set the socket path for IPC communication
     pub fn with_socket_path(mut self, path: impl Into<String>) -> Self {
         self.socket_path = path.into();
         self
     }
 
+    /// Create a file at `path`, containing the server's PID, once the socket
+    /// is listening.
+    ///
+    /// A process that spawns the server can wait for the file to appear
+    /// rather than polling the socket. The server doesn't remove it.
+    pub fn with_ready_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ready_file = Some(path.into());
+        self
+    }
+
+    /// Serve `fd`, one end of a connected socket, instead of listening on the
+    /// socket path.
+    ///
+    /// The client on the other end is the only client, and the server exits
+    /// when it disconnects, whatever the [`DisconnectPolicy`]. No socket,
+    /// ready or PID file is created.
+    pub fn with_inherited_fd(mut self, fd: OwnedFd) -> Self {
+        self.inherited_fd = Some(fd);
+        self
+    }
+
+    /// Write a PID file next to the socket while the server is running.
+    ///
+    /// The file is named after the socket with a `.pid` suffix (see
+    /// [`pid_file_path`](crate::pid_file_path)), and is removed on shutdown.
+    pub fn with_pid_file(mut self, enabled: bool) -> Self {
+        self.pid_file = enabled;
+        self
+    }
+
+    /// Decide what happens to the bindings when the client disconnects
+    /// without asking the server to shut down.
+    ///
+    /// By default they are unbound and the server exits. Keeping them lets
+    /// a restarted client reconnect without rebinding. A client can choose
+    /// a policy of its own with
+    /// [`Client::with_disconnect_policy`](crate::Client::with_disconnect_policy).
+    pub fn with_disconnect_policy(mut self, policy: DisconnectPolicy) -> Self {
+        self.disconnect_policy = policy;
+        self
+    }
+
+    /// Serve the server's [`Metrics`](crate::Metrics) at
+    /// `http://{address}/metrics`, in the Prometheus text format.
+    ///
+    /// Clients can also get them with
+    /// [`IPCConnection::get_metrics`](crate::IPCConnection::get_metrics).
+    #[cfg(feature = "metrics-http")]
+    pub fn with_metrics_address(mut self, address: std::net::SocketAddr) -> Self {
+        self.metrics_address = Some(address);
+        self
+    }
+
+    /// Allow clients to simulate hotkey presses with `Trigger` requests.
+    ///
+    /// This is intended for end-to-end tests and demos, which can then exercise
+    /// the event pipeline without generating real OS key events.
+    pub fn with_test_triggers(mut self, enabled: bool) -> Self {
+        self.allow_test_triggers = enabled;
+        self
+    }
+
+    /// Handle hotkey presses in the server instead of forwarding them.
+    ///
+    /// The handler decides which keys are bound, and the client receives the
+    /// events it produces rather than `HotkeyTriggered` events. Clients can't
+    /// rebind keys on a server with a handler.
+    pub fn with_handler(mut self, handler: impl Handler) -> Self {
+        self.handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Bind keys as soon as the server starts, so that it is useful before a
+    /// client sends a `Rebind`.
+    ///
+    /// Presses are forwarded to the primary client as `HotkeyTriggered`
+    /// events, like keys the client binds itself. A `Rebind` replaces them.
+    /// They can't be combined with a [`Handler`], which decides what is
+    /// bound.
+    pub fn with_bindings(mut self, bindings: Vec<(String, Key)>) -> Self {
+        self.bindings = bindings;
+        self
+    }
+
     /// Run the server
     ///
     /// This will:
     /// 1. Create a tao event loop on the current thread (must be main thread on macOS)
     /// 2. Create a HotkeyManager
     /// 3. Start an IPC server in a background thread
-    /// 4. Run the event loop until shutdown is requested
+    /// 4. Run the event loop until shutdown is requested, reporting changes
+    ///    of the frontmost application to the client
     ///
     /// The server will automatically shut down when:
-    /// - The IPC client disconnects
+    /// - The IPC client disconnects, unless the [`DisconnectPolicy`] keeps
+    ///   the bindings for a new client
     /// - An error occurs in the IPC server
     /// - The event loop is explicitly terminated
-    pub fn run(self) -> Result<()> {
-        info!("Starting hotkey server on socket: {}", self.socket_path);
+    pub fn run(mut self) -> Result<()> {
+        // Claim a socket handed down by the spawning process before starting
+        // any threads, since claiming it removes its environment variable
+        if self.inherited_fd.is_none() {
+            self.inherited_fd = inherited_socket();
+        }
+        match &self.inherited_fd {
+            Some(_) => info!("Starting hotkey server on an inherited socket"),
+            None => info!("Starting hotkey server on socket: {}", self.socket_path),
+        }
+        if self.handler.is_some() && !self.bindings.is_empty() {
+            return Err(Error::HotkeyOperation(
+                "Bindings can't be combined with a handler".to_string(),
+            ));
+        }
 
-        // Create the tao event loop (must be on main thread for macOS)
-        let mut event_loop = EventLoop::new();
+        // Create the tao event loop (must be on main thread for macOS). It
+        // sleeps until woken by an event, so the IPC thread wakes it to shut
+        // down.
+        let mut event_loop = EventLoopBuilder::<()>::with_user_event().build();
+        let wake = event_loop.create_proxy();
 
         // Set activation policy to Accessory on macOS to prevent dock icon
         #[cfg(target_os = "macos")]
@@ -65,14 +224,51 @@ impl Server {
             .map_err(|e| Error::HotkeyOperation(format!("Failed to create HotkeyManager: {e}")))?;
         info!("HotkeyManager created successfully");
 
+        let inherited = self.inherited_fd.map(UnixStream::from);
+
+        // Record our PID so tooling can find us without a connection
+        let mut pid_file = if self.pid_file && inherited.is_none() {
+            Some(PidFile::create(&self.socket_path)?)
+        } else {
+            None
+        };
+
         // Create the IPC server
-        let ipc_server = IPCServer::new(&self.socket_path, manager);
+        let ready_file = self.ready_file.filter(|_| inherited.is_none());
+        let remove_socket = inherited.is_none();
+        let mut ipc_server = IPCServer::new(&self.socket_path, manager)
+            .with_test_triggers(self.allow_test_triggers)
+            .with_ready_file(ready_file)
+            .with_inherited_stream(inherited)
+            .with_disconnect_policy(self.disconnect_policy);
+        #[cfg(feature = "metrics-http")]
+        {
+            ipc_server = ipc_server.with_metrics_address(self.metrics_address);
+        }
+        if let Some(handler) = self.handler {
+            ipc_server = ipc_server.with_handler(handler);
+        }
+        if !self.bindings.is_empty() {
+            info!(
+                "Binding {} keys from the server config",
+                self.bindings.len()
+            );
+            ipc_server.bind_forwarded(&self.bindings)?;
+        }
+
+        // Watch the frontmost application from the main thread
+        let app_notifier = ipc_server.app_notifier();
+        let app_observer = AppObserver::new(move |app| {
+            debug!("Frontmost application changed: {:?}", app);
+            app_notifier.notify(app);
+        });
 
         // Create shutdown coordination
         let shutdown_requested = Arc::new(AtomicBool::new(false));
         let shutdown_requested_clone = shutdown_requested.clone();
 
         // Spawn IPC server in background thread
+        let socket_path = self.socket_path.clone();
         let _server_thread = thread::spawn(move || {
             // Create a tokio runtime for the IPC server
             let runtime = match tokio::runtime::Runtime::new() {
@@ -86,25 +282,41 @@ impl Server {
 
             info!("IPC server thread started, waiting for client connection...");
 
-            // Run the IPC server
+            // Run the IPC server until it finishes or we are asked to
+            // terminate
             runtime.block_on(async {
-                if let Err(e) = ipc_server.run().await {
-                    error!("IPC server error: {}", e);
+                tokio::select! {
+                    result = ipc_server.run() => {
+                        if let Err(e) = result {
+                            error!("IPC server error: {}", e);
+                        }
+                    }
+                    _ = terminated() => {
+                        info!("Received SIGTERM, cleaning up");
+                        if remove_socket {
+                            let _ = std::fs::remove_file(&socket_path);
+                        }
+                    }
                 }
             });
 
             info!("IPC server thread ending, signaling shutdown");
             shutdown_requested_clone.store(true, Ordering::SeqCst);
+            let _ = wake.send_event(());
         });
 
         // Run the event loop on the main thread
         info!("Starting tao event loop...");
         event_loop.run(move |event, _, control_flow| {
+            // Observe the frontmost application for as long as the loop runs
+            let _ = &app_observer;
             *control_flow = ControlFlow::Wait;
 
             // Check for shutdown
             if shutdown_requested.load(Ordering::SeqCst) {
                 info!("Shutdown requested, exiting event loop");
+                // The event loop exits the process, so clean up explicitly
+                drop(pid_file.take());
                 *control_flow = ControlFlow::Exit;
                 return;
             }
@@ -131,6 +343,66 @@ impl Server {
     }
 }
 
+/// Run a hotkey server with the default configuration, until its client
+/// disconnects.
+///
+/// This is all a dedicated server binary needs:
+///
+/// ```no_run
+/// fn main() -> hotkey_manager::Result<()> {
+///     hotkey_manager::run_server()
+/// }
+/// ```
+///
+/// Like [`Server::new`], this honours the [`SOCKET_ENV`](crate::SOCKET_ENV)
+/// environment variable. Must be called on the main thread.
+pub fn run_server() -> Result<()> {
+    run_server_with(Server::new())
+}
+
+/// Run a hotkey server listening on `socket_path`. See [`run_server`].
+pub fn run_server_on(socket_path: impl Into<String>) -> Result<()> {
+    run_server_with(Server::new().with_socket_path(socket_path))
+}
+
+/// Run a configured hotkey server. See [`run_server`].
+pub fn run_server_with(server: Server) -> Result<()> {
+    server.run()
+}
+
+/// Parse a JSON object mapping identifiers to key strings
+fn parse_bindings(text: &str) -> Result<Vec<(String, Key)>> {
+    let entries: BTreeMap<String, String> =
+        serde_json::from_str(text).map_err(|e| Error::InvalidConfig(vec![e.to_string()]))?;
+    let mut bindings = Vec::new();
+    let mut errors = Vec::new();
+    for (identifier, key) in entries {
+        match key.parse::<Key>() {
+            Ok(key) => bindings.push((identifier, key)),
+            Err(e) => errors.push(format!("{identifier}: {e}")),
+        }
+    }
+    if errors.is_empty() {
+        Ok(bindings)
+    } else {
+        Err(Error::InvalidConfig(errors))
+    }
+}
+
+/// Resolves when the process receives SIGTERM
+async fn terminated() {
+    use tokio::signal::unix::{signal, SignalKind};
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            terminate.recv().await;
+        }
+        Err(e) => {
+            warn!("Failed to listen for SIGTERM: {}", e);
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -146,11 +418,89 @@ mod tests {
             .with_socket_path("/initial/path.sock")
             .with_socket_path("/another/path.sock");
         assert_eq!(server.socket_path, "/another/path.sock");
+
+        // Test with_pid_file
+        let server = Server::new().with_pid_file(true);
+        assert!(server.pid_file);
+
+        // Test with_test_triggers
+        let server = Server::new().with_test_triggers(true);
+        assert!(server.allow_test_triggers);
+
+        // Test with_handler
+        struct Nothing;
+        impl Handler for Nothing {
+            fn keys(&self) -> Vec<crate::Key> {
+                Vec::new()
+            }
+            fn handle_key(&mut self, _key: &crate::Key) -> Vec<crate::IPCResponse> {
+                Vec::new()
+            }
+        }
+        let server = Server::new().with_handler(Nothing);
+        assert!(server.handler.is_some());
+
+        // Test with_ready_file
+        let server = Server::new().with_ready_file("/tmp/server.ready");
+        assert_eq!(server.ready_file, Some(PathBuf::from("/tmp/server.ready")));
+
+        // Test with_disconnect_policy
+        let server = Server::new().with_disconnect_policy(DisconnectPolicy::KeepForever);
+        assert_eq!(server.disconnect_policy, DisconnectPolicy::KeepForever);
     }
 
     #[test]
     fn test_server_default() {
         let server = Server::default();
-        assert_eq!(server.socket_path, DEFAULT_SOCKET_PATH);
+        assert_eq!(server.socket_path, crate::user_socket_path());
+        assert!(!server.pid_file);
+        assert!(!server.allow_test_triggers);
+        assert!(server.handler.is_none());
+        assert!(server.bindings.is_empty());
+        assert!(server.ready_file.is_none());
+        assert_eq!(server.disconnect_policy, DisconnectPolicy::Unbind);
+    }
+
+    #[test]
+    fn test_parse_disconnect_policy() {
+        assert_eq!(
+            "unbind".parse::<DisconnectPolicy>().unwrap(),
+            DisconnectPolicy::Unbind
+        );
+        assert_eq!(
+            "keep".parse::<DisconnectPolicy>().unwrap(),
+            DisconnectPolicy::KeepForever
+        );
+        assert_eq!(
+            "30".parse::<DisconnectPolicy>().unwrap(),
+            DisconnectPolicy::Keep(std::time::Duration::from_secs(30))
+        );
+        assert!(matches!(
+            "forever".parse::<DisconnectPolicy>(),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_bindings() {
+        let bindings =
+            parse_bindings(r#"{"search": "cmd+shift+f", "launcher": "cmd+space"}"#).unwrap();
+        assert_eq!(
+            bindings,
+            vec![
+                ("launcher".to_string(), "cmd+space".parse().unwrap()),
+                ("search".to_string(), "cmd+shift+f".parse().unwrap()),
+            ]
+        );
+
+        match parse_bindings(r#"{"a": "cmd+nope", "b": "cmd+a", "c": ""}"#) {
+            Err(Error::InvalidConfig(errors)) => {
+                assert_eq!(errors.len(), 2);
+                assert!(errors[0].starts_with("a: "));
+                assert!(errors[1].starts_with("c: "));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+        assert!(matches!(parse_bindings("[]"), Err(Error::InvalidConfig(_))));
     }
 }
@@ -0,0 +1,168 @@
+//! Default socket paths.
+//!
+//! Each user gets a socket of their own, so that users sharing a machine
+//! each talk to their own server. The socket goes in `$XDG_RUNTIME_DIR` if
+//! it is set, since that directory is already private to the user, and
+//! otherwise in `$TMPDIR` or `/tmp`, named with the user's ID.
+//!
+//! Releases before per-user sockets used [`LEGACY_SOCKET_PATH`]. Clients
+//! using the default path also try it, if the current user owns it, so that
+//! they still reach a server started by an older release.
+//!
+//! A server can also be handed a connected socket instead, named by
+//! [`FD_ENV`], in which case no path is involved at all.
+
+use crate::{FD_ENV, LEGACY_SOCKET_PATH, SOCKET_ENV};
+use std::env;
+use std::ffi::OsString;
+use std::os::fd::{FromRawFd, OwnedFd, RawFd};
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+use tracing::warn;
+
+/// Name of the socket in a directory private to the user
+const SOCKET_NAME: &str = "hotkey-manager.sock";
+
+/// The current user's ID
+fn current_uid() -> u32 {
+    // SAFETY: getuid has no memory safety requirements, and can't fail
+    unsafe { libc::getuid() }
+}
+
+/// The per-user socket path, given the runtime and temporary directories
+fn user_socket_path_in(
+    runtime_dir: Option<OsString>,
+    tmp_dir: Option<OsString>,
+    uid: u32,
+) -> PathBuf {
+    let non_empty = |dir: Option<OsString>| dir.filter(|dir| !dir.is_empty()).map(PathBuf::from);
+    if let Some(dir) = non_empty(runtime_dir) {
+        return dir.join(SOCKET_NAME);
+    }
+    non_empty(tmp_dir)
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(format!("hotkey-manager-{uid}.sock"))
+}
+
+/// The current user's socket path, which servers listen on and clients
+/// connect to unless told otherwise
+pub fn user_socket_path() -> String {
+    user_socket_path_in(
+        env::var_os("XDG_RUNTIME_DIR"),
+        env::var_os("TMPDIR"),
+        current_uid(),
+    )
+    .to_string_lossy()
+    .into_owned()
+}
+
+/// The socket path to use when none is given: the one named by
+/// [`SOCKET_ENV`] if it is set, otherwise the [user's](user_socket_path)
+pub fn default_socket_path() -> String {
+    env::var(SOCKET_ENV)
+        .ok()
+        .filter(|path| !path.is_empty())
+        .unwrap_or_else(user_socket_path)
+}
+
+/// The legacy socket path to try when nothing answers on `socket_path`:
+/// only for the user's default path, and only if the user owns the legacy
+/// socket
+pub(crate) fn legacy_fallback(socket_path: &str) -> Option<&'static str> {
+    if socket_path != user_socket_path() || socket_path == LEGACY_SOCKET_PATH {
+        return None;
+    }
+    let metadata = std::fs::metadata(LEGACY_SOCKET_PATH).ok()?;
+    (metadata.uid() == current_uid()).then_some(LEGACY_SOCKET_PATH)
+}
+
+/// Claim the socket named by [`FD_ENV`], if the process was given one.
+///
+/// The variable is removed, so the descriptor is only claimed once and
+/// isn't passed on, and the descriptor is marked close-on-exec again. Since
+/// removing a variable races with other threads reading the environment,
+/// this should be called before the process starts any. Descriptors that
+/// aren't open sockets, and the standard streams, are ignored.
+pub(crate) fn inherited_socket() -> Option<OwnedFd> {
+    let value = env::var(FD_ENV).ok()?;
+    env::remove_var(FD_ENV);
+    let fd = match value.parse::<RawFd>() {
+        Ok(fd) if fd > libc::STDERR_FILENO => fd,
+        _ => {
+            warn!("Ignoring invalid {}: {:?}", FD_ENV, value);
+            return None;
+        }
+    };
+    if !is_socket(fd) {
+        warn!(
+            "Ignoring {}: descriptor {} isn't an open socket",
+            FD_ENV, fd
+        );
+        return None;
+    }
+    // SAFETY: F_GETFD and F_SETFD only read and write the descriptor's
+    // flags, and the descriptor is open
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+    // SAFETY: as above
+    if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC) } < 0 {
+        warn!(
+            "Ignoring {}: failed to update descriptor {}: {}",
+            FD_ENV,
+            fd,
+            std::io::Error::last_os_error()
+        );
+        return None;
+    }
+    // SAFETY: the descriptor is an open socket, and was handed to this
+    // process to own
+    Some(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+/// Whether a descriptor is open, and refers to a socket
+fn is_socket(fd: RawFd) -> bool {
+    let mut stat = std::mem::MaybeUninit::<libc::stat>::uninit();
+    // SAFETY: fstat writes a stat to the pointer, which is valid for one,
+    // and fails harmlessly if the descriptor isn't open
+    if unsafe { libc::fstat(fd, stat.as_mut_ptr()) } != 0 {
+        return false;
+    }
+    // SAFETY: fstat succeeded, so it initialized the stat
+    let stat = unsafe { stat.assume_init() };
+    stat.st_mode & libc::S_IFMT == libc::S_IFSOCK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::fd::AsRawFd;
+
+    #[test]
+    fn test_user_socket_path() {
+        let dir = |s: &str| Some(OsString::from(s));
+        assert_eq!(
+            user_socket_path_in(dir("/run/user/501"), dir("/var/tmp"), 501),
+            PathBuf::from("/run/user/501/hotkey-manager.sock")
+        );
+        assert_eq!(
+            user_socket_path_in(None, dir("/var/folders/xy/T/"), 501),
+            PathBuf::from("/var/folders/xy/T/hotkey-manager-501.sock")
+        );
+        assert_eq!(
+            user_socket_path_in(dir(""), None, 0),
+            PathBuf::from("/tmp/hotkey-manager-0.sock")
+        );
+
+        // Paths other than the user's default never fall back
+        assert_eq!(legacy_fallback("/custom/path.sock"), None);
+    }
+
+    #[test]
+    fn test_is_socket() {
+        let (socket, _other) = std::os::unix::net::UnixStream::pair().unwrap();
+        assert!(is_socket(socket.as_raw_fd()));
+
+        let file = std::fs::File::open("/dev/null").unwrap();
+        assert!(!is_socket(file.as_raw_fd()));
+        assert!(!is_socket(-1));
+    }
+}
@@ -0,0 +1,93 @@
+//! Usage statistics for bound hotkeys.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How often a hotkey has been triggered since the server started
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HotkeyStats {
+    /// Identifier the hotkey is bound with
+    pub identifier: String,
+    /// Number of times the hotkey was triggered
+    pub count: u64,
+    /// When the hotkey was last triggered, in milliseconds since the Unix
+    /// epoch
+    pub last_triggered_ms: u64,
+}
+
+/// Trigger statistics, keyed by identifier
+///
+/// Statistics are kept by identifier rather than registration, so they
+/// survive unbinding and rebinding the same hotkey.
+#[derive(Debug, Default)]
+pub(crate) struct Stats {
+    entries: HashMap<String, HotkeyStats>,
+}
+
+impl Stats {
+    /// Record that the hotkey with an identifier was triggered at a time
+    pub(crate) fn record(&mut self, identifier: &str, at: SystemTime) {
+        let at = at
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        let entry = self
+            .entries
+            .entry(identifier.to_string())
+            .or_insert_with(|| HotkeyStats {
+                identifier: identifier.to_string(),
+                count: 0,
+                last_triggered_ms: 0,
+            });
+        entry.count += 1;
+        entry.last_triggered_ms = at;
+    }
+
+    /// All statistics, most triggered first
+    pub(crate) fn all(&self) -> Vec<HotkeyStats> {
+        let mut all: Vec<_> = self.entries.values().cloned().collect();
+        all.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.identifier.cmp(&b.identifier))
+        });
+        all
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_record() {
+        let mut stats = Stats::default();
+        let at = |ms| UNIX_EPOCH + Duration::from_millis(ms);
+        stats.record("b", at(10));
+        stats.record("a", at(20));
+        stats.record("b", at(30));
+        stats.record("c", at(40));
+        assert_eq!(
+            stats.all(),
+            vec![
+                HotkeyStats {
+                    identifier: "b".to_string(),
+                    count: 2,
+                    last_triggered_ms: 30,
+                },
+                HotkeyStats {
+                    identifier: "a".to_string(),
+                    count: 1,
+                    last_triggered_ms: 20,
+                },
+                HotkeyStats {
+                    identifier: "c".to_string(),
+                    count: 1,
+                    last_triggered_ms: 40,
+                },
+            ]
+        );
+    }
+}
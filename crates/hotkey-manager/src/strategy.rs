@@ -0,0 +1,135 @@
+//! [proptest] strategies for keys and IPC requests.
+//!
+//! Enabled by the `proptest` feature. Generated values are always valid: keys
+//! use codes and modifiers that can be bound, so they display and parse back
+//! to an equal key, and requests survive a round trip through the wire
+//! format. Downstream crates can use them to fuzz their own config handling.
+//!
+//! ```
+//! use hotkey_manager::{strategy, Key};
+//! use proptest::prelude::*;
+//!
+//! proptest!(|(key in strategy::key())| {
+//!     prop_assert_eq!(Key::parse(&key.to_string()).unwrap(), key);
+//! });
+//! ```
+
+use crate::ipc::{DisconnectPolicy, IPCRequest};
+use crate::key::CODES;
+use crate::Key;
+use global_hotkey::hotkey::Modifiers;
+use proptest::arbitrary::Arbitrary;
+use proptest::prelude::*;
+use std::time::Duration;
+
+/// Modifiers that keys can be bound with, `None` when there are none
+pub fn modifiers() -> impl Strategy<Value = Option<Modifiers>> {
+    let flags = [
+        Modifiers::CONTROL,
+        Modifiers::ALT,
+        Modifiers::SHIFT,
+        Modifiers::SUPER,
+    ];
+    proptest::sample::subsequence(flags.to_vec(), 0..=flags.len()).prop_map(|flags| {
+        let mods = flags
+            .into_iter()
+            .fold(Modifiers::empty(), |acc, flag| acc | flag);
+        (!mods.is_empty()).then_some(mods)
+    })
+}
+
+/// Keys that can be bound, in canonical form
+pub fn key() -> impl Strategy<Value = Key> {
+    (proptest::sample::select(CODES), modifiers())
+        .prop_map(|(code, modifiers)| Key::new(code, modifiers))
+}
+
+/// Names of groups, topics and identifiers
+fn name() -> impl Strategy<Value = String> {
+    "[a-z][a-z0-9_-]{0,15}"
+}
+
+/// Small JSON payloads for published messages
+fn payload() -> impl Strategy<Value = serde_json::Value> {
+    prop_oneof![
+        Just(serde_json::Value::Null),
+        any::<bool>().prop_map(serde_json::Value::from),
+        any::<i64>().prop_map(serde_json::Value::from),
+        ".{0,32}".prop_map(serde_json::Value::from),
+    ]
+}
+
+/// Disconnect policies
+pub fn disconnect_policy() -> impl Strategy<Value = DisconnectPolicy> {
+    prop_oneof![
+        Just(DisconnectPolicy::Unbind),
+        (0..86_400u64).prop_map(|secs| DisconnectPolicy::Keep(Duration::from_secs(secs))),
+        Just(DisconnectPolicy::KeepForever),
+    ]
+}
+
+/// Every kind of request a client can send
+pub fn ipc_request() -> impl Strategy<Value = IPCRequest> {
+    let keys = || proptest::collection::vec(key(), 0..8);
+    prop_oneof![
+        Just(IPCRequest::Ping),
+        Just(IPCRequest::Shutdown),
+        keys().prop_map(|keys| IPCRequest::Rebind { keys }),
+        (keys(), keys()).prop_map(|(bind, unbind)| IPCRequest::UpdateBindings { bind, unbind }),
+        (key(), any::<u64>(), any::<bool>())
+            .prop_map(|(key, ttl_ms, once)| IPCRequest::BindTemporary { key, ttl_ms, once }),
+        (name(), keys()).prop_map(|(group, keys)| IPCRequest::BindGroup { group, keys }),
+        name().prop_map(|group| IPCRequest::DisableGroup { group }),
+        name().prop_map(|group| IPCRequest::EnableGroup { group }),
+        name().prop_map(|group| IPCRequest::UnbindGroup { group }),
+        name().prop_map(|identifier| IPCRequest::Trigger { identifier }),
+        any::<u64>().prop_map(|timeout_ms| IPCRequest::Capture { timeout_ms }),
+        Just(IPCRequest::ReloadConfig),
+        (name(), payload()).prop_map(|(topic, payload)| IPCRequest::Publish { topic, payload }),
+        name().prop_map(|topic| IPCRequest::Subscribe { topic }),
+        Just(IPCRequest::GetStats),
+        Just(IPCRequest::GetMetrics),
+        disconnect_policy().prop_map(|policy| IPCRequest::SetDisconnectPolicy { policy }),
+        ("[0-9a-f]{16}", any::<Option<u64>>())
+            .prop_map(|(token, last_seq)| IPCRequest::Resume { token, last_seq }),
+    ]
+}
+
+impl Arbitrary for Key {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Key>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        key().boxed()
+    }
+}
+
+impl Arbitrary for IPCRequest {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<IPCRequest>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        ipc_request().boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn test_key_round_trip(key in any::<Key>()) {
+            prop_assert_eq!(Key::parse(&key.to_string()).unwrap(), key.clone());
+            prop_assert_eq!(key.canonical().to_string(), key.to_string());
+            let json = serde_json::to_string(&key).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Key>(&json).unwrap(), key);
+        }
+
+        #[test]
+        fn test_ipc_request_round_trip(request in any::<IPCRequest>()) {
+            let data = serde_json::to_vec(&request).unwrap();
+            prop_assert_eq!(serde_json::from_slice::<IPCRequest>(&data).unwrap(), request);
+        }
+    }
+}
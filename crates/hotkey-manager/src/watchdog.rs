@@ -0,0 +1,145 @@
+//! Connection health monitoring for clients.
+//!
+//! The watchdog pings the server over a connection of its own, so that it
+//! never competes with the client for requests and events.
+
+use crate::ipc::{IPCClient, IPCConnection};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::time::{sleep, timeout};
+use tracing::{info, warn};
+
+/// Pings slower than this mark the connection as degraded
+const DEGRADED_LATENCY: Duration = Duration::from_millis(250);
+
+/// How long to wait to connect or for a ping response
+const PING_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The state of a client's connection to its server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// The server responds promptly
+    Connected,
+    /// The server is reachable, but slow or failing to respond
+    Degraded,
+    /// The server can't be reached
+    Disconnected,
+}
+
+/// The health of a client's connection to its server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionHealth {
+    /// The current state of the connection
+    pub state: ConnectionState,
+    /// Round trip time of the last successful ping, if there has been one
+    pub latency: Option<Duration>,
+}
+
+impl ConnectionHealth {
+    /// Health with no ping measurement
+    pub(crate) fn new(state: ConnectionState) -> Self {
+        Self {
+            state,
+            latency: None,
+        }
+    }
+
+    /// Health after a successful ping that took `latency`
+    fn pinged(latency: Duration) -> Self {
+        let state = if latency > DEGRADED_LATENCY {
+            ConnectionState::Degraded
+        } else {
+            ConnectionState::Connected
+        };
+        Self {
+            state,
+            latency: Some(latency),
+        }
+    }
+}
+
+/// Type alias for functions called when the connection state changes
+pub(crate) type StateHook = Arc<dyn Fn(ConnectionState) + Send + Sync>;
+
+/// Record new health, calling the hook if the state has changed
+pub(crate) fn update(
+    health: &Mutex<ConnectionHealth>,
+    hook: Option<&StateHook>,
+    new: ConnectionHealth,
+) {
+    let previous = std::mem::replace(&mut *health.lock().expect("health mutex poisoned"), new);
+    if previous.state != new.state {
+        match new.state {
+            ConnectionState::Connected => info!("Connection to server is healthy"),
+            ConnectionState::Degraded => warn!("Connection to server is degraded"),
+            ConnectionState::Disconnected => warn!("Connection to server lost"),
+        }
+        if let Some(hook) = hook {
+            hook(new.state);
+        }
+    }
+}
+
+/// Ping the server at `socket_path` every `interval`, until the task is
+/// aborted.
+pub(crate) async fn run(
+    socket_path: String,
+    interval: Duration,
+    health: Arc<Mutex<ConnectionHealth>>,
+    hook: Option<StateHook>,
+) {
+    let mut connection = None;
+    loop {
+        sleep(interval).await;
+        let observed = probe(&socket_path, &mut connection).await;
+        update(&health, hook.as_ref(), observed);
+    }
+}
+
+/// Ping the server, connecting first if there is no connection
+async fn probe(socket_path: &str, connection: &mut Option<IPCConnection>) -> ConnectionHealth {
+    if connection.is_none() {
+        match timeout(PING_TIMEOUT, IPCClient::new(socket_path).connect()).await {
+            Ok(Ok(new)) => *connection = Some(new),
+            _ => return ConnectionHealth::new(ConnectionState::Disconnected),
+        }
+    }
+    let Some(ipc) = connection.as_mut() else {
+        return ConnectionHealth::new(ConnectionState::Disconnected);
+    };
+    let start = Instant::now();
+    match timeout(PING_TIMEOUT, ipc.ping()).await {
+        Ok(Ok(())) => ConnectionHealth::pinged(start.elapsed()),
+        _ => {
+            // A late response would be mistaken for the next one, so start
+            // over with a new connection
+            *connection = None;
+            ConnectionHealth::new(ConnectionState::Degraded)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update() {
+        let health = Mutex::new(ConnectionHealth::new(ConnectionState::Disconnected));
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let recorded = changes.clone();
+        let hook: StateHook = Arc::new(move |state| recorded.lock().unwrap().push(state));
+
+        let fast = ConnectionHealth::pinged(Duration::from_millis(5));
+        update(&health, Some(&hook), fast);
+        update(&health, Some(&hook), fast);
+        let slow = ConnectionHealth::pinged(Duration::from_secs(1));
+        update(&health, Some(&hook), slow);
+
+        assert_eq!(*health.lock().unwrap(), slow);
+        assert_eq!(
+            *changes.lock().unwrap(),
+            vec![ConnectionState::Connected, ConnectionState::Degraded]
+        );
+    }
+}
@@ -0,0 +1,296 @@
+//! Diagnose common setup problems.
+//!
+//! Each check reports a status, and for anything that isn't fine, a
+//! suggested fix.
+
+use std::fmt;
+use std::path::Path;
+
+use hotkey_manager::{Client, pid_file_path};
+use keymode::Mode;
+
+/// Outcome of a check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+    Skip,
+}
+
+/// The result of a single check
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn with_fix(mut self, fix: impl Into<String>) -> Self {
+        self.fix = Some(fix.into());
+        self
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self.status {
+            Status::Ok => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+            Status::Skip => "skip",
+        };
+        write!(f, "[{status:>4}] {}: {}", self.name, self.detail)?;
+        if let Some(fix) = &self.fix {
+            write!(f, "\n       Fix: {fix}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Run every check, then print the results
+///
+/// Returns whether all checks passed, allowing warnings.
+pub async fn run(socket: &str, config: Option<&Path>) -> bool {
+    let client = Client::new().with_socket_path(socket);
+    let (server, version) = check_server(&client, socket).await;
+    let mut checks = vec![
+        check_permissions(),
+        check_socket_dir(Path::new(socket)),
+        server,
+    ];
+    checks.extend(check_pid_file(&client, socket));
+    checks.push(check_version(version.as_ref()));
+    checks.push(check_config(config));
+
+    for check in &checks {
+        println!("{check}");
+    }
+    checks.iter().all(|check| check.status != Status::Fail)
+}
+
+/// Whether this process is trusted for accessibility
+#[cfg(target_os = "macos")]
+fn accessibility_trusted() -> bool {
+    #[link(name = "ApplicationServices", kind = "framework")]
+    unsafe extern "C" {
+        fn AXIsProcessTrusted() -> bool;
+    }
+    // SAFETY: AXIsProcessTrusted takes no arguments and only reads state
+    unsafe { AXIsProcessTrusted() }
+}
+
+/// Check the macOS accessibility permission
+fn check_permissions() -> Check {
+    const NAME: &str = "Permissions";
+    #[cfg(target_os = "macos")]
+    {
+        if accessibility_trusted() {
+            Check::new(NAME, Status::Ok, "accessibility access granted")
+        } else {
+            Check::new(NAME, Status::Warn, "accessibility access not granted").with_fix(
+                "if hotkeys aren't delivered, allow the app running hotki-cli in System \
+                 Settings › Privacy & Security › Accessibility",
+            )
+        }
+    }
+    #[cfg(not(target_os = "macos"))]
+    Check::new(NAME, Status::Skip, "only checked on macOS")
+}
+
+/// Check that a server could create its socket
+fn check_socket_dir(socket: &Path) -> Check {
+    const NAME: &str = "Socket directory";
+    let dir = match socket.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if !dir.is_dir() {
+        return Check::new(
+            NAME,
+            Status::Fail,
+            format!("{} does not exist", dir.display()),
+        )
+        .with_fix(format!(
+            "create it, or use a socket path in an existing directory: mkdir -p {}",
+            dir.display()
+        ));
+    }
+    let mut probe = socket.as_os_str().to_owned();
+    probe.push(format!(".doctor-{}", std::process::id()));
+    match std::fs::write(&probe, "") {
+        Ok(()) => {
+            let _ = std::fs::remove_file(&probe);
+            Check::new(NAME, Status::Ok, format!("{} is writable", dir.display()))
+        }
+        Err(e) => Check::new(
+            NAME,
+            Status::Fail,
+            format!("{} is not writable: {e}", dir.display()),
+        )
+        .with_fix("fix the directory's permissions, or use a socket path in a writable directory"),
+    }
+}
+
+/// Check that a server responds to pings, returning the version it reports
+/// if it does
+async fn check_server(client: &Client, socket: &str) -> (Check, Option<Option<String>>) {
+    const NAME: &str = "Server";
+    match client.probe().await {
+        Ok(version) => {
+            let detail = match client.discover() {
+                Some(pid) => format!("responding on {socket} (PID {pid})"),
+                None => format!("responding on {socket}"),
+            };
+            (Check::new(NAME, Status::Ok, detail), Some(version))
+        }
+        Err(e) if Path::new(socket).exists() => (
+            Check::new(
+                NAME,
+                Status::Fail,
+                format!("{socket} exists, but no server responds on it ({e})"),
+            )
+            .with_fix(format!(
+                "the socket is stale; remove it with `rm {socket}`, or start a server, which \
+                 replaces it"
+            )),
+            None,
+        ),
+        Err(_) => (
+            Check::new(NAME, Status::Warn, format!("no server running on {socket}")).with_fix(
+                "clients start one as needed; to keep one running, use `hotki-cli install-service`",
+            ),
+            None,
+        ),
+    }
+}
+
+/// Check for a PID file left behind by a server that is no longer running
+fn check_pid_file(client: &Client, socket: &str) -> Option<Check> {
+    let path = pid_file_path(socket);
+    if !path.exists() || client.discover().is_some() {
+        return None;
+    }
+    Some(
+        Check::new(
+            "PID file",
+            Status::Warn,
+            format!("{} names a process that isn't running", path.display()),
+        )
+        .with_fix(format!("remove it with `rm {}`", path.display())),
+    )
+}
+
+/// Check that the server is from the same release as this client
+///
+/// `version` is `None` if no server responded, and `Some(None)` if the
+/// server doesn't report its version.
+fn check_version(version: Option<&Option<String>>) -> Check {
+    const NAME: &str = "Version";
+    let fix = "restart the server so that it runs the same release as the client: \
+               `hotki-cli install-service` reinstalls and restarts the service";
+    match version {
+        None => Check::new(NAME, Status::Skip, "no server to compare with"),
+        Some(Some(version)) if version == hotkey_manager::VERSION => {
+            Check::new(NAME, Status::Ok, format!("client and server are {version}"))
+        }
+        Some(Some(version)) => Check::new(
+            NAME,
+            Status::Fail,
+            format!("server is {version}, client is {}", hotkey_manager::VERSION),
+        )
+        .with_fix(fix),
+        Some(None) => Check::new(
+            NAME,
+            Status::Warn,
+            "server doesn't report its version, so it predates this client",
+        )
+        .with_fix(fix),
+    }
+}
+
+/// Check that a mode definition loads, and report validation issues
+fn check_config(path: Option<&Path>) -> Check {
+    const NAME: &str = "Config";
+    let Some(path) = path else {
+        return Check::new(NAME, Status::Skip, "no config file given");
+    };
+    let mode = match Mode::load(path) {
+        Ok(mode) => mode,
+        Err(e) => {
+            return Check::new(NAME, Status::Fail, e)
+                .with_fix("correct the error, then run `hotki-cli reload` for a hosting server");
+        }
+    };
+    let issues = mode.validate();
+    if issues.is_empty() {
+        Check::new(NAME, Status::Ok, format!("{} is valid", path.display()))
+    } else {
+        let issues: Vec<String> = issues.iter().map(ToString::to_string).collect();
+        Check::new(NAME, Status::Warn, issues.join("; "))
+            .with_fix("these bindings load, but may not behave as intended")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_checks() {
+        let dir = std::env::temp_dir().join(format!("hotki-doctor-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("test.sock");
+        let socket_str = socket.to_str().unwrap();
+
+        assert_eq!(check_socket_dir(&socket).status, Status::Ok);
+        assert_eq!(
+            check_socket_dir(&dir.join("missing/test.sock")).status,
+            Status::Fail
+        );
+
+        // No socket means no server, a leftover one is stale
+        let client = Client::new().with_socket_path(socket_str);
+        let (check, version) = check_server(&client, socket_str).await;
+        assert_eq!((check.status, version), (Status::Warn, None));
+        std::fs::write(&socket, "").unwrap();
+        let (check, _) = check_server(&client, socket_str).await;
+        assert_eq!(check.status, Status::Fail);
+        assert!(check.fix.unwrap().contains(&format!("rm {socket_str}")));
+
+        std::fs::write(pid_file_path(&socket), "999999999\n").unwrap();
+        assert_eq!(
+            check_pid_file(&client, socket_str).map(|check| check.status),
+            Some(Status::Warn)
+        );
+
+        let current = Some(hotkey_manager::VERSION.to_string());
+        assert_eq!(check_version(Some(&current)).status, Status::Ok);
+        assert_eq!(
+            check_version(Some(&Some("0.0.0-old".to_string()))).status,
+            Status::Fail
+        );
+        assert_eq!(check_version(Some(&None)).status, Status::Warn);
+        assert_eq!(check_version(None).status, Status::Skip);
+
+        let config = dir.join("keys.ron");
+        std::fs::write(&config, r#"[("a", "Action", shell("true"))]"#).unwrap();
+        assert_eq!(check_config(Some(&config)).status, Status::Ok);
+        std::fs::write(&config, "[(").unwrap();
+        assert_eq!(check_config(Some(&config)).status, Status::Fail);
+        assert_eq!(check_config(None).status, Status::Skip);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,3 +1,6 @@
+mod doctor;
+mod tui;
+
 use std::{
     sync::{
         Arc,
@@ -7,13 +10,19 @@ use std::{
 };
 
 use anyhow::{Context, Result};
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
 use tokio::{signal, time::sleep};
 use tracing::{debug, error, info};
 use tracing_subscriber::{EnvFilter, fmt, prelude::*};
 
-use hotkey_manager::{Client, IPCConnection, IPCResponse, Key, Server};
-use keymode::{Mode, State};
+use crate::tui::{Tui, View};
+use hotkey_manager::{
+    Client, DisconnectPolicy, IPCConnection, IPCResponse, Key, LaunchAgent, Server,
+};
+use keymode::{Hosted, Mode, Sort, State, sections};
+
+/// The terminal HUD, as used by the client
+type Terminal = Tui<ratatui::backend::CrosstermBackend<std::io::Stdout>>;
 
 #[derive(Debug, Clone, ValueEnum)]
 enum LogLevel {
@@ -24,11 +33,84 @@ enum LogLevel {
     Trace,
 }
 
+/// Label of the LaunchAgent that runs the standalone server
+const SERVICE_LABEL: &str = "si.corte.hotki-server";
+
+/// How often the terminal HUD checks for new command output
+const OUTPUT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Report whether a server is running, using its PID file
+    Status,
+    /// Install a LaunchAgent that keeps the server running at login
+    InstallService {
+        /// File for server output (defaults to ~/Library/Logs/hotki-server.log)
+        #[arg(long)]
+        log: Option<std::path::PathBuf>,
+    },
+    /// Stop the server service and remove its LaunchAgent
+    UninstallService,
+    /// Print a JSON Schema for mode definition files
+    Schema,
+    /// Ask a server started with a config file to reload it
+    Reload,
+    /// Wait for a key combination to be pressed and print it
+    Capture {
+        /// Seconds to wait for a key press
+        #[arg(long, default_value_t = 10)]
+        timeout: u64,
+    },
+    /// Print each key combination pressed until the stop key, to help
+    /// write configs
+    Record {
+        /// Key that ends recording
+        #[arg(long, value_name = "KEY", default_value = "escape")]
+        stop: String,
+        /// Seconds to wait for each key press before giving up
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+        /// Print a skeleton RON mode definition with a binding for each key
+        #[arg(long)]
+        ron: bool,
+    },
+    /// Send a message to the clients subscribed to a topic
+    Publish {
+        /// Name of the topic
+        topic: String,
+        /// Message content, as JSON (anything else is sent as a string)
+        payload: String,
+    },
+    /// Print messages published to a topic until interrupted
+    Subscribe {
+        /// Name of the topic
+        topic: String,
+    },
+    /// Show how often each hotkey has been triggered since the server started
+    Stats,
+    /// Show the server's counters and gauges, for monitoring
+    Metrics {
+        /// Print them in the Prometheus text format
+        #[arg(long)]
+        prometheus: bool,
+    },
+    /// Check for common setup problems, and suggest fixes
+    Doctor {
+        /// Mode definition file to validate
+        config: Option<std::path::PathBuf>,
+    },
+}
+
 #[derive(Parser, Debug)]
 #[command(name = "hotki-cli")]
 #[command(about = "Hotkey manager client and server", long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    /// Path to RON mode definition file
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Path to mode definition file (RON, TOML, YAML or JSON, by extension).
+    /// With --server, the server loads it and runs the mode loop itself.
     #[arg(required_unless_present = "server")]
     config: Option<std::path::PathBuf>,
 
@@ -36,9 +118,68 @@ struct Args {
     #[arg(long)]
     server: bool,
 
+    /// Spawn a server that runs the mode loop, and only display its events
+    #[arg(long, conflicts_with_all = ["server", "state_file"])]
+    hosted: bool,
+
+    /// Write a PID file next to the socket (server mode only)
+    #[arg(long, requires = "server")]
+    pid_file: bool,
+
+    /// Let clients simulate hotkey presses, for testing (server mode only)
+    #[arg(long, requires = "server")]
+    allow_test_triggers: bool,
+
+    /// Socket path of the hotkey server, to connect to or listen on
+    /// (defaults to $HOTKEY_SOCKET, or a per-user path in $XDG_RUNTIME_DIR
+    /// or $TMPDIR)
+    #[arg(long, value_name = "PATH", global = true)]
+    socket: Option<String>,
+
+    /// What to do with the bindings when the client disconnects without
+    /// shutting the server down: unbind, keep (until a client reconnects),
+    /// or a number of seconds to wait for one (server mode only)
+    #[arg(
+        long,
+        value_name = "POLICY",
+        default_value = "unbind",
+        requires = "server"
+    )]
+    on_disconnect: DisconnectPolicy,
+
+    /// Serve metrics for Prometheus at http://ADDRESS/metrics (server mode
+    /// only)
+    #[cfg(feature = "metrics-http")]
+    #[arg(long, value_name = "ADDRESS", requires = "server")]
+    metrics_address: Option<std::net::SocketAddr>,
+
     /// Set the log level
     #[arg(short, long, value_enum)]
     log_level: Option<LogLevel>,
+
+    /// Sort policy for displayed keys: config, key, or desc
+    #[arg(long, default_value = "config")]
+    sort: Sort,
+
+    /// Add a hidden binding that exits to the root to every mode that doesn't
+    /// bind the key already (defaults to escape if no key is given)
+    #[arg(
+        long,
+        value_name = "KEY",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "escape"
+    )]
+    panic_key: Option<String>,
+
+    /// Remember the active mode in this file, and return to it on restart
+    #[arg(long, conflicts_with = "server")]
+    state_file: Option<std::path::PathBuf>,
+
+    /// Show the current mode's keys and command output in a terminal HUD
+    /// instead of printing them
+    #[arg(long, conflicts_with = "server")]
+    tui: bool,
 }
 
 fn main() -> Result<()> {
@@ -71,47 +212,482 @@ fn main() -> Result<()> {
             .init();
     }
 
-    if args.server {
+    let socket = args
+        .socket
+        .unwrap_or_else(hotkey_manager::default_socket_path);
+
+    if let Some(command) = args.command {
+        match command {
+            Command::Status => status(&socket),
+            Command::Schema => println!("{}", keymode::schema_json::<Mode>()),
+            Command::InstallService { log } => install_service(&socket, log)?,
+            Command::UninstallService => uninstall_service()?,
+            Command::Reload => {
+                let runtime =
+                    tokio::runtime::Runtime::new().context("Failed to create Tokio runtime")?;
+                runtime.block_on(reload(&socket))?;
+            }
+            Command::Capture { timeout } => {
+                let runtime =
+                    tokio::runtime::Runtime::new().context("Failed to create Tokio runtime")?;
+                runtime.block_on(capture(&socket, Duration::from_secs(timeout)))?;
+            }
+            Command::Record { stop, timeout, ron } => {
+                let stop = Key::parse(&stop).context("Invalid stop key")?;
+                let runtime =
+                    tokio::runtime::Runtime::new().context("Failed to create Tokio runtime")?;
+                runtime.block_on(record(&socket, &stop, Duration::from_secs(timeout), ron))?;
+            }
+            Command::Publish { topic, payload } => {
+                let runtime =
+                    tokio::runtime::Runtime::new().context("Failed to create Tokio runtime")?;
+                runtime.block_on(publish(&socket, &topic, &payload))?;
+            }
+            Command::Subscribe { topic } => {
+                let runtime =
+                    tokio::runtime::Runtime::new().context("Failed to create Tokio runtime")?;
+                runtime.block_on(subscribe(&socket, &topic))?;
+            }
+            Command::Stats => {
+                let runtime =
+                    tokio::runtime::Runtime::new().context("Failed to create Tokio runtime")?;
+                runtime.block_on(stats(&socket))?;
+            }
+            Command::Metrics { prometheus } => {
+                let runtime =
+                    tokio::runtime::Runtime::new().context("Failed to create Tokio runtime")?;
+                runtime.block_on(metrics(&socket, prometheus))?;
+            }
+            Command::Doctor { config } => {
+                let runtime =
+                    tokio::runtime::Runtime::new().context("Failed to create Tokio runtime")?;
+                if !runtime.block_on(doctor::run(&socket, config.as_deref())) {
+                    anyhow::bail!("Found problems, see the fixes above");
+                }
+            }
+        }
+        Ok(())
+    } else if args.server {
         info!("Starting hotki-cli server");
-        Server::new().run()?;
+        let mut server = Server::new()
+            .with_pid_file(args.pid_file)
+            .with_test_triggers(args.allow_test_triggers)
+            .with_disconnect_policy(args.on_disconnect)
+            .with_socket_path(socket);
+        #[cfg(feature = "metrics-http")]
+        if let Some(address) = args.metrics_address {
+            server = server.with_metrics_address(address);
+        }
+        if let Some(path) = args.config {
+            info!("Hosting keymode in the server");
+            let state = load_state(&path, args.sort, args.panic_key.as_deref())?;
+            let panic_key = args.panic_key;
+            let hosted = Hosted::new(state).with_loader(move || {
+                load_mode(&path, panic_key.as_deref()).map_err(|e| vec![format!("{e:#}")])
+            });
+            server = server.with_handler(hosted);
+        }
+        server.run()?;
         Ok(())
+    } else if args.hosted {
+        info!("Starting hotki-cli client for a hosted server");
+        let runtime = tokio::runtime::Runtime::new().context("Failed to create Tokio runtime")?;
+        runtime.block_on(hosted_client_main(
+            &socket,
+            args.config,
+            args.sort,
+            args.panic_key,
+            args.tui,
+        ))
     } else {
         info!("Starting hotki-cli client");
         let runtime = tokio::runtime::Runtime::new().context("Failed to create Tokio runtime")?;
-        runtime.block_on(client_main(args.config))
+        runtime.block_on(client_main(
+            &socket,
+            args.config,
+            args.sort,
+            args.panic_key,
+            args.state_file,
+            args.tui,
+        ))
+    }
+}
+
+/// Print the status of the server on a socket
+fn status(socket: &str) {
+    let client = Client::new().with_socket_path(socket);
+    match client.discover() {
+        Some(pid) => println!("Server running (PID: {pid})"),
+        None => println!("No server running"),
+    }
+}
+
+/// Ask the running server to reload its config, listing any problems
+async fn reload(socket: &str) -> Result<()> {
+    let mut client = Client::new()
+        .with_socket_path(socket)
+        .connect()
+        .await
+        .context("Failed to connect to hotkey server")?;
+    let result = client
+        .connection()
+        .context("Failed to get client connection")?
+        .reload_config()
+        .await;
+    let _ = client.disconnect(false).await;
+    match result {
+        Ok(()) => {
+            println!("Config reloaded");
+            Ok(())
+        }
+        Err(hotkey_manager::Error::InvalidConfig(errors)) => {
+            for error in &errors {
+                eprintln!("{error}");
+            }
+            anyhow::bail!("Config is invalid, keeping the current one")
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Publish a message to a topic on the running server
+async fn publish(socket: &str, topic: &str, payload: &str) -> Result<()> {
+    let payload = serde_json::from_str(payload)
+        .unwrap_or_else(|_| serde_json::Value::String(payload.to_string()));
+    let mut client = Client::new()
+        .with_socket_path(socket)
+        .connect()
+        .await
+        .context("Failed to connect to hotkey server")?;
+    let result = client
+        .connection()
+        .context("Failed to get client connection")?
+        .publish(topic, payload)
+        .await;
+    let _ = client.disconnect(false).await;
+    let delivered = result?;
+    println!("Delivered to {delivered} subscribers");
+    Ok(())
+}
+
+/// Print each message published to a topic as a line of JSON
+async fn subscribe(socket: &str, topic: &str) -> Result<()> {
+    let mut client = Client::new()
+        .with_socket_path(socket)
+        .connect()
+        .await
+        .context("Failed to connect to hotkey server")?;
+    let connection = client
+        .connection()
+        .context("Failed to get client connection")?;
+    connection.subscribe(topic).await?;
+    loop {
+        tokio::select! {
+            event = connection.recv_event() => match event? {
+                IPCResponse::Published { payload, .. } => println!("{payload}"),
+                other => debug!("Ignoring event: {:?}", other),
+            },
+            _ = signal::ctrl_c() => break,
+        }
+    }
+    let _ = client.disconnect(false).await;
+    Ok(())
+}
+
+/// Print trigger counts for the running server's hotkeys, most used first
+async fn stats(socket: &str) -> Result<()> {
+    let mut client = Client::new()
+        .with_socket_path(socket)
+        .connect()
+        .await
+        .context("Failed to connect to hotkey server")?;
+    let result = client
+        .connection()
+        .context("Failed to get client connection")?
+        .get_stats()
+        .await;
+    let _ = client.disconnect(false).await;
+    let stats = result?;
+    if stats.is_empty() {
+        println!("No hotkeys have been triggered");
+        return Ok(());
+    }
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+    for stat in stats {
+        let ago = Duration::from_millis(now.saturating_sub(stat.last_triggered_ms)).as_secs();
+        println!("{:>6}  {}  (last {ago}s ago)", stat.count, stat.identifier);
+    }
+    Ok(())
+}
+
+/// Print the running server's metrics
+async fn metrics(socket: &str, prometheus: bool) -> Result<()> {
+    let mut client = Client::new()
+        .with_socket_path(socket)
+        .connect()
+        .await
+        .context("Failed to connect to hotkey server")?;
+    let result = client
+        .connection()
+        .context("Failed to get client connection")?
+        .get_metrics()
+        .await;
+    let _ = client.disconnect(false).await;
+    let metrics = result?;
+    if prometheus {
+        print!("{}", metrics.to_prometheus());
+        return Ok(());
+    }
+    println!("Events delivered:  {}", metrics.events_delivered);
+    println!("Rebinds:           {}", metrics.rebinds);
+    println!("Active bindings:   {}", metrics.active_bindings);
+    println!("IPC errors:        {}", metrics.ipc_errors);
+    println!("Callbacks:         {}", metrics.callbacks);
+    if let Some(average) = metrics
+        .callback_latency_us_total
+        .checked_div(metrics.callbacks)
+    {
+        println!(
+            "Callback latency:  {average}µs average, {}µs max",
+            metrics.callback_latency_us_max
+        );
+    }
+    if metrics.registrations > 0 {
+        println!(
+            "Register time:     {}µs p50, {}µs p95 over {} registrations",
+            metrics.register_latency_us_p50, metrics.register_latency_us_p95, metrics.registrations
+        );
+    }
+    if metrics.unregistrations > 0 {
+        println!(
+            "Unregister time:   {}µs p50, {}µs p95 over {} unregistrations",
+            metrics.unregister_latency_us_p50,
+            metrics.unregister_latency_us_p95,
+            metrics.unregistrations
+        );
+    }
+    Ok(())
+}
+
+/// Install and (re)load the server LaunchAgent
+///
+/// Reinstalling replaces the agent definition and restarts the server, so
+/// running this after an upgrade picks up the new binary.
+fn install_service(socket: &str, log: Option<std::path::PathBuf>) -> Result<()> {
+    let log = match log {
+        Some(log) => log,
+        None => std::env::var_os("HOME")
+            .map(|home| std::path::PathBuf::from(home).join("Library/Logs/hotki-server.log"))
+            .context("HOME environment variable is not set")?,
+    };
+    let exe = std::env::current_exe().context("Failed to locate the hotki-cli executable")?;
+    let agent = LaunchAgent::new(SERVICE_LABEL, exe)
+        .with_args([
+            "--server",
+            "--pid-file",
+            "--log-level",
+            "info",
+            "--socket",
+            socket,
+        ])
+        .with_keep_alive(true)
+        .with_log_path(&log);
+
+    if agent.is_installed() {
+        // Stop the old server so the new definition takes effect
+        if let Err(e) = agent.unload() {
+            debug!("Failed to unload existing service: {}", e);
+        }
+    }
+    let changed = agent.write()?;
+    agent.load()?;
+
+    let path = agent.plist_path()?;
+    if changed {
+        println!("Installed service: {}", path.display());
+    } else {
+        println!("Reloaded service: {}", path.display());
+    }
+    println!("Server socket: {socket}, log: {}", log.display());
+    Ok(())
+}
+
+/// Unload and remove the server LaunchAgent
+fn uninstall_service() -> Result<()> {
+    let exe = std::env::current_exe().context("Failed to locate the hotki-cli executable")?;
+    let agent = LaunchAgent::new(SERVICE_LABEL, exe);
+    if !agent.is_installed() {
+        println!("Service is not installed");
+        return Ok(());
+    }
+    if let Err(e) = agent.unload() {
+        debug!("Failed to unload service: {}", e);
+    }
+    agent.remove()?;
+    println!("Removed service: {}", agent.plist_path()?.display());
+    Ok(())
+}
+
+/// Print the next key combination pressed, in config syntax
+async fn capture(socket: &str, timeout: Duration) -> Result<()> {
+    let mut client = Client::new()
+        .with_socket_path(socket)
+        .with_auto_spawn_server()
+        .connect()
+        .await
+        .context("Failed to connect to hotkey server")?;
+    println!("Press a key combination...");
+    let result = client
+        .connection()
+        .context("Failed to get client connection")?
+        .capture(timeout)
+        .await;
+    if let Err(e) = client.disconnect(true).await {
+        debug!("Error during disconnect: {}", e);
+    }
+    match result.context("Failed to capture key")? {
+        Some(key) => println!("{key}"),
+        None => anyhow::bail!("No key pressed within {} seconds", timeout.as_secs()),
+    }
+    Ok(())
+}
+
+/// Print available keys (excluding hidden ones), grouped by section
+fn print_keys(state: &State) {
+    let visible: Vec<_> = state
+        .keys()
+        .into_iter()
+        .filter(|(_, _, attrs)| !attrs.hide)
+        .collect();
+    println!("\n\nAvailable keys:");
+    for (section, entries) in sections(&visible) {
+        let indent = match section {
+            Some(title) => {
+                println!("  {title}:");
+                "    "
+            }
+            None => "  ",
+        };
+        for (key, desc, attrs) in entries {
+            match &attrs.icon {
+                Some(icon) => println!("{indent}{key} - {icon} {desc}"),
+                None => println!("{indent}{key} - {desc}"),
+            }
+        }
+    }
+    if let Some(count) = state.count() {
+        println!("Count: {count}");
+    }
+}
+
+/// Show a message, or a warning, in the terminal HUD if there is one and
+/// print it otherwise
+fn report(tui: &mut Option<Terminal>, text: String, warning: bool) -> Result<()> {
+    match tui {
+        Some(tui) if warning => tui.push_output([format!("Warning: {text}")]),
+        Some(tui) => tui.push_output([text]),
+        None if warning => {
+            eprintln!("Warning: {text}");
+            Ok(())
+        }
+        None => {
+            println!("{text}");
+            Ok(())
+        }
+    }
+}
+
+/// Print key combinations as they are pressed, until the stop key
+///
+/// Every key is captured globally while recording, including Ctrl+C, so a
+/// stop key ends it instead. Prompts go to stderr, so the output can be
+/// redirected into a config file.
+async fn record(socket: &str, stop: &Key, timeout: Duration, ron: bool) -> Result<()> {
+    let mut client = Client::new()
+        .with_socket_path(socket)
+        .with_auto_spawn_server()
+        .connect()
+        .await
+        .context("Failed to connect to hotkey server")?;
+    eprintln!("Press key combinations to record them, {stop} to stop...");
+    if ron {
+        println!("[");
+    }
+    let connection = client
+        .connection()
+        .context("Failed to get client connection")?;
+    let result = loop {
+        match connection.capture(timeout).await {
+            Ok(Some(key)) if key == *stop => break Ok(()),
+            Ok(Some(key)) if ron => println!("    ({:?}, \"\", shell(\"\")),", key.to_string()),
+            Ok(Some(key)) => println!("{key}"),
+            Ok(None) => {
+                eprintln!("No key pressed within {} seconds", timeout.as_secs());
+                break Ok(());
+            }
+            Err(e) => break Err(anyhow::Error::from(e).context("Failed to capture key")),
+        }
+    };
+    if ron {
+        println!("]");
     }
+    if let Err(e) = client.disconnect(true).await {
+        debug!("Error during disconnect: {}", e);
+    }
+    result
 }
 
 /// Process hotkey events in a loop
-async fn process_hotkey_events(connection: &mut IPCConnection, state: &mut State) -> Result<bool> {
+async fn process_hotkey_events(
+    connection: &mut IPCConnection,
+    state: &mut State,
+    tui: &mut Option<Terminal>,
+) -> Result<bool> {
+    // Report problems from commands running in the background
+    for warning in state.take_warnings() {
+        report(tui, warning, true)?;
+    }
+
     // Rebind keys for current mode
-    let keys = state.keys();
-    let key_refs: Vec<Key> = keys.iter().map(|(k, _, _)| k.clone()).collect();
     connection
-        .rebind(&key_refs)
+        .rebind(&state.bound_keys())
         .await
         .context("Failed to rebind hotkeys")?;
 
-    // Print available keys before each event (excluding hidden ones)
-    println!("\n\nAvailable keys:");
-    for (key, desc, attrs) in &keys {
-        if !attrs.hide {
-            println!("  {key} - {desc}");
-        }
+    // Show available keys before each event
+    match tui {
+        Some(tui) => tui.set_view(View::from_state(state))?,
+        None => print_keys(state),
     }
 
-    match connection.recv_event().await {
+    // Command output arrives in the background, so wait for it alongside
+    // events
+    let event = loop {
+        tokio::select! {
+            event = connection.recv_event() => break event,
+            _ = sleep(OUTPUT_POLL_INTERVAL), if tui.is_some() => {
+                if let Some(tui) = tui {
+                    tui.push_output(state.take_output())?;
+                }
+            }
+        }
+    };
+
+    match event {
         Ok(IPCResponse::HotkeyTriggered(key)) => {
             debug!("Received hotkey event: {}", key);
             match state.handle_key(&key) {
                 Ok(handled) => {
                     // Display user message if present
                     if !handled.user.is_empty() {
-                        println!("{}", handled.user);
+                        report(tui, handled.user, false)?;
                     }
                     // Display warning if present
                     if !handled.warn.is_empty() {
-                        eprintln!("Warning: {}", handled.warn);
+                        report(tui, handled.warn, true)?;
                     }
                 }
                 Err(e) => {
@@ -120,6 +696,9 @@ async fn process_hotkey_events(connection: &mut IPCConnection, state: &mut State
                 }
             }
         }
+        Ok(IPCResponse::AppChanged { bundle_id, name }) => {
+            debug!("Frontmost application: {} ({})", name, bundle_id);
+        }
         Ok(response) => {
             info!("Received unexpected response: {:?}", response);
         }
@@ -132,29 +711,158 @@ async fn process_hotkey_events(connection: &mut IPCConnection, state: &mut State
     Ok(false) // Continue processing
 }
 
-async fn client_main(config_path: Option<std::path::PathBuf>) -> Result<()> {
-    // Load and parse RON mode definition
-    let path = config_path.expect("Config path is required for client mode");
-    info!("Loading mode configuration from: {:?}", path);
-    let ron_content = std::fs::read_to_string(&path)
-        .with_context(|| format!("Failed to read config file: {path:?}"))?;
+/// Run a client that leaves the mode loop to the server and prints its events
+async fn hosted_client_main(
+    socket: &str,
+    config_path: Option<std::path::PathBuf>,
+    sort: Sort,
+    panic_key: Option<String>,
+    tui: bool,
+) -> Result<()> {
+    let path = config_path.context("Config path is required for client mode")?;
+    let mut server_args = vec![
+        "--server".to_string(),
+        path.display().to_string(),
+        "--sort".to_string(),
+        sort.to_string(),
+    ];
+    if let Some(panic_key) = panic_key {
+        server_args.push(format!("--panic-key={panic_key}"));
+    }
+    let exe = std::env::current_exe().context("Failed to find the current executable")?;
+    let mut client = Client::new()
+        .with_socket_path(socket)
+        .with_server_command(exe, server_args)
+        .with_server_socket_arg(hotkey_manager::SOCKET_ARG)
+        .with_server_ready_handshake()
+        .connect()
+        .await
+        .context("Failed to connect to hotkey server")?;
+    info!("Connected to server (PID: {:?})", client.server_pid());
+
+    let mut tui = if tui { Some(Tui::start()?) } else { None };
+    let connection = client
+        .connection()
+        .context("Failed to get client connection")?;
+    let result = tokio::select! {
+        result = async {
+            loop {
+                match connection.recv_event().await? {
+                    IPCResponse::ModeChanged { modes, keys } => match &mut tui {
+                        Some(tui) => tui.set_view(View::from_keys(modes, keys))?,
+                        None => {
+                            println!("\n\n[{}] Available keys:", modes.join(" > "));
+                            for (key, desc) in keys {
+                                println!("  {key} - {desc}");
+                            }
+                        }
+                    },
+                    IPCResponse::ActionExecuted { desc, .. } => {
+                        report(&mut tui, format!("> {desc}"), false)?
+                    }
+                    IPCResponse::ChordPending { prefix, .. } => {
+                        let prefix: Vec<String> = prefix.iter().map(Key::to_string).collect();
+                        report(&mut tui, format!("{} -", prefix.join(" ")), false)?
+                    }
+                    IPCResponse::ChordAborted => report(&mut tui, "Sequence cancelled".to_string(), false)?,
+                    IPCResponse::Message { text, warning } => report(&mut tui, text, warning)?,
+                    IPCResponse::AppChanged { bundle_id, name } => {
+                        debug!("Frontmost application: {} ({})", name, bundle_id)
+                    }
+                    response => info!("Received unexpected response: {:?}", response),
+                }
+            }
+        } => result,
+        _ = signal::ctrl_c() => {
+            info!("Received Ctrl+C, shutting down...");
+            Ok(())
+        }
+    };
+
+    drop(tui);
+    if let Err(e) = client.disconnect(true).await {
+        debug!("Error during disconnect: {}", e);
+    }
+    result
+}
 
-    let mode = match Mode::from_ron(&ron_content) {
+/// Read a mode path saved by [`save_mode_path`], one key per line
+fn load_mode_path(path: &std::path::Path) -> Vec<String> {
+    std::fs::read_to_string(path)
+        .map(|s| s.lines().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// Save the active mode path so that a restart can return to it
+fn save_mode_path(path: &std::path::Path, state: &State) {
+    let content: String = state
+        .mode_path()
+        .iter()
+        .map(|key| format!("{key}\n"))
+        .collect();
+    if let Err(e) = std::fs::write(path, content) {
+        error!("Failed to write state file {:?}: {}", path, e);
+    }
+}
+
+/// Load a mode definition, reporting validation issues as warnings
+fn load_mode(path: &std::path::Path, panic_key: Option<&str>) -> Result<Mode> {
+    // Load and parse the mode definition, detecting its format by extension
+    info!("Loading mode configuration from: {:?}", path);
+    let mut mode = match Mode::load(path) {
         Ok(mode) => {
             info!("Successfully parsed mode configuration");
             mode
         }
         Err(e) => {
-            error!("Failed to parse RON mode definition: {}", e);
+            error!("Failed to parse mode definition: {}", e);
             return Err(anyhow::anyhow!("Invalid mode configuration: {}", e));
         }
     };
 
-    // Create keymode state
-    let mut state = State::new(mode);
+    for issue in mode.validate() {
+        eprintln!("Warning: {issue}");
+    }
+
+    if let Some(panic_key) = panic_key {
+        let key = Key::parse(panic_key).context("Invalid panic key")?;
+        mode.add_panic_key(&key);
+    }
+    Ok(mode)
+}
+
+/// Load a mode definition and create a keymode state for it
+fn load_state(path: &std::path::Path, sort: Sort, panic_key: Option<&str>) -> Result<State> {
+    let mode = load_mode(path, panic_key)?;
+    let mut state = State::new(mode).with_sort(sort);
+    if let Some(dir) = path.parent() {
+        state = state.with_var("config_dir", dir.display().to_string());
+    }
+    Ok(state)
+}
+
+async fn client_main(
+    socket: &str,
+    config_path: Option<std::path::PathBuf>,
+    sort: Sort,
+    panic_key: Option<String>,
+    state_file: Option<std::path::PathBuf>,
+    tui: bool,
+) -> Result<()> {
+    let path = config_path.expect("Config path is required for client mode");
+    let mut state = load_state(&path, sort, panic_key.as_deref())?;
+    if tui {
+        // Command output would otherwise write over the display
+        state = state.with_captured_output();
+    }
+    if let Some(state_file) = &state_file {
+        let depth = state.restore_path(&load_mode_path(state_file));
+        debug!("Restored mode depth {} from {:?}", depth, state_file);
+    }
 
     let shutdown_sent = Arc::new(AtomicBool::new(false));
     let mut client = Client::new()
+        .with_socket_path(socket)
         .with_auto_spawn_server()
         .connect()
         .await
@@ -172,6 +880,8 @@ async fn client_main(config_path: Option<std::path::PathBuf>) -> Result<()> {
         shutdown_sent_ctrlc.store(true, Ordering::SeqCst);
     });
 
+    let mut tui = if tui { Some(Tui::start()?) } else { None };
+
     // Get the connection
     let connection = client
         .connection()
@@ -185,7 +895,11 @@ async fn client_main(config_path: Option<std::path::PathBuf>) -> Result<()> {
         tokio::select! {
             result = async {
                 loop {
-                    match process_hotkey_events(connection, &mut state).await {
+                    let result = process_hotkey_events(connection, &mut state, &mut tui).await;
+                    if let Some(state_file) = &state_file {
+                        save_mode_path(state_file, &state);
+                    }
+                    match result {
                         Ok(should_exit) => {
                             if should_exit {
                                 break Ok(());
@@ -213,6 +927,7 @@ async fn client_main(config_path: Option<std::path::PathBuf>) -> Result<()> {
     }
     .await;
 
+    drop(tui);
     info!("\nShutting down...");
     // Try to disconnect gracefully, but don't fail if the connection is already broken
     if let Err(e) = client.disconnect(true).await {
@@ -0,0 +1,304 @@
+//! A terminal HUD for the client, drawn with ratatui.
+//!
+//! The display shows the active modes as a breadcrumb, the current mode's
+//! keys grouped by section, and a pane with messages, warnings and the output
+//! of shell commands.
+
+use std::collections::VecDeque;
+use std::io::{Stdout, stdout};
+
+use anyhow::{Context, Result};
+use keymode::{State, sections};
+use ratatui::{
+    Frame, Terminal,
+    backend::{Backend, CrosstermBackend},
+    crossterm::{
+        cursor::{Hide, Show},
+        execute,
+        terminal::{EnterAlternateScreen, LeaveAlternateScreen},
+    },
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph},
+};
+
+/// Number of output lines kept for display
+const OUTPUT_LINES: usize = 200;
+
+/// Height of the output pane, including its border
+const OUTPUT_HEIGHT: u16 = 10;
+
+/// A displayed binding
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub section: Option<String>,
+    pub key: String,
+    pub desc: String,
+}
+
+/// What the HUD shows for the current mode
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct View {
+    /// Names of the active modes, from outermost to current
+    pub modes: Vec<String>,
+    /// Visible bindings, in display order
+    pub entries: Vec<Entry>,
+    /// A pending count or filter, shown after the breadcrumb
+    pub status: Option<String>,
+}
+
+impl View {
+    /// Build a view of a keymode state, leaving out hidden bindings
+    pub fn from_state(state: &State) -> Self {
+        let keys: Vec<_> = state
+            .keys()
+            .into_iter()
+            .filter(|(_, _, attrs)| !attrs.hide)
+            .collect();
+        let mut entries = Vec::new();
+        for (section, group) in sections(&keys) {
+            for (key, desc, attrs) in group {
+                entries.push(Entry {
+                    section: section.map(String::from),
+                    key: key.to_string(),
+                    desc: match &attrs.icon {
+                        Some(icon) => format!("{icon} {desc}"),
+                        None => desc.clone(),
+                    },
+                });
+            }
+        }
+        let status = match (state.filter(), state.count()) {
+            (Some(filter), _) => Some(format!("/{filter}")),
+            (None, Some(count)) => Some(format!("{count}×")),
+            (None, None) => None,
+        };
+        Self {
+            modes: state.mode_names().into_iter().map(String::from).collect(),
+            entries,
+            status,
+        }
+    }
+
+    /// Build a view from the modes and (key, description) pairs reported by
+    /// a server hosting the mode loop
+    pub fn from_keys(modes: Vec<String>, keys: Vec<(String, String)>) -> Self {
+        Self {
+            modes,
+            entries: keys
+                .into_iter()
+                .map(|(key, desc)| Entry {
+                    section: None,
+                    key,
+                    desc,
+                })
+                .collect(),
+            status: None,
+        }
+    }
+}
+
+/// The terminal HUD
+pub struct Tui<B: Backend> {
+    terminal: Terminal<B>,
+    view: View,
+    output: VecDeque<String>,
+    /// Whether the terminal was switched to the alternate screen, and must be
+    /// restored on drop
+    restore: bool,
+}
+
+impl Tui<CrosstermBackend<Stdout>> {
+    /// Take over the terminal, switching to the alternate screen
+    ///
+    /// Raw mode is left off, so Ctrl+C still interrupts the client.
+    pub fn start() -> Result<Self> {
+        execute!(stdout(), EnterAlternateScreen, Hide).context("Failed to set up terminal")?;
+        let mut tui = Self::new(CrosstermBackend::new(stdout()))?;
+        tui.restore = true;
+        tui.terminal.clear()?;
+        Ok(tui)
+    }
+}
+
+impl<B: Backend> Tui<B> {
+    /// Create a HUD drawing to a backend
+    pub fn new(backend: B) -> Result<Self> {
+        Ok(Self {
+            terminal: Terminal::new(backend).context("Failed to create terminal")?,
+            view: View::default(),
+            output: VecDeque::with_capacity(OUTPUT_LINES),
+            restore: false,
+        })
+    }
+
+    /// Show a new view, redrawing if it changed
+    pub fn set_view(&mut self, view: View) -> Result<()> {
+        if view != self.view {
+            self.view = view;
+            self.draw()?;
+        }
+        Ok(())
+    }
+
+    /// Add lines to the output pane, discarding the oldest if full
+    pub fn push_output(&mut self, lines: impl IntoIterator<Item = String>) -> Result<()> {
+        let mut changed = false;
+        for line in lines {
+            if self.output.len() >= OUTPUT_LINES {
+                self.output.pop_front();
+            }
+            self.output.push_back(line);
+            changed = true;
+        }
+        if changed {
+            self.draw()?;
+        }
+        Ok(())
+    }
+
+    /// Redraw the whole display
+    pub fn draw(&mut self) -> Result<()> {
+        let (view, output) = (&self.view, &self.output);
+        self.terminal
+            .draw(|frame| render(frame, view, output))
+            .context("Failed to draw terminal")?;
+        Ok(())
+    }
+}
+
+impl<B: Backend> Drop for Tui<B> {
+    fn drop(&mut self) {
+        if self.restore {
+            let _ = execute!(stdout(), Show, LeaveAlternateScreen);
+        }
+    }
+}
+
+/// Draw the breadcrumb, keys and output panes
+fn render(frame: &mut Frame, view: &View, output: &VecDeque<String>) {
+    let [crumb_area, keys_area, output_area] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(3),
+        Constraint::Length(OUTPUT_HEIGHT),
+    ])
+    .areas(frame.area());
+
+    let mut crumb = vec![Span::styled(
+        if view.modes.is_empty() {
+            "(root)".to_string()
+        } else {
+            view.modes.join(" › ")
+        },
+        Style::new().add_modifier(Modifier::BOLD),
+    )];
+    if let Some(status) = &view.status {
+        crumb.push(Span::styled(
+            format!("  {status}"),
+            Style::new().fg(Color::Yellow),
+        ));
+    }
+    frame.render_widget(
+        Paragraph::new(Line::from(crumb)).block(Block::bordered().title("hotki")),
+        crumb_area,
+    );
+
+    let key_width = view
+        .entries
+        .iter()
+        .map(|entry| entry.key.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut lines = Vec::new();
+    let mut section = None;
+    for entry in &view.entries {
+        if entry.section.is_some() && entry.section != section {
+            lines.push(Line::styled(
+                entry.section.clone().unwrap_or_default(),
+                Style::new().fg(Color::DarkGray),
+            ));
+        }
+        section = entry.section.clone();
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!(" {:>key_width$} ", entry.key),
+                Style::new().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!(" {}", entry.desc)),
+        ]));
+    }
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("Keys")),
+        keys_area,
+    );
+
+    // Keep the newest lines in view
+    let visible = usize::from(OUTPUT_HEIGHT.saturating_sub(2));
+    let lines: Vec<Line> = output
+        .iter()
+        .skip(output.len().saturating_sub(visible))
+        .map(|line| Line::raw(line.as_str()))
+        .collect();
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("Output")),
+        output_area,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotkey_manager::Key;
+    use keymode::Mode;
+    use ratatui::backend::TestBackend;
+
+    /// The text on each row of a test terminal
+    fn rows(tui: &Tui<TestBackend>) -> Vec<String> {
+        let buffer = tui.terminal.backend().buffer();
+        let width = buffer.area.width as usize;
+        buffer
+            .content()
+            .chunks(width)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_tui() {
+        let root = Mode::from_ron(
+            r#"[
+            ("m", "Menu", mode([
+                ("a", "Action", shell("true"), (section: "Tools")),
+                ("x", "Hidden", exit, (hide: true)),
+            ])),
+        ]"#,
+        )
+        .unwrap();
+        let mut state = State::new(root);
+        state.handle_key(&Key::parse("m").unwrap()).unwrap();
+        let view = View::from_state(&state);
+        assert_eq!(view.modes, vec!["Menu"]);
+        assert_eq!(
+            view.entries,
+            vec![Entry {
+                section: Some("Tools".to_string()),
+                key: "a".to_string(),
+                desc: "Action".to_string(),
+            }]
+        );
+
+        let mut tui = Tui::new(TestBackend::new(40, 20)).unwrap();
+        tui.set_view(view).unwrap();
+        tui.push_output((0..12).map(|i| format!("line {i}")))
+            .unwrap();
+        let rows = rows(&tui);
+        assert!(rows[1].contains("Menu"));
+        assert!(rows.iter().any(|row| row.contains("Tools")));
+        assert!(rows.iter().any(|row| row.contains("a  Action")));
+        assert!(rows.iter().all(|row| !row.contains("Hidden")));
+        // Only the newest output lines fit
+        assert!(rows.iter().any(|row| row.contains("line 11")));
+        assert!(rows.iter().all(|row| !row.contains("line 3")));
+    }
+}
@@ -1,32 +1,610 @@
-use keymode::Mode;
+use hotkey_manager::Key;
+use keymode::{Attrs, Format, Mode, Pos, ShellContext, Sort, Visibility};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+/// Style of the mode indicator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default, JsonSchema)]
 #[serde(rename_all = "lowercase")]
-pub enum Pos {
-    N,
+pub enum Indicator {
+    /// No indicator
     #[default]
-    NE,
-    E,
-    SE,
-    S,
-    SW,
-    W,
-    NW,
-    Center,
+    Off,
+    /// A dot
+    Dot,
+    /// The names of the active modes
+    Name,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Color scheme of the HUD and logs windows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Appearance {
+    /// Follow the system appearance, including changes while running
+    #[default]
+    Auto,
+    /// Always dark
+    Dark,
+    /// Always light
+    Light,
+}
+
+impl Appearance {
+    /// CSS class that selects the palette, applied to the root element of a
+    /// window
+    pub fn class(self) -> &'static str {
+        match self {
+            // The stylesheet follows the system by default
+            Self::Auto => "",
+            Self::Dark => "appearance-dark",
+            Self::Light => "appearance-light",
+        }
+    }
+}
+
+/// Material of the blur behind the HUD on macOS
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Vibrancy {
+    /// No blur
+    #[default]
+    Off,
+    /// The material of heads-up display windows
+    Hud,
+    /// The material of popovers
+    Popover,
+    /// The material of menus
+    Menu,
+    /// The material of sidebars
+    Sidebar,
+    /// The material of tooltips
+    Tooltip,
+}
+
+impl Vibrancy {
+    /// The `NSVisualEffectMaterial` value of the material, if there is one
+    pub fn material(self) -> Option<isize> {
+        match self {
+            Self::Off => None,
+            Self::Menu => Some(5),
+            Self::Popover => Some(6),
+            Self::Sidebar => Some(7),
+            Self::Hud => Some(13),
+            Self::Tooltip => Some(17),
+        }
+    }
+}
+
+fn default_opacity() -> f64 {
+    1.0
+}
+
+/// Default size in logical pixels of the HUD's text
+const DEFAULT_FONT_SIZE: f64 = 16.0;
+
+fn default_font_size() -> f64 {
+    DEFAULT_FONT_SIZE
+}
+
+fn default_monospace() -> bool {
+    true
+}
+
+/// Fonts of the HUD
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Theme {
+    /// CSS font family of the HUD's text, such as `"Helvetica Neue"`. The
+    /// system font if not set.
+    #[serde(default)]
+    pub font_family: Option<String>,
+    /// Size in logical pixels of the HUD's text. Rows, and the window, grow
+    /// with it.
+    #[serde(default = "default_font_size")]
+    pub font_size: f64,
+    /// Show keys in a monospace font, rather than `font_family`
+    #[serde(default = "default_monospace")]
+    pub monospace: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            font_family: None,
+            font_size: DEFAULT_FONT_SIZE,
+            monospace: true,
+        }
+    }
+}
+
+impl Theme {
+    /// Inline CSS applying the fonts to the HUD
+    pub fn style(&self) -> String {
+        let family = self
+            .font_family
+            .as_ref()
+            .map(|family| format!("font-family: {family}; "))
+            .unwrap_or_default();
+        format!("{family}font-size: {}px;", self.font_size)
+    }
+
+    /// Height in logical pixels of a line of text, with the stylesheet's
+    /// line height of 1.5
+    pub fn line_height(&self) -> f64 {
+        self.font_size * 1.5
+    }
+}
+
+/// Default number of log lines kept in memory
+pub const DEFAULT_LOG_CAPACITY: usize = 20_000;
+
+/// Default size in bytes at which the log file is rotated
+const DEFAULT_LOG_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Default number of rotated log files kept
+const DEFAULT_LOG_FILES: usize = 5;
+
+fn default_log_capacity() -> usize {
+    DEFAULT_LOG_CAPACITY
+}
+
+fn default_log_file_size() -> u64 {
+    DEFAULT_LOG_FILE_SIZE
+}
+
+fn default_log_files() -> usize {
+    DEFAULT_LOG_FILES
+}
+
+/// Where hotki's logs are kept
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Logs {
+    /// Number of recent log lines kept in memory and shown in the logs
+    /// window
+    #[serde(default = "default_log_capacity")]
+    pub capacity: usize,
+    /// Also write logs to `~/Library/Logs/hotki/hotki.log`, so they survive
+    /// a crash
+    #[serde(default)]
+    pub persist: bool,
+    /// Size in bytes at which the log file is rotated
+    #[serde(default = "default_log_file_size")]
+    pub max_file_size: u64,
+    /// Number of rotated log files kept besides the current one
+    #[serde(default = "default_log_files")]
+    pub max_files: usize,
+}
+
+impl Default for Logs {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_LOG_CAPACITY,
+            persist: false,
+            max_file_size: DEFAULT_LOG_FILE_SIZE,
+            max_files: DEFAULT_LOG_FILES,
+        }
+    }
+}
+
+/// Default distance in logical pixels between the HUD and the screen edges it
+/// is anchored to
+const DEFAULT_OFFSET: f64 = 20.0;
+
+fn default_offset() -> f64 {
+    DEFAULT_OFFSET
+}
+
+/// Distance of the HUD from the screen edges it is anchored to
+///
+/// Offsets only apply along an axis the HUD is anchored on, so `x` is ignored
+/// for positions centered horizontally, and `y` for those centered
+/// vertically.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Offset {
+    /// Distance in logical pixels from the left or right edge
+    #[serde(default = "default_offset")]
+    pub x: f64,
+    /// Distance in logical pixels from the top or bottom edge
+    #[serde(default = "default_offset")]
+    pub y: f64,
+}
+
+fn default_hud() -> bool {
+    true
+}
+
+fn default_indicator_pos() -> Pos {
+    Pos::NW
+}
+
+/// Default time in milliseconds before the HUD auto-hides
+const DEFAULT_AUTO_HIDE_MS: u64 = 3000;
+
+fn default_auto_hide_ms() -> u64 {
+    DEFAULT_AUTO_HIDE_MS
+}
+
+/// Default duration in milliseconds of the HUD feedback shown when a binding fires
+const DEFAULT_FLASH_MS: u64 = 600;
+
+fn default_flash_ms() -> u64 {
+    DEFAULT_FLASH_MS
+}
+
+/// Default duration in milliseconds of messages shown in the HUD
+const DEFAULT_MESSAGE_MS: u64 = 1500;
+
+fn default_message_ms() -> u64 {
+    DEFAULT_MESSAGE_MS
+}
+
+/// Default width of a HUD column in logical pixels
+const DEFAULT_WIDTH: f64 = 400.0;
+
+fn default_width() -> f64 {
+    DEFAULT_WIDTH
+}
+
+/// Default key for the injected exit binding
+const DEFAULT_PANIC_KEY: &str = "escape";
+
+fn default_panic_key() -> String {
+    DEFAULT_PANIC_KEY.to_string()
+}
+
+/// hotki configuration
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Config {
+    /// Key bindings of the root mode
     pub keys: Mode,
+    /// Screen position of the HUD. Can be overridden per mode with the
+    /// `hud_pos` binding attribute.
     #[serde(default)]
     pub pos: Pos,
+    /// Distance in logical pixels between the HUD and the left or right
+    /// screen edge it is anchored to
+    #[serde(default = "default_offset")]
+    pub offset_x: f64,
+    /// Distance in logical pixels between the HUD and the top or bottom
+    /// screen edge it is anchored to
+    #[serde(default = "default_offset")]
+    pub offset_y: f64,
+    /// Offsets for particular positions, overriding `offset_x` and `offset_y`
+    #[serde(default)]
+    pub offsets: HashMap<Pos, Offset>,
+    /// Color scheme: `auto` to follow the system, or `dark` or `light`
+    #[serde(default)]
+    pub appearance: Appearance,
+    /// Fonts of the HUD
+    #[serde(default)]
+    pub theme: Theme,
+    /// Opacity of the HUD's background, from `0.0` for fully transparent to
+    /// `1.0` for opaque. The text stays opaque.
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+    /// Blur the desktop behind the HUD on macOS, with a material such as
+    /// `hud` or `popover`. The blur shows through an `opacity` below `1.0`.
+    #[serde(default)]
+    pub vibrancy: Vibrancy,
+    /// Show the HUD listing the current mode's keys. With this off, use
+    /// `indicator` to see when a mode is active. Can be overridden per mode
+    /// with the `hud` binding attribute.
+    #[serde(default = "default_hud")]
+    pub hud: bool,
+    /// Show a small always-on-top indicator while a mode is active,
+    /// separate from the HUD: `off`, `dot`, or `name` for the mode's name
+    #[serde(default)]
+    pub indicator: Indicator,
+    /// Screen position of the indicator
+    #[serde(default = "default_indicator_pos")]
+    pub indicator_pos: Pos,
+    /// Time in milliseconds without a keypress before the HUD hides itself
+    /// while a mode is active. `0` means never auto-hide. Can be overridden
+    /// per mode with the `auto_hide_ms` binding attribute.
+    #[serde(default = "default_auto_hide_ms")]
+    pub auto_hide_ms: u64,
+    /// Width of a HUD column in logical pixels. Can be overridden per mode
+    /// with the `hud_width` binding attribute.
+    #[serde(default = "default_width")]
+    pub width: f64,
+    /// Maximum number of rows in a HUD column. Modes with more visible keys
+    /// are laid out in multiple columns. Unbounded if not set.
+    #[serde(default)]
+    pub max_rows: Option<usize>,
+    /// Default sort policy for displayed keys
+    #[serde(default)]
+    pub sort: Sort,
+    /// Duration in milliseconds of the feedback shown in the HUD when a
+    /// binding fires. `0` disables feedback.
+    #[serde(default = "default_flash_ms")]
+    pub flash_ms: u64,
+    /// Duration in milliseconds of messages shown in the HUD, either by
+    /// `message` actions or published to the `hud.message` topic
+    #[serde(default = "default_message_ms")]
+    pub message_ms: u64,
+    /// Time in milliseconds to wait after a mode change before rebinding
+    /// keys, so that mode changes in quick succession rebind only once, to
+    /// the final mode's keys. `0` rebinds straight away.
+    #[serde(default)]
+    pub rebind_delay_ms: u64,
+    /// Variables for `{name}` placeholders in shell commands. `{key}`,
+    /// `{mode}` and `{config_dir}` are also available.
+    #[serde(default)]
+    pub vars: HashMap<String, String>,
+    /// Named shell commands. A binding's command of the form `@name args...`
+    /// runs the named command with any arguments appended.
+    #[serde(default)]
+    pub commands: HashMap<String, String>,
+    /// Default working directory for shell commands. A leading `~` is
+    /// expanded to the home directory. Bindings can override this with their
+    /// `cwd` attribute.
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Environment variables for shell commands. Bindings can add to or
+    /// override these with their `env` attribute.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Return to the mode that was active when hotki last exited, instead of
+    /// starting at the root
+    #[serde(default)]
+    pub restore_mode: bool,
+    /// Add a hidden binding for `panic_key` to every mode that doesn't bind
+    /// it already, returning to the root
+    #[serde(default)]
+    pub panic: bool,
+    /// Key used for the binding added by `panic`
+    #[serde(default = "default_panic_key")]
+    pub panic_key: String,
+    /// Key that shows the HUD and activates the root mode, such as
+    /// `"cmd+space"`. When set, only this key is bound until it is pressed,
+    /// and pressing it again hides the HUD.
+    #[serde(default)]
+    pub activation: Option<String>,
+    /// At the root, register only the keys that enter a mode as global
+    /// hotkeys. Other root bindings need the `activation` key to be pressed
+    /// first. Reduces conflicts with other apps' shortcuts.
+    #[serde(default)]
+    pub entries_only: bool,
+    /// Key that starts filtering the HUD's keys by description while a mode
+    /// is active, such as `"slash"`. Letters, digits and space then type into
+    /// the filter, and return triggers the first match.
+    #[serde(default)]
+    pub filter_key: Option<String>,
+    /// Where hotki's logs are kept
+    #[serde(default)]
+    pub logs: Logs,
+}
+
+impl Config {
+    /// Parse a config from a string in the given format
+    ///
+    /// Command aliases are resolved here, so unknown aliases are parse errors,
+    /// the panic key is added if enabled, and the activation key is checked.
+    pub fn parse(s: &str, format: Format) -> Result<Self, String> {
+        let mut config: Self = format.parse(s)?;
+        config
+            .keys
+            .resolve_aliases(&config.commands)
+            .map_err(|errors| {
+                errors
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            })?;
+        if config.panic {
+            let key = Key::parse(&config.panic_key)
+                .map_err(|e| format!("Invalid panic_key '{}': {e}", config.panic_key))?;
+            config.keys.add_panic_key(&key);
+        }
+        if let Some(activation) = &config.activation {
+            Key::parse(activation)
+                .map_err(|e| format!("Invalid activation key '{activation}': {e}"))?;
+        }
+        if config.theme.font_size <= 0.0 || !config.theme.font_size.is_finite() {
+            return Err(format!(
+                "Invalid font_size {}: expected a positive number",
+                config.theme.font_size
+            ));
+        }
+        if !(0.0..=1.0).contains(&config.opacity) {
+            return Err(format!(
+                "Invalid opacity {}: expected a number from 0.0 to 1.0",
+                config.opacity
+            ));
+        }
+        if let Some(filter_key) = &config.filter_key {
+            Key::parse(filter_key)
+                .map_err(|e| format!("Invalid filter key '{filter_key}': {e}"))?;
+        }
+        if config.logs.capacity == 0 {
+            return Err("Invalid logs capacity 0: expected at least 1 line".to_string());
+        }
+        if config.logs.max_file_size == 0 {
+            return Err("Invalid logs max_file_size 0: expected at least 1 byte".to_string());
+        }
+        Ok(config)
+    }
+
+    /// A config with no bindings, for running while the user's config is
+    /// broken
+    pub fn fallback() -> Self {
+        Self::parse("(keys: [])", Format::Ron).expect("fallback config parses")
+    }
+
+    /// Inline CSS for the HUD's background, making it translucent if the
+    /// opacity is below 1
+    pub fn background_style(&self) -> String {
+        if self.opacity < 1.0 {
+            format!(
+                "background-color: color-mix(in srgb, var(--hud-bg) {}%, transparent);",
+                self.opacity * 100.0
+            )
+        } else {
+            String::new()
+        }
+    }
+
+    /// The offset of the HUD from the edges at its position
+    pub fn offset(&self) -> Offset {
+        self.offset_at(self.pos)
+    }
+
+    /// Whether the HUD is shown in a mode, given the attributes of the
+    /// binding that entered it, if any
+    pub fn hud_in(&self, mode: Option<&Attrs>) -> bool {
+        match mode.and_then(|attrs| attrs.hud) {
+            Some(visibility) => visibility == Visibility::Shown,
+            None => self.hud,
+        }
+    }
+
+    /// The screen position of the HUD in a mode, given the attributes of the
+    /// binding that entered it, if any
+    pub fn pos_in(&self, mode: Option<&Attrs>) -> Pos {
+        mode.and_then(|attrs| attrs.hud_pos).unwrap_or(self.pos)
+    }
+
+    /// The width of a HUD column in a mode, given the attributes of the
+    /// binding that entered it, if any
+    pub fn width_in(&self, mode: Option<&Attrs>) -> f64 {
+        mode.and_then(|attrs| attrs.hud_width).unwrap_or(self.width)
+    }
+
+    /// The offset of a window from the edges at a position
+    pub fn offset_at(&self, pos: Pos) -> Offset {
+        self.offsets.get(&pos).copied().unwrap_or(Offset {
+            x: self.offset_x,
+            y: self.offset_y,
+        })
+    }
+
+    /// The activation key, if one is set
+    ///
+    /// The key is checked when the config is parsed.
+    pub fn activation_key(&self) -> Option<Key> {
+        self.activation
+            .as_deref()
+            .and_then(|key| Key::parse(key).ok())
+    }
+
+    /// The filter key, if one is set
+    ///
+    /// The key is checked when the config is parsed.
+    pub fn filter_key(&self) -> Option<Key> {
+        self.filter_key
+            .as_deref()
+            .and_then(|key| Key::parse(key).ok())
+    }
+
+    /// The default context for shell commands
+    pub fn shell_context(&self) -> ShellContext {
+        ShellContext {
+            cwd: self.cwd.clone(),
+            env: self.env.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Load a config file, detecting the format from its extension
+    ///
+    /// Sets the `config_dir` variable to the file's directory unless the
+    /// config defines it.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config file '{}': {e}", path.display()))?;
+        let mut config = Self::parse(&content, Format::from_path(path))
+            .map_err(|e| format!("Failed to parse config file '{}': {e}", path.display()))?;
+        if let Some(dir) = path.parent() {
+            config
+                .vars
+                .entry("config_dir".to_string())
+                .or_insert_with(|| dir.display().to_string());
+        }
+        Ok(config)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_config_shell_context() {
+        let config = Config::parse(
+            r#"(
+            keys: [("b", "Build", shell("make"), (cwd: "~/src", env: {"CC": "clang"}))],
+            cwd: "~",
+            env: {"CC": "gcc", "LANG": "C"},
+        )"#,
+            Format::Ron,
+        )
+        .unwrap();
+        let (_, _, attrs) = config.keys.get_binding(&Key::parse("b").unwrap()).unwrap();
+        let context = config.shell_context().merge(&attrs.shell_context());
+        assert_eq!(context.cwd.as_deref(), Some("~/src"));
+        assert_eq!(context.env["CC"], "clang");
+        assert_eq!(context.env["LANG"], "C");
+    }
+
+    #[test]
+    fn test_config_panic_key() {
+        let text = r#"(keys: [("m", "Menu", mode([("a", "A", shell("true"))]))], panic: true)"#;
+        let config = Config::parse(text, Format::Ron).unwrap();
+        assert_eq!(config.panic_key, "escape");
+        let mut state = keymode::State::new(config.keys);
+        state.handle_key(&Key::parse("m").unwrap()).unwrap();
+        assert_eq!(state.depth(), 1);
+        state.handle_key(&Key::parse("escape").unwrap()).unwrap();
+        assert_eq!(state.depth(), 0);
+
+        let err = Config::parse(r#"(keys: [], panic: true, panic_key: "nope")"#, Format::Ron)
+            .unwrap_err();
+        assert!(err.starts_with("Invalid panic_key 'nope'"));
+    }
+
+    #[test]
+    fn test_config_activation() {
+        let config = Config::parse(r#"(keys: [], activation: "cmd+space")"#, Format::Ron).unwrap();
+        assert_eq!(
+            config.activation_key(),
+            Some(Key::parse("cmd+space").unwrap())
+        );
+        let config = Config::parse(r#"(keys: [])"#, Format::Ron).unwrap();
+        assert_eq!(config.activation_key(), None);
+
+        let err = Config::parse(r#"(keys: [], activation: "nope")"#, Format::Ron).unwrap_err();
+        assert!(err.starts_with("Invalid activation key 'nope'"));
+
+        let config = Config::parse(r#"(keys: [], filter_key: "/")"#, Format::Ron).unwrap();
+        assert_eq!(config.filter_key(), Some(Key::parse("slash").unwrap()));
+        let err = Config::parse(r#"(keys: [], filter_key: "nope")"#, Format::Ron).unwrap_err();
+        assert!(err.starts_with("Invalid filter key 'nope'"));
+    }
+
+    #[test]
+    fn test_config_commands() {
+        let config = Config::parse(
+            r#"(
+            keys: [("e", "Edit", shell("@editor ~/notes.md"))],
+            commands: {"editor": "open -a TextEdit"},
+        )"#,
+            Format::Ron,
+        )
+        .unwrap();
+        let (_, action, _) = config.keys.get_binding(&Key::parse("e").unwrap()).unwrap();
+        assert_eq!(
+            action,
+            &keymode::Action::Shell("open -a TextEdit ~/notes.md".to_string())
+        );
+
+        let err =
+            Config::parse(r#"(keys: [("e", "Edit", shell("@editor"))])"#, Format::Ron).unwrap_err();
+        assert_eq!(err, "root: unknown command alias '@editor'");
+    }
+
     #[test]
     fn test_config_deserialization() {
         // Test with proper Config struct format
@@ -39,9 +617,18 @@ mod tests {
                 ])),
             ],
             pos: n,
+            vars: {"term": "kitty"},
         )"#;
 
-        let config: Config = ron::from_str(config_text).unwrap();
+        let config = Config::parse(config_text, Format::Ron).unwrap();
+        assert_eq!(config.vars.get("term").map(String::as_str), Some("kitty"));
+        assert_eq!(config.shell_context(), ShellContext::default());
+        assert!(!config.restore_mode);
+        assert_eq!(config.auto_hide_ms, DEFAULT_AUTO_HIDE_MS);
+        assert_eq!(config.flash_ms, DEFAULT_FLASH_MS);
+        assert_eq!(config.message_ms, DEFAULT_MESSAGE_MS);
+        assert_eq!(config.width, DEFAULT_WIDTH);
+        assert_eq!(config.max_rows, None);
 
         // Verify we have the expected keys
         let keys = config.keys.keys();
@@ -54,4 +641,240 @@ mod tests {
         assert!(key_strings.contains(&"b".to_string()));
         assert!(key_strings.contains(&"m".to_string()));
     }
+
+    #[test]
+    fn test_config_fallback() {
+        let config = Config::fallback();
+        assert_eq!(config.keys.keys().count(), 0);
+        assert_eq!(config.activation_key(), None);
+    }
+
+    #[test]
+    fn test_config_offset() {
+        let config = Config::parse(r#"(keys: [])"#, Format::Ron).unwrap();
+        assert_eq!(
+            config.offset(),
+            Offset {
+                x: DEFAULT_OFFSET,
+                y: DEFAULT_OFFSET
+            }
+        );
+
+        let text = r#"(
+            keys: [],
+            pos: sw,
+            offset_x: 5.0,
+            offset_y: 10.0,
+            offsets: {ne: (y: 40.0)},
+        )"#;
+        let mut config = Config::parse(text, Format::Ron).unwrap();
+        assert_eq!(config.offset(), Offset { x: 5.0, y: 10.0 });
+        config.pos = Pos::NE;
+        assert_eq!(
+            config.offset(),
+            Offset {
+                x: DEFAULT_OFFSET,
+                y: 40.0
+            }
+        );
+
+        let toml = "keys = []\npos = \"n\"\n[offsets.n]\nx = 0.0\ny = 64.0\n";
+        let config = Config::parse(toml, Format::Toml).unwrap();
+        assert_eq!(config.offset(), Offset { x: 0.0, y: 64.0 });
+    }
+
+    #[test]
+    fn test_config_appearance() {
+        let config = Config::parse(r#"(keys: [])"#, Format::Ron).unwrap();
+        assert_eq!(config.appearance, Appearance::Auto);
+        assert_eq!(config.appearance.class(), "");
+
+        let config = Config::parse(r#"(keys: [], appearance: light)"#, Format::Ron).unwrap();
+        assert_eq!(config.appearance, Appearance::Light);
+        assert_eq!(config.appearance.class(), "appearance-light");
+    }
+
+    #[test]
+    fn test_config_indicator() {
+        let config = Config::parse(r#"(keys: [])"#, Format::Ron).unwrap();
+        assert!(config.hud);
+        assert_eq!(config.indicator, Indicator::Off);
+        assert_eq!(config.indicator_pos, Pos::NW);
+
+        let text = r#"(
+            keys: [],
+            hud: false,
+            indicator: name,
+            indicator_pos: s,
+            offsets: {s: (y: 4.0)},
+        )"#;
+        let config = Config::parse(text, Format::Ron).unwrap();
+        assert!(!config.hud);
+        assert_eq!(config.indicator, Indicator::Name);
+        assert_eq!(config.offset_at(config.indicator_pos).y, 4.0);
+        assert_eq!(config.offset().y, DEFAULT_OFFSET);
+    }
+
+    #[test]
+    fn test_config_mode_overrides() {
+        let text = r#"(
+            keys: [
+                ("m", "Menu", mode([]), (hud: shown, hud_pos: center, hud_width: 600.0)),
+                ("q", "Quiet", mode([]), (hud: hidden)),
+            ],
+            hud: false,
+            pos: sw,
+        )"#;
+        let config = Config::parse(text, Format::Ron).unwrap();
+        let attrs = |key: &str| {
+            let (_, attrs) = config
+                .keys
+                .get_with_attrs(&Key::parse(key).unwrap())
+                .unwrap();
+            attrs.clone()
+        };
+        assert!(!config.hud_in(None));
+        assert!(config.hud_in(Some(&attrs("m"))));
+        assert_eq!(config.pos_in(None), Pos::SW);
+        assert_eq!(config.pos_in(Some(&attrs("m"))), Pos::Center);
+        assert_eq!(config.width_in(Some(&attrs("m"))), 600.0);
+
+        assert!(!Config::fallback().hud_in(Some(&attrs("q"))));
+        assert_eq!(config.width_in(Some(&attrs("q"))), DEFAULT_WIDTH);
+    }
+
+    #[test]
+    fn test_config_theme() {
+        let config = Config::fallback();
+        assert_eq!(config.theme, Theme::default());
+        assert_eq!(config.theme.style(), "font-size: 16px;");
+        assert_eq!(config.theme.line_height(), 24.0);
+
+        let text = r#"(
+            keys: [],
+            theme: (font_family: "Menlo", font_size: 20.0, monospace: false),
+        )"#;
+        let config = Config::parse(text, Format::Ron).unwrap();
+        assert_eq!(config.theme.style(), "font-family: Menlo; font-size: 20px;");
+        assert_eq!(config.theme.line_height(), 30.0);
+        assert!(!config.theme.monospace);
+
+        let err = Config::parse(r#"(keys: [], theme: (font_size: 0.0))"#, Format::Ron);
+        assert!(err.unwrap_err().contains("font_size"));
+    }
+
+    #[test]
+    fn test_config_logs() {
+        let config = Config::fallback();
+        assert_eq!(config.logs, Logs::default());
+        assert_eq!(config.logs.capacity, DEFAULT_LOG_CAPACITY);
+        assert!(!config.logs.persist);
+
+        let text = r#"(keys: [], logs: (capacity: 500, persist: true, max_files: 2))"#;
+        let config = Config::parse(text, Format::Ron).unwrap();
+        assert_eq!(config.logs.capacity, 500);
+        assert!(config.logs.persist);
+        assert_eq!(config.logs.max_file_size, DEFAULT_LOG_FILE_SIZE);
+        assert_eq!(config.logs.max_files, 2);
+
+        let err = Config::parse(r#"(keys: [], logs: (capacity: 0))"#, Format::Ron);
+        assert!(err.unwrap_err().contains("capacity"));
+        let err = Config::parse(r#"(keys: [], logs: (max_file_size: 0))"#, Format::Ron);
+        assert!(err.unwrap_err().contains("max_file_size"));
+    }
+
+    #[test]
+    fn test_config_background() {
+        let config = Config::fallback();
+        assert_eq!(config.opacity, 1.0);
+        assert_eq!(config.vibrancy, Vibrancy::Off);
+        assert_eq!(config.vibrancy.material(), None);
+        assert!(config.background_style().is_empty());
+
+        let config =
+            Config::parse(r#"(keys: [], opacity: 0.75, vibrancy: hud)"#, Format::Ron).unwrap();
+        assert_eq!(
+            config.background_style(),
+            "background-color: color-mix(in srgb, var(--hud-bg) 75%, transparent);"
+        );
+        assert_eq!(config.vibrancy.material(), Some(13));
+
+        let err = Config::parse(r#"(keys: [], opacity: 1.5)"#, Format::Ron);
+        assert!(err.unwrap_err().contains("opacity"));
+    }
+
+    #[test]
+    fn test_config_auto_hide() {
+        let config = Config::parse(r#"(keys: [], auto_hide_ms: 0)"#, Format::Ron).unwrap();
+        assert_eq!(config.auto_hide_ms, 0);
+        assert_eq!(config.rebind_delay_ms, 0);
+
+        let config = Config::parse(r#"(keys: [], rebind_delay_ms: 5)"#, Format::Ron).unwrap();
+        assert_eq!(config.rebind_delay_ms, 5);
+    }
+
+    #[test]
+    fn test_config_layout() {
+        let config =
+            Config::parse(r#"(keys: [], width: 300.0, max_rows: 10)"#, Format::Ron).unwrap();
+        assert_eq!(config.width, 300.0);
+        assert_eq!(config.max_rows, Some(10));
+        assert_eq!(config.sort, Sort::Config);
+
+        let config = Config::parse(r#"(keys: [], sort: desc)"#, Format::Ron).unwrap();
+        assert_eq!(config.sort, Sort::Desc);
+    }
+
+    #[test]
+    fn test_config_load_formats() {
+        let dir = std::env::temp_dir().join(format!("hotki-config-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let toml_path = dir.join("config.toml");
+        std::fs::write(
+            &toml_path,
+            r#"
+pos = "sw"
+max_rows = 5
+keys = [
+    ["a", "Hello", { shell = "echo hello" }],
+    ["m", "Menu", { mode = [["x", "Back", "pop"]] }],
+]
+"#,
+        )
+        .unwrap();
+        let config = Config::load(&toml_path).unwrap();
+        assert!(matches!(config.pos, Pos::SW));
+        assert_eq!(config.max_rows, Some(5));
+        assert_eq!(config.keys.keys().count(), 2);
+
+        let json_path = dir.join("config.json");
+        std::fs::write(&json_path, r#"{"keys": [["a", "Hello", "exit"]]}"#).unwrap();
+        let config = Config::load(&json_path).unwrap();
+        assert_eq!(config.auto_hide_ms, DEFAULT_AUTO_HIDE_MS);
+        assert_eq!(config.keys.keys().count(), 1);
+        assert_eq!(
+            config.vars.get("config_dir"),
+            Some(&dir.display().to_string())
+        );
+
+        let err = Config::load(dir.join("missing.yaml")).unwrap_err();
+        assert!(err.starts_with("Failed to read config file"));
+
+        // Parse errors say where the problem is
+        std::fs::write(&json_path, "{\"keys\": [\n  [\"a\", \"Hello\"\n}").unwrap();
+        let err = Config::load(&json_path).unwrap_err();
+        assert!(err.contains("line 3"), "{err}");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_config_schema() {
+        let schema = keymode::schema_json::<Config>();
+        assert!(schema.contains(r#""title": "Config""#));
+        assert!(schema.contains(r#""Mode": {"#));
+        assert!(schema.contains(r#""Pos": {"#));
+        assert!(schema.contains("Screen position of the HUD"));
+    }
 }
@@ -0,0 +1,165 @@
+//! Crash reports.
+//!
+//! [`install`] sets a panic hook that writes a report before the default
+//! hook runs: the panic message, the mode path and bound keys last recorded
+//! with [`record_state`], a backtrace, and the recent lines from the ring
+//! buffer. The next time hotki starts, [`pending`] finds the reports left
+//! behind, so the user can be offered a look at them.
+
+use std::{
+    backtrace::Backtrace,
+    fmt::Write as _,
+    fs, io, panic,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use hotkey_manager::Key;
+
+use crate::ringbuffer;
+
+/// Name of the GUI process in crash reports
+pub const APP: &str = "hotki";
+
+/// Name of the hotkey server process in crash reports
+pub const SERVER: &str = "server";
+
+/// State worth knowing about when a crash happens
+#[derive(Debug, Default, Clone, PartialEq)]
+struct State {
+    mode_path: Vec<String>,
+    bound_keys: Vec<String>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    mode_path: Vec::new(),
+    bound_keys: Vec::new(),
+});
+
+/// Record the active mode path and bound keys, for a crash report
+pub fn record_state(mode_path: Vec<String>, bound_keys: &[Key]) {
+    let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    state.mode_path = mode_path;
+    state.bound_keys = bound_keys.iter().map(ToString::to_string).collect();
+}
+
+/// Path of the crash report for a process
+fn report_path(dir: &Path, process: &str) -> PathBuf {
+    dir.join(format!("{process}-crash.log"))
+}
+
+/// Write a crash report to `dir` when the process panics
+pub fn install(dir: PathBuf, process: &'static str) {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        // The panicking thread may hold either lock, so don't wait on them
+        let state = STATE
+            .try_lock()
+            .map(|state| state.clone())
+            .unwrap_or_default();
+        let logs = ringbuffer::try_get_logs().unwrap_or_default();
+        let backtrace = Backtrace::force_capture().to_string();
+        let report = format_report(process, &info.to_string(), &state, &backtrace, &logs);
+
+        let path = report_path(&dir, process);
+        match fs::create_dir_all(&dir).and_then(|()| fs::write(&path, report)) {
+            Ok(()) => eprintln!("Crash report written to {}", path.display()),
+            Err(e) => eprintln!("Failed to write crash report to '{}': {e}", path.display()),
+        }
+        default_hook(info);
+    }));
+}
+
+/// The text of a crash report
+fn format_report(
+    process: &str,
+    panic: &str,
+    state: &State,
+    backtrace: &str,
+    logs: &[String],
+) -> String {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let mut report = String::new();
+    let _ = writeln!(
+        report,
+        "{process} crashed at {time} (seconds since the epoch)"
+    );
+    let _ = writeln!(report, "{panic}");
+    let _ = writeln!(report);
+    let _ = writeln!(report, "Mode path: [{}]", state.mode_path.join(", "));
+    let _ = writeln!(report, "Bound keys: [{}]", state.bound_keys.join(", "));
+    let _ = writeln!(report);
+    let _ = writeln!(report, "Backtrace:");
+    let _ = writeln!(report, "{backtrace}");
+    let _ = writeln!(report);
+    let _ = writeln!(report, "Recent logs:");
+    for line in logs {
+        report.push_str(line);
+    }
+    report
+}
+
+/// Crash reports left in `dir` that haven't been dismissed
+pub fn pending(dir: &Path) -> Vec<PathBuf> {
+    [APP, SERVER]
+        .iter()
+        .map(|process| report_path(dir, process))
+        .filter(|path| path.exists())
+        .collect()
+}
+
+/// Set a crash report aside, so it is no longer pending. Only the latest
+/// dismissed report of each process is kept.
+pub fn dismiss(report: &Path) -> io::Result<()> {
+    fs::rename(report, report.with_extension("old.log"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_format_report() {
+        let state = State {
+            mode_path: vec!["cmd+m".to_string(), "w".to_string()],
+            bound_keys: vec!["a".to_string(), "esc".to_string()],
+        };
+        let logs = vec!["INFO first\n".to_string(), "WARN second\n".to_string()];
+        let report = format_report(APP, "panicked at src/hud.rs:1:1", &state, "0: main", &logs);
+        assert!(report.starts_with("hotki crashed at "));
+        assert!(report.contains("panicked at src/hud.rs:1:1\n"));
+        assert!(report.contains("Mode path: [cmd+m, w]\n"));
+        assert!(report.contains("Bound keys: [a, esc]\n"));
+        assert!(report.contains("Backtrace:\n0: main\n"));
+        assert!(report.ends_with("Recent logs:\nINFO first\nWARN second\n"));
+    }
+
+    #[test]
+    fn test_pending() {
+        let dir = env::temp_dir().join(format!("hotki-crash-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        assert!(pending(&dir).is_empty());
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(report_path(&dir, SERVER), "report").unwrap();
+        fs::write(report_path(&dir, APP), "report").unwrap();
+        let reports = pending(&dir);
+        assert_eq!(
+            reports,
+            vec![report_path(&dir, APP), report_path(&dir, SERVER)]
+        );
+
+        for report in &reports {
+            dismiss(report).unwrap();
+        }
+        assert!(pending(&dir).is_empty());
+        assert!(dir.join("hotki-crash.old.log").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
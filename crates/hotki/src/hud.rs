@@ -4,24 +4,112 @@ use dioxus::{
     },
     prelude::*,
 };
-use std::rc::Rc;
-use tracing::{debug, info};
+use std::{
+    process::ExitStatus,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+use tracing::{debug, error, info, warn};
 
 const MAIN_CSS: Asset = asset!("/assets/main.css");
 const TAILWIND_CSS: Asset = asset!("/assets/tailwind.css");
 
-use hotkey_manager::{Client, IPCResponse, Key};
-use keymode::State;
+use hotkey_manager::{Backoff, Client, ConnectionState, IPCResponse, Key, RetryPolicy};
+use keymode::{sections, Attrs, Pos, State};
+
+use crate::config::{Config, Offset};
+use crate::crash;
+use crate::indicator;
+use crate::respawn::{self, Breaker};
+use crate::settings::Settings;
+use crate::tray::{self, TrayStatus};
+use crate::vibrancy;
+
+/// Topic other clients publish to in order to show a message in the HUD
+const MESSAGE_TOPIC: &str = "hud.message";
+
+/// How often to check that the hotkey server is responding
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Delay before the first attempt to reconnect to the hotkey server, which
+/// doubles with each failed attempt up to `RECONNECT_MAX_DELAY`
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest delay between attempts to reconnect to the hotkey server
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// How long to wait for the spawned server's exit to be noticed after its
+/// connection closes
+const SERVER_EXIT_GRACE: Duration = Duration::from_millis(500);
+
+/// Server failures within `SERVER_FAILURE_WINDOW` after which it is no
+/// longer respawned
+const SERVER_FAILURE_LIMIT: usize = 3;
+
+/// Period over which server failures are counted
+const SERVER_FAILURE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Longest wait for a hotkey event before checking the tray and server again
+const EVENT_POLL: Duration = Duration::from_millis(100);
+
+/// Grid layout of the visible HUD keys
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Layout {
+    rows: usize,
+    columns: usize,
+}
 
-use crate::config::{Config, Pos};
+impl Layout {
+    /// Lay out `visible_count` keys in columns of at most `max_rows` rows
+    fn new(visible_count: usize, max_rows: Option<usize>) -> Self {
+        match max_rows {
+            Some(max_rows) if max_rows > 0 && visible_count > max_rows => Self {
+                rows: max_rows,
+                columns: visible_count.div_ceil(max_rows),
+            },
+            _ => Self {
+                rows: visible_count,
+                columns: 1,
+            },
+        }
+    }
 
-const WINDOW_WIDTH: f64 = 400.0;
-const WINDOW_PADDING: f64 = 20.0;
+    /// CSS for the key container, flowing keys down each column in turn
+    fn style(&self) -> String {
+        if self.columns > 1 {
+            format!(
+                "display: grid; grid-auto-flow: column; \
+                 grid-template-rows: repeat({}, auto); \
+                 grid-template-columns: repeat({}, minmax(0, 1fr)); \
+                 row-gap: 8px; column-gap: 16px;",
+                self.rows, self.columns
+            )
+        } else {
+            String::new()
+        }
+    }
+}
+
+/// Count the HUD rows needed for the visible keys, including section titles
+fn visible_rows(keys: &[(Key, String, Attrs)]) -> usize {
+    let visible: Vec<_> = keys
+        .iter()
+        .filter(|(_, _, attrs)| !attrs.hide)
+        .cloned()
+        .collect();
+    let titles = sections(&visible)
+        .iter()
+        .filter(|(title, _)| title.is_some())
+        .count();
+    visible.len() + titles
+}
 
-/// Calculates the exact window height needed to contain the HUD content without clipping.
+/// Estimates the window height needed to contain the HUD content without clipping.
 ///
-/// This function must precisely match the CSS layout to prevent content from being clipped.
-/// All values correspond to specific CSS rules and DOM structure.
+/// The window is sized with this estimate before it is shown, and then fitted
+/// to the rendered content (see [`fit_to_content`]). The estimate is all there
+/// is if measuring fails, so it should match the CSS layout closely. All values
+/// correspond to specific CSS rules and DOM structure.
 ///
 /// # Layout Structure
 /// ```
@@ -38,9 +126,20 @@ const WINDOW_PADDING: f64 = 20.0;
 /// - `.hud-container` padding: 20px → 40px total vertical padding (assets/main.css:31)
 /// - `.mb-4` margin-bottom: 16px (tailwind.css:186, --spacing * 4 = 4px * 4)
 /// - `.space-y-2` margin: 8px between items (tailwind.css:219, --spacing * 2 = 4px * 2)
-/// - Base line-height: 1.5 → 24px for 16px font (tailwind.css:41)
+/// - Base line-height: 1.5 → 24px for the default 16px font (tailwind.css:41)
 /// - `.py-1` padding: 4px top+bottom (tailwind.css:257, --spacing * 1 = 4px * 1)
-fn calculate_window_height(visible_count: usize, has_error: bool, is_connected: bool) -> f64 {
+///
+/// Spacing is in `rem`, so it stays the same when the theme changes the font
+/// size, while `line_height` grows with it.
+///
+/// For multi-column layouts, `rows` is the number of rows in the tallest column.
+fn calculate_window_height(
+    rows: usize,
+    has_error: bool,
+    is_connected: bool,
+    has_flash: bool,
+    line_height: f64,
+) -> f64 {
     // CSS .hud-container padding: 20px (top) + 20px (bottom) = 40px total
     let padding = 40.0;
 
@@ -49,82 +148,58 @@ fn calculate_window_height(visible_count: usize, has_error: bool, is_connected:
 
     // Each key item height calculation (increased to prevent clipping):
     // - .flex.items-center container with default line-height: 1.5
-    // - Key span: 16px font × 1.5 line-height = 24px + .py-1 (4px top+bottom) = 32px
-    // - Description span: 16px font × 1.5 line-height = 24px
+    // - Key span: line height + .py-1 (4px top+bottom) = 32px at 16px
+    // - Description span: line height = 24px at 16px
     // - .space-y-2 adds 8px margin-bottom between items
-    // - Total per item: max(32px, 24px) + 8px = 40px
+    // - Total per item: max(32px, 24px) + 8px = 40px at 16px
     // - Adding extra padding to ensure no clipping
-    let item_height = 44.0;
+    let item_height = line_height + 20.0;
+
+    // Error message height: line height + .mb-4 (16px) = 40px at 16px
+    let line_with_margin = line_height + 16.0;
+    let error_height = if has_error { line_with_margin } else { 0.0 };
 
-    // Error message height: 16px font × 1.5 line-height = 24px + .mb-4 (16px) = 40px
-    let error_height = if has_error { 40.0 } else { 0.0 };
+    // Connection status height: the same as the error message
+    let connection_height = if !is_connected { line_with_margin } else { 0.0 };
 
-    // Connection status height: 16px font × 1.5 line-height = 24px + .mb-4 (16px) = 40px
-    let connection_height = if !is_connected { 40.0 } else { 0.0 };
+    // Feedback toast or message height: same as the error message
+    let flash_height = if has_flash { line_with_margin } else { 0.0 };
 
-    let content_height = (visible_count as f64 * item_height) + error_height + connection_height;
+    let content_height =
+        (rows as f64 * item_height) + error_height + connection_height + flash_height;
     content_height + padding + margin
 }
 
-fn calculate_window_position(
+/// Top-left corner of the HUD window, offset from the edges it is anchored to
+pub(crate) fn calculate_window_position(
     pos: Pos,
     screen_width: f64,
     screen_height: f64,
     window_width: f64,
     window_height: f64,
-    padding: f64,
+    offset: Offset,
 ) -> (f64, f64) {
+    let left = offset.x;
+    let right = screen_width - window_width - offset.x;
+    let center_x = (screen_width / 2.0) - (window_width / 2.0);
+    let top = offset.y;
+    let bottom = screen_height - window_height - offset.y;
+    let center_y = (screen_height / 2.0) - (window_height / 2.0);
     match pos {
-        Pos::N => {
-            let x = (screen_width / 2.0) - (window_width / 2.0);
-            let y = padding;
-            (x, y)
-        }
-        Pos::NE => {
-            let x = screen_width - window_width - padding;
-            let y = padding;
-            (x, y)
-        }
-        Pos::E => {
-            let x = screen_width - window_width - padding;
-            let y = (screen_height / 2.0) - (window_height / 2.0);
-            (x, y)
-        }
-        Pos::SE => {
-            let x = screen_width - window_width - padding;
-            let y = screen_height - window_height - padding;
-            (x, y)
-        }
-        Pos::S => {
-            let x = (screen_width / 2.0) - (window_width / 2.0);
-            let y = screen_height - window_height - padding;
-            (x, y)
-        }
-        Pos::SW => {
-            let x = padding;
-            let y = screen_height - window_height - padding;
-            (x, y)
-        }
-        Pos::W => {
-            let x = padding;
-            let y = (screen_height / 2.0) - (window_height / 2.0);
-            (x, y)
-        }
-        Pos::NW => {
-            let x = padding;
-            let y = padding;
-            (x, y)
-        }
-        Pos::Center => {
-            let x = (screen_width / 2.0) - (window_width / 2.0);
-            let y = (screen_height / 2.0) - (window_height / 2.0);
-            (x, y)
-        }
+        Pos::N => (center_x, top),
+        Pos::NE => (right, top),
+        Pos::E => (right, center_y),
+        Pos::SE => (right, bottom),
+        Pos::S => (center_x, bottom),
+        Pos::SW => (left, bottom),
+        Pos::W => (left, center_y),
+        Pos::NW => (left, top),
+        Pos::Center => (center_x, center_y),
     }
 }
 
 /// Configure HUD window properties (decorations, positioning, visibility, etc.)
-fn setup_hud_window(window: &Rc<DesktopService>) {
+fn setup_hud_window(window: &Rc<DesktopService>, config: &Config) {
     // Set HUD window properties
     window.set_decorations(false);
     window.set_always_on_top(true);
@@ -133,35 +208,108 @@ fn setup_hud_window(window: &Rc<DesktopService>) {
     window.set_visible(false);
     window.set_closable(true);
     window.set_cursor_visible(false);
+    vibrancy::apply(window, config.vibrancy);
 }
 
 /// Position and size the window based on current content and configuration
+///
+/// `mode` is the attributes of the binding that entered the current mode,
+/// whose HUD overrides take precedence over the config.
 fn position_and_size_window(
     window: &Rc<DesktopService>,
-    visible_count: usize,
+    row_count: usize,
     has_error: bool,
     is_connected: bool,
+    has_flash: bool,
     config: &Config,
+    mode: Option<&Attrs>,
 ) {
-    let window_height = calculate_window_height(visible_count, has_error, is_connected);
+    let layout = Layout::new(row_count, config.max_rows);
+    let window_height = calculate_window_height(
+        layout.rows,
+        has_error,
+        is_connected,
+        has_flash,
+        config.theme.line_height(),
+    );
+    let window_width = config.width_in(mode) * layout.columns as f64;
 
     // Debug output to understand initial sizing
-    debug!("initial show - visible_count: {visible_count}, calculated height: {window_height}");
+    debug!(
+        "initial show - row_count: {row_count}, layout: {layout:?}, \
+         calculated size: {window_width}x{window_height}"
+    );
 
-    window.set_inner_size(LogicalSize::new(WINDOW_WIDTH, window_height));
+    place_window(
+        window,
+        window_width,
+        window_height,
+        config.pos_in(mode),
+        config,
+    );
+}
+
+/// Script returning the height in logical pixels that the HUD content needs,
+/// including the container's margins, or null if it hasn't rendered
+const MEASURE_HEIGHT_JS: &str = r#"
+const container = document.querySelector(".hud-container");
+if (!container) {
+    return null;
+}
+const style = getComputedStyle(container);
+return container.getBoundingClientRect().height
+    + parseFloat(style.marginTop)
+    + parseFloat(style.marginBottom);
+"#;
+
+/// Resize the window to the measured height of the rendered content, keeping
+/// its width. Leaves the estimated size in place if measuring fails.
+async fn fit_to_content(window: &Rc<DesktopService>, pos: Pos, config: &Config) {
+    let height = match document::eval(MEASURE_HEIGHT_JS)
+        .join::<Option<f64>>()
+        .await
+    {
+        Ok(Some(height)) if height > 0.0 => height.ceil(),
+        Ok(_) => return,
+        Err(e) => {
+            debug!("Failed to measure HUD content: {e:?}");
+            return;
+        }
+    };
+    let size = window.inner_size().to_logical::<f64>(window.scale_factor());
+    if (size.height - height).abs() >= 1.0 {
+        debug!("Fitting HUD to content: {}x{height}", size.width);
+        place_window(window, size.width, height, pos, config);
+    }
+}
+
+/// Size the window and position it on screen at `pos`, with the config's
+/// offset for that position
+fn place_window(
+    window: &Rc<DesktopService>,
+    window_width: f64,
+    window_height: f64,
+    pos: Pos,
+    config: &Config,
+) {
+    window.set_inner_size(LogicalSize::new(window_width, window_height));
 
     // Position window
     if let Some(monitor) = window.current_monitor() {
         let screen_size = monitor.size();
         let scale_factor = monitor.scale_factor();
+        let offset = config.offset_at(pos);
 
         let (physical_x, physical_y) = calculate_window_position(
-            config.pos,
+            pos,
             screen_size.width as f64,
             screen_size.height as f64,
-            WINDOW_WIDTH * scale_factor,
+            window_width * scale_factor,
             window_height * scale_factor,
-            WINDOW_PADDING * scale_factor,
+            Offset {
+                x: offset.x * scale_factor,
+                y: offset.y * scale_factor,
+            },
         );
 
         let logical_x = physical_x / scale_factor;
@@ -171,13 +319,45 @@ fn position_and_size_window(
     }
 }
 
+/// Transient feedback shown when a binding fires
+#[derive(Debug, Clone, PartialEq)]
+struct Flash {
+    key: Key,
+    desc: String,
+    at: Instant,
+}
+
+/// A transient message shown in the HUD
+#[derive(Debug, Clone, PartialEq)]
+struct Notice {
+    text: String,
+    at: Instant,
+}
+
+impl Notice {
+    fn new(text: String) -> Self {
+        Self {
+            text,
+            at: Instant::now(),
+        }
+    }
+}
+
 /// State container for HUD signals
+#[derive(Clone, Copy)]
 struct HudState {
     keymode_state: Signal<State>,
-    current_keys: Signal<Vec<(Key, String, keymode::Attrs)>>,
+    current_keys: Signal<Vec<(Key, String, Attrs)>>,
     error_msg: Signal<String>,
     is_connected: Signal<bool>,
+    /// Whether the server is reachable but not responding promptly
+    degraded: Signal<bool>,
+    /// The current attempt to reconnect to the server, if reconnecting
+    reconnect_attempt: Signal<Option<u32>>,
     should_rebind: Signal<bool>,
+    last_activity: Signal<Instant>,
+    flash: Signal<Option<Flash>>,
+    message: Signal<Option<Notice>>,
 }
 
 /// Handle a triggered hotkey and update window state accordingly
@@ -187,39 +367,90 @@ fn handle_triggered_key(
     initial_config: &Config,
     state: &mut HudState,
 ) {
+    state.last_activity.set(Instant::now());
+
+    // Look up the binding's description before handling, since the action
+    // may change the current mode
+    let desc = state
+        .current_keys
+        .read()
+        .iter()
+        .find(|(k, _, _)| k == key)
+        .map(|(_, desc, _)| desc.clone());
+
     // Handle the key
+    let was_suspended = state.keymode_state.read().is_suspended();
     let result = state.keymode_state.write().handle_key(key);
     match result {
-        Ok(_handled) => {
+        Ok(handled) => {
+            if !handled.warn.is_empty() {
+                state.error_msg.set(handled.warn);
+            }
+            if !handled.user.is_empty() {
+                state.message.set(Some(Notice::new(handled.user)));
+            }
+            let suspended = state.keymode_state.read().is_suspended();
+            if suspended != was_suspended {
+                state
+                    .message
+                    .set(Some(Notice::new(suspension_notice(suspended))));
+            }
+            if initial_config.flash_ms > 0 {
+                if let Some(desc) = desc {
+                    state.flash.set(Some(Flash {
+                        key: key.clone(),
+                        desc,
+                        at: Instant::now(),
+                    }));
+                }
+            }
+
+            if initial_config.restore_mode {
+                save_mode_path(state.keymode_state.read().mode_path());
+            }
+
             // Update current keys after handling
             let keys = state.keymode_state.read().keys();
             state.current_keys.set(keys.clone());
+            let status = TrayStatus::from_state(&state.keymode_state.read());
+            indicator::publish(status.modes.clone());
+            tray::publish(status);
             window.set_visible(false);
             state.should_rebind.set(true);
 
-            // Check depth to show/hide window
-            let depth = state.keymode_state.read().depth();
+            // Check whether a mode is active to show/hide window. The window
+            // stays up at the root while feedback is showing.
+            let active = state.keymode_state.read().is_active();
+            let has_flash = state.flash.read().is_some()
+                || state.message.read().is_some()
+                || state.keymode_state.read().count().is_some();
+            let mode = state.keymode_state.read().mode_attrs().cloned();
             let window_ref = window.clone();
-            if depth > 0 && !window_ref.is_visible() {
-                // Calculate and set window size before showing
-                let visible_count = state
-                    .current_keys
-                    .read()
-                    .iter()
-                    .filter(|(_, _, attrs)| !attrs.hide)
-                    .count();
+            if initial_config.hud_in(mode.as_ref())
+                && (active || has_flash)
+                && !window_ref.is_visible()
+            {
+                // Calculate and set window size before showing. Keys are
+                // only listed when a mode is active.
+                let row_count = if active {
+                    visible_rows(&state.current_keys.read())
+                } else {
+                    0
+                };
 
                 position_and_size_window(
                     &window_ref,
-                    visible_count,
+                    row_count,
                     !state.error_msg.read().is_empty(),
                     *state.is_connected.read(),
+                    has_flash,
                     initial_config,
+                    mode.as_ref(),
                 );
 
                 // Now show the window (only if logs window is not showing)
                 window_ref.set_visible(true);
-            } else if depth == 0 && window_ref.is_visible() {
+            } else if !active && window_ref.is_visible() {
                 window_ref.set_visible(false);
             }
         }
@@ -229,49 +460,193 @@ fn handle_triggered_key(
     }
 }
 
-/// Bind or rebind keys with the hotkey server
-async fn bind_keys(connection: &mut hotkey_manager::IPCConnection, state: &mut HudState) {
+/// Show a message from another client, bringing up the HUD if it is hidden
+fn show_message(
+    text: String,
+    window: &Rc<DesktopService>,
+    initial_config: &Config,
+    state: &mut HudState,
+) {
+    state.message.set(Some(Notice::new(text)));
+    let mode = state.keymode_state.read().mode_attrs().cloned();
+    if initial_config.hud_in(mode.as_ref()) && !window.is_visible() {
+        let row_count = if state.keymode_state.read().is_active() {
+            visible_rows(&state.current_keys.read())
+        } else {
+            0
+        };
+        position_and_size_window(
+            window,
+            row_count,
+            !state.error_msg.read().is_empty(),
+            *state.is_connected.read(),
+            true,
+            initial_config,
+            mode.as_ref(),
+        );
+        window.set_visible(true);
+    }
+}
+
+/// The notice shown when the hotkeys are suspended or resumed
+fn suspension_notice(suspended: bool) -> String {
+    if suspended {
+        "Hotkeys suspended".to_string()
+    } else {
+        "Hotkeys resumed".to_string()
+    }
+}
+
+/// Persist the active mode path so that the next run can return to it
+fn save_mode_path(mode_path: Vec<String>) {
+    let Some(path) = Settings::default_path() else {
+        return;
+    };
+    let mut settings = Settings::load(&path);
+    if settings.mode_path != mode_path {
+        settings.mode_path = mode_path;
+        if let Err(e) = settings.save(&path) {
+            error!("Failed to save settings to '{}': {e}", path.display());
+        }
+    }
+}
+
+/// Load the mode path saved by the previous run
+fn saved_mode_path() -> Vec<String> {
+    Settings::default_path()
+        .map(|path| Settings::load(&path).mode_path)
+        .unwrap_or_default()
+}
+
+/// Bind or rebind keys with the hotkey server.
+///
+/// `bound` holds the keys bound so far on this connection, if known, so that
+/// only the keys that changed are rebound.
+async fn bind_keys(
+    connection: &mut hotkey_manager::IPCConnection,
+    state: &mut HudState,
+    bound: &mut Option<Vec<Key>>,
+) {
     let keys = state.keymode_state.read().keys();
-    state.current_keys.set(keys.clone());
-    let key_refs: Vec<Key> = keys.iter().map(|(k, _, _)| k.clone()).collect();
+    state.current_keys.set(keys);
+    let key_refs = state.keymode_state.read().bound_keys();
+    crash::record_state(state.keymode_state.read().mode_path(), &key_refs);
 
-    if let Err(e) = connection.rebind(&key_refs).await {
-        state.error_msg.set(format!("Failed to bind keys: {e}"));
+    let result = match bound.take() {
+        Some(old) => connection.rebind_diff(&old, &key_refs).await,
+        None => connection.rebind(&key_refs).await,
+    };
+    match result {
+        Ok(()) => *bound = Some(key_refs),
+        Err(e) => state.error_msg.set(format!("Failed to bind keys: {e}")),
     }
 }
 
-/// Main event processing loop for handling hotkey triggers
+/// Main event processing loop for handling hotkey triggers, until the
+/// connection is lost or the user asks to reconnect
 async fn run_event_loop(
     connection: &mut hotkey_manager::IPCConnection,
     window: &Rc<DesktopService>,
     initial_config: &Config,
     state: &mut HudState,
-) {
-    // Initial key binding
-    bind_keys(connection, state).await;
+    connection_states: &mut tokio::sync::mpsc::UnboundedReceiver<ConnectionState>,
+    server_exits: &mut Option<tokio::sync::mpsc::UnboundedReceiver<ExitStatus>>,
+) -> Disconnect {
+    // Initial key binding, replacing whatever the server had bound
+    let rebind_delay = Duration::from_millis(initial_config.rebind_delay_ms);
+    let mut rebind_at: Option<Instant> = None;
+    let mut bound = None;
+    bind_keys(connection, state, &mut bound).await;
+    if let Err(e) = connection.subscribe(MESSAGE_TOPIC).await {
+        error!("Failed to subscribe to {MESSAGE_TOPIC}: {e}");
+    }
+
+    // Show the HUD straight away if a restored mode is active
+    let status = TrayStatus::from_state(&state.keymode_state.read());
+    indicator::publish(status.modes);
+    let mode = state.keymode_state.read().mode_attrs().cloned();
+    if initial_config.hud_in(mode.as_ref()) && state.keymode_state.read().is_active() {
+        position_and_size_window(
+            window,
+            visible_rows(&state.current_keys.read()),
+            !state.error_msg.read().is_empty(),
+            *state.is_connected.read(),
+            false,
+            initial_config,
+            mode.as_ref(),
+        );
+        window.set_visible(true);
+    }
 
     loop {
-        // Check if we need to rebind keys
+        if let Some(Ok(status)) = server_exits.as_mut().map(|exits| exits.try_recv()) {
+            warn!("Hotkey server exited: {status}");
+            return Disconnect::ServerFailed;
+        }
+
+        // Reflect the watchdog's view of the connection
+        while let Ok(connection_state) = connection_states.try_recv() {
+            if connection_state == ConnectionState::Disconnected {
+                warn!("Hotkey server stopped answering pings");
+                return Disconnect::ServerFailed;
+            }
+            state
+                .is_connected
+                .set(connection_state == ConnectionState::Connected);
+            state
+                .degraded
+                .set(connection_state == ConnectionState::Degraded);
+        }
+
+        if tray::take_reconnect() {
+            return Disconnect::Requested;
+        }
+
+        if tray::take_toggle_suspended() {
+            let suspended = !state.keymode_state.read().is_suspended();
+            state.keymode_state.write().set_suspended(suspended);
+            let status = TrayStatus::from_state(&state.keymode_state.read());
+            indicator::publish(status.modes.clone());
+            tray::publish(status);
+            show_message(suspension_notice(suspended), window, initial_config, state);
+            state.should_rebind.set(true);
+        }
+
+        // Check if we need to rebind keys. With a rebind delay, rebinds
+        // asked for within the delay are coalesced, binding the keys of the
+        // mode reached last.
         if *state.should_rebind.read() {
             state.should_rebind.set(false);
-            bind_keys(connection, state).await;
+            rebind_at.get_or_insert_with(|| Instant::now() + rebind_delay);
+        }
+        if rebind_at.is_some_and(|at| Instant::now() >= at) {
+            rebind_at = None;
+            bind_keys(connection, state, &mut bound).await;
         }
 
-        // Process events with timeout
-        match tokio::time::timeout(
-            std::time::Duration::from_millis(100),
-            connection.recv_event(),
-        )
-        .await
-        {
+        // Process events with timeout, waking for a pending rebind
+        let wait = rebind_at.map_or(EVENT_POLL, |at| {
+            at.saturating_duration_since(Instant::now()).min(EVENT_POLL)
+        });
+        match tokio::time::timeout(wait, connection.recv_event()).await {
             Ok(Ok(IPCResponse::HotkeyTriggered(key))) => {
                 handle_triggered_key(&key, window, initial_config, state);
             }
+            Ok(Ok(IPCResponse::Published { payload, .. })) => {
+                let text = match payload.as_str() {
+                    Some(text) => text.to_string(),
+                    None => payload.to_string(),
+                };
+                show_message(text, window, initial_config, state);
+            }
             Ok(Ok(_)) => {}
             Ok(Err(e)) => {
                 state.error_msg.set(format!("Connection error: {e}"));
-                state.is_connected.set(false);
-                break;
+                if let Some(status) = wait_server_exit(server_exits).await {
+                    warn!("Hotkey server exited: {status}");
+                    return Disconnect::ServerFailed;
+                }
+                return Disconnect::Lost;
             }
             Err(_) => {
                 // Timeout, continue loop
@@ -280,62 +655,248 @@ async fn run_event_loop(
     }
 }
 
-/// Handle server connection and key event processing
+/// How a connection to the hotkey server ended
+enum Disconnect {
+    /// Connecting failed
+    Failed,
+    /// The connection was lost
+    Lost,
+    /// The user asked to reconnect from the tray
+    Requested,
+    /// The spawned server exited, or stopped answering pings
+    ServerFailed,
+}
+
+/// Wait briefly for the spawned server to exit, returning its status if it
+/// does
+async fn wait_server_exit(
+    server_exits: &mut Option<tokio::sync::mpsc::UnboundedReceiver<ExitStatus>>,
+) -> Option<ExitStatus> {
+    let exits = server_exits.as_mut()?;
+    tokio::time::timeout(SERVER_EXIT_GRACE, exits.recv())
+        .await
+        .ok()
+        .flatten()
+}
+
+/// Socket path of the hotkey server, provided as context to the windows
+/// that connect to it
+#[derive(Debug, Clone)]
+pub struct ServerSocket(pub String);
+
+/// Keep a connection to the hotkey server, reconnecting when it is lost
 async fn handle_server_connection(
     window: Rc<DesktopService>,
     initial_config: Config,
-    keymode_state: Signal<State>,
-    current_keys: Signal<Vec<(Key, String, keymode::Attrs)>>,
-    mut error_msg: Signal<String>,
-    mut is_connected: Signal<bool>,
-    should_rebind: Signal<bool>,
+    socket: String,
+    mut state: HudState,
 ) {
-    // Try to connect to the server
-    match Client::new().with_auto_spawn_server().connect().await {
-        Ok(mut client) => {
-            info!("Connected to hotkey server");
-            is_connected.set(true);
-
-            // Get connection and use it
-            match client.connection() {
-                Ok(connection) => {
-                    let mut state = HudState {
-                        keymode_state,
-                        current_keys,
-                        error_msg,
-                        is_connected,
-                        should_rebind,
-                    };
-                    run_event_loop(connection, &window, &initial_config, &mut state).await;
-                    let _ = client.disconnect(true).await;
+    let backoff = RetryPolicy::exponential(RECONNECT_DELAY, RECONNECT_MAX_DELAY);
+    let mut breaker = Breaker::new(SERVER_FAILURE_LIMIT, SERVER_FAILURE_WINDOW);
+    let mut attempt = 0;
+    let mut respawning = false;
+    loop {
+        match serve_connection(&window, &initial_config, &socket, &mut state, respawning).await {
+            Disconnect::Requested => {
+                info!("Reconnecting to hotkey server on request");
+                attempt = 0;
+                respawning = false;
+                continue;
+            }
+            Disconnect::ServerFailed => {
+                attempt = 0;
+                respawning = true;
+                if !breaker.record(Instant::now()) {
+                    info!("Respawning hotkey server");
+                    continue;
                 }
-                Err(e) => {
-                    error_msg.set(format!("Failed to get connection: {e}"));
-                    is_connected.set(false);
+                error!("Hotkey server keeps crashing, no longer respawning it");
+                state
+                    .error_msg
+                    .set("Hotkey server keeps crashing".to_string());
+                respawn::set_failing(true);
+                while !tray::take_reconnect() {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
                 }
+                respawn::set_failing(false);
+                breaker.reset();
+                respawning = false;
+                continue;
             }
+            Disconnect::Lost => {
+                attempt = 0;
+                respawning = false;
+            }
+            Disconnect::Failed => {}
+        }
+        attempt += 1;
+        state.reconnect_attempt.set(Some(attempt));
+        let delay = backoff
+            .next_delay(attempt, Duration::ZERO)
+            .unwrap_or(RECONNECT_MAX_DELAY);
+        info!("Reconnecting to hotkey server in {delay:?} (attempt {attempt})");
+
+        // Wait out the delay, unless the user asks to reconnect now
+        let deadline = Instant::now() + delay;
+        while Instant::now() < deadline && !tray::take_reconnect() {
+            tokio::time::sleep(Duration::from_millis(100)).await;
         }
+    }
+}
+
+/// Connect to the server and process its events until the connection ends.
+/// With `respawning`, the previous server failed, and the user is told once
+/// a new one is up.
+async fn serve_connection(
+    window: &Rc<DesktopService>,
+    initial_config: &Config,
+    socket: &str,
+    state: &mut HudState,
+    respawning: bool,
+) -> Disconnect {
+    // This connection serves any reconnect request made so far
+    tray::take_reconnect();
+
+    // The watchdog runs on another task, so pass its updates over a channel
+    let (states_tx, mut states_rx) = tokio::sync::mpsc::unbounded_channel();
+    let client = Client::new()
+        .with_socket_path(socket)
+        .with_auto_spawn_server()
+        .with_watchdog(WATCHDOG_INTERVAL)
+        .on_state_change(move |connection_state| {
+            let _ = states_tx.send(connection_state);
+        });
+
+    let mut client = match client.connect().await {
+        Ok(client) => client,
         Err(e) => {
-            error_msg.set(format!("Failed to connect to server: {e}"));
-            is_connected.set(false);
+            state
+                .error_msg
+                .set(format!("Failed to connect to server: {e}"));
+            state.is_connected.set(false);
+            return Disconnect::Failed;
         }
+    };
+    info!("Connected to hotkey server");
+    if state.reconnect_attempt.read().is_some() {
+        // Clear the error that sent us reconnecting
+        state.reconnect_attempt.set(None);
+        state.error_msg.set(String::new());
+    }
+    state.is_connected.set(true);
+    if respawning {
+        state.error_msg.set(String::new());
+        show_message(
+            "Hotkey server restarted".to_string(),
+            window,
+            initial_config,
+            state,
+        );
     }
+
+    let mut server_exits = client.server_exit_events();
+    let disconnect = match client.connection() {
+        Ok(connection) => {
+            run_event_loop(
+                connection,
+                window,
+                initial_config,
+                state,
+                &mut states_rx,
+                &mut server_exits,
+            )
+            .await
+        }
+        Err(e) => {
+            state
+                .error_msg
+                .set(format!("Failed to get connection: {e}"));
+            Disconnect::Failed
+        }
+    };
+    state.is_connected.set(false);
+    state.degraded.set(false);
+    let _ = client.close(true).await;
+    disconnect
 }
 
 #[component]
 pub fn HudWindow() -> Element {
     let initial_config = use_context::<Config>();
+    let ServerSocket(socket) = use_context::<ServerSocket>();
 
-    let keymode_state = use_signal(|| State::new(initial_config.keys.clone()));
-    let current_keys = use_signal(Vec::<(Key, String, keymode::Attrs)>::new);
-    let error_msg = use_signal(String::new);
+    let keymode_state = use_signal(|| {
+        let mut state = State::new(initial_config.keys.clone())
+            .with_sort(initial_config.sort)
+            .with_vars(initial_config.vars.clone())
+            .with_shell_context(initial_config.shell_context())
+            .with_entries_only(initial_config.entries_only);
+        if let Some(key) = initial_config.activation_key() {
+            state = state.with_activation(key);
+        }
+        if let Some(key) = initial_config.filter_key() {
+            state = state.with_filter_key(key);
+        }
+        if initial_config.restore_mode {
+            let depth = state.restore_path(&saved_mode_path());
+            debug!("Restored mode depth {depth}");
+        }
+        state
+    });
+    let current_keys = use_signal(Vec::<(Key, String, Attrs)>::new);
+    let mut error_msg = use_signal(String::new);
     let is_connected = use_signal(|| false);
+    let degraded = use_signal(|| false);
+    let reconnect_attempt = use_signal(|| None::<u32>);
     let should_rebind = use_signal(|| false);
+    let last_activity = use_signal(Instant::now);
+    let mut flash = use_signal(|| None::<Flash>);
+    let mut message = use_signal(|| None::<Notice>);
+    let auto_hide_ms = initial_config.auto_hide_ms;
+    let flash_ms = initial_config.flash_ms;
+    let message_ms = initial_config.message_ms;
+    let max_rows = initial_config.max_rows;
+    let container_class = format!("hud-container {}", initial_config.appearance.class());
+    let container_style = format!(
+        "{} {}",
+        initial_config.theme.style(),
+        initial_config.background_style()
+    );
+    let key_class = if initial_config.theme.monospace {
+        "font-mono hud-key px-2 py-1 rounded"
+    } else {
+        "hud-key px-2 py-1 rounded"
+    };
 
     // Configure the HUD window properties
     use_hook({
+        let config = initial_config.clone();
         move || {
-            setup_hud_window(&window());
+            setup_hud_window(&window(), &config);
+        }
+    });
+
+    // Fit the window to the content whenever the content changes
+    use_effect({
+        let config = initial_config.clone();
+        move || {
+            // Read everything the content depends on, to rerun on changes
+            let _ = (
+                current_keys.read().len(),
+                error_msg.read().len(),
+                *is_connected.read(),
+                *degraded.read(),
+                *reconnect_attempt.read(),
+                flash.read().is_some(),
+                message.read().is_some(),
+                keymode_state.read().count(),
+                keymode_state.read().filter().map(str::len),
+            );
+            let pos = config.pos_in(keymode_state.read().mode_attrs());
+            let config = config.clone();
+            spawn(async move {
+                fit_to_content(&window(), pos, &config).await;
+            });
         }
     });
 
@@ -345,23 +906,71 @@ pub fn HudWindow() -> Element {
             handle_server_connection(
                 window(),
                 initial_config.clone(),
-                keymode_state,
-                current_keys,
-                error_msg,
-                is_connected,
-                should_rebind,
+                socket.clone(),
+                HudState {
+                    keymode_state,
+                    current_keys,
+                    error_msg,
+                    is_connected,
+                    degraded,
+                    reconnect_attempt,
+                    should_rebind,
+                    last_activity,
+                    flash,
+                    message,
+                },
             )
         }
     });
 
-    // Monitor window visibility and auto-hide when depth is 0
+    // Monitor window visibility, hiding when no mode is active or when the
+    // auto-hide timeout has elapsed without a keypress
     use_coroutine({
         move |_: UnboundedReceiver<()>| {
             async move {
                 loop {
-                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-                    // Only auto-hide if keymode depth is 0
-                    if window().is_visible() && keymode_state.read().depth() == 0 {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+
+                    // Expire keypress feedback
+                    let flash_expired = flash
+                        .read()
+                        .as_ref()
+                        .is_some_and(|f| f.at.elapsed() >= Duration::from_millis(flash_ms));
+                    if flash_expired {
+                        flash.set(None);
+                    }
+                    let message_expired = message
+                        .read()
+                        .as_ref()
+                        .is_some_and(|m| m.at.elapsed() >= Duration::from_millis(message_ms));
+                    if message_expired {
+                        message.set(None);
+                    }
+
+                    // Surface problems from commands running in the background
+                    if let Some(warning) = keymode_state.read().take_warnings().pop() {
+                        error_msg.set(warning);
+                    }
+
+                    if !window().is_visible() {
+                        continue;
+                    }
+                    let state = keymode_state.read();
+                    if !state.is_active() {
+                        if flash.read().is_none() && message.read().is_none() {
+                            window().set_visible(false);
+                        }
+                        continue;
+                    }
+                    // A per-mode override takes precedence, and 0 means never
+                    let timeout = state
+                        .mode_attrs()
+                        .and_then(|attrs| attrs.auto_hide_ms)
+                        .unwrap_or(auto_hide_ms);
+                    if timeout > 0
+                        && last_activity.read().elapsed() >= Duration::from_millis(timeout)
+                    {
+                        debug!("Auto-hiding HUD after {timeout}ms");
                         window().set_visible(false);
                     }
                 }
@@ -369,6 +978,21 @@ pub fn HudWindow() -> Element {
         }
     });
 
+    let visible_keys: Vec<_> = current_keys
+        .read()
+        .iter()
+        .filter(|(_, _, attrs)| !attrs.hide)
+        .cloned()
+        .collect();
+    let groups = sections(&visible_keys);
+    let layout = Layout::new(visible_rows(&visible_keys), max_rows);
+    let in_mode = keymode_state.read().is_active();
+    let flashed = flash.read().clone();
+    let notice = message.read().clone();
+    let count = keymode_state.read().count();
+    let filter = keymode_state.read().filter().map(String::from);
+    let flashed_key = flashed.as_ref().map(|f| f.key.clone());
+
     rsx! {
         document::Link { rel: "stylesheet", href: MAIN_CSS }
         document::Link { rel: "stylesheet", href: TAILWIND_CSS }
@@ -376,28 +1000,76 @@ pub fn HudWindow() -> Element {
             "#app {{ background: transparent; }}"
         }
         div {
-            class: "hud-container",
+            class: container_class,
+            style: container_style,
             if !error_msg.read().is_empty() {
                 div { class: "text-red-500 mb-4",
                     {error_msg.read().clone()}
                 }
             }
 
-            if !*is_connected.read() {
+            if *degraded.read() {
                 div { class: "text-yellow-500 mb-4",
-                    "Connecting to hotkey server..."
+                    "Hotkey server is not responding..."
+                }
+            } else if !*is_connected.read() {
+                if let Some(attempt) = *reconnect_attempt.read() {
+                    div { class: "text-yellow-500 mb-4",
+                        "Reconnecting… attempt {attempt}"
+                    }
+                } else {
+                    div { class: "text-yellow-500 mb-4",
+                        "Connecting to hotkey server..."
+                    }
                 }
             }
 
-            div { class: "text-white",
-                div { class: "space-y-2",
-                    for (key, desc, attrs) in current_keys.read().iter() {
-                        if !attrs.hide {
-                            div { class: "flex items-center space-x-4",
-                                span { class: "font-mono bg-gray-700 px-2 py-1 rounded",
+            // A pending count or a message takes the place of the feedback
+            // toast
+            if let Some(count) = count {
+                div { class: "hud-count mb-4",
+                    "{count}×"
+                }
+            } else if let Some(notice) = notice {
+                div { class: "hud-message mb-4",
+                    {notice.text}
+                }
+            } else if let Some(flashed) = flashed {
+                div { class: "hud-toast mb-4",
+                    "✓ {flashed.desc}"
+                }
+            }
+
+            if let Some(filter) = filter {
+                div { class: "hud-filter mb-4",
+                    "/{filter}"
+                }
+            }
+
+            div {
+                div {
+                    class: if layout.columns > 1 { "" } else { "space-y-2" },
+                    style: layout.style(),
+                    for (title, entries) in groups.into_iter().filter(|_| in_mode) {
+                        if let Some(title) = title {
+                            div { class: "hud-section", {title} }
+                        }
+                        for (key, desc, attrs) in entries {
+                            div {
+                                class: if flashed_key.as_ref() == Some(key) {
+                                    "flex items-center space-x-4 hud-flash-row"
+                                } else {
+                                    "flex items-center space-x-4"
+                                },
+                                span { class: key_class,
                                     {key.to_string()}
                                 }
-                                span { class: "text-gray-300",
+                                if let Some(icon) = &attrs.icon {
+                                    span { class: "hud-icon", {icon.clone()} }
+                                }
+                                span {
+                                    class: "hud-desc",
+                                    style: attrs.color.as_ref().map(|c| format!("color: {c};")),
                                     {desc.clone()}
                                 }
                             }
@@ -410,7 +1082,7 @@ pub fn HudWindow() -> Element {
 }
 
 /// Create the HUD window as a popup
-pub fn create_hud_window(config: Config) {
+pub fn create_hud_window(config: Config, socket: String) {
     let window = dioxus::desktop::window();
     let window_config = DioxusConfig::new().with_window(
         WindowBuilder::new()
@@ -422,5 +1094,6 @@ pub fn create_hud_window(config: Config) {
     );
     let dom = VirtualDom::new(HudWindow);
     dom.provide_root_context(config);
+    dom.provide_root_context(ServerSocket(socket));
     window.new_window(dom, window_config);
 }
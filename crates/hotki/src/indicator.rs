@@ -0,0 +1,132 @@
+//! A small always-on-top window showing whether a mode is active, and which.
+//!
+//! Like the tray, the indicator lives in its own window, so the HUD publishes
+//! the active modes here and the indicator polls for them.
+
+use dioxus::{
+    desktop::{window, Config as DioxusConfig, LogicalPosition, LogicalSize, WindowBuilder},
+    prelude::*,
+};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::config::{Config, Indicator, Offset};
+use crate::hud::calculate_window_position;
+
+const MAIN_CSS: Asset = asset!("/assets/main.css");
+
+/// How often the indicator checks for mode changes
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+static MODES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Publish the names of the active modes, from outermost to current
+pub fn publish(modes: Vec<String>) {
+    *MODES.lock().unwrap() = modes;
+}
+
+/// The names of the active modes
+fn current() -> Vec<String> {
+    MODES.lock().unwrap().clone()
+}
+
+/// Size of the indicator window in logical pixels
+fn window_size(indicator: Indicator) -> (f64, f64) {
+    match indicator {
+        Indicator::Name => (240.0, 40.0),
+        Indicator::Dot | Indicator::Off => (24.0, 24.0),
+    }
+}
+
+/// Configure, size and position the indicator window
+fn setup_indicator_window(config: &Config) {
+    let window = window();
+    window.set_decorations(false);
+    window.set_always_on_top(true);
+    window.set_resizable(false);
+    window.set_visible_on_all_workspaces(true);
+    window.set_visible(false);
+    window.set_cursor_visible(false);
+
+    let (width, height) = window_size(config.indicator);
+    window.set_inner_size(LogicalSize::new(width, height));
+    if let Some(monitor) = window.current_monitor() {
+        let screen_size = monitor.size();
+        let scale_factor = monitor.scale_factor();
+        let offset = config.offset_at(config.indicator_pos);
+        let (physical_x, physical_y) = calculate_window_position(
+            config.indicator_pos,
+            screen_size.width as f64,
+            screen_size.height as f64,
+            width * scale_factor,
+            height * scale_factor,
+            Offset {
+                x: offset.x * scale_factor,
+                y: offset.y * scale_factor,
+            },
+        );
+        window.set_outer_position(LogicalPosition::new(
+            physical_x / scale_factor,
+            physical_y / scale_factor,
+        ));
+    }
+}
+
+#[component]
+fn IndicatorWindow() -> Element {
+    let config = use_context::<Config>();
+    let mut modes = use_signal(Vec::<String>::new);
+
+    use_hook({
+        let config = config.clone();
+        move || setup_indicator_window(&config)
+    });
+
+    // Follow the modes published by the HUD, showing the window only while a
+    // mode is active
+    use_future(move || async move {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let current = current();
+            if *modes.read() != current {
+                window().set_visible(!current.is_empty());
+                modes.set(current);
+            }
+        }
+    });
+
+    let container_class = format!("hud-indicator {}", config.appearance.class());
+    rsx! {
+        document::Link { rel: "stylesheet", href: MAIN_CSS }
+        document::Style {
+            "#app {{ background: transparent; }}"
+        }
+        div { class: container_class,
+            if config.indicator == Indicator::Name {
+                span { class: "hud-indicator-name",
+                    {modes.read().join(" › ")}
+                }
+            } else {
+                span { class: "hud-indicator-dot" }
+            }
+        }
+    }
+}
+
+/// Create the indicator window, if the config enables it
+pub fn create_indicator_window(config: Config) {
+    if config.indicator == Indicator::Off {
+        return;
+    }
+    let window = dioxus::desktop::window();
+    let window_config = DioxusConfig::new().with_window(
+        WindowBuilder::new()
+            .with_transparent(true)
+            .with_visible(false)
+            .with_resizable(false)
+            .with_decorations(false),
+    );
+    let dom = VirtualDom::new(IndicatorWindow);
+    dom.provide_root_context(config);
+    window.new_window(dom, window_config);
+}
@@ -0,0 +1,125 @@
+//! A log file that survives crashes.
+//!
+//! [`LogFile`] appends every line as it is logged, so nothing is lost if
+//! hotki dies. Once the file reaches its size limit it is rotated:
+//! `hotki.log` becomes `hotki.1.log`, `hotki.1.log` becomes `hotki.2.log`,
+//! and so on, dropping the oldest beyond the number of files kept.
+
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Name of the current log file, without extension
+const STEM: &str = "hotki";
+
+/// An append-only log file, rotated by size
+#[derive(Debug)]
+pub struct LogFile {
+    dir: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl LogFile {
+    /// Default directory for log files
+    pub fn default_dir() -> Option<PathBuf> {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Logs/hotki"))
+    }
+
+    /// Open the log file in a directory, creating both if needed
+    ///
+    /// The file is rotated once writing would take it past `max_size` bytes,
+    /// and up to `max_files` rotated files are kept.
+    pub fn open(dir: &Path, max_size: u64, max_files: usize) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(Self::path_in(dir, 0))?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            max_size,
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    /// Path of the current log file
+    pub fn path(&self) -> PathBuf {
+        Self::path_in(&self.dir, 0)
+    }
+
+    /// Path of a log file in `dir`: the current one for `0`, otherwise the
+    /// rotated one with that number
+    fn path_in(dir: &Path, index: usize) -> PathBuf {
+        if index == 0 {
+            dir.join(format!("{STEM}.log"))
+        } else {
+            dir.join(format!("{STEM}.{index}.log"))
+        }
+    }
+
+    /// Append a line, which should include its newline
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64;
+        if self.size > 0 && self.size + len > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += len;
+        Ok(())
+    }
+
+    /// Shift the rotated files up by one and start a new current file
+    fn rotate(&mut self) -> io::Result<()> {
+        for index in (0..self.max_files).rev() {
+            match fs::rename(
+                Self::path_in(&self.dir, index),
+                Self::path_in(&self.dir, index + 1),
+            ) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        self.file = File::create(self.path())?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation() {
+        let dir = env::temp_dir().join(format!("hotki-logfile-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut file = LogFile::open(&dir, 10, 2).unwrap();
+        file.write_line("first\n").unwrap();
+        file.write_line("second\n").unwrap();
+        file.write_line("third\n").unwrap();
+        file.write_line("fourth\n").unwrap();
+
+        let read = |index| fs::read_to_string(LogFile::path_in(&dir, index)).ok();
+        assert_eq!(read(0).as_deref(), Some("fourth\n"));
+        assert_eq!(read(1).as_deref(), Some("third\n"));
+        assert_eq!(read(2).as_deref(), Some("second\n"));
+        assert_eq!(read(3), None);
+
+        // Reopening appends to the current file
+        let mut file = LogFile::open(&dir, 100, 2).unwrap();
+        file.write_line("fifth\n").unwrap();
+        assert_eq!(read(0).as_deref(), Some("fourth\nfifth\n"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,30 @@
+//! Start-at-login support via a macOS LaunchAgent.
+
+use hotkey_manager::{LaunchAgent, Result};
+use std::env;
+
+/// Label of the LaunchAgent that starts hotki at login
+const LOGIN_AGENT_LABEL: &str = "si.corte.hotki";
+
+/// The LaunchAgent for the currently running hotki binary
+fn login_agent() -> Result<LaunchAgent> {
+    let mut agent = LaunchAgent::new(LOGIN_AGENT_LABEL, env::current_exe()?);
+    if let Ok(config) = env::var("HOTKI_CONFIG") {
+        agent = agent.with_env("HOTKI_CONFIG", config);
+    }
+    Ok(agent)
+}
+
+/// Install or remove the login LaunchAgent
+///
+/// The agent is rewritten when enabled, so it follows the binary if it moves.
+/// It is not loaded immediately, since hotki is already running.
+pub fn set_start_at_login(enabled: bool) -> Result<()> {
+    let agent = login_agent()?;
+    if enabled {
+        agent.write()?;
+    } else {
+        agent.remove()?;
+    }
+    Ok(())
+}
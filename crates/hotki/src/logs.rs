@@ -1,55 +1,159 @@
-use crate::ringbuffer::get_logs;
+//! The logs window.
+//!
+//! Lines stream in from the ring buffer as they are logged, oldest at the
+//! top. Only the rows in view are rendered, so a full buffer of tens of
+//! thousands of lines doesn't bog down the webview. While scrolled to the
+//! bottom, the view follows new lines as they arrive.
+
+use crate::ringbuffer;
 use dioxus::prelude::*;
+use std::collections::VecDeque;
+use std::ops::Range;
+
+/// Height of each row in pixels. Rows don't wrap, so all have this height.
+const ROW_HEIGHT: f64 = 20.0;
+
+/// Rows rendered above and below the viewport, so fast scrolling doesn't
+/// show blank space before the next render
+const OVERSCAN: usize = 20;
+
+/// How close to the bottom, in pixels, still counts as at the bottom
+const BOTTOM_SLACK: f64 = ROW_HEIGHT;
+
+/// Viewport height assumed until the scroll area reports its size
+const DEFAULT_VIEWPORT_HEIGHT: f64 = 800.0;
+
+/// Scroll the log area to the bottom
+const SCROLL_TO_BOTTOM_JS: &str = r#"
+const el = document.getElementById("logs-scroll");
+if (el) { el.scrollTop = el.scrollHeight; }
+"#;
+
+/// The rows of `total` to render for a viewport scrolled to `scroll_top`
+fn visible_range(scroll_top: f64, viewport_height: f64, total: usize) -> Range<usize> {
+    let first = (scroll_top.max(0.0) / ROW_HEIGHT).floor() as usize;
+    let shown = (viewport_height.max(0.0) / ROW_HEIGHT).ceil() as usize;
+    let start = first.saturating_sub(OVERSCAN).min(total);
+    let end = first
+        .saturating_add(shown)
+        .saturating_add(OVERSCAN)
+        .min(total);
+    start..end.max(start)
+}
 
 #[component]
 pub fn LogsWindow() -> Element {
-    // Get logs and reverse them (newest first)
-    let logs = use_resource(move || async move {
-        let mut logs = get_logs();
-        logs.reverse(); // Show newest logs first
-        logs
+    let mut lines = use_signal(VecDeque::<String>::new);
+    let mut scroll_top = use_signal(|| 0.0);
+    let mut viewport_height = use_signal(|| DEFAULT_VIEWPORT_HEIGHT);
+    let mut follow = use_signal(|| true);
+
+    // Append lines as they are logged
+    use_future(move || async move {
+        let Some(mut updates) = ringbuffer::subscribe() else {
+            return;
+        };
+        let capacity = ringbuffer::capacity();
+        let mut seen = 0;
+        loop {
+            let (new, pushed) = ringbuffer::logs_since(seen);
+            seen = pushed;
+            if !new.is_empty() {
+                let mut lines = lines.write();
+                lines.extend(new);
+                let excess = lines.len().saturating_sub(capacity);
+                lines.drain(..excess);
+            }
+            if updates.changed().await.is_err() {
+                return;
+            }
+        }
     });
 
-    match &*logs.read_unchecked() {
-        Some(log_lines) => {
-            rsx! {
-                div {
-                    class: "logs-container",
-                    style: "
-                        width: 100vw;
-                        height: 100vh;
-                        background: #1e1e1e;
-                        color: #d4d4d4;
-                        font-family: 'SF Mono', 'Monaco', 'Inconsolata', 'Roboto Mono', monospace;
-                        font-size: 12px;
-                        overflow-y: auto;
-                        padding: 16px;
-                        box-sizing: border-box;
-                    ",
-                    div {
-                        class: "logs-header",
-                        style: "
-                            border-bottom: 1px solid #333;
-                            padding-bottom: 8px;
-                            margin-bottom: 16px;
-                            color: #888;
-                            font-weight: 600;
-                        ",
-                        "Logs ({log_lines.len()} entries)"
+    // Keep the newest line in view while following
+    use_effect(move || {
+        let _ = lines.read().len();
+        if *follow.peek() {
+            spawn(async move {
+                let _ = document::eval(SCROLL_TO_BOTTOM_JS).await;
+            });
+        }
+    });
+
+    let total = lines.read().len();
+    let range = visible_range(*scroll_top.read(), *viewport_height.read(), total);
+    let offset = range.start as f64 * ROW_HEIGHT;
+    let content_height = total as f64 * ROW_HEIGHT;
+    let rows: Vec<(usize, String)> = lines
+        .read()
+        .range(range.clone())
+        .cloned()
+        .enumerate()
+        .map(|(i, line)| (range.start + i, line))
+        .collect();
+
+    rsx! {
+        div {
+            class: "logs-container",
+            style: "
+                width: 100vw;
+                height: 100vh;
+                display: flex;
+                flex-direction: column;
+                background: var(--logs-bg);
+                color: var(--logs-fg);
+                font-family: 'SF Mono', 'Monaco', 'Inconsolata', 'Roboto Mono', monospace;
+                font-size: 12px;
+                padding: 16px;
+                box-sizing: border-box;
+            ",
+            div {
+                class: "logs-header",
+                style: "
+                    border-bottom: 1px solid var(--logs-border);
+                    padding-bottom: 8px;
+                    margin-bottom: 16px;
+                    color: var(--logs-muted);
+                    font-weight: 600;
+                ",
+                "Logs ({total} entries)"
+            }
+            div {
+                id: "logs-scroll",
+                class: "logs-content",
+                style: "flex: 1; overflow-y: auto; position: relative;",
+                onscroll: move |event| {
+                    let top = f64::from(event.scroll_top());
+                    let height = f64::from(event.client_height());
+                    let bottom = f64::from(event.scroll_height()) - height;
+                    scroll_top.set(top);
+                    viewport_height.set(height);
+                    follow.set(top >= bottom - BOTTOM_SLACK);
+                },
+                onresize: move |event| {
+                    if let Ok(size) = event.get_content_box_size() {
+                        viewport_height.set(size.height);
                     }
+                },
+                div {
+                    style: "height: {content_height}px; position: relative;",
                     div {
-                        class: "logs-content",
-                        for (index, line) in log_lines.iter().enumerate() {
+                        style: "position: absolute; top: {offset}px; left: 0; right: 0;",
+                        for (index, line) in rows {
                             div {
                                 key: "{index}",
                                 class: "log-line",
                                 style: "
-                                    padding: 4px 8px;
-                                    border-bottom: 1px solid #2a2a2a;
-                                    line-height: 1.4;
-                                    white-space: pre-wrap;
-                                    word-break: break-all;
+                                    height: {ROW_HEIGHT}px;
+                                    line-height: {ROW_HEIGHT}px;
+                                    padding: 0 8px;
+                                    border-bottom: 1px solid var(--logs-row-border);
+                                    box-sizing: border-box;
+                                    white-space: pre;
+                                    overflow: hidden;
+                                    text-overflow: ellipsis;
                                 ",
+                                title: "{line}",
                                 "{line}"
                             }
                         }
@@ -57,23 +161,24 @@ pub fn LogsWindow() -> Element {
                 }
             }
         }
-        None => {
-            rsx! {
-                div {
-                    class: "logs-loading",
-                    style: "
-                        width: 100vw;
-                        height: 100vh;
-                        background: #1e1e1e;
-                        color: #d4d4d4;
-                        display: flex;
-                        align-items: center;
-                        justify-content: center;
-                        font-family: system-ui;
-                    ",
-                    "Loading logs..."
-                }
-            }
-        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_range() {
+        assert_eq!(visible_range(0.0, 400.0, 0), 0..0);
+        assert_eq!(visible_range(0.0, 400.0, 10), 0..10);
+        assert_eq!(visible_range(0.0, 400.0, 10_000), 0..40);
+
+        // Scrolled into the middle, with overscan on both sides
+        let top = 5_000.0 * ROW_HEIGHT;
+        assert_eq!(visible_range(top, 400.0, 10_000), 4_980..5_040);
+
+        // Scrolled past the end, as while the list shrinks
+        assert_eq!(visible_range(top, 400.0, 100), 100..100);
     }
 }
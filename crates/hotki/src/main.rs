@@ -1,18 +1,30 @@
 mod config;
+mod crash;
 mod hud;
+mod indicator;
+mod logfile;
+mod login;
 mod logs;
+mod respawn;
 mod ringbuffer;
+mod settings;
+mod tray;
+mod vibrancy;
 
-use crate::config::Config;
-use crate::hud::create_hud_window;
+use crate::config::{Config, Logs, DEFAULT_LOG_CAPACITY};
+use crate::hud::{create_hud_window, ServerSocket};
+use crate::indicator::create_indicator_window;
+use crate::logfile::LogFile;
 use crate::logs::LogsWindow;
 use crate::ringbuffer::init_tracing;
+use crate::settings::Settings;
+use crate::tray::TrayStatus;
 use clap::Parser;
 use dioxus::{
     desktop::{
         trayicon::{
             init_tray_icon,
-            menu::{Menu, MenuItem, PredefinedMenuItem},
+            menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu},
             Icon,
         },
         use_muda_event_handler, window, Config as DioxusConfig, WindowCloseBehaviour,
@@ -23,8 +35,8 @@ use dioxus::{
 use dioxus_desktop::tao::platform::macos::{ActivationPolicy, EventLoopWindowTargetExtMacOS};
 
 use hotkey_manager::Server;
-use std::{env, fs, process};
-use tracing::{debug, error, info, Level};
+use std::{env, path::PathBuf, process, time::Duration};
+use tracing::{debug, error, info, warn, Level};
 
 fn get_config_path() -> String {
     match env::var("HOTKI_CONFIG") {
@@ -35,7 +47,7 @@ fn get_config_path() -> String {
                 Ok(home) => format!("{home}/.hotki.ron"),
                 Err(_) => {
                     error!("Error: Neither HOTKI_CONFIG nor HOME environment variables are set");
-                    error!("Please set HOTKI_CONFIG to the path of your RON configuration file");
+                    error!("Please set HOTKI_CONFIG to the path of your configuration file");
                     error!("Example: HOTKI_CONFIG=/path/to/config.ron hotki");
                     process::exit(1);
                 }
@@ -64,7 +76,8 @@ const TAILWIND_CSS: Asset = asset!("/assets/tailwind.css");
 #[command(name = "hotki")]
 #[command(about = "Hotkey Manager GUI", long_about = None)]
 #[command(after_help = r#"ENVIRONMENT VARIABLES:
-  HOTKI_CONFIG    Path to RON configuration file (defaults to ~/.hotki.ron)
+  HOTKI_CONFIG    Path to configuration file in RON, TOML, YAML or JSON,
+                  detected by extension (defaults to ~/.hotki.ron)
 
 EXAMPLES:
   Run GUI (with default config):
@@ -79,21 +92,56 @@ struct Args {
     /// Run as hotkey server (no GUI)
     #[arg(long)]
     server: bool,
+
+    /// Let clients simulate hotkey presses, for testing (server mode only)
+    #[arg(long, requires = "server")]
+    allow_test_triggers: bool,
+
+    /// Socket path of the hotkey server, to connect to or listen on
+    /// (defaults to $HOTKEY_SOCKET, or a per-user path in $XDG_RUNTIME_DIR
+    /// or $TMPDIR)
+    #[arg(long, value_name = "PATH")]
+    socket: Option<String>,
+
+    /// Print a JSON Schema for the config file and exit
+    #[arg(long, conflicts_with = "server")]
+    schema: bool,
 }
 
 fn main() {
-    // Initialize tracing with info level and 2048 entry ring buffer
-    init_tracing(Level::INFO, 2048);
+    // Initialize tracing with info level. The config can change how many
+    // lines are kept once it is loaded.
+    init_tracing(Level::INFO, DEFAULT_LOG_CAPACITY);
 
     // Filter out empty arguments that dx might pass
     let args_vec: Vec<String> = env::args().filter(|arg| !arg.is_empty()).collect();
 
     let args = Args::parse_from(args_vec);
 
-    if args.server {
+    if let Some(dir) = LogFile::default_dir() {
+        crash::install(
+            dir,
+            if args.server {
+                crash::SERVER
+            } else {
+                crash::APP
+            },
+        );
+    }
+
+    let socket = args
+        .socket
+        .unwrap_or_else(hotkey_manager::default_socket_path);
+
+    if args.schema {
+        println!("{}", keymode::schema_json::<Config>());
+    } else if args.server {
         // Run in server mode
         info!("Starting hotkey server...");
-        if let Err(e) = Server::new().run() {
+        let server = Server::new()
+            .with_test_triggers(args.allow_test_triggers)
+            .with_socket_path(socket);
+        if let Err(e) = server.run() {
             error!("Failed to run server: {e}");
             process::exit(1);
         }
@@ -102,25 +150,22 @@ fn main() {
         // Load config from environment variable or default to ~/.hotki.ron
         let config_path = get_config_path();
 
-        let config_content = match fs::read_to_string(&config_path) {
-            Ok(content) => {
+        // Run with an empty config if the user's is broken, so that the
+        // problem can be shown and fixed without the app disappearing
+        let (config, config_error) = match load_config(&config_path) {
+            Ok(config) => {
                 info!("Loaded config from: {config_path}");
-                content
+                (config, None)
             }
             Err(e) => {
-                error!("Failed to read config file '{config_path}': {e}");
-                process::exit(1);
-            }
-        };
-
-        // Parse the confikj jjjg
-        let config = match ron::from_str::<Config>(&config_content) {
-            Ok(config) => config,
-            Err(e) => {
-                error!("Failed to parse config file '{config_path}': {e}");
-                process::exit(1);
+                error!("{e}");
+                (Config::fallback(), Some(e))
             }
         };
+        setup_logs(&config.logs);
+        let crash_reports = LogFile::default_dir()
+            .map(|dir| crash::pending(&dir))
+            .unwrap_or_default();
 
         use dioxus::desktop::WindowBuilder;
 
@@ -153,34 +198,188 @@ fn main() {
         LaunchBuilder::desktop()
             .with_cfg(dioxus_config)
             .with_context(config)
+            .with_context(ConfigError(config_error))
+            .with_context(CrashReports(crash_reports))
+            .with_context(ServerSocket(socket))
             .launch(LogsApp);
     }
 }
 
+/// Apply the config's log settings to the ring buffer
+fn setup_logs(logs: &Logs) {
+    ringbuffer::set_capacity(logs.capacity);
+    if !logs.persist {
+        return;
+    }
+    let Some(dir) = LogFile::default_dir() else {
+        warn!("Not persisting logs: HOME is not set");
+        return;
+    };
+    match LogFile::open(&dir, logs.max_file_size, logs.max_files).and_then(ringbuffer::persist) {
+        Ok(()) => info!("Persisting logs to {}", dir.display()),
+        Err(e) => warn!("Failed to persist logs to {}: {e}", dir.display()),
+    }
+}
+
+/// Why the user's config couldn't be loaded, if it couldn't
+#[derive(Debug, Clone)]
+struct ConfigError(Option<String>);
+
+/// Reports left by crashes of the previous session
+#[derive(Debug, Clone)]
+struct CrashReports(Vec<PathBuf>);
+
+/// Load the config, detecting its format from the file extension
+///
+/// Every problem is reported at once. Unparseable keys are errors, and other
+/// issues are logged as warnings.
+fn load_config(config_path: &str) -> Result<Config, String> {
+    let config = Config::load(config_path)?;
+    let mut errors = Vec::new();
+    for issue in config.keys.validate() {
+        if issue.is_invalid_key() {
+            errors.push(format!("Config error in '{config_path}': {issue}"));
+        } else {
+            warn!("Config issue in '{config_path}': {issue}");
+        }
+    }
+    if errors.is_empty() {
+        Ok(config)
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+/// Reveal the config file in Finder
+fn reveal_config() {
+    if let Some(config_path) = get_config_path_safe() {
+        // Use the 'open' command to reveal the file in Finder
+        let _ = std::process::Command::new("open")
+            .arg("-R") // -R flag reveals the file in Finder
+            .arg(&config_path)
+            .spawn();
+    } else {
+        error!("Cannot determine config path: HOME environment variable not set");
+    }
+}
+
+/// Replace this instance of hotki with a new one, to pick up a fixed config
+fn relaunch() {
+    let exe = match env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            error!("Failed to find the hotki executable: {e}");
+            return;
+        }
+    };
+    match process::Command::new(exe)
+        .args(env::args_os().skip(1))
+        .spawn()
+    {
+        Ok(_) => process::exit(0),
+        Err(e) => error!("Failed to relaunch hotki: {e}"),
+    }
+}
+
+/// Load persisted settings from the default location
+fn load_settings() -> Settings {
+    Settings::default_path()
+        .map(|path| Settings::load(&path))
+        .unwrap_or_default()
+}
+
+/// Flip the start-at-login preference, updating the LaunchAgent and saving the
+/// new value
+fn toggle_start_at_login() {
+    let Some(path) = Settings::default_path() else {
+        error!("Cannot determine settings path: HOME environment variable not set");
+        return;
+    };
+    let mut settings = Settings::load(&path);
+    settings.start_at_login = !settings.start_at_login;
+    if let Err(e) = login::set_start_at_login(settings.start_at_login) {
+        error!("Failed to update login item: {e}");
+        return;
+    }
+    if let Err(e) = settings.save(&path) {
+        error!("Failed to save settings to '{}': {e}", path.display());
+    }
+    info!("Start at login: {}", settings.start_at_login);
+}
+
+/// Maximum number of bindings listed in the tray's bindings submenu
+const TRAY_MAX_BINDINGS: usize = 20;
+
+/// Build the tray menu, including submenus reflecting the keymode status
+fn build_tray_menu(status: &TrayStatus) -> Menu {
+    let tray_menu = Menu::new();
+
+    // Show the current mode and its bindings
+    let mode_text = if status.suspended {
+        "Hotkeys suspended".to_string()
+    } else if status.in_mode() {
+        format!("Mode: {}", status.modes.join(" › "))
+    } else {
+        "Mode: root".to_string()
+    };
+    let mode_item = MenuItem::new(mode_text, false, None);
+    let bindings = Submenu::new("Bindings", !status.keys.is_empty());
+    for (key, desc) in status.keys.iter().take(TRAY_MAX_BINDINGS) {
+        let _ = bindings.append(&MenuItem::new(format!("{key}\t{desc}"), false, None));
+    }
+    let recent = Submenu::new("Recent Actions", !status.recent.is_empty());
+    for desc in &status.recent {
+        let _ = recent.append(&MenuItem::new(desc, false, None));
+    }
+
+    // Add menu items with IDs to handle click events
+    let config_path = env::var("HOTKI_CONFIG").unwrap_or_else(|_| "Config not found".to_string());
+    let config_item = MenuItem::with_id("config", &config_path, false, None);
+    let reveal_item = MenuItem::with_id("reveal", "Reveal Config in Finder", true, None);
+    let logs_item = MenuItem::with_id("logs", "Logs", true, None);
+    let reconnect_item = MenuItem::with_id("reconnect", "Reconnect", true, None);
+    let suspend_item =
+        CheckMenuItem::with_id("suspend", "Suspend Hotkeys", true, status.suspended, None);
+    let login_item = CheckMenuItem::with_id(
+        "login",
+        "Start at Login",
+        Settings::default_path().is_some(),
+        load_settings().start_at_login,
+        None,
+    );
+    let quit_item = MenuItem::with_id("quit", "Quit", true, None);
+
+    let _ = tray_menu.append(&mode_item);
+    let _ = tray_menu.append(&bindings);
+    let _ = tray_menu.append(&recent);
+    let _ = tray_menu.append(&PredefinedMenuItem::separator());
+    let _ = tray_menu.append(&config_item);
+    let _ = tray_menu.append(&reveal_item);
+    let _ = tray_menu.append(&logs_item);
+    let _ = tray_menu.append(&reconnect_item);
+    let _ = tray_menu.append(&suspend_item);
+    let _ = tray_menu.append(&login_item);
+    let _ = tray_menu.append(&PredefinedMenuItem::separator());
+    let _ = tray_menu.append(&quit_item);
+
+    tray_menu
+}
+
 #[component]
 fn LogsApp() -> Element {
-    use_hook(|| {
+    let ticon = use_hook(|| {
         // Set the close behavior for the main window
         // This will hide the window instead of closing it when the user clicks the close button
         window().set_close_behavior(WindowCloseBehaviour::WindowHides);
 
-        // Create a simple tray menu
-        let tray_menu = Menu::new();
-
-        // Add menu items with IDs to handle click events
-        let config_path =
-            env::var("HOTKI_CONFIG").unwrap_or_else(|_| "Config not found".to_string());
-        let config_item = MenuItem::with_id("config", &config_path, false, None);
-        let reveal_item = MenuItem::with_id("reveal", "Reveal Config in Finder", true, None);
-        let logs_item = MenuItem::with_id("logs", "Logs", true, None);
-        let separator = PredefinedMenuItem::separator();
-        let quit_item = MenuItem::with_id("quit", "Quit", true, None);
+        // Refresh the login item, so it follows the binary if it has moved
+        if load_settings().start_at_login {
+            if let Err(e) = login::set_start_at_login(true) {
+                error!("Failed to update login item: {e}");
+            }
+        }
 
-        let _ = tray_menu.append(&config_item);
-        let _ = tray_menu.append(&reveal_item);
-        let _ = tray_menu.append(&logs_item);
-        let _ = tray_menu.append(&separator);
-        let _ = tray_menu.append(&quit_item);
+        let tray_menu = build_tray_menu(&TrayStatus::default());
 
         // Initialize tray icon with custom logo
         let icon_bytes = include_bytes!("../logo/tray-icon.png");
@@ -198,6 +397,21 @@ fn LogsApp() -> Element {
         let _ = ticon.set_tooltip(Some("Hotki"));
 
         debug!("Tray icon initialized");
+        ticon
+    });
+
+    // Refresh the tray menu and tooltip when the HUD publishes a new status
+    use_future(move || {
+        let ticon = ticon.clone();
+        async move {
+            loop {
+                tokio::time::sleep(Duration::from_millis(250)).await;
+                if let Some(status) = tray::take_update() {
+                    ticon.set_menu(Some(Box::new(build_tray_menu(&status))));
+                    let _ = ticon.set_tooltip(Some(status.tooltip()));
+                }
+            }
+        }
     });
 
     // Handle tray menu click events
@@ -206,21 +420,24 @@ fn LogsApp() -> Element {
             match event.id().as_ref() {
                 "reveal" => {
                     debug!("Reveal config in Finder clicked");
-                    if let Some(config_path) = get_config_path_safe() {
-                        // Use the 'open' command to reveal the file in Finder
-                        let _ = std::process::Command::new("open")
-                            .arg("-R") // -R flag reveals the file in Finder
-                            .arg(&config_path)
-                            .spawn();
-                    } else {
-                        error!("Cannot determine config path: HOME environment variable not set");
-                    }
+                    reveal_config();
                 }
                 "logs" => {
                     debug!("Logs menu item clicked");
                     window().set_visible(true);
                     window().set_focus();
                 }
+                "reconnect" => {
+                    debug!("Reconnect menu item clicked");
+                    tray::request_reconnect();
+                }
+                "suspend" => {
+                    debug!("Suspend menu item clicked");
+                    tray::request_toggle_suspended();
+                }
+                "login" => {
+                    toggle_start_at_login();
+                }
                 "quit" => {
                     // Quit the application
                     process::exit(0);
@@ -232,15 +449,163 @@ fn LogsApp() -> Element {
 
     // Create HUD window as a popup
     let config = use_context::<Config>();
+    let ServerSocket(socket) = use_context::<ServerSocket>();
+    let appearance = config.appearance.class();
     use_effect(move || {
-        create_hud_window(config.clone());
+        create_hud_window(config.clone(), socket.clone());
+        create_indicator_window(config.clone());
     });
 
     rsx! {
         document::Link { rel: "stylesheet", href: MAIN_CSS }
         document::Link { rel: "stylesheet", href: TAILWIND_CSS }
 
-        // Main app is now the logs window
-        LogsWindow {}
+        div { class: appearance,
+            ConfigErrorBanner {}
+            CrashBanner {}
+            ServerFailingBanner {}
+
+            // Main app is now the logs window
+            LogsWindow {}
+        }
+    }
+}
+
+/// An offer to look at the reports left by crashes of the previous session.
+/// Shown above the logs, which open at startup when there are any.
+#[component]
+fn CrashBanner() -> Element {
+    let CrashReports(initial_reports) = use_context::<CrashReports>();
+    let mut reports = use_signal(|| initial_reports);
+    use_hook(move || {
+        if !reports.read().is_empty() {
+            window().set_visible(true);
+            window().set_focus();
+        }
+    });
+
+    if reports.read().is_empty() {
+        return rsx! {};
+    }
+    let mut dismiss = move |view: bool| {
+        for report in reports.read().iter() {
+            if view {
+                let _ = process::Command::new("open").arg(report).spawn();
+            }
+            if let Err(e) = crash::dismiss(report) {
+                warn!("Failed to dismiss crash report '{}': {e}", report.display());
+            }
+        }
+        reports.set(Vec::new());
+    };
+    rsx! {
+        div {
+            style: "
+                background: #3a331e;
+                color: #f0e0b0;
+                font-family: system-ui;
+                padding: 16px;
+            ",
+            div { style: "font-weight: 600; margin-bottom: 12px;",
+                "The previous session of Hotki crashed."
+            }
+            button { onclick: move |_| dismiss(true), "View Report" }
+            button { style: "margin-left: 8px;", onclick: move |_| dismiss(false), "Dismiss" }
+        }
+    }
+}
+
+/// Shown above the logs when the hotkey server keeps crashing and is no
+/// longer respawned, with a way to try again. The logs open when it appears.
+#[component]
+fn ServerFailingBanner() -> Element {
+    let mut failing = use_signal(|| false);
+    use_future(move || async move {
+        loop {
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            let now = respawn::is_failing();
+            if now != *failing.peek() {
+                failing.set(now);
+                if now {
+                    window().set_visible(true);
+                    window().set_focus();
+                }
+            }
+        }
+    });
+
+    if !*failing.read() {
+        return rsx! {};
+    }
+    rsx! {
+        div {
+            style: "
+                background: #3a1e1e;
+                color: #f0b0b0;
+                font-family: system-ui;
+                padding: 16px;
+            ",
+            div { style: "font-weight: 600; margin-bottom: 8px;",
+                "The hotkey server keeps crashing, so Hotki has stopped restarting it."
+            }
+            div { style: "margin-bottom: 12px;",
+                "Hotkeys won't work until it is running again. The logs below may show why it crashed."
+            }
+            button { onclick: move |_| tray::request_reconnect(), "Try Again" }
+        }
+    }
+}
+
+/// The reason the config couldn't be loaded, with ways to fix it. Shown
+/// above the logs, which open at startup when there is a problem.
+#[component]
+fn ConfigErrorBanner() -> Element {
+    let ConfigError(initial_error) = use_context::<ConfigError>();
+    let mut config_error = use_signal(|| initial_error);
+    use_hook(move || {
+        if config_error.read().is_some() {
+            window().set_visible(true);
+            window().set_focus();
+        }
+    });
+
+    let Some(message) = config_error.read().clone() else {
+        return rsx! {};
+    };
+    rsx! {
+        div {
+            style: "
+                background: #3a1e1e;
+                color: #f0b0b0;
+                font-family: system-ui;
+                padding: 16px;
+            ",
+            div { style: "font-weight: 600; margin-bottom: 8px;",
+                "Hotki couldn't load its config, and is running without bindings."
+            }
+            div {
+                style: "
+                    font-family: 'SF Mono', 'Monaco', monospace;
+                    font-size: 12px;
+                    white-space: pre-wrap;
+                    margin-bottom: 12px;
+                ",
+                "{message}"
+            }
+            button { onclick: move |_| reveal_config(), "Reveal Config" }
+            button {
+                style: "margin-left: 8px;",
+                onclick: move |_| {
+                    let Some(path) = get_config_path_safe() else {
+                        return;
+                    };
+                    match load_config(&path) {
+                        Ok(_) => relaunch(),
+                        Err(e) => config_error.set(Some(e)),
+                    }
+                },
+                "Retry"
+            }
+        }
     }
 }
@@ -0,0 +1,79 @@
+//! Respawning the hotkey server.
+//!
+//! The HUD respawns the server whenever it exits or stops answering pings.
+//! A server that keeps crashing trips a [`Breaker`], which stops the
+//! respawning. The HUD then marks the server as failing here, for the main
+//! window to ask the user what to do, and waits for a reconnect request.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+static FAILING: AtomicBool = AtomicBool::new(false);
+
+/// Stops respawning a server that fails too often
+#[derive(Debug, Clone)]
+pub struct Breaker {
+    limit: usize,
+    window: Duration,
+    failures: VecDeque<Instant>,
+}
+
+impl Breaker {
+    /// Trip after `limit` failures within `window`
+    pub fn new(limit: usize, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            failures: VecDeque::with_capacity(limit),
+        }
+    }
+
+    /// Record a failure at `now`, returning whether the breaker has tripped
+    pub fn record(&mut self, now: Instant) -> bool {
+        while let Some(&first) = self.failures.front() {
+            if now.duration_since(first) < self.window {
+                break;
+            }
+            self.failures.pop_front();
+        }
+        self.failures.push_back(now);
+        self.failures.len() >= self.limit
+    }
+
+    /// Forget past failures
+    pub fn reset(&mut self) {
+        self.failures.clear();
+    }
+}
+
+/// Mark the server as failing, or as recovered
+pub fn set_failing(failing: bool) {
+    FAILING.store(failing, Ordering::SeqCst);
+}
+
+/// Whether the server keeps crashing and is no longer being respawned
+pub fn is_failing() -> bool {
+    FAILING.load(Ordering::SeqCst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut breaker = Breaker::new(3, Duration::from_secs(60));
+
+        assert!(!breaker.record(at(0)));
+        assert!(!breaker.record(at(10)));
+        // The first failure has aged out of the window
+        assert!(!breaker.record(at(65)));
+        assert!(breaker.record(at(66)));
+
+        breaker.reset();
+        assert!(!breaker.record(at(67)));
+    }
+}
@@ -1,34 +1,94 @@
 use std::{
     collections::VecDeque,
     io::Write,
-    sync::{Arc, Mutex, OnceLock},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock, TryLockError,
+    },
 };
+use tokio::sync::watch;
 use tracing::Level;
 use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt, util::SubscriberInitExt};
 
+use crate::logfile::LogFile;
+
 static GLOBAL_RING_BUFFER: OnceLock<Arc<RingBuffer>> = OnceLock::new();
 
 /// Ring buffer for storing log entries with a fixed capacity
 #[derive(Debug)]
 pub struct RingBuffer {
     buffer: Mutex<VecDeque<String>>,
-    capacity: usize,
+    capacity: AtomicUsize,
+    /// Number of lines ever pushed, watched by subscribers
+    pushed: watch::Sender<u64>,
+    /// File that every line is also written to, if persisting
+    file: Mutex<Option<LogFile>>,
 }
 
 impl RingBuffer {
     pub fn new(capacity: usize) -> Self {
         Self {
             buffer: Mutex::new(VecDeque::with_capacity(capacity)),
-            capacity,
+            capacity: AtomicUsize::new(capacity),
+            pushed: watch::Sender::new(0),
+            file: Mutex::new(None),
         }
     }
 
     pub fn push(&self, line: String) {
         let mut buffer = self.buffer.lock().unwrap();
-        if buffer.len() >= self.capacity {
+        let mut file = self.file.lock().unwrap();
+        if let Some(log_file) = file.as_mut() {
+            if let Err(e) = log_file.write_line(&line) {
+                // Logging the failure would come back here, so report it
+                // directly and stop persisting
+                eprintln!("Failed to write '{}': {e}", log_file.path().display());
+                *file = None;
+            }
+        }
+        if buffer.len() >= self.capacity() {
             buffer.pop_front();
         }
         buffer.push_back(line);
+        self.pushed.send_modify(|pushed| *pushed += 1);
+    }
+
+    /// Change the number of lines kept, dropping the oldest if there are
+    /// more than that
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut buffer = self.buffer.lock().unwrap();
+        self.capacity.store(capacity, Ordering::Relaxed);
+        let excess = buffer.len().saturating_sub(capacity);
+        buffer.drain(..excess);
+    }
+
+    /// Write every line to a file as well, starting with those already held
+    pub fn persist(&self, mut log_file: LogFile) -> std::io::Result<()> {
+        let buffer = self.buffer.lock().unwrap();
+        for line in buffer.iter() {
+            log_file.write_line(line)?;
+        }
+        *self.file.lock().unwrap() = Some(log_file);
+        Ok(())
+    }
+
+    /// The lines still held that were pushed after the first `seen`, and the
+    /// number of lines pushed so far, to pass as `seen` next time
+    pub fn since(&self, seen: u64) -> (Vec<String>, u64) {
+        let buffer = self.buffer.lock().unwrap();
+        let pushed = *self.pushed.borrow();
+        let new = usize::try_from(pushed.saturating_sub(seen)).unwrap_or(usize::MAX);
+        let skip = buffer.len().saturating_sub(new);
+        (buffer.iter().skip(skip).cloned().collect(), pushed)
+    }
+
+    /// Watch the number of lines pushed, to learn when there are new ones
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.pushed.subscribe()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Relaxed)
     }
 
     #[allow(dead_code)]
@@ -36,6 +96,16 @@ impl RingBuffer {
         let buffer = self.buffer.lock().unwrap();
         buffer.iter().cloned().collect()
     }
+
+    /// The lines held, or `None` if the buffer is locked
+    pub fn try_get_logs(&self) -> Option<Vec<String>> {
+        let buffer = match self.buffer.try_lock() {
+            Ok(buffer) => buffer,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => return None,
+        };
+        Some(buffer.iter().cloned().collect())
+    }
 }
 
 impl Write for &RingBuffer {
@@ -114,11 +184,80 @@ pub fn init_tracing(log_level: Level, ring_buffer_size: usize) {
     subscriber.init();
 }
 
-/// Get the current logs from the global ring buffer
-pub fn get_logs() -> Vec<String> {
+/// Get the lines in the global ring buffer logged after the first `seen`,
+/// and the number of lines logged so far
+pub fn logs_since(seen: u64) -> (Vec<String>, u64) {
+    match GLOBAL_RING_BUFFER.get() {
+        Some(buffer) => buffer.since(seen),
+        None => (Vec::new(), seen),
+    }
+}
+
+/// Watch the number of lines logged to the global ring buffer
+pub fn subscribe() -> Option<watch::Receiver<u64>> {
+    GLOBAL_RING_BUFFER.get().map(|buffer| buffer.subscribe())
+}
+
+/// Change the number of lines the global ring buffer holds
+pub fn set_capacity(capacity: usize) {
     if let Some(buffer) = GLOBAL_RING_BUFFER.get() {
-        buffer.get_logs()
-    } else {
-        Vec::new()
+        buffer.set_capacity(capacity);
+    }
+}
+
+/// Write every line logged to the global ring buffer to a file as well
+pub fn persist(log_file: LogFile) -> std::io::Result<()> {
+    match GLOBAL_RING_BUFFER.get() {
+        Some(buffer) => buffer.persist(log_file),
+        None => Ok(()),
+    }
+}
+
+/// The lines in the global ring buffer, without waiting on its lock, for a
+/// panic hook that may run while the lock is held
+pub fn try_get_logs() -> Option<Vec<String>> {
+    GLOBAL_RING_BUFFER.get()?.try_get_logs()
+}
+
+/// The number of lines the global ring buffer holds
+pub fn capacity() -> usize {
+    GLOBAL_RING_BUFFER
+        .get()
+        .map_or(0, |buffer| buffer.capacity())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_since() {
+        let buffer = RingBuffer::new(3);
+        let mut updates = buffer.subscribe();
+        assert_eq!(buffer.since(0), (Vec::new(), 0));
+
+        buffer.push("a".to_string());
+        buffer.push("b".to_string());
+        assert!(updates.has_changed().unwrap());
+        assert_eq!(buffer.since(0), (vec!["a".to_string(), "b".to_string()], 2));
+        assert_eq!(buffer.since(1), (vec!["b".to_string()], 2));
+        assert_eq!(buffer.since(2), (Vec::new(), 2));
+        updates.mark_unchanged();
+
+        // Lines that fell out of the buffer are skipped
+        for line in ["c", "d", "e"] {
+            buffer.push(line.to_string());
+        }
+        assert!(updates.has_changed().unwrap());
+        let (lines, pushed) = buffer.since(1);
+        assert_eq!(lines, vec!["c", "d", "e"]);
+        assert_eq!(pushed, 5);
+        assert_eq!(buffer.since(4), (vec!["e".to_string()], 5));
+
+        buffer.set_capacity(1);
+        assert_eq!(buffer.capacity(), 1);
+        assert_eq!(buffer.get_logs(), vec!["e"]);
+        buffer.push("f".to_string());
+        assert_eq!(buffer.since(0), (vec!["f".to_string()], 6));
     }
 }
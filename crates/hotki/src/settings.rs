@@ -0,0 +1,75 @@
+//! Persistent user preferences that are managed from the GUI rather than the
+//! config file, along with session state that outlives a restart.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+/// Preferences persisted between runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    /// Launch hotki when the user logs in
+    #[serde(default)]
+    pub start_at_login: bool,
+    /// Keys of the bindings that entered the active mode when hotki last
+    /// ran, saved when the config's `restore_mode` is enabled
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mode_path: Vec<String>,
+}
+
+impl Settings {
+    /// Default location of the settings file
+    pub fn default_path() -> Option<PathBuf> {
+        env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join("Library/Application Support/hotki/settings.ron"))
+    }
+
+    /// Load settings from a file, falling back to defaults if the file is
+    /// missing or invalid
+    pub fn load(path: &Path) -> Self {
+        match fs::read_to_string(path) {
+            Ok(content) => ron::from_str(&content).unwrap_or_else(|e| {
+                warn!("Ignoring invalid settings file '{}': {e}", path.display());
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Save settings to a file, creating its parent directory if needed
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(io::Error::other)?;
+        fs::write(path, content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_roundtrip() {
+        let path = env::temp_dir()
+            .join(format!("hotki-settings-test-{}", std::process::id()))
+            .join("settings.ron");
+        assert_eq!(Settings::load(&path), Settings::default());
+
+        let settings = Settings {
+            start_at_login: true,
+            mode_path: vec!["w".to_string(), "ctrl+m".to_string()],
+        };
+        settings.save(&path).unwrap();
+        assert_eq!(Settings::load(&path), settings);
+
+        fs::write(&path, "not ron").unwrap();
+        assert_eq!(Settings::load(&path), Settings::default());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,143 @@
+//! Status shown in the tray icon.
+//!
+//! The HUD and the tray menu live in separate windows, so the HUD publishes a
+//! snapshot of its keymode [`State`] here whenever it changes, and the tray
+//! polls for updates. Requests from the tray menu go the other way.
+
+use keymode::State;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+static PENDING: Mutex<Option<TrayStatus>> = Mutex::new(None);
+
+static RECONNECT: AtomicBool = AtomicBool::new(false);
+
+static TOGGLE_SUSPENDED: AtomicBool = AtomicBool::new(false);
+
+/// A snapshot of the keymode state for display in the tray
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrayStatus {
+    /// Names of the active modes, from outermost to current
+    pub modes: Vec<String>,
+    /// Visible bindings in the current mode as (key, description) pairs
+    pub keys: Vec<(String, String)>,
+    /// Descriptions of recently executed actions, newest first
+    pub recent: Vec<String>,
+    /// Whether a master toggle has suspended the hotkeys
+    pub suspended: bool,
+}
+
+impl TrayStatus {
+    /// Build a snapshot from a keymode state
+    pub fn from_state(state: &State) -> Self {
+        Self {
+            modes: state.mode_names().into_iter().map(String::from).collect(),
+            keys: state
+                .keys()
+                .into_iter()
+                .filter(|(_, _, attrs)| !attrs.hide)
+                .map(|(key, desc, _)| (key.to_string(), desc))
+                .collect(),
+            recent: state.history().map(String::from).collect(),
+            suspended: state.is_suspended(),
+        }
+    }
+
+    /// Whether a mode is currently active
+    pub fn in_mode(&self) -> bool {
+        !self.modes.is_empty()
+    }
+
+    /// Tooltip text for the tray icon
+    pub fn tooltip(&self) -> String {
+        if self.suspended {
+            "Hotki: suspended".to_string()
+        } else if self.in_mode() {
+            format!("Hotki: {}", self.modes.join(" › "))
+        } else {
+            "Hotki".to_string()
+        }
+    }
+}
+
+/// Publish a new status for the tray to pick up
+pub fn publish(status: TrayStatus) {
+    *PENDING.lock().unwrap() = Some(status);
+}
+
+/// Take the most recently published status, if any has been published since
+/// the last call
+pub fn take_update() -> Option<TrayStatus> {
+    PENDING.lock().unwrap().take()
+}
+
+/// Ask the HUD to reconnect to the hotkey server
+pub fn request_reconnect() {
+    RECONNECT.store(true, Ordering::SeqCst);
+}
+
+/// Whether a reconnect has been requested since the last call
+pub fn take_reconnect() -> bool {
+    RECONNECT.swap(false, Ordering::SeqCst)
+}
+
+/// Ask the HUD to suspend the hotkeys, or resume them if suspended
+pub fn request_toggle_suspended() {
+    TOGGLE_SUSPENDED.store(true, Ordering::SeqCst);
+}
+
+/// Whether suspending or resuming has been requested since the last call
+pub fn take_toggle_suspended() -> bool {
+    TOGGLE_SUSPENDED.swap(false, Ordering::SeqCst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hotkey_manager::Key;
+    use keymode::Mode;
+
+    #[test]
+    fn test_tray_status() {
+        let root = Mode::from_ron(
+            r#"[
+            ("m", "Menu", mode([
+                ("a", "Action", shell("true"), (noexit: true)),
+                ("x", "Hidden", exit, (hide: true)),
+            ])),
+        ]"#,
+        )
+        .unwrap();
+        let mut state = State::new(root);
+
+        let status = TrayStatus::from_state(&state);
+        assert!(!status.in_mode());
+        assert_eq!(status.tooltip(), "Hotki");
+
+        state.handle_key(&Key::parse("m").unwrap()).unwrap();
+        state.handle_key(&Key::parse("a").unwrap()).unwrap();
+        let status = TrayStatus::from_state(&state);
+        assert_eq!(status.modes, vec!["Menu"]);
+        assert_eq!(status.keys, vec![("a".to_string(), "Action".to_string())]);
+        assert_eq!(status.recent, vec!["Action"]);
+        assert_eq!(status.tooltip(), "Hotki: Menu");
+
+        state.set_suspended(true);
+        let status = TrayStatus::from_state(&state);
+        assert!(status.suspended);
+        assert_eq!(status.tooltip(), "Hotki: suspended");
+
+        publish(status.clone());
+        assert_eq!(take_update(), Some(status));
+        assert_eq!(take_update(), None);
+    }
+
+    #[test]
+    fn test_reconnect_request() {
+        assert!(!take_reconnect());
+        request_reconnect();
+        request_reconnect();
+        assert!(take_reconnect());
+        assert!(!take_reconnect());
+    }
+}
@@ -0,0 +1,76 @@
+//! Blur behind the HUD window.
+//!
+//! On macOS, an `NSVisualEffectView` is placed behind the web view, shaped
+//! like the HUD's container, so the desktop shows through blurred wherever
+//! the container's background is translucent. Other platforms have no blur.
+
+use dioxus::desktop::DesktopService;
+use std::rc::Rc;
+
+use crate::config::Vibrancy;
+
+/// Margin around the HUD container in logical pixels (assets/main.css)
+#[cfg(target_os = "macos")]
+const CONTAINER_MARGIN: f64 = 20.0;
+
+/// Corner radius of the HUD container in logical pixels (assets/main.css)
+#[cfg(target_os = "macos")]
+const CONTAINER_RADIUS: f64 = 12.0;
+
+/// Blur the desktop behind the HUD container with a material
+#[cfg(target_os = "macos")]
+pub fn apply(window: &Rc<DesktopService>, vibrancy: Vibrancy) {
+    use cocoa::base::{id, nil, YES};
+    use cocoa::foundation::{NSPoint, NSRect, NSSize};
+    use dioxus_desktop::tao::platform::macos::WindowExtMacOS;
+    use objc::{class, msg_send, sel, sel_impl};
+
+    /// NSVisualEffectBlendingModeBehindWindow
+    const BEHIND_WINDOW: isize = 0;
+    /// NSVisualEffectStateActive, so the blur doesn't depend on focus
+    const ACTIVE: isize = 1;
+    /// NSViewWidthSizable | NSViewHeightSizable
+    const SIZABLE: usize = 2 | 16;
+    /// NSWindowBelow
+    const BELOW: isize = -1;
+
+    let Some(material) = vibrancy.material() else {
+        return;
+    };
+    // SAFETY: the view belongs to a live window, and is only touched from
+    // the main thread, which runs the UI
+    unsafe {
+        let view = window.ns_view() as id;
+        let bounds: NSRect = msg_send![view, bounds];
+        let frame = NSRect::new(
+            NSPoint::new(
+                bounds.origin.x + CONTAINER_MARGIN,
+                bounds.origin.y + CONTAINER_MARGIN,
+            ),
+            NSSize::new(
+                (bounds.size.width - 2.0 * CONTAINER_MARGIN).max(0.0),
+                (bounds.size.height - 2.0 * CONTAINER_MARGIN).max(0.0),
+            ),
+        );
+        let effect: id = msg_send![class!(NSVisualEffectView), alloc];
+        let effect: id = msg_send![effect, initWithFrame: frame];
+        let _: () = msg_send![effect, setMaterial: material];
+        let _: () = msg_send![effect, setBlendingMode: BEHIND_WINDOW];
+        let _: () = msg_send![effect, setState: ACTIVE];
+        let _: () = msg_send![effect, setAutoresizingMask: SIZABLE];
+        let _: () = msg_send![effect, setWantsLayer: YES];
+        let layer: id = msg_send![effect, layer];
+        let _: () = msg_send![layer, setCornerRadius: CONTAINER_RADIUS];
+        let _: () = msg_send![layer, setMasksToBounds: YES];
+        let _: () = msg_send![view, addSubview: effect positioned: BELOW relativeTo: nil];
+        let _: () = msg_send![effect, release];
+    }
+}
+
+/// Blur is only supported on macOS
+#[cfg(not(target_os = "macos"))]
+pub fn apply(_window: &Rc<DesktopService>, vibrancy: Vibrancy) {
+    if vibrancy != Vibrancy::Off {
+        tracing::debug!("HUD vibrancy is only supported on macOS");
+    }
+}
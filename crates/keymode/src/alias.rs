@@ -0,0 +1,178 @@
+//! Command aliases.
+//!
+//! A shell command of the form `@name args...` refers to a command defined
+//! once in the config. Aliases are resolved when the config is loaded, so
+//! the rest of keymode only ever sees plain commands. A command starting
+//! with `@@` runs with a single literal `@`.
+
+use std::collections::HashMap;
+
+use crate::mode::{Action, Mode};
+use crate::validate::ValidationError;
+
+impl Mode {
+    /// Replace `@name` command aliases throughout the tree
+    ///
+    /// Any arguments after the alias name are appended to the aliased
+    /// command. Every undefined alias is reported, and the tree is left
+    /// partially resolved if there are any.
+    pub fn resolve_aliases(
+        &mut self,
+        commands: &HashMap<String, String>,
+    ) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        resolve_mode(self, commands, &mut Vec::new(), &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn resolve_mode(
+    mode: &mut Mode,
+    commands: &HashMap<String, String>,
+    path: &mut Vec<String>,
+    errors: &mut Vec<ValidationError>,
+) {
+    for (_, desc, action, attrs) in mode.entries_mut() {
+        for hook in [&mut attrs.on_enter, &mut attrs.on_exit]
+            .into_iter()
+            .flatten()
+        {
+            resolve_in_place(hook, commands, path, errors);
+        }
+        match action {
+            Action::Shell(cmd) => resolve_in_place(cmd, commands, path, errors),
+            Action::Toggle { on, off } => {
+                resolve_in_place(on, commands, path, errors);
+                resolve_in_place(off, commands, path, errors);
+            }
+            Action::Mode(child) => {
+                path.push(desc.clone());
+                resolve_mode(child, commands, path, errors);
+                path.pop();
+            }
+            Action::Pop | Action::Exit | Action::Message(_) => {}
+        }
+    }
+}
+
+/// Resolve a command in place, recording an error if its alias is unknown
+fn resolve_in_place(
+    cmd: &mut String,
+    commands: &HashMap<String, String>,
+    path: &[String],
+    errors: &mut Vec<ValidationError>,
+) {
+    match resolve(cmd, commands) {
+        Ok(Some(resolved)) => *cmd = resolved,
+        Ok(None) => {}
+        Err(name) => errors.push(ValidationError::UnknownAlias {
+            path: path.to_vec(),
+            name,
+        }),
+    }
+}
+
+/// Resolve a single command
+///
+/// Returns `Ok(None)` if the command is not an alias, and the alias name if
+/// it is undefined.
+fn resolve(cmd: &str, commands: &HashMap<String, String>) -> Result<Option<String>, String> {
+    let Some(rest) = cmd.strip_prefix('@') else {
+        return Ok(None);
+    };
+    if rest.starts_with('@') {
+        return Ok(Some(rest.to_string()));
+    }
+    let (name, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let command = commands.get(name).ok_or_else(|| name.to_string())?;
+    let args = args.trim_start();
+    if args.is_empty() {
+        Ok(Some(command.clone()))
+    } else {
+        Ok(Some(format!("{command} {args}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands() -> HashMap<String, String> {
+        HashMap::from([(
+            "editor".to_string(),
+            "open -a 'Visual Studio Code'".to_string(),
+        )])
+    }
+
+    #[test]
+    fn test_resolve() {
+        let commands = commands();
+        assert_eq!(resolve("ls", &commands), Ok(None));
+        assert_eq!(
+            resolve("@editor", &commands),
+            Ok(Some("open -a 'Visual Studio Code'".to_string()))
+        );
+        assert_eq!(
+            resolve("@editor  ~/notes.md", &commands),
+            Ok(Some("open -a 'Visual Studio Code' ~/notes.md".to_string()))
+        );
+        assert_eq!(resolve("@@home", &commands), Ok(Some("@home".to_string())));
+        assert_eq!(resolve("@nope x", &commands), Err("nope".to_string()));
+    }
+
+    #[test]
+    fn test_resolve_aliases() {
+        let mut mode = Mode::from_ron(
+            r#"[
+            ("e", "Edit", shell("@editor")),
+            ("m", "Menu", mode([
+                ("n", "Notes", shell("@editor ~/notes.md")),
+                ("b", "Bad", shell("@browser")),
+            ])),
+            ("x", "Also bad", shell("@missing")),
+        ]"#,
+        )
+        .unwrap();
+        let errors = mode.resolve_aliases(&commands()).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                ValidationError::UnknownAlias {
+                    path: vec!["Menu".to_string()],
+                    name: "browser".to_string(),
+                },
+                ValidationError::UnknownAlias {
+                    path: vec![],
+                    name: "missing".to_string(),
+                },
+            ]
+        );
+
+        let mut mode = Mode::from_ron(
+            r#"[
+            ("e", "Edit", shell("@editor")),
+            ("m", "Menu", mode([
+                ("n", "Notes", shell("@editor ~/notes.md")),
+            ]), (on_exit: "@editor b")),
+            ("t", "Toggle", toggle(on: "@editor a", off: "true")),
+        ]"#,
+        )
+        .unwrap();
+        mode.resolve_aliases(&commands()).unwrap();
+        let expected = Mode::from_ron(
+            r#"[
+            ("e", "Edit", shell("open -a 'Visual Studio Code'")),
+            ("m", "Menu", mode([
+                ("n", "Notes", shell("open -a 'Visual Studio Code' ~/notes.md")),
+            ]), (on_exit: "open -a 'Visual Studio Code' b")),
+            ("t", "Toggle", toggle(on: "open -a 'Visual Studio Code' a", off: "true")),
+        ]"#,
+        )
+        .unwrap();
+        assert_eq!(mode, expected);
+    }
+}
@@ -0,0 +1,76 @@
+//! Configuration file formats.
+//!
+//! RON is the native format, but TOML, YAML and JSON are also accepted. The
+//! structure is the same in every format: a binding is a sequence of key,
+//! description, action and optional attributes. Actions with arguments are
+//! written as `{ shell = "ls" }` in TOML, `!shell ls` in YAML, and
+//! `{"shell": "ls"}` in JSON.
+
+use serde::de::DeserializeOwned;
+use std::path::Path;
+
+use crate::mode::ron_options;
+
+/// A configuration file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Ron,
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl Format {
+    /// Detect the format from a path's extension
+    ///
+    /// Paths with no extension or an unknown one are treated as RON.
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        let ext = path
+            .as_ref()
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_lowercase);
+        match ext.as_deref() {
+            Some("toml") => Format::Toml,
+            Some("yaml" | "yml") => Format::Yaml,
+            Some("json") => Format::Json,
+            _ => Format::Ron,
+        }
+    }
+
+    /// Deserialize a value from a string in this format
+    pub fn parse<T: DeserializeOwned>(self, s: &str) -> Result<T, String> {
+        match self {
+            Format::Ron => ron_options().from_str(s).map_err(|e| e.to_string()),
+            Format::Toml => toml::from_str(s).map_err(|e| e.to_string()),
+            Format::Yaml => serde_yaml::from_str(s).map_err(|e| e.to_string()),
+            Format::Json => serde_json::from_str(s).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Format::Ron => "RON",
+            Format::Toml => "TOML",
+            Format::Yaml => "YAML",
+            Format::Json => "JSON",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(Format::from_path("a/config.toml"), Format::Toml);
+        assert_eq!(Format::from_path("config.YAML"), Format::Yaml);
+        assert_eq!(Format::from_path("config.yml"), Format::Yaml);
+        assert_eq!(Format::from_path("config.json"), Format::Json);
+        assert_eq!(Format::from_path("config.ron"), Format::Ron);
+        assert_eq!(Format::from_path(".hotki"), Format::Ron);
+    }
+}
@@ -0,0 +1,260 @@
+//! Running keymode inside the hotkey server.
+//!
+//! [`Hosted`] adapts a [`State`] to the server's [`Handler`] interface, so
+//! the server owns the mode stack and performs rebinds itself. Clients then
+//! only render the `ModeChanged`, `ActionExecuted` and `Message` events it
+//! sends.
+
+use hotkey_manager::{Handler, IPCResponse, Key};
+
+use crate::mode::Mode;
+use crate::state::State;
+
+/// Loads a fresh copy of a hosted config, returning every problem found
+type Loader = Box<dyn FnMut() -> Result<Mode, Vec<String>> + Send>;
+
+/// A keymode state hosted by the hotkey server
+pub struct Hosted {
+    state: State,
+    loader: Option<Loader>,
+}
+
+impl std::fmt::Debug for Hosted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Hosted")
+            .field("state", &self.state)
+            .field("reloadable", &self.loader.is_some())
+            .finish()
+    }
+}
+
+impl Hosted {
+    /// Host a keymode state
+    pub fn new(state: State) -> Self {
+        Self {
+            state,
+            loader: None,
+        }
+    }
+
+    /// Support reloading, using a function that loads and validates the
+    /// config again
+    pub fn with_loader(
+        mut self,
+        loader: impl FnMut() -> Result<Mode, Vec<String>> + Send + 'static,
+    ) -> Self {
+        self.loader = Some(Box::new(loader));
+        self
+    }
+
+    /// The hosted state
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// An event describing the active mode and its visible bindings
+    fn mode_changed(&self) -> IPCResponse {
+        IPCResponse::ModeChanged {
+            modes: self
+                .state
+                .mode_names()
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            keys: self
+                .state
+                .keys()
+                .into_iter()
+                .filter(|(_, _, attrs)| !attrs.hide)
+                .map(|(key, desc, _)| (key.to_string(), desc))
+                .collect(),
+        }
+    }
+
+    /// Warnings reported by commands since the last call
+    fn warnings(&self) -> impl Iterator<Item = IPCResponse> {
+        self.state
+            .take_warnings()
+            .into_iter()
+            .map(|warning| message(warning, true))
+    }
+}
+
+fn message(text: String, warning: bool) -> IPCResponse {
+    IPCResponse::Message { text, warning }
+}
+
+impl Handler for Hosted {
+    fn keys(&self) -> Vec<Key> {
+        self.state.bound_keys()
+    }
+
+    fn handle_key(&mut self, key: &Key) -> Vec<IPCResponse> {
+        // Look up the description first, since the action may change modes
+        let desc = self
+            .state
+            .keys()
+            .into_iter()
+            .find(|(k, _, _)| k == key)
+            .map(|(_, desc, _)| desc);
+        let was_suspended = self.state.is_suspended();
+
+        let mut events = Vec::new();
+        match self.state.handle_key(key) {
+            Ok(handled) => {
+                if let Some(desc) = desc {
+                    events.push(IPCResponse::ActionExecuted {
+                        key: key.clone(),
+                        desc,
+                    });
+                }
+                if !handled.user.is_empty() {
+                    events.push(message(handled.user, false));
+                }
+                if !handled.warn.is_empty() {
+                    events.push(message(handled.warn, true));
+                }
+            }
+            Err(e) => events.push(message(format!("Error handling key: {e}"), true)),
+        }
+        if self.state.is_suspended() != was_suspended {
+            let text = if was_suspended {
+                "Hotkeys resumed"
+            } else {
+                "Hotkeys suspended"
+            };
+            events.push(message(text.to_string(), false));
+        }
+        events.extend(self.warnings());
+        events.push(self.mode_changed());
+        events
+    }
+
+    fn poll(&mut self) -> Vec<IPCResponse> {
+        self.warnings().collect()
+    }
+
+    fn snapshot(&self) -> Vec<IPCResponse> {
+        vec![self.mode_changed()]
+    }
+
+    fn reload(&mut self) -> Result<(), Vec<String>> {
+        let loader = self
+            .loader
+            .as_mut()
+            .ok_or_else(|| vec!["No config loader is set".to_string()])?;
+        let root = loader()?;
+        self.state.set_root(root);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(s: &str) -> Key {
+        Key::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_hosted() {
+        let root = Mode::from_ron(
+            r#"[
+            ("m", "Menu", mode([
+                ("a", "Action", shell("true"), (noexit: true)),
+                ("x", "Hidden", exit, (hide: true)),
+            ])),
+        ]"#,
+        )
+        .unwrap();
+        let mut hosted = Hosted::new(State::new(root));
+        assert_eq!(hosted.keys(), vec![key("m")]);
+        assert!(matches!(
+            hosted.snapshot().as_slice(),
+            [IPCResponse::ModeChanged { modes, keys }]
+                if modes.is_empty() && keys == &[("m".to_string(), "Menu".to_string())]
+        ));
+
+        let events = hosted.handle_key(&key("m"));
+        assert!(matches!(
+            events.as_slice(),
+            [
+                IPCResponse::ActionExecuted { desc, .. },
+                IPCResponse::ModeChanged { modes, keys },
+            ] if desc == "Menu" && modes == &["Menu"] && keys.len() == 1
+        ));
+        assert!(hosted.keys().contains(&key("x")));
+        assert_eq!(hosted.state().depth(), 1);
+
+        // Unbound keys produce no action, but still report the mode
+        let events = hosted.handle_key(&key("z"));
+        assert!(matches!(
+            events.as_slice(),
+            [IPCResponse::ModeChanged { .. }]
+        ));
+        assert!(hosted.reload().is_err());
+
+        // Master toggles report the suspension
+        let root = Mode::from_ron(r#"[("f12", "Games", exit, (master_toggle: true))]"#).unwrap();
+        let mut hosted = Hosted::new(State::new(root));
+        assert!(matches!(
+            hosted.handle_key(&key("f12")).as_slice(),
+            [
+                IPCResponse::ActionExecuted { .. },
+                IPCResponse::Message { text, warning: false },
+                IPCResponse::ModeChanged { .. },
+            ] if text == "Hotkeys suspended"
+        ));
+        assert!(hosted.state().is_suspended());
+    }
+
+    #[test]
+    fn test_hosted_poll() {
+        let root =
+            Mode::from_ron(r#"[("s", "Slow", shell("sleep 5"), (timeout_ms: 20))]"#).unwrap();
+        let mut hosted = Hosted::new(State::new(root));
+        assert!(hosted.poll().is_empty());
+
+        // The timeout is reported without another key being pressed
+        hosted.handle_key(&key("s"));
+        let start = std::time::Instant::now();
+        let events = loop {
+            let events = hosted.poll();
+            if !events.is_empty() || start.elapsed().as_secs() >= 3 {
+                break events;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        };
+        assert!(matches!(
+            events.as_slice(),
+            [IPCResponse::Message { text, warning: true }] if text.contains("timed out")
+        ));
+    }
+
+    #[test]
+    fn test_hosted_reload() {
+        let configs = [
+            r#"[("m", "Menu", mode([("b", "B", exit)]))]"#,
+            r#"[("m", "Menu", mode([("c", "C", exit)]))]"#,
+        ];
+        let mut next = configs.into_iter();
+        let mut hosted =
+            Hosted::new(State::new(Mode::from_ron(configs[0]).unwrap())).with_loader(move || {
+                match next.next() {
+                    Some(config) => Mode::from_ron(config).map_err(|e| vec![e]),
+                    None => Err(vec!["gone".to_string()]),
+                }
+            });
+        hosted.handle_key(&key("m"));
+
+        hosted.reload().unwrap();
+        hosted.reload().unwrap();
+        // The mode path is kept across reloads
+        assert_eq!(hosted.state().mode_names(), vec!["Menu"]);
+        assert!(hosted.keys().contains(&key("c")));
+
+        assert_eq!(hosted.reload(), Err(vec!["gone".to_string()]));
+        assert!(hosted.keys().contains(&key("c")));
+    }
+}
@@ -1,6 +1,27 @@
+mod alias;
+mod format;
+mod host;
 mod mode;
+#[cfg(feature = "schema")]
+mod schema;
 mod shell;
 mod state;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+mod template;
+mod validate;
 
-pub use mode::{Action, Attrs, Mode};
-pub use state::{Handled, State};
+pub use format::Format;
+pub use host::Hosted;
+pub use mode::{Action, ActionKind, Attrs, Mode, Pos, Sort, Visibility, ron_options};
+pub use shell::ShellContext;
+pub use state::{Handled, Section, State, Triggered, sections};
+pub use validate::ValidationError;
+
+#[cfg(feature = "schema")]
+pub use schema::schema_json;
+#[cfg(feature = "schema")]
+pub use schemars;
+
+#[cfg(feature = "proptest")]
+pub use proptest;
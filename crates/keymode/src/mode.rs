@@ -1,19 +1,178 @@
+use crate::format::Format;
+use crate::shell::ShellContext;
+use crate::validate::ValidationError;
 use hotkey_manager::Key;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// Sort policy for displayed keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Sort {
+    /// Keep the order in which keys appear in the config
+    #[default]
+    Config,
+    /// Sort alphabetically by key
+    Key,
+    /// Sort alphabetically by description
+    Desc,
+}
+
+impl std::fmt::Display for Sort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Sort::Config => "config",
+            Sort::Key => "key",
+            Sort::Desc => "desc",
+        })
+    }
+}
+
+impl std::str::FromStr for Sort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "config" => Ok(Sort::Config),
+            "key" => Ok(Sort::Key),
+            "desc" => Ok(Sort::Desc),
+            _ => Err(format!(
+                "Unknown sort policy '{s}', expected one of: config, key, desc"
+            )),
+        }
+    }
+}
+
+/// Screen position of the HUD
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Pos {
+    N,
+    #[default]
+    NE,
+    E,
+    SE,
+    S,
+    SW,
+    W,
+    NW,
+    Center,
+}
+
+/// Whether the HUD is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    /// Show the HUD
+    Shown,
+    /// Keep the HUD hidden
+    Hidden,
+}
 
 /// Attributes for key bindings
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Attrs {
+    /// Stay in the current mode after running a shell command. On a binding
+    /// that enters a mode, the commands in that mode stay in it unless they
+    /// say otherwise.
     #[serde(default)]
     pub noexit: bool,
+    /// Leave this many modes after running a shell command, rather than
+    /// returning to the root. On a `pop` binding, the number of modes to
+    /// pop, and on a binding that enters a mode, the number to leave first,
+    /// so that the new mode replaces them. Takes precedence over `noexit`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub popmode: Option<usize>,
+    /// Make this binding available in all modes entered below this one
     #[serde(default)]
     pub global: bool,
+    /// Bind the key without showing it in the HUD
     #[serde(default)]
     pub hide: bool,
+    /// Make this binding a master switch, which suspends every other hotkey
+    /// until it is pressed again. Its action isn't run.
+    #[serde(default)]
+    pub master_toggle: bool,
+    /// Auto-hide timeout for the HUD while in the mode entered by this
+    /// binding, overriding the global setting. `0` means never auto-hide.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_hide_ms: Option<u64>,
+    /// Show or hide the HUD while in the mode entered by this binding,
+    /// overriding the global setting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hud: Option<Visibility>,
+    /// Screen position of the HUD while in the mode entered by this binding,
+    /// overriding the global setting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hud_pos: Option<Pos>,
+    /// Width of a HUD column in logical pixels while in the mode entered by
+    /// this binding, overriding the global setting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hud_width: Option<f64>,
+    /// Section title used to group this binding when displayed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+    /// Display order of this binding. Lower values are shown first, and
+    /// bindings without an order are treated as `0`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<i32>,
+    /// Sort policy for the keys of the mode entered by this binding,
+    /// overriding the state's default policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<Sort>,
+    /// Working directory for shell commands run by this binding. A leading
+    /// `~` is expanded to the home directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Environment variables for shell commands run by this binding
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+    /// Kill shell commands run by this binding, and any processes they
+    /// started, if they run longer than this many milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    /// Shell command run when the mode entered by this binding is entered,
+    /// with the binding's `cwd`, `env` and `timeout_ms`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_enter: Option<String>,
+    /// Shell command run when the mode entered by this binding is left,
+    /// whether by popping it or by returning to the root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_exit: Option<String>,
+    /// Ignore presses of this binding within this many milliseconds of the
+    /// last time it ran, guarding against accidental double-presses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debounce_ms: Option<u64>,
+    /// An emoji or other short text shown before the description
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// CSS color of the description when displayed in the HUD, such as
+    /// `"orange"` or `"#ff8800"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+impl Attrs {
+    /// The shell context set by this binding's `cwd`, `env` and `timeout_ms`
+    /// attributes
+    pub fn shell_context(&self) -> ShellContext {
+        ShellContext {
+            cwd: self.cwd.clone(),
+            env: self.env.clone(),
+            timeout: self.timeout_ms.map(Duration::from_millis),
+        }
+    }
 }
 
 /// Actions that can be triggered by hotkeys
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "lowercase")]
 pub enum Action {
     /// Execute a shell command
@@ -24,19 +183,90 @@ pub enum Action {
     Pop,
     /// Pop all modes until the root mode is reached
     Exit,
+    /// Alternate between two shell commands, starting with `on`. While on,
+    /// the binding is shown with a check mark. Toggles with the same
+    /// commands share their state.
+    Toggle {
+        /// Command run to switch on
+        on: String,
+        /// Command run to switch off
+        off: String,
+    },
+    /// Show a transient message, such as in the HUD. Placeholders are
+    /// expanded as in shell commands.
+    Message(String),
+}
+
+/// The kind of an [`Action`], without its contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionKind {
+    Shell,
+    Mode,
+    Pop,
+    Exit,
+    Toggle,
+    Message,
 }
 
 impl Action {
+    /// The kind of this action
+    pub fn kind(&self) -> ActionKind {
+        match self {
+            Action::Shell(_) => ActionKind::Shell,
+            Action::Mode(_) => ActionKind::Mode,
+            Action::Pop => ActionKind::Pop,
+            Action::Exit => ActionKind::Exit,
+            Action::Toggle { .. } => ActionKind::Toggle,
+            Action::Message(_) => ActionKind::Message,
+        }
+    }
+
     /// Create a Shell action
     pub fn shell(cmd: impl Into<String>) -> Self {
         Action::Shell(cmd.into())
     }
+
+    /// Create a Message action
+    pub fn message(text: impl Into<String>) -> Self {
+        Action::Message(text.into())
+    }
+
+    /// Create a Toggle action
+    pub fn toggle(on: impl Into<String>, off: impl Into<String>) -> Self {
+        Action::Toggle {
+            on: on.into(),
+            off: off.into(),
+        }
+    }
 }
 
 /// A collection of key bindings with their associated actions and descriptions
-#[derive(Debug, Clone, PartialEq, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct Mode {
+    /// Bindings in the order they appear in the config
     keys: Vec<(Key, String, Action, Attrs)>,
+    /// Position in `keys` of the first binding of each key
+    index: HashMap<Key, usize>,
+    /// Bindings whose key failed to parse, kept so that validation can report
+    /// every problem at once. These are never bound.
+    invalid: Vec<InvalidEntry>,
+}
+
+// The index follows from the bindings, so it isn't compared
+impl PartialEq for Mode {
+    fn eq(&self, other: &Self) -> bool {
+        self.keys == other.keys && self.invalid == other.invalid
+    }
+}
+
+/// A binding whose key string could not be parsed
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct InvalidEntry {
+    pub key: String,
+    pub desc: String,
+    pub action: Action,
+    pub attrs: Attrs,
+    pub error: String,
 }
 
 // Manual Serialize implementation that respects transparent
@@ -46,13 +276,22 @@ impl Serialize for Mode {
         S: serde::Serializer,
     {
         use serde::ser::SerializeSeq;
-        let mut seq = serializer.serialize_seq(Some(self.keys.len()))?;
-        for (key, desc, action, attrs) in &self.keys {
+        let mut seq = serializer.serialize_seq(Some(self.keys.len() + self.invalid.len()))?;
+        let entries = self
+            .keys
+            .iter()
+            .map(|(key, desc, action, attrs)| (key.to_string(), desc, action, attrs))
+            .chain(
+                self.invalid
+                    .iter()
+                    .map(|e| (e.key.clone(), &e.desc, &e.action, &e.attrs)),
+            );
+        for (key, desc, action, attrs) in entries {
             // Serialize as a tuple with key converted to string
             if attrs == &Attrs::default() {
-                seq.serialize_element(&(key.to_string(), desc, action))?;
+                seq.serialize_element(&(key, desc, action))?;
             } else {
-                seq.serialize_element(&(key.to_string(), desc, action, attrs))?;
+                seq.serialize_element(&(key, desc, action, attrs))?;
             }
         }
         seq.end()
@@ -65,49 +304,203 @@ impl<'de> Deserialize<'de> for Mode {
     where
         D: serde::Deserializer<'de>,
     {
-        #[derive(Deserialize)]
-        #[serde(untagged)]
-        enum Entry {
-            Simple(String, String, Action),
-            WithAttrs(String, String, Action, Attrs),
+        // A binding is a (key, description, action) tuple with optional
+        // trailing attributes. We use a tuple visitor rather than an untagged
+        // enum, because untagged enums buffer their input and lose the enum
+        // information RON needs to deserialize actions.
+        struct Entry(String, String, Action, Attrs);
+
+        impl<'de> Deserialize<'de> for Entry {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                struct EntryVisitor;
+
+                impl<'de> serde::de::Visitor<'de> for EntryVisitor {
+                    type Value = Entry;
+
+                    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        f.write_str("a (key, description, action) tuple with optional attributes")
+                    }
+
+                    fn visit_seq<A>(self, mut seq: A) -> Result<Entry, A::Error>
+                    where
+                        A: serde::de::SeqAccess<'de>,
+                    {
+                        use serde::de::Error;
+                        let key = seq
+                            .next_element()?
+                            .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+                        let desc = seq
+                            .next_element()?
+                            .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+                        let action = seq
+                            .next_element()?
+                            .ok_or_else(|| A::Error::invalid_length(2, &self))?;
+                        let attrs = seq.next_element()?.unwrap_or_default();
+                        Ok(Entry(key, desc, action, attrs))
+                    }
+                }
+
+                deserializer.deserialize_tuple(4, EntryVisitor)
+            }
         }
 
         let entries = Vec::<Entry>::deserialize(deserializer)?;
         let mut keys = Vec::new();
-
-        for entry in entries {
-            match entry {
-                Entry::Simple(k, n, a) => match Key::parse(&k) {
-                    Ok(key) => keys.push((key, n, a, Attrs::default())),
-                    Err(e) => {
-                        return Err(serde::de::Error::custom(format!("Invalid key '{k}': {e}")));
-                    }
-                },
-                Entry::WithAttrs(k, n, a, attrs) => match Key::parse(&k) {
-                    Ok(key) => keys.push((key, n, a, attrs)),
-                    Err(e) => {
-                        return Err(serde::de::Error::custom(format!("Invalid key '{k}': {e}")));
-                    }
-                },
+        let mut invalid = Vec::new();
+
+        // Invalid keys don't fail deserialization, so that validation can
+        // report all of them. `Mode::from_ron` rejects modes containing any.
+        for Entry(k, n, a, attrs) in entries {
+            match Key::parse(&k) {
+                Ok(key) => keys.push((key, n, a, attrs)),
+                Err(e) => invalid.push(InvalidEntry {
+                    key: k,
+                    desc: n,
+                    action: a,
+                    attrs,
+                    error: e.to_string(),
+                }),
             }
         }
 
-        Ok(Mode { keys })
+        let mut mode = Mode::from_entries(keys);
+        mode.invalid = invalid;
+        Ok(mode)
     }
 }
 
+/// RON parsing options used for configuration files
+///
+/// Enables the `implicit_some` extension, so optional values such as
+/// `(auto_hide_ms: 0)` don't need to be wrapped in `Some(...)`.
+pub fn ron_options() -> ron::Options {
+    ron::Options::default().with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+}
+
 impl Mode {
     /// Create a Mode from a RON string
+    ///
+    /// Optional attributes can be written without a `Some(...)` wrapper. If
+    /// any keys in the tree are invalid, the error lists all of them.
     pub fn from_ron(ron_str: &str) -> Result<Self, String> {
-        ron::from_str(ron_str).map_err(|e| format!("Failed to parse RON: {e}"))
+        Self::parse(ron_str, Format::Ron)
+    }
+
+    /// Create a Mode from a string in the given format
+    ///
+    /// TOML documents can't have a sequence at the top level, so in TOML the
+    /// bindings are given as a top-level `keys` array.
+    pub fn parse(s: &str, format: Format) -> Result<Self, String> {
+        /// Top-level TOML table holding a mode
+        #[derive(Deserialize)]
+        struct TomlMode {
+            keys: Mode,
+        }
+
+        let mode: Self = match format {
+            Format::Toml => format.parse::<TomlMode>(s).map(|m| m.keys),
+            _ => format.parse(s),
+        }
+        .map_err(|e| format!("Failed to parse {format}: {e}"))?;
+        mode.check_keys()?;
+        Ok(mode)
+    }
+
+    /// Create a Mode from parsed bindings
+    pub(crate) fn from_entries(keys: Vec<(Key, String, Action, Attrs)>) -> Self {
+        let mut mode = Mode::default();
+        for entry in keys {
+            mode.push(entry);
+        }
+        mode
+    }
+
+    /// Add a binding after the others
+    fn push(&mut self, entry: (Key, String, Action, Attrs)) {
+        self.index.entry(entry.0.clone()).or_insert(self.keys.len());
+        self.keys.push(entry);
+    }
+
+    /// Load a Mode from a file, detecting the format from its extension
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        Self::parse(&content, Format::from_path(path))
+    }
+
+    /// Add a hidden binding that exits to the root to every mode below this
+    /// one, so a deep mode can always be left
+    ///
+    /// Modes that already bind the key keep their own binding. The key isn't
+    /// added to this mode itself, since keys at the root are grabbed
+    /// system-wide.
+    pub fn add_panic_key(&mut self, key: &Key) {
+        for (_, _, action, _) in &mut self.keys {
+            if let Action::Mode(child) = action {
+                if child.get_binding(key).is_none() {
+                    child.push((
+                        key.clone(),
+                        "Exit".to_string(),
+                        Action::Exit,
+                        Attrs {
+                            hide: true,
+                            ..Default::default()
+                        },
+                    ));
+                }
+                child.add_panic_key(key);
+            }
+        }
+    }
+
+    /// Keys of the master toggle bindings anywhere in the tree, without
+    /// duplicates
+    pub(crate) fn master_toggle_keys(&self) -> Vec<Key> {
+        let mut keys = Vec::new();
+        self.collect_master_toggle_keys(&mut keys);
+        keys
+    }
+
+    fn collect_master_toggle_keys(&self, keys: &mut Vec<Key>) {
+        for (key, _, action, attrs) in &self.keys {
+            if attrs.master_toggle && !keys.contains(key) {
+                keys.push(key.clone());
+            }
+            if let Action::Mode(child) = action {
+                child.collect_master_toggle_keys(keys);
+            }
+        }
+    }
+
+    /// Fail with a list of every invalid key in the tree
+    pub fn check_keys(&self) -> Result<(), String> {
+        let invalid: Vec<String> = self
+            .validate()
+            .into_iter()
+            .filter(ValidationError::is_invalid_key)
+            .map(|e| e.to_string())
+            .collect();
+        if invalid.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Invalid keys:\n  {}", invalid.join("\n  ")))
+        }
     }
 
     /// Get the action and attributes associated with a key
     pub fn get_with_attrs(&self, key: &Key) -> Option<(&Action, &Attrs)> {
-        self.keys
-            .iter()
-            .find(|(k, _, _, _)| k == key)
-            .map(|(_, _, action, attrs)| (action, attrs))
+        self.get_binding(key)
+            .map(|(_, action, attrs)| (action, attrs))
+    }
+
+    /// Get the description, action and attributes associated with a key
+    pub fn get_binding(&self, key: &Key) -> Option<(&str, &Action, &Attrs)> {
+        let (_, desc, action, attrs) = &self.keys[*self.index.get(key)?];
+        Some((desc.as_str(), action, attrs))
     }
 
     /// Get all keys in this mode
@@ -124,6 +517,26 @@ impl Mode {
         self.keys.iter().map(|(k, _, _, _)| k)
     }
 
+    /// Iterate over the bindings in this mode
+    pub(crate) fn entries(&self) -> impl Iterator<Item = &(Key, String, Action, Attrs)> + '_ {
+        self.keys.iter()
+    }
+
+    /// Iterate mutably over the bindings in this mode. Keys can't be
+    /// changed, since they are indexed.
+    pub(crate) fn entries_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (&Key, &mut String, &mut Action, &mut Attrs)> + '_ {
+        self.keys
+            .iter_mut()
+            .map(|(key, desc, action, attrs)| (&*key, desc, action, attrs))
+    }
+
+    /// Iterate over the bindings in this mode whose key failed to parse
+    pub(crate) fn invalid_entries(&self) -> impl Iterator<Item = &InvalidEntry> + '_ {
+        self.invalid.iter()
+    }
+
     /// Get all keys with their names and attributes
     pub fn keys_with_attrs(&self) -> impl Iterator<Item = (Key, String, Attrs)> + '_ {
         self.keys
@@ -159,6 +572,19 @@ mod tests {
             matches!(mode.get_with_attrs(&key("s")), Some((Action::Shell(cmd), _)) if cmd == "echo hello")
         );
         assert_eq!(mode.get_with_attrs(&key("x")), None);
+
+        // Keys are looked up by chord, and the first binding of a key wins
+        let mode = Mode::from_ron(
+            r#"[
+            ("ctrl+a", "First", exit),
+            ("control+a", "Second", pop),
+        ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            mode.get_binding(&key("ctrl+a")).map(|(desc, _, _)| desc),
+            Some("First")
+        );
     }
 
     #[test]
@@ -192,6 +618,107 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_sort_roundtrip() {
+        for sort in [Sort::Config, Sort::Key, Sort::Desc] {
+            assert_eq!(sort.to_string().parse::<Sort>(), Ok(sort));
+        }
+        assert!("size".parse::<Sort>().is_err());
+    }
+
+    #[test]
+    fn test_icon_color() {
+        let mode = Mode::from_ron(
+            r#"[
+            ("b", "Browser", shell("open -a Safari"), (icon: "🌐", color: "steelblue")),
+            ("t", "Terminal", shell("open -a Terminal")),
+        ]"#,
+        )
+        .unwrap();
+        let (_, attrs) = mode.get_with_attrs(&key("b")).unwrap();
+        assert_eq!(attrs.icon.as_deref(), Some("🌐"));
+        assert_eq!(attrs.color.as_deref(), Some("steelblue"));
+        let (_, attrs) = mode.get_with_attrs(&key("t")).unwrap();
+        assert!(attrs.icon.is_none() && attrs.color.is_none());
+    }
+
+    #[test]
+    fn test_add_panic_key() {
+        let mut mode = Mode::from_ron(
+            r#"[
+            ("a", "Apps", mode([
+                ("w", "Windows", mode([])),
+                ("escape", "Back", pop),
+            ])),
+            ("s", "Shell", shell("true")),
+        ]"#,
+        )
+        .unwrap();
+        let esc = key("escape");
+        mode.add_panic_key(&esc);
+        assert!(mode.get_binding(&esc).is_none());
+
+        let Some((_, Action::Mode(apps), _)) = mode.get_binding(&key("a")) else {
+            panic!("expected a mode");
+        };
+        assert!(matches!(
+            apps.get_binding(&esc),
+            Some(("Back", Action::Pop, _))
+        ));
+
+        let Some((_, Action::Mode(windows), _)) = apps.get_binding(&key("w")) else {
+            panic!("expected a mode");
+        };
+        let (desc, action, attrs) = windows.get_binding(&esc).unwrap();
+        assert_eq!((desc, action), ("Exit", &Action::Exit));
+        assert!(attrs.hide);
+    }
+
+    #[test]
+    fn test_parse_formats() {
+        let expected = Mode::from_ron(
+            r#"[
+            ("q", "Exit", exit),
+            ("s", "Shell", shell("ls"), (noexit: true)),
+            ("m", "Menu", mode([
+                ("p", "Back", pop),
+            ])),
+        ]"#,
+        )
+        .unwrap();
+
+        let toml = r#"
+            keys = [
+                ["q", "Exit", "exit"],
+                ["s", "Shell", { shell = "ls" }, { noexit = true }],
+                ["m", "Menu", { mode = [
+                    ["p", "Back", "pop"],
+                ] }],
+            ]
+        "#;
+        assert_eq!(Mode::parse(toml, Format::Toml).unwrap(), expected);
+
+        let yaml = r#"
+- [q, Exit, exit]
+- [s, Shell, !shell ls, {noexit: true}]
+- - m
+  - Menu
+  - !mode
+    - [p, Back, pop]
+"#;
+        assert_eq!(Mode::parse(yaml, Format::Yaml).unwrap(), expected);
+
+        let json = r#"[
+            ["q", "Exit", "exit"],
+            ["s", "Shell", {"shell": "ls"}, {"noexit": true}],
+            ["m", "Menu", {"mode": [["p", "Back", "pop"]]}]
+        ]"#;
+        assert_eq!(Mode::parse(json, Format::Json).unwrap(), expected);
+
+        let err = Mode::parse("[[\"q\"]]", Format::Json).unwrap_err();
+        assert!(err.starts_with("Failed to parse JSON"));
+    }
+
     #[test]
     fn test_from_ron_error() {
         let invalid_ron = r#"[
@@ -301,6 +828,7 @@ mod tests {
                 ),
                 (key("p"), "Back".to_string(), Action::Pop, Attrs::default()),
             ],
+            ..Default::default()
         };
 
         let git_mode = Mode {
@@ -317,8 +845,7 @@ mod tests {
                     Action::shell("git log"),
                     Attrs {
                         noexit: true,
-                        global: false,
-                        hide: false,
+                        ..Default::default()
                     },
                 ),
                 (
@@ -335,6 +862,7 @@ mod tests {
                 ),
                 (key("q"), "Back".to_string(), Action::Pop, Attrs::default()),
             ],
+            ..Default::default()
         };
 
         let files_mode = Mode {
@@ -351,12 +879,12 @@ mod tests {
                     Action::shell("tree"),
                     Attrs {
                         noexit: true,
-                        global: false,
-                        hide: false,
+                        ..Default::default()
                     },
                 ),
                 (key("q"), "Back".to_string(), Action::Pop, Attrs::default()),
             ],
+            ..Default::default()
         };
 
         let expected = Mode {
@@ -381,6 +909,7 @@ mod tests {
                     Attrs::default(),
                 ),
             ],
+            ..Default::default()
         };
 
         // Deserialize from RON text
@@ -0,0 +1,75 @@
+//! JSON Schema generation for configuration files.
+//!
+//! Schemas let editors offer completion and validation for configs. They
+//! describe the JSON form of the config, which the TOML and YAML forms
+//! mirror.
+
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
+use std::borrow::Cow;
+
+use crate::mode::{Action, Attrs, Mode};
+
+impl JsonSchema for Mode {
+    fn schema_name() -> Cow<'static, str> {
+        "Mode".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let action = generator.subschema_for::<Action>();
+        let attrs = generator.subschema_for::<Attrs>();
+        json_schema!({
+            "description": "A list of key bindings",
+            "type": "array",
+            "items": {
+                "description": "A binding: key, description and action, with optional attributes",
+                "type": "array",
+                "prefixItems": [
+                    {
+                        "description": "Key combination, such as \"ctrl+shift+a\"",
+                        "type": "string"
+                    },
+                    {
+                        "description": "Description shown in the HUD",
+                        "type": "string"
+                    },
+                    action,
+                    attrs
+                ],
+                "items": false,
+                "minItems": 3,
+                "maxItems": 4
+            }
+        })
+    }
+}
+
+/// Generate a pretty-printed JSON Schema for a config type
+pub fn schema_json<T: JsonSchema>() -> String {
+    let schema = schemars::schema_for!(T);
+    serde_json::to_string_pretty(&schema).expect("schemas always serialize")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_schema() {
+        let schema: serde_json::Value = serde_json::from_str(&schema_json::<Mode>()).unwrap();
+        assert_eq!(schema["title"], "Mode");
+        assert_eq!(schema["type"], "array");
+        assert_eq!(schema["items"]["minItems"], 3);
+
+        let defs = &schema["$defs"];
+        assert!(defs["Action"].is_object());
+        assert_eq!(
+            defs["Attrs"]["properties"]["noexit"]["type"],
+            serde_json::json!("boolean")
+        );
+
+        // Nested modes refer back to the root schema
+        let action = defs["Action"].to_string();
+        assert!(action.contains("\"shell\""));
+        assert!(action.contains("\"mode\""));
+    }
+}
@@ -1,6 +1,265 @@
-use tracing::info;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
 
-/// Execute a shell command
-pub fn execute_shell(command: &str) {
+/// How often a command with a timeout is checked for completion
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The context a shell command runs in
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShellContext {
+    /// Working directory. A leading `~` is expanded to the home directory.
+    /// Inherited from the calling process if not set.
+    pub cwd: Option<String>,
+    /// Variables added to the inherited environment
+    pub env: HashMap<String, String>,
+    /// Time after which the command and any processes it started are killed
+    pub timeout: Option<Duration>,
+}
+
+impl ShellContext {
+    /// Layer another context over this one
+    ///
+    /// The other context's working directory and timeout take precedence if
+    /// set, and its environment variables override ours.
+    pub fn merge(&self, other: &ShellContext) -> ShellContext {
+        let mut env = self.env.clone();
+        env.extend(other.env.iter().map(|(k, v)| (k.clone(), v.clone())));
+        ShellContext {
+            cwd: other.cwd.clone().or_else(|| self.cwd.clone()),
+            env,
+            timeout: other.timeout.or(self.timeout),
+        }
+    }
+
+    /// The working directory with `~` expanded
+    fn dir(&self) -> Option<PathBuf> {
+        let cwd = self.cwd.as_deref()?;
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        match (cwd.strip_prefix('~'), home) {
+            (Some(""), Some(home)) => Some(home),
+            (Some(rest), Some(home)) if rest.starts_with('/') => {
+                Some(home.join(rest.trim_start_matches('/')))
+            }
+            _ => Some(PathBuf::from(cwd)),
+        }
+    }
+}
+
+/// Execute a shell command with `sh -c`, without waiting for it to finish
+///
+/// The command runs in its own process group. If it is killed for exceeding
+/// the context's timeout, a message is sent to `warnings`. With `output`, the
+/// lines the command writes to stdout and stderr are sent there instead of
+/// going to our own.
+pub fn execute_shell(
+    command: &str,
+    context: &ShellContext,
+    warnings: &Sender<String>,
+    output: Option<&Sender<String>>,
+) -> Result<(), String> {
     info!("Executing shell command: {}", command);
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(command)
+        .envs(&context.env)
+        .stdin(Stdio::null())
+        .process_group(0);
+    if let Some(dir) = context.dir() {
+        cmd.current_dir(dir);
+    }
+    if output.is_some() {
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to run '{command}': {e}"))?;
+    if let Some(output) = output {
+        if let Some(stdout) = child.stdout.take() {
+            forward_lines(stdout, output.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_lines(stderr, output.clone());
+        }
+    }
+    // Reap the child in the background so it doesn't linger as a zombie
+    let command = command.to_string();
+    let timeout = context.timeout;
+    let warnings = warnings.clone();
+    std::thread::spawn(move || wait(child, &command, timeout, &warnings));
+    Ok(())
+}
+
+/// Send each line read from a command's output in the background
+fn forward_lines(stream: impl Read + Send + 'static, output: Sender<String>) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else { break };
+            if output.send(line).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Wait for a command to finish, killing it if it runs past the timeout
+fn wait(mut child: Child, command: &str, timeout: Option<Duration>, warnings: &Sender<String>) {
+    let status = match timeout {
+        None => child.wait(),
+        Some(timeout) => {
+            let deadline = Instant::now() + timeout;
+            loop {
+                match child.try_wait() {
+                    Ok(Some(status)) => break Ok(status),
+                    Ok(None) if Instant::now() >= deadline => {
+                        kill_group(&mut child);
+                        let _ = child.wait();
+                        let msg = format!(
+                            "Command '{command}' timed out after {}ms and was killed",
+                            timeout.as_millis()
+                        );
+                        warn!("{msg}");
+                        let _ = warnings.send(msg);
+                        return;
+                    }
+                    Ok(None) => std::thread::sleep(POLL_INTERVAL),
+                    Err(e) => break Err(e),
+                }
+            }
+        }
+    };
+    match status {
+        Ok(status) if status.success() => debug!("Command '{command}' finished"),
+        Ok(status) => warn!("Command '{command}' exited with {status}"),
+        Err(e) => warn!("Failed to wait for '{command}': {e}"),
+    }
+}
+
+/// Kill a child and every process in its group
+fn kill_group(child: &mut Child) {
+    let killed = libc::pid_t::try_from(child.id())
+        // SAFETY: killpg has no memory safety requirements
+        .is_ok_and(|group| unsafe { libc::killpg(group, libc::SIGKILL) } == 0);
+    if !killed {
+        let _ = child.kill();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn test_merge() {
+        let global = ShellContext {
+            cwd: Some("/tmp".to_string()),
+            env: HashMap::from([
+                ("A".to_string(), "1".to_string()),
+                ("B".to_string(), "2".to_string()),
+            ]),
+            timeout: None,
+        };
+        let binding = ShellContext {
+            env: HashMap::from([("B".to_string(), "3".to_string())]),
+            timeout: Some(Duration::from_secs(1)),
+            ..Default::default()
+        };
+        let merged = global.merge(&binding);
+        assert_eq!(merged.cwd.as_deref(), Some("/tmp"));
+        assert_eq!(merged.env["A"], "1");
+        assert_eq!(merged.env["B"], "3");
+        assert_eq!(merged.timeout, Some(Duration::from_secs(1)));
+
+        let binding = ShellContext {
+            cwd: Some("~/src".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(global.merge(&binding).cwd.as_deref(), Some("~/src"));
+    }
+
+    #[test]
+    fn test_dir() {
+        let home = PathBuf::from(std::env::var_os("HOME").unwrap());
+        let dir = |cwd: &str| {
+            ShellContext {
+                cwd: Some(cwd.to_string()),
+                ..Default::default()
+            }
+            .dir()
+        };
+        assert_eq!(dir("~"), Some(home.clone()));
+        assert_eq!(dir("~/projects"), Some(home.join("projects")));
+        assert_eq!(dir("~other"), Some(PathBuf::from("~other")));
+        assert_eq!(dir("/tmp"), Some(PathBuf::from("/tmp")));
+        assert_eq!(ShellContext::default().dir(), None);
+    }
+
+    #[test]
+    fn test_execute_shell() {
+        let dir = std::env::temp_dir().join(format!("keymode-shell-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let context = ShellContext {
+            cwd: Some(dir.to_string_lossy().into_owned()),
+            env: HashMap::from([("KEYMODE_TEST".to_string(), "hello".to_string())]),
+            timeout: None,
+        };
+        let (tx, _rx) = channel();
+        execute_shell("echo $KEYMODE_TEST > out.txt", &context, &tx, None).unwrap();
+        let out = dir.join("out.txt");
+        for _ in 0..100 {
+            if std::fs::read_to_string(&out).is_ok_and(|s| s == "hello\n") {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "hello\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let missing = ShellContext {
+            cwd: Some("/nonexistent/keymode".to_string()),
+            ..Default::default()
+        };
+        assert!(execute_shell("true", &missing, &tx, None).is_err());
+
+        // Captured output arrives line by line, from stdout and stderr
+        let (out_tx, out_rx) = channel();
+        execute_shell(
+            "echo one; echo two >&2",
+            &ShellContext::default(),
+            &tx,
+            Some(&out_tx),
+        )
+        .unwrap();
+        let mut lines: Vec<String> = (0..2)
+            .map(|_| out_rx.recv_timeout(Duration::from_secs(3)).unwrap())
+            .collect();
+        lines.sort();
+        assert_eq!(lines, vec!["one", "two"]);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let (tx, rx) = channel();
+        let context = ShellContext {
+            timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let start = Instant::now();
+        execute_shell("sleep 5 & wait", &context, &tx, None).unwrap();
+        let msg = rx.recv_timeout(Duration::from_secs(3)).unwrap();
+        assert!(start.elapsed() < Duration::from_secs(3));
+        assert_eq!(
+            msg,
+            "Command 'sleep 5 & wait' timed out after 50ms and was killed"
+        );
+
+        execute_shell("true", &context, &tx, None).unwrap();
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+    }
 }
@@ -1,6 +1,17 @@
-use crate::mode::{Action, Attrs, Mode};
-use crate::shell::execute_shell;
+use crate::mode::{Action, ActionKind, Attrs, Mode, Sort};
+use crate::shell::{ShellContext, execute_shell};
+use crate::template::{expand, references};
 use hotkey_manager::Key;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::OnceLock;
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::time::{Duration, Instant};
+
+/// Number of executed actions remembered by [`State::history`]
+const HISTORY_LEN: usize = 10;
+
+/// Largest accepted count prefix
+const MAX_COUNT: u32 = 999;
 
 /// Result of handling a key press
 #[derive(Debug, Default)]
@@ -9,6 +20,37 @@ pub struct Handled {
     pub user: String,
     /// Warning message
     pub warn: String,
+    /// The binding whose action ran, if any
+    pub triggered: Option<Triggered>,
+}
+
+/// A binding whose action ran in response to a key press
+#[derive(Debug, Clone, PartialEq)]
+pub struct Triggered {
+    /// The key that was pressed
+    pub key: Key,
+    /// The binding's description
+    pub desc: String,
+    /// The kind of action that ran
+    pub action: ActionKind,
+    /// Names of the modes the binding ran in, from outermost to current
+    pub path: Vec<String>,
+    /// Names of the modes active once the action had run, as returned by
+    /// [`State::mode_names`]
+    pub mode_path: Vec<String>,
+}
+
+impl Triggered {
+    /// The names of the modes the binding ran in followed by its
+    /// description, such as `Git ▸ Status`
+    pub fn breadcrumb(&self) -> String {
+        self.path
+            .iter()
+            .chain(std::iter::once(&self.desc))
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" ▸ ")
+    }
 }
 
 impl Handled {
@@ -18,11 +60,56 @@ impl Handled {
     }
 }
 
+/// A mode on the stack, along with the key, description and attributes of
+/// the binding that entered it
+#[derive(Debug)]
+struct Frame {
+    key: Key,
+    name: String,
+    mode: Mode,
+    attrs: Attrs,
+}
+
 /// Manages a stack of modes for hierarchical key binding navigation
 #[derive(Debug)]
 pub struct State {
     root: Mode,
-    mode_stack: Vec<Mode>,
+    mode_stack: Vec<Frame>,
+    /// Default sort policy for displayed keys
+    sort: Sort,
+    /// Descriptions of recently executed shell actions, newest last
+    history: VecDeque<String>,
+    /// Variables available to shell command placeholders
+    vars: HashMap<String, String>,
+    /// Toggles that are currently on, identified by their (on, off) commands
+    toggles: HashSet<(String, String)>,
+    /// Count typed before a binding, if any
+    count: Option<u32>,
+    /// Default context for shell commands, which bindings can override
+    shell: ShellContext,
+    /// Warnings from shell commands that are still running when an action is
+    /// handled, such as timeouts
+    warnings: (Sender<String>, Receiver<String>),
+    /// Lines written by shell commands, if their output is captured
+    output: Option<(Sender<String>, Receiver<String>)>,
+    /// Key that activates the root mode. If set, only this key is bound
+    /// until it is pressed.
+    activation: Option<Key>,
+    /// Whether the root mode has been activated with the activation key
+    active: bool,
+    /// Grab only the keys that enter a mode while at the inactive root
+    entries_only: bool,
+    /// When debounced bindings last ran, identified by mode path and key
+    last_run: HashMap<(Vec<String>, Key), Instant>,
+    /// Key that starts filtering the current mode's keys by description
+    filter_key: Option<Key>,
+    /// The filter typed so far, if filtering
+    filter: Option<String>,
+    /// Whether a master toggle has suspended every other binding
+    suspended: bool,
+    /// The current mode's keys, ignoring the filter, until the mode or a
+    /// toggle changes
+    key_cache: OnceLock<Vec<(Key, String, Attrs)>>,
 }
 
 impl State {
@@ -31,63 +118,403 @@ impl State {
         Self {
             root,
             mode_stack: Vec::new(),
+            sort: Sort::default(),
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            vars: HashMap::new(),
+            toggles: HashSet::new(),
+            count: None,
+            shell: ShellContext::default(),
+            warnings: channel(),
+            output: None,
+            activation: None,
+            active: false,
+            entries_only: false,
+            last_run: HashMap::new(),
+            filter_key: None,
+            filter: None,
+            suspended: false,
+            key_cache: OnceLock::new(),
         }
     }
 
+    /// Add variables for expanding `{name}` placeholders in shell commands
+    ///
+    /// The built-ins `{key}` (the key that was pressed), `{mode}` (the name
+    /// of the current mode, empty at the root) and `{count}` (the count
+    /// prefix, 1 if none was typed) take precedence over these.
+    pub fn with_vars(mut self, vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.vars.extend(vars);
+        self
+    }
+
+    /// Add a single placeholder variable
+    pub fn with_var(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.vars.insert(name.into(), value.into());
+        self
+    }
+
+    /// Require an activation key to enter the root mode
+    ///
+    /// Until the key is pressed, [`State::bound_keys`] contains only the
+    /// activation key. Pressing it again, or leaving the root mode, returns
+    /// to the inactive state.
+    pub fn with_activation(mut self, key: Key) -> Self {
+        self.activation = Some(key);
+        self
+    }
+
+    /// Grab only the root keys that enter a mode while at the root
+    ///
+    /// Other root bindings are then only available after activating the root
+    /// mode with the activation key, if one is set. This keeps the number of
+    /// global hotkeys registered with the OS small.
+    pub fn with_entries_only(mut self, entries_only: bool) -> Self {
+        self.entries_only = entries_only;
+        self
+    }
+
+    /// Set the default working directory and environment for shell commands
+    ///
+    /// Bindings can override these with their `cwd` and `env` attributes.
+    pub fn with_shell_context(mut self, context: ShellContext) -> Self {
+        self.shell = context;
+        self
+    }
+
+    /// Set a key that starts filtering the keys of the active mode
+    ///
+    /// While filtering, unmodified letters, digits and space are typed into
+    /// the filter instead of triggering bindings, and [`State::keys`] only
+    /// returns bindings whose description fuzzy-matches it. Backspace deletes
+    /// a character, escape stops filtering, and return triggers the first
+    /// visible match. Other bound keys, such as those with modifiers, stay
+    /// triggerable.
+    pub fn with_filter_key(mut self, key: Key) -> Self {
+        self.filter_key = Some(key);
+        self
+    }
+
+    /// Capture the output of shell commands instead of letting it go to our
+    /// own stdout and stderr
+    ///
+    /// Captured lines are retrieved with [`State::take_output`]. This keeps
+    /// commands from writing over a terminal interface.
+    pub fn with_captured_output(mut self) -> Self {
+        self.output = Some(channel());
+        self
+    }
+
+    /// Set the default sort policy for keys returned by [`State::keys`]
+    ///
+    /// Modes can override this with the `sort` attribute on the binding that
+    /// enters them.
+    pub fn with_sort(mut self, sort: Sort) -> Self {
+        self.sort = sort;
+        self.key_cache.take();
+        self
+    }
+
     /// Process a key press and handle the action internally
     /// Returns a Result containing information about the handled action
     pub fn handle_key(&mut self, key: &Key) -> Result<Handled, String> {
+        if self.suspended {
+            if self.root.master_toggle_keys().contains(key) {
+                self.suspended = false;
+            }
+            return Ok(Handled::new());
+        }
+        if self.activation.as_ref() == Some(key) {
+            if self.is_active() {
+                self.reset();
+            } else {
+                self.active = true;
+            }
+            return Ok(Handled::new());
+        }
+        if self.filter.is_some() {
+            if let Some(handled) = self.handle_filter_key(key) {
+                return handled;
+            }
+        } else if self.is_active() && self.filter_key.as_ref() == Some(key) {
+            self.filter = Some(String::new());
+            self.count = None;
+            return Ok(Handled::new());
+        }
+        if let Some((desc, action, attrs)) = self.binding(key) {
+            if attrs.master_toggle {
+                self.set_suspended(true);
+                return Ok(Handled::new());
+            }
+            if let Some(ms) = attrs.debounce_ms
+                && self.debounced(key, Duration::from_millis(ms))
+            {
+                self.count = None;
+                return Ok(Handled::new());
+            }
+            let path = self.mode_names().into_iter().map(String::from).collect();
+            let mut handled = self.execute_action(key, &desc, &action, &attrs)?;
+            handled.triggered = Some(Triggered {
+                key: key.clone(),
+                desc,
+                action: action.kind(),
+                path,
+                mode_path: self.mode_names().into_iter().map(String::from).collect(),
+            });
+            return Ok(handled);
+        }
+
+        // Unbound digits inside a mode accumulate a count prefix. A leading
+        // zero is ignored.
+        if self.is_active()
+            && let Some(digit) = count_digit(key)
+            && (digit > 0 || self.count.is_some())
+        {
+            self.count = Some((self.count.unwrap_or(0) * 10 + digit).min(MAX_COUNT));
+            return Ok(Handled::new());
+        }
+
+        // Key not found
+        Ok(Handled::new())
+    }
+
+    /// Handle a key press while filtering
+    ///
+    /// Returns `None` if the key isn't used by the filter and should be
+    /// handled as a binding.
+    fn handle_filter_key(&mut self, key: &Key) -> Option<Result<Handled, String>> {
+        let filter = self.filter.as_mut()?;
+        match key.to_string().as_str() {
+            "escape" => self.filter = None,
+            "backspace" => {
+                if filter.pop().is_none() {
+                    self.filter = None;
+                }
+            }
+            "enter" => {
+                let first = self.keys().into_iter().find(|(_, _, attrs)| !attrs.hide);
+                self.filter = None;
+                if let Some((key, _, _)) = first {
+                    return Some(self.handle_key(&key));
+                }
+            }
+            _ => filter.push(filter_char(key)?),
+        }
+        Some(Ok(Handled::new()))
+    }
+
+    /// Whether a press of a debounced binding comes too soon after the last
+    /// time it ran. If not, the press is recorded as the last run.
+    fn debounced(&mut self, key: &Key, window: Duration) -> bool {
+        let id = (self.mode_path(), key.clone());
+        let now = Instant::now();
+        if let Some(last) = self.last_run.get(&id)
+            && now.duration_since(*last) < window
+        {
+            return true;
+        }
+        self.last_run.insert(id, now);
+        false
+    }
+
+    /// Find the binding for a key in the current mode, including global
+    /// bindings from enclosing modes
+    fn binding(&self, key: &Key) -> Option<(String, Action, Attrs)> {
         // First try to find key in current mode
-        let current_mode = if let Some(mode) = self.mode_stack.last() {
-            mode
+        let current_mode = if let Some(frame) = self.mode_stack.last() {
+            &frame.mode
         } else {
             &self.root
         };
 
-        if let Some((action, attrs)) = current_mode.get_with_attrs(key) {
-            let action = action.clone();
-            let attrs = attrs.clone();
-            return self.execute_action(&action, &attrs);
+        if let Some((desc, action, attrs)) = current_mode.get_binding(key) {
+            return Some((desc.to_string(), action.clone(), attrs.clone()));
         }
 
         // If not found, check global keys from parent modes (in reverse order, from root up)
         // Check root first
-        if let Some((action, attrs)) = self.root.get_with_attrs(key)
+        if let Some((desc, action, attrs)) = self.root.get_binding(key)
             && attrs.global
             && !self.mode_stack.is_empty()
         {
-            let action = action.clone();
-            let attrs = attrs.clone();
-            return self.execute_action(&action, &attrs);
+            return Some((desc.to_string(), action.clone(), attrs.clone()));
         }
 
         // Check each mode in the stack (excluding the last one which was already checked)
         let stack_len = self.mode_stack.len();
         if stack_len > 1 {
             for i in 0..stack_len - 1 {
-                if let Some((action, attrs)) = self.mode_stack[i].get_with_attrs(key)
+                if let Some((desc, action, attrs)) = self.mode_stack[i].mode.get_binding(key)
                     && attrs.global
                 {
-                    let action = action.clone();
-                    let attrs = attrs.clone();
-                    return self.execute_action(&action, &attrs);
+                    return Some((desc.to_string(), action.clone(), attrs.clone()));
                 }
             }
         }
+        None
+    }
 
-        // Key not found
-        Ok(Handled::new())
+    /// Get the keys of the bindings that entered the active modes, from
+    /// outermost to current
+    ///
+    /// The path can be saved and passed to [`State::restore_path`] to return
+    /// to the same mode later, for example after a restart.
+    pub fn mode_path(&self) -> Vec<String> {
+        self.mode_stack
+            .iter()
+            .map(|frame| frame.key.to_string())
+            .collect()
+    }
+
+    /// Reset to the root mode, then enter the modes along a path returned by
+    /// [`State::mode_path`]
+    ///
+    /// Stops at the first key that no longer enters a mode, so a path saved
+    /// with an older config restores as much as still applies. Returns the
+    /// resulting depth.
+    /// Modes are restored as they were, so their `on_enter` and `on_exit`
+    /// hooks don't run.
+    pub fn restore_path(&mut self, path: &[impl AsRef<str>]) -> usize {
+        self.clear();
+        for key in path {
+            let Ok(key) = Key::parse(key.as_ref()) else {
+                break;
+            };
+            match self.binding(&key) {
+                Some((desc, Action::Mode(mode), attrs)) => self.push_mode(Frame {
+                    key,
+                    name: desc,
+                    mode,
+                    attrs,
+                }),
+                _ => break,
+            }
+        }
+        self.depth()
+    }
+
+    /// Replace the root mode, for example after the config is reloaded
+    ///
+    /// Settings, history and toggle states are kept, and the active modes are
+    /// restored as far as they still exist in the new tree.
+    pub fn set_root(&mut self, root: Mode) {
+        let path = self.mode_path();
+        let active = self.active;
+        self.root = root;
+        self.restore_path(&path);
+        self.active = active;
+    }
+
+    /// Take warnings reported by shell commands since the last call
+    ///
+    /// Commands run in the background, so problems that happen after a key
+    /// is handled, like a command being killed for exceeding its
+    /// `timeout_ms`, are reported here rather than in [`Handled::warn`].
+    pub fn take_warnings(&self) -> Vec<String> {
+        self.warnings.1.try_iter().collect()
+    }
+
+    /// Take lines written by shell commands since the last call
+    ///
+    /// Always empty unless output is captured with
+    /// [`State::with_captured_output`].
+    pub fn take_output(&self) -> Vec<String> {
+        self.output
+            .as_ref()
+            .map(|(_, rx)| rx.try_iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Get the filter typed so far, if filtering
+    ///
+    /// See [`State::with_filter_key`].
+    pub fn filter(&self) -> Option<&str> {
+        self.filter.as_deref()
+    }
+
+    /// Get the count typed so far for the next binding, if any
+    pub fn count(&self) -> Option<u32> {
+        self.count
+    }
+
+    /// Get the keys that should be grabbed in the current mode
+    ///
+    /// This is every key from [`State::keys`], plus the unbound digit keys
+    /// used to type a count prefix when a mode is active. With an activation
+    /// key, it is only the activation key until the root mode is activated,
+    /// and the activation key is included afterwards. With
+    /// [`State::with_entries_only`], it is only the keys that enter a mode
+    /// until a mode is active. With a filter key, it is included while a mode
+    /// is active, and while filtering, the keys used to type and edit the
+    /// filter are too. Bindings hidden by the filter stay bound. While
+    /// suspended by a master toggle, it is only the master toggle keys.
+    pub fn bound_keys(&self) -> Vec<Key> {
+        if self.suspended {
+            return self.root.master_toggle_keys();
+        }
+        if let Some(activation) = &self.activation
+            && !self.is_active()
+        {
+            return vec![activation.clone()];
+        }
+        if self.entries_only && !self.is_active() {
+            return self
+                .root
+                .entries()
+                .filter(|(_, _, action, _)| matches!(action, Action::Mode(_)))
+                .map(|(key, _, _, _)| key.clone())
+                .collect();
+        }
+        let mut keys: Vec<Key> = self.all_keys().iter().map(|(k, _, _)| k.clone()).collect();
+        let mut extra: Vec<Key> = self.activation.iter().cloned().collect();
+        if self.is_active() {
+            extra.extend((0..10).map(|d| Key::parse(&d.to_string()).expect("digit keys parse")));
+            extra.extend(self.filter_key.iter().cloned());
+        }
+        if self.filter.is_some() {
+            extra.extend(
+                ('a'..='z')
+                    .map(String::from)
+                    .chain(["space", "backspace", "escape", "enter"].map(String::from))
+                    .map(|k| Key::parse(&k).expect("filter keys parse")),
+            );
+        }
+        for key in extra {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        keys
     }
 
-    /// Execute an action with the given attributes
-    fn execute_action(&mut self, action: &Action, attrs: &Attrs) -> Result<Handled, String> {
+    /// Execute the action bound to a key, with the binding's description and
+    /// attributes
+    fn execute_action(
+        &mut self,
+        key: &Key,
+        desc: &str,
+        action: &Action,
+        attrs: &Attrs,
+    ) -> Result<Handled, String> {
+        let count = self.count.take();
+        self.filter = None;
         match action {
             Action::Mode(new_mode) => {
-                self.mode_stack.push(new_mode.clone());
+                if let Some(levels) = attrs.popmode {
+                    self.leave(levels);
+                }
+                self.push_mode(Frame {
+                    key: key.clone(),
+                    name: desc.to_string(),
+                    mode: new_mode.clone(),
+                    attrs: attrs.clone(),
+                });
+                if let Some(cmd) = &attrs.on_enter {
+                    self.run_hook(key, cmd, attrs);
+                }
                 Ok(Handled::new())
             }
             Action::Pop => {
-                self.mode_stack.pop();
+                self.leave(attrs.popmode.unwrap_or(1));
                 Ok(Handled::new())
             }
             Action::Exit => {
@@ -95,18 +522,201 @@ impl State {
                 Ok(Handled::new())
             }
             Action::Shell(cmd) => {
-                execute_shell(cmd);
-                if !attrs.noexit {
-                    self.reset();
+                // With a count, commands that don't use it are repeated,
+                // one run after another in a single shell
+                let count = count.unwrap_or(1);
+                let mut script = self.expand(key, cmd, count);
+                if count > 1 && !references(cmd, "count") {
+                    script = repeated(&script, count);
                 }
-                Ok(Handled::new())
+                let context = self.shell.merge(&attrs.shell_context());
+                let output = self.output.as_ref().map(|(tx, _)| tx);
+                let mut handled = Handled::new();
+                if let Err(e) = execute_shell(&script, &context, &self.warnings.0, output) {
+                    handled.warn = e;
+                }
+                self.record(desc);
+                self.finish(attrs);
+                Ok(handled)
+            }
+            Action::Toggle { on, off } => {
+                // The toggle's label changes
+                self.key_cache.take();
+                let id = (on.clone(), off.clone());
+                let cmd = if self.toggles.remove(&id) {
+                    off
+                } else {
+                    self.toggles.insert(id);
+                    on
+                };
+                let context = self.shell.merge(&attrs.shell_context());
+                let output = self.output.as_ref().map(|(tx, _)| tx);
+                let mut handled = Handled::new();
+                if let Err(e) = execute_shell(
+                    &self.expand(key, cmd, 1),
+                    &context,
+                    &self.warnings.0,
+                    output,
+                ) {
+                    handled.warn = e;
+                }
+                self.record(desc);
+                self.finish(attrs);
+                Ok(handled)
+            }
+            Action::Message(text) => {
+                let mut handled = Handled::new();
+                handled.user = self.expand(key, text, count.unwrap_or(1));
+                self.record(desc);
+                self.finish(attrs);
+                Ok(handled)
             }
         }
     }
 
-    /// Reset to the root mode
+    /// Leave modes after a binding's action has run: as many as its
+    /// `popmode` says, none if it or the current mode is `noexit`, and
+    /// otherwise all of them
+    fn finish(&mut self, attrs: &Attrs) {
+        match attrs.popmode {
+            Some(levels) => self.leave(levels),
+            None if attrs.noexit || self.mode_attrs().is_some_and(|mode| mode.noexit) => {}
+            None => self.reset(),
+        }
+    }
+
+    /// Leave `levels` modes, innermost first. Leaving more modes than are
+    /// active deactivates the root mode.
+    fn leave(&mut self, levels: usize) {
+        for _ in 0..levels {
+            if self.mode_stack.is_empty() {
+                self.active = false;
+                break;
+            }
+            self.exit_mode();
+        }
+    }
+
+    /// Leave the current mode, running its `on_exit` hook
+    fn exit_mode(&mut self) {
+        if let Some(frame) = self.mode_stack.last()
+            && let Some(cmd) = &frame.attrs.on_exit
+        {
+            self.run_hook(&frame.key, cmd, &frame.attrs);
+        }
+        self.mode_stack.pop();
+        self.key_cache.take();
+    }
+
+    /// Enter a mode
+    fn push_mode(&mut self, frame: Frame) {
+        self.mode_stack.push(frame);
+        self.key_cache.take();
+    }
+
+    /// Run a mode's `on_enter` or `on_exit` hook while the mode is current.
+    /// Failures are reported by [`State::take_warnings`].
+    fn run_hook(&self, key: &Key, cmd: &str, attrs: &Attrs) {
+        let context = self.shell.merge(&attrs.shell_context());
+        let output = self.output.as_ref().map(|(tx, _)| tx);
+        if let Err(e) = execute_shell(
+            &self.expand(key, cmd, 1),
+            &context,
+            &self.warnings.0,
+            output,
+        ) {
+            let _ = self.warnings.0.send(e);
+        }
+    }
+
+    /// Whether a toggle action is currently on
+    pub fn is_toggled(&self, on: &str, off: &str) -> bool {
+        self.toggles.contains(&(on.to_string(), off.to_string()))
+    }
+
+    /// The description of a binding as displayed, with a check mark for
+    /// toggles that are on
+    fn label(&self, desc: &str, action: &Action) -> String {
+        match action {
+            Action::Toggle { on, off } if self.is_toggled(on, off) => format!("{desc} ✓"),
+            _ => desc.to_string(),
+        }
+    }
+
+    /// Expand placeholders in a shell command run by a key in the current mode
+    fn expand(&self, key: &Key, cmd: &str, count: u32) -> String {
+        let key = key.to_string();
+        let count = count.to_string();
+        let mode = self.mode_names().last().copied().unwrap_or_default();
+        expand(cmd, |name| match name {
+            "key" => Some(key.as_str()),
+            "mode" => Some(mode),
+            "count" => Some(count.as_str()),
+            _ => self.vars.get(name).map(String::as_str),
+        })
+    }
+
+    /// Remember an executed action, discarding the oldest if full
+    fn record(&mut self, desc: &str) {
+        if self.history.len() >= HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(desc.to_string());
+    }
+
+    /// Get the descriptions of recently executed actions, newest first
+    pub fn history(&self) -> impl Iterator<Item = &str> + '_ {
+        self.history.iter().rev().map(String::as_str)
+    }
+
+    /// Get the names of the active modes, from outermost to current
+    ///
+    /// A mode's name is the description of the binding that entered it. The
+    /// result is empty at the root mode.
+    pub fn mode_names(&self) -> Vec<&str> {
+        self.mode_stack
+            .iter()
+            .map(|frame| frame.name.as_str())
+            .collect()
+    }
+
+    /// Reset to the root mode, discarding any pending count and deactivating
+    /// the root mode
+    ///
+    /// The `on_exit` hooks of the active modes run, innermost first.
     pub fn reset(&mut self) {
+        while !self.mode_stack.is_empty() {
+            self.exit_mode();
+        }
+        self.clear();
+    }
+
+    /// Reset to the root mode without running any hooks
+    fn clear(&mut self) {
         self.mode_stack.clear();
+        self.key_cache.take();
+        self.count = None;
+        self.filter = None;
+        self.active = false;
+    }
+
+    /// Whether a master toggle has suspended every binding but the master
+    /// toggles themselves
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    /// Suspend or resume every binding but the master toggles, as pressing a
+    /// binding with the `master_toggle` attribute does
+    ///
+    /// Suspending returns to the root mode. While suspended,
+    /// [`State::bound_keys`] contains only the master toggle keys from
+    /// anywhere in the tree, and pressing one of them resumes.
+    pub fn set_suspended(&mut self, suspended: bool) {
+        if suspended {
+            self.reset();
+        }
+        self.suspended = suspended;
     }
 
     /// Get the current mode depth (0 = root)
@@ -114,27 +724,63 @@ impl State {
         self.mode_stack.len()
     }
 
+    /// Whether a mode is active, so that its keys should be displayed
+    ///
+    /// This is true inside any mode below the root, and at the root after it
+    /// has been activated with the activation key.
+    pub fn is_active(&self) -> bool {
+        self.active || !self.mode_stack.is_empty()
+    }
+
+    /// Get the attributes of the binding that entered the current mode
+    ///
+    /// Returns `None` at the root mode.
+    pub fn mode_attrs(&self) -> Option<&Attrs> {
+        self.mode_stack.last().map(|frame| &frame.attrs)
+    }
+
     /// Get all keys from the current mode as (Key, String, Attrs) tuples
     /// This includes global keys from parent modes
+    ///
+    /// Keys are sorted by their `order` attribute, then by the current mode's
+    /// sort policy. While filtering, only keys whose description fuzzy-matches
+    /// the filter are returned.
     pub fn keys(&self) -> Vec<(Key, String, Attrs)> {
+        let mut keys = self.all_keys().to_vec();
+        if let Some(filter) = &self.filter {
+            keys.retain(|(_, desc, _)| fuzzy_match(filter, desc));
+        }
+        keys
+    }
+
+    /// Get all keys from the current mode, ignoring the filter
+    fn all_keys(&self) -> &[(Key, String, Attrs)] {
+        self.key_cache.get_or_init(|| self.collect_keys())
+    }
+
+    /// Collect and sort the keys from the current mode, ignoring the filter
+    fn collect_keys(&self) -> Vec<(Key, String, Attrs)> {
         let mut keys = Vec::new();
-        let mut seen_keys = std::collections::HashSet::new();
+        let mut seen_keys = HashSet::new();
 
         // Get all keys from current mode first (they take precedence)
-        let current_mode = self.mode_stack.last().unwrap_or(&self.root);
-        for (k, desc, attrs) in current_mode.keys_with_attrs() {
-            seen_keys.insert(k.to_string());
-            keys.push((k, desc, attrs));
+        let current_mode = self
+            .mode_stack
+            .last()
+            .map(|frame| &frame.mode)
+            .unwrap_or(&self.root);
+        for (k, desc, action, attrs) in current_mode.entries() {
+            seen_keys.insert(k);
+            keys.push((k.clone(), self.label(desc, action), attrs.clone()));
         }
 
         // Add global keys from each mode in the stack (in reverse order, excluding current)
         let stack_len = self.mode_stack.len();
         if stack_len > 0 {
             for i in (0..stack_len - 1).rev() {
-                for (k, desc, attrs) in self.mode_stack[i].keys_with_attrs() {
-                    if attrs.global && !seen_keys.contains(&k.to_string()) {
-                        seen_keys.insert(k.to_string());
-                        keys.push((k, desc, attrs));
+                for (k, desc, action, attrs) in self.mode_stack[i].mode.entries() {
+                    if attrs.global && seen_keys.insert(k) {
+                        keys.push((k.clone(), self.label(desc, action), attrs.clone()));
                     }
                 }
             }
@@ -142,17 +788,99 @@ impl State {
 
         // Add global keys from root (unless we're already at root)
         if !self.mode_stack.is_empty() {
-            for (k, desc, attrs) in self.root.keys_with_attrs() {
-                if attrs.global && !seen_keys.contains(&k.to_string()) {
-                    keys.push((k, desc, attrs));
+            for (k, desc, action, attrs) in self.root.entries() {
+                if attrs.global && seen_keys.insert(k) {
+                    keys.push((k.clone(), self.label(desc, action), attrs.clone()));
                 }
             }
         }
 
+        let sort = self
+            .mode_attrs()
+            .and_then(|attrs| attrs.sort)
+            .unwrap_or(self.sort);
+        sort_keys(&mut keys, sort);
         keys
     }
 }
 
+/// A script running `cmd` `runs` times, one after another
+fn repeated(cmd: &str, runs: u32) -> String {
+    format!(
+        "keymode_run=0\nwhile [ \"$keymode_run\" -lt {runs} ]; do\n{cmd}\nkeymode_run=$((keymode_run + 1))\ndone"
+    )
+}
+
+/// The value of a digit key without modifiers
+fn count_digit(key: &Key) -> Option<u32> {
+    let s = key.to_string();
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => c.to_digit(10),
+        _ => None,
+    }
+}
+
+/// The character typed into the filter by an unmodified letter, digit or
+/// space key
+fn filter_char(key: &Key) -> Option<char> {
+    match key.to_string().as_str() {
+        "space" => Some(' '),
+        s => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_alphanumeric() => Some(c),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Whether the characters of a filter appear in order in a description,
+/// ignoring case and spaces in the filter
+fn fuzzy_match(filter: &str, desc: &str) -> bool {
+    let mut desc = desc.chars().flat_map(char::to_lowercase);
+    filter
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .all(|c| desc.any(|d| d == c))
+}
+
+/// Sort keys by their `order` attribute, then by the given policy
+///
+/// The sort is stable, so keys that compare equal keep their config order.
+fn sort_keys(keys: &mut [(Key, String, Attrs)], sort: Sort) {
+    keys.sort_by(|(ka, da, aa), (kb, db, ab)| {
+        let order = aa.order.unwrap_or(0).cmp(&ab.order.unwrap_or(0));
+        order.then_with(|| match sort {
+            Sort::Config => std::cmp::Ordering::Equal,
+            Sort::Key => ka.to_string().cmp(&kb.to_string()),
+            Sort::Desc => da.to_lowercase().cmp(&db.to_lowercase()),
+        })
+    });
+}
+
+/// A group of displayed keys sharing a section title
+pub type Section<'a> = (Option<&'a str>, Vec<&'a (Key, String, Attrs)>);
+
+/// Group keys by their `section` attribute
+///
+/// Sections are returned in the order in which they first appear, and keys
+/// keep their relative order within each section. Keys without a section are
+/// grouped under `None`.
+pub fn sections(keys: &[(Key, String, Attrs)]) -> Vec<Section<'_>> {
+    let mut sections: Vec<Section<'_>> = Vec::new();
+    for entry in keys {
+        let title = entry.2.section.as_deref();
+        match sections.iter_mut().find(|(t, _)| *t == title) {
+            Some((_, entries)) => entries.push(entry),
+            None => sections.push((title, vec![entry])),
+        }
+    }
+    sections
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -301,6 +1029,101 @@ mod tests {
         assert_eq!(state.depth(), 2); // Should still be in deep menu
     }
 
+    #[test]
+    fn test_popmode() {
+        let root = Mode::from_ron(
+            r#"[
+            ("a", "A", mode([
+                ("b", "B", mode([
+                    ("c", "C", mode([
+                        ("u", "Up", shell("true"), (popmode: 1)),
+                        ("p", "Pop two", pop, (popmode: 2)),
+                        ("s", "Sibling", mode([]), (popmode: 1)),
+                    ])),
+                    ("k", "Keep", shell("true")),
+                ]), (noexit: true)),
+                ("t", "Stay", shell("true")),
+                ("x", "Leave", shell("true"), (popmode: 9)),
+            ])),
+        ]"#,
+        )
+        .unwrap();
+        let mut state = State::new(root);
+        let enter = |state: &mut State, keys: &[&str]| {
+            for k in keys {
+                state.handle_key(&key(k)).unwrap();
+            }
+        };
+
+        // Commands can leave some of the modes rather than all of them
+        enter(&mut state, &["a", "b", "c", "u"]);
+        assert_eq!(state.mode_names(), vec!["A", "B"]);
+
+        // Pops can pop several modes
+        enter(&mut state, &["c", "p"]);
+        assert_eq!(state.mode_names(), vec!["A"]);
+
+        // Entering a mode can replace the current one
+        enter(&mut state, &["b", "c", "s"]);
+        assert_eq!(state.mode_names(), vec!["A", "B", "Sibling"]);
+
+        // Commands in a noexit mode stay in it, while commands in other modes
+        // return to the root
+        state.reset();
+        enter(&mut state, &["a", "b", "k"]);
+        assert_eq!(state.mode_names(), vec!["A", "B"]);
+        state.reset();
+        enter(&mut state, &["a", "t"]);
+        assert_eq!(state.depth(), 0);
+
+        // Leaving more modes than are active returns to the root
+        enter(&mut state, &["a", "x"]);
+        assert_eq!(state.depth(), 0);
+        assert!(!state.is_active());
+    }
+
+    #[test]
+    fn test_mode_hooks() {
+        let root = Mode::from_ron(
+            r#"[
+            ("m", "Menu", mode([
+                ("s", "Sub", mode([
+                    ("x", "Run", shell("true")),
+                ]), (on_enter: "echo enter {mode}", on_exit: "echo exit {mode}")),
+                ("q", "Back", pop),
+            ]), (on_enter: "echo enter {mode}", on_exit: "echo exit {mode}")),
+        ]"#,
+        )
+        .unwrap();
+        let mut state = State::new(root).with_captured_output();
+        // Hooks run in the background, so wait for their output in turn
+        let expect = |state: &mut State, k: &str, lines: &[&str]| {
+            state.handle_key(&key(k)).unwrap();
+            let start = std::time::Instant::now();
+            let mut output = Vec::new();
+            while output.len() < lines.len() && start.elapsed().as_secs() < 3 {
+                output.extend(state.take_output());
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            output.sort();
+            let mut lines = lines.to_vec();
+            lines.sort();
+            assert_eq!(output, lines);
+        };
+
+        expect(&mut state, "m", &["enter Menu"]);
+        expect(&mut state, "q", &["exit Menu"]);
+        expect(&mut state, "m", &["enter Menu"]);
+        expect(&mut state, "s", &["enter Sub"]);
+        expect(&mut state, "x", &["exit Sub", "exit Menu"]);
+        assert_eq!(state.depth(), 0);
+
+        // Restoring a path doesn't run hooks
+        assert_eq!(state.restore_path(&["m", "s"]), 2);
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(state.take_output().is_empty());
+    }
+
     #[test]
     fn test_global_keys_behavior() {
         // Create modes with global keys
@@ -428,6 +1251,570 @@ mod tests {
         assert_eq!(state.depth(), 2); // Should NOT reset due to noexit
     }
 
+    #[test]
+    fn test_mode_attrs() {
+        let root = Mode::from_ron(
+            r#"[
+            ("m", "Menu", mode([
+                ("s", "Sub", mode([
+                    ("x", "Exit", exit),
+                ])),
+            ]), (auto_hide_ms: 0)),
+        ]"#,
+        )
+        .unwrap();
+
+        let mut state = State::new(root);
+        assert_eq!(state.mode_attrs(), None);
+
+        state.handle_key(&key("m")).unwrap();
+        assert_eq!(state.mode_attrs().unwrap().auto_hide_ms, Some(0));
+
+        state.handle_key(&key("s")).unwrap();
+        assert_eq!(state.mode_attrs().unwrap().auto_hide_ms, None);
+
+        state.handle_key(&key("x")).unwrap();
+        assert_eq!(state.mode_attrs(), None);
+    }
+
+    #[test]
+    fn test_mode_names() {
+        let root = Mode::from_ron(
+            r#"[
+            ("m", "Menu", mode([
+                ("s", "Sub", mode([
+                    ("p", "Back", pop),
+                ])),
+            ])),
+        ]"#,
+        )
+        .unwrap();
+
+        let mut state = State::new(root);
+        assert!(state.mode_names().is_empty());
+
+        state.handle_key(&key("m")).unwrap();
+        state.handle_key(&key("s")).unwrap();
+        assert_eq!(state.mode_names(), vec!["Menu", "Sub"]);
+
+        state.handle_key(&key("p")).unwrap();
+        assert_eq!(state.mode_names(), vec!["Menu"]);
+    }
+
+    #[test]
+    fn test_history() {
+        let root = Mode::from_ron(
+            r#"[
+            ("a", "First", shell("true"), (noexit: true)),
+            ("b", "Second", shell("true"), (noexit: true)),
+            ("m", "Menu", mode([])),
+        ]"#,
+        )
+        .unwrap();
+
+        let mut state = State::new(root);
+        assert_eq!(state.history().count(), 0);
+
+        state.handle_key(&key("a")).unwrap();
+        state.handle_key(&key("m")).unwrap();
+        state.reset();
+        state.handle_key(&key("b")).unwrap();
+        assert_eq!(state.history().collect::<Vec<_>>(), vec!["Second", "First"]);
+
+        for _ in 0..HISTORY_LEN {
+            state.handle_key(&key("a")).unwrap();
+        }
+        assert_eq!(state.history().count(), HISTORY_LEN);
+        assert!(state.history().all(|desc| desc == "First"));
+    }
+
+    #[test]
+    fn test_toggle() {
+        let root = Mode::from_ron(
+            r#"[
+            ("t", "Mute", toggle(on: "true", off: "true"), (noexit: true, global: true)),
+            ("m", "Menu", mode([])),
+        ]"#,
+        )
+        .unwrap();
+        let mut state = State::new(root);
+        let desc = |state: &State| state.keys()[0].1.clone();
+        assert_eq!(desc(&state), "Mute");
+        assert!(!state.is_toggled("true", "true"));
+
+        state.handle_key(&key("t")).unwrap();
+        assert!(state.is_toggled("true", "true"));
+        assert_eq!(desc(&state), "Mute ✓");
+
+        // The state is shared by global bindings in nested modes
+        state.handle_key(&key("m")).unwrap();
+        assert_eq!(desc(&state), "Mute ✓");
+        state.handle_key(&key("t")).unwrap();
+        assert_eq!(desc(&state), "Mute");
+        assert_eq!(state.history().collect::<Vec<_>>(), vec!["Mute", "Mute"]);
+    }
+
+    #[test]
+    fn test_activation() {
+        let root = Mode::from_ron(
+            r#"[
+            ("a", "Action", shell("true")),
+            ("m", "Menu", mode([("b", "Back", pop)])),
+            ("q", "Close", pop),
+        ]"#,
+        )
+        .unwrap();
+        let mut state = State::new(root).with_activation(key("cmd+space"));
+        assert_eq!(state.bound_keys(), vec![key("cmd+space")]);
+        assert!(!state.is_active());
+
+        state.handle_key(&key("cmd+space")).unwrap();
+        assert!(state.is_active());
+        assert_eq!(state.depth(), 0);
+        assert_eq!(
+            state.bound_keys(),
+            vec![key("a"), key("m"), key("q"), key("cmd+space")]
+                .into_iter()
+                .chain((0..10).map(|d| key(&d.to_string())))
+                .collect::<Vec<_>>()
+        );
+
+        // Pressing the activation key again deactivates
+        state.handle_key(&key("cmd+space")).unwrap();
+        assert!(!state.is_active());
+
+        // Returning to the root from a nested mode keeps the root active,
+        // popping the root deactivates it
+        state.handle_key(&key("cmd+space")).unwrap();
+        state.handle_key(&key("m")).unwrap();
+        state.handle_key(&key("b")).unwrap();
+        assert!(state.is_active());
+        state.handle_key(&key("q")).unwrap();
+        assert!(!state.is_active());
+
+        // Actions that exit deactivate
+        state.handle_key(&key("cmd+space")).unwrap();
+        state.handle_key(&key("a")).unwrap();
+        assert!(!state.is_active());
+        assert_eq!(state.bound_keys(), vec![key("cmd+space")]);
+    }
+
+    #[test]
+    fn test_triggered() {
+        let root = Mode::from_ron(
+            r#"[
+            ("g", "Git", mode([
+                ("s", "Status", shell("true")),
+                ("l", "Log", shell("true"), (noexit: true)),
+            ])),
+        ]"#,
+        )
+        .unwrap();
+        let mut state = State::new(root);
+
+        let handled = state.handle_key(&key("g")).unwrap();
+        let triggered = handled.triggered.unwrap();
+        assert_eq!(triggered.action, ActionKind::Mode);
+        assert!(triggered.path.is_empty());
+        assert_eq!(triggered.mode_path, vec!["Git"]);
+
+        let triggered = state.handle_key(&key("l")).unwrap().triggered.unwrap();
+        assert_eq!(triggered.key, key("l"));
+        assert_eq!(triggered.mode_path, vec!["Git"]);
+
+        let triggered = state.handle_key(&key("s")).unwrap().triggered.unwrap();
+        assert_eq!(triggered.action, ActionKind::Shell);
+        assert_eq!(triggered.breadcrumb(), "Git ▸ Status");
+        assert!(triggered.mode_path.is_empty());
+
+        // Unbound keys trigger nothing
+        assert!(state.handle_key(&key("x")).unwrap().triggered.is_none());
+    }
+
+    #[test]
+    fn test_master_toggle() {
+        let root = Mode::from_ron(
+            r#"[
+            ("a", "Action", shell("true")),
+            ("cmd+escape", "Hotkeys", exit, (master_toggle: true)),
+            ("m", "Menu", mode([
+                ("b", "Back", pop),
+                ("f12", "Games", exit, (master_toggle: true)),
+            ])),
+        ]"#,
+        )
+        .unwrap();
+        let mut state = State::new(root).with_activation(key("cmd+space"));
+        state.handle_key(&key("cmd+space")).unwrap();
+        state.handle_key(&key("m")).unwrap();
+
+        // A master toggle suspends everything else and returns to the root
+        state.handle_key(&key("f12")).unwrap();
+        assert!(state.is_suspended());
+        assert!(!state.is_active());
+        assert_eq!(state.bound_keys(), vec![key("cmd+escape"), key("f12")]);
+        state.handle_key(&key("cmd+space")).unwrap();
+        assert!(!state.is_active());
+        assert_eq!(state.history().count(), 0);
+
+        // Any master toggle resumes
+        state.handle_key(&key("cmd+escape")).unwrap();
+        assert!(!state.is_suspended());
+        assert_eq!(state.bound_keys(), vec![key("cmd+space")]);
+
+        state.set_suspended(true);
+        assert_eq!(state.bound_keys().len(), 2);
+        state.set_suspended(false);
+        assert_eq!(state.bound_keys(), vec![key("cmd+space")]);
+    }
+
+    #[test]
+    fn test_entries_only() {
+        let root = Mode::from_ron(
+            r#"[
+            ("a", "Action", shell("true")),
+            ("m", "Menu", mode([("b", "B", shell("true"))])),
+            ("w", "Windows", mode([])),
+        ]"#,
+        )
+        .unwrap();
+        let mut state = State::new(root).with_entries_only(true);
+        assert_eq!(state.bound_keys(), vec![key("m"), key("w")]);
+        state.handle_key(&key("m")).unwrap();
+        assert!(state.bound_keys().contains(&key("b")));
+
+        // With an activation key, the root keys are bound once activated
+        let mut state = state.with_activation(key("cmd+space"));
+        state.reset();
+        assert_eq!(state.bound_keys(), vec![key("cmd+space")]);
+        state.handle_key(&key("cmd+space")).unwrap();
+        assert!(state.bound_keys().contains(&key("a")));
+    }
+
+    #[test]
+    fn test_filter() {
+        let root = Mode::from_ron(
+            r#"[
+            ("m", "Menu", mode([
+                ("a", "Open Browser", message("browser"), (noexit: true)),
+                ("b", "Open Terminal", message("terminal")),
+                ("c", "Close Window", message("close")),
+                ("cmd+k", "Kill", message("kill")),
+            ])),
+        ]"#,
+        )
+        .unwrap();
+        let mut state = State::new(root).with_filter_key(key("slash"));
+        let descs = |state: &State| -> Vec<String> {
+            state.keys().into_iter().map(|(_, desc, _)| desc).collect()
+        };
+
+        // The filter key only applies inside a mode
+        assert!(!state.bound_keys().contains(&key("slash")));
+        state.handle_key(&key("m")).unwrap();
+        assert!(state.bound_keys().contains(&key("slash")));
+        state.handle_key(&key("slash")).unwrap();
+        assert_eq!(state.filter(), Some(""));
+        assert!(state.bound_keys().contains(&key("backspace")));
+
+        // Typed keys go to the filter rather than triggering bindings
+        for k in ["o", "p", "space", "t"] {
+            state.handle_key(&key(k)).unwrap();
+        }
+        assert_eq!(state.filter(), Some("op t"));
+        assert_eq!(descs(&state), vec!["Open Terminal"]);
+        assert!(state.history().next().is_none());
+        state.handle_key(&key("backspace")).unwrap();
+        state.handle_key(&key("backspace")).unwrap();
+        assert_eq!(descs(&state), vec!["Open Browser", "Open Terminal"]);
+
+        // Return triggers the first match and stops filtering
+        let handled = state.handle_key(&key("enter")).unwrap();
+        assert_eq!(handled.user, "browser");
+        assert_eq!(state.filter(), None);
+        assert_eq!(state.depth(), 1);
+
+        // Bindings that can't be typed stay triggerable, even when hidden
+        state.handle_key(&key("slash")).unwrap();
+        state.handle_key(&key("c")).unwrap();
+        assert_eq!(descs(&state), vec!["Close Window"]);
+        let handled = state.handle_key(&key("cmd+k")).unwrap();
+        assert_eq!(handled.user, "kill");
+        assert_eq!(state.filter(), None);
+
+        // Escape and backspace on an empty filter stop filtering
+        state.handle_key(&key("m")).unwrap();
+        state.handle_key(&key("slash")).unwrap();
+        state.handle_key(&key("escape")).unwrap();
+        assert_eq!(state.filter(), None);
+        state.handle_key(&key("slash")).unwrap();
+        state.handle_key(&key("backspace")).unwrap();
+        assert_eq!(state.filter(), None);
+        assert_eq!(descs(&state).len(), 4);
+    }
+
+    #[test]
+    fn test_debounce() {
+        let root = Mode::from_ron(
+            r#"[
+            ("m", "Menu", mode([
+                ("s", "Slow", shell("true"), (noexit: true, debounce_ms: 60000)),
+                ("f", "Fast", shell("true"), (noexit: true, debounce_ms: 1)),
+            ])),
+        ]"#,
+        )
+        .unwrap();
+        let mut state = State::new(root);
+        state.handle_key(&key("m")).unwrap();
+        state.handle_key(&key("s")).unwrap();
+        state.handle_key(&key("s")).unwrap();
+        assert_eq!(state.history().collect::<Vec<_>>(), vec!["Slow"]);
+
+        state.handle_key(&key("f")).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        state.handle_key(&key("f")).unwrap();
+        assert_eq!(
+            state.history().collect::<Vec<_>>(),
+            vec!["Fast", "Fast", "Slow"]
+        );
+    }
+
+    #[test]
+    fn test_message() {
+        let root = Mode::from_ron(
+            r#"[
+            ("m", "Menu", mode([
+                ("v", "Volume", message("Volume {count}0% in {mode}")),
+            ])),
+        ]"#,
+        )
+        .unwrap();
+        let mut state = State::new(root);
+        state.handle_key(&key("m")).unwrap();
+        state.handle_key(&key("4")).unwrap();
+        let handled = state.handle_key(&key("v")).unwrap();
+        assert_eq!(handled.user, "Volume 40% in Menu");
+        assert_eq!(state.depth(), 0);
+        assert_eq!(state.history().collect::<Vec<_>>(), vec!["Volume"]);
+    }
+
+    #[test]
+    fn test_shell_timeout() {
+        let root = Mode::from_ron(
+            r#"[
+            ("s", "Slow", shell("sleep 5"), (timeout_ms: 20)),
+        ]"#,
+        )
+        .unwrap();
+        let mut state = State::new(root);
+        let handled = state.handle_key(&key("s")).unwrap();
+        assert!(handled.warn.is_empty());
+        let start = std::time::Instant::now();
+        let warnings = loop {
+            let warnings = state.take_warnings();
+            if !warnings.is_empty() || start.elapsed().as_secs() >= 3 {
+                break warnings;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        };
+        assert_eq!(
+            warnings,
+            vec!["Command 'sleep 5' timed out after 20ms and was killed"]
+        );
+    }
+
+    #[test]
+    fn test_captured_output() {
+        let root = Mode::from_ron(r#"[("e", "Echo", shell("echo {key}"))]"#).unwrap();
+        let mut state = State::new(root).with_captured_output();
+        state.handle_key(&key("e")).unwrap();
+        let start = std::time::Instant::now();
+        let output = loop {
+            let output = state.take_output();
+            if !output.is_empty() || start.elapsed().as_secs() >= 3 {
+                break output;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        };
+        assert_eq!(output, vec!["e"]);
+    }
+
+    #[test]
+    fn test_restore_path() {
+        let root = Mode::from_ron(
+            r#"[
+            ("g", "Global", mode([
+                ("x", "X", exit),
+            ]), (global: true)),
+            ("w", "Windows", mode([
+                ("ctrl+m", "Move", mode([])),
+                ("s", "Shell", shell("true")),
+            ])),
+        ]"#,
+        )
+        .unwrap();
+        let mut state = State::new(root);
+        assert!(state.mode_path().is_empty());
+
+        state.handle_key(&key("w")).unwrap();
+        state.handle_key(&key("ctrl+m")).unwrap();
+        let path = state.mode_path();
+        assert_eq!(path, vec!["w", "ctrl+m"]);
+
+        let mut restored = State::new(state.root.clone());
+        assert_eq!(restored.restore_path(&path), 2);
+        assert_eq!(restored.mode_names(), vec!["Windows", "Move"]);
+
+        // Global modes can be entered from anywhere
+        assert_eq!(restored.restore_path(&["w", "g"]), 2);
+        assert_eq!(restored.mode_names(), vec!["Windows", "Global"]);
+
+        // Restoring stops at keys that don't enter a mode
+        assert_eq!(restored.restore_path(&["w", "s", "ctrl+m"]), 1);
+        assert_eq!(restored.restore_path(&["bogus+key"]), 0);
+    }
+
+    #[test]
+    fn test_count_prefix() {
+        let root = Mode::from_ron(
+            r#"[
+            ("0", "Zero", shell("true")),
+            ("w", "Windows", mode([
+                ("j", "Down", shell("true"), (noexit: true)),
+                ("1", "One", pop),
+            ])),
+        ]"#,
+        )
+        .unwrap();
+        let mut state = State::new(root);
+
+        // Digits are not grabbed or counted at the root
+        assert!(!state.bound_keys().contains(&key("5")));
+        state.handle_key(&key("5")).unwrap();
+        assert_eq!(state.count(), None);
+
+        state.handle_key(&key("w")).unwrap();
+        let bound = state.bound_keys();
+        assert!(bound.contains(&key("5")) && bound.contains(&key("0")));
+        assert_eq!(bound.iter().filter(|k| **k == key("1")).count(), 1);
+
+        // Leading zeros are ignored, and bound digits are not counted
+        state.handle_key(&key("0")).unwrap();
+        assert_eq!(state.count(), None);
+        state.handle_key(&key("2")).unwrap();
+        state.handle_key(&key("0")).unwrap();
+        assert_eq!(state.count(), Some(20));
+        state.handle_key(&key("j")).unwrap();
+        assert_eq!(state.count(), None);
+        assert_eq!(state.depth(), 1);
+
+        for _ in 0..4 {
+            state.handle_key(&key("9")).unwrap();
+        }
+        assert_eq!(state.count(), Some(MAX_COUNT));
+        state.reset();
+        assert_eq!(state.count(), None);
+    }
+
+    #[test]
+    fn test_repeated() {
+        let output = std::process::Command::new("sh")
+            .args(["-c", &repeated("echo run", 3)])
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "run\nrun\nrun\n");
+    }
+
+    #[test]
+    fn test_expand_placeholders() {
+        let root = Mode::from_ron(
+            r#"[
+            ("m", "Windows", mode([])),
+        ]"#,
+        )
+        .unwrap();
+        let mut state = State::new(root)
+            .with_vars([("term".to_string(), "kitty".to_string())])
+            .with_var("key", "shadowed");
+
+        assert_eq!(
+            state.expand(&key("a"), "{term} {key} [{mode}]", 1),
+            "kitty a []"
+        );
+        state.handle_key(&key("m")).unwrap();
+        assert_eq!(
+            state.expand(&key("ctrl+x"), "{term} {key} [{mode}] {other} x{count}", 3),
+            "kitty ctrl+x [Windows] {other} x3"
+        );
+    }
+
+    #[test]
+    fn test_sections() {
+        let root = Mode::from_ron(
+            r#"[
+            ("a", "Left", shell("left"), (section: "Window")),
+            ("b", "Safari", shell("safari"), (section: "Apps")),
+            ("c", "Right", shell("right"), (section: "Window")),
+            ("d", "Reload", shell("reload")),
+        ]"#,
+        )
+        .unwrap();
+
+        let state = State::new(root);
+        let keys = state.keys();
+        let sections = sections(&keys);
+
+        let titles: Vec<_> = sections.iter().map(|(title, _)| *title).collect();
+        assert_eq!(titles, vec![Some("Window"), Some("Apps"), None]);
+
+        let window: Vec<_> = sections[0]
+            .1
+            .iter()
+            .map(|(_, desc, _)| desc.as_str())
+            .collect();
+        assert_eq!(window, vec!["Left", "Right"]);
+        assert_eq!(sections[1].1.len(), 1);
+        assert_eq!(sections[2].1.len(), 1);
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let root = Mode::from_ron(
+            r#"[
+            ("c", "banana", shell("b")),
+            ("a", "Cherry", shell("c")),
+            ("b", "apple", shell("a")),
+            ("z", "Last", shell("z"), (order: 1)),
+            ("y", "First", shell("y"), (order: -1)),
+            ("m", "Menu", mode([
+                ("q", "Quux", shell("q")),
+                ("p", "Pop", pop),
+            ]), (sort: key)),
+        ]"#,
+        )
+        .unwrap();
+
+        let descs = |state: &State| -> Vec<String> {
+            state.keys().into_iter().map(|(_, desc, _)| desc).collect()
+        };
+
+        let state = State::new(root.clone());
+        assert_eq!(
+            descs(&state),
+            vec!["First", "banana", "Cherry", "apple", "Menu", "Last"]
+        );
+
+        let state = State::new(root.clone()).with_sort(Sort::Desc);
+        assert_eq!(
+            descs(&state),
+            vec!["First", "apple", "banana", "Cherry", "Menu", "Last"]
+        );
+
+        let mut state = State::new(root).with_sort(Sort::Desc);
+        state.handle_key(&key("m")).unwrap();
+        assert_eq!(descs(&state), vec!["Pop", "Quux"]);
+    }
+
     #[test]
     fn test_hide_attribute() {
         // Create modes with hidden keys
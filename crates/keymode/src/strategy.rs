@@ -0,0 +1,183 @@
+//! [proptest] strategies for modes.
+//!
+//! Enabled by the `proptest` feature. Generated modes only contain valid keys,
+//! and survive a round trip through every config format that can represent
+//! them. Keys come from [`hotkey_manager::strategy`].
+
+use hotkey_manager::strategy::key;
+use proptest::arbitrary::Arbitrary;
+use proptest::prelude::*;
+use std::collections::HashMap;
+
+use crate::mode::{Action, Attrs, Mode, Pos, Sort, Visibility};
+
+/// Short strings for descriptions, commands and attribute values
+fn text() -> impl Strategy<Value = String> {
+    "[a-zA-Z0-9 _.~/-]{0,24}"
+}
+
+/// Sort policies
+pub fn sort() -> impl Strategy<Value = Sort> {
+    prop_oneof![Just(Sort::Config), Just(Sort::Key), Just(Sort::Desc)]
+}
+
+/// HUD positions
+pub fn pos() -> impl Strategy<Value = Pos> {
+    proptest::sample::select(vec![
+        Pos::N,
+        Pos::NE,
+        Pos::E,
+        Pos::SE,
+        Pos::S,
+        Pos::SW,
+        Pos::W,
+        Pos::NW,
+        Pos::Center,
+    ])
+}
+
+/// HUD visibilities
+pub fn visibility() -> impl Strategy<Value = Visibility> {
+    prop_oneof![Just(Visibility::Shown), Just(Visibility::Hidden)]
+}
+
+/// Binding attributes
+pub fn attrs() -> impl Strategy<Value = Attrs> {
+    (
+        (any::<bool>(), any::<bool>(), any::<bool>(), any::<bool>()),
+        (
+            proptest::option::of(any::<u32>().prop_map(u64::from)),
+            proptest::option::of(text()),
+            proptest::option::of(any::<i32>()),
+            proptest::option::of(sort()),
+        ),
+        (
+            proptest::option::of(text()),
+            proptest::collection::hash_map("[A-Z_]{1,8}", text(), 0..3),
+            proptest::option::of(any::<u32>().prop_map(u64::from)),
+            proptest::option::of(any::<u32>().prop_map(u64::from)),
+        ),
+        (proptest::option::of(text()), proptest::option::of(text())),
+        (
+            proptest::option::of(visibility()),
+            proptest::option::of(pos()),
+            proptest::option::of(any::<u16>().prop_map(f64::from)),
+        ),
+        (
+            proptest::option::of(any::<u8>().prop_map(usize::from)),
+            proptest::option::of(text()),
+            proptest::option::of(text()),
+        ),
+    )
+        .prop_map(
+            |(
+                (noexit, global, hide, master_toggle),
+                (auto_hide_ms, section, order, sort),
+                (cwd, env, timeout_ms, debounce_ms),
+                (icon, color),
+                (hud, hud_pos, hud_width),
+                (popmode, on_enter, on_exit),
+            )| Attrs {
+                noexit,
+                popmode,
+                global,
+                hide,
+                master_toggle,
+                auto_hide_ms,
+                hud,
+                hud_pos,
+                hud_width,
+                section,
+                order,
+                sort,
+                cwd,
+                env: env.into_iter().collect::<HashMap<_, _>>(),
+                timeout_ms,
+                on_enter,
+                on_exit,
+                debounce_ms,
+                icon,
+                color,
+            },
+        )
+}
+
+/// Actions other than entering a mode
+fn leaf_action() -> impl Strategy<Value = Action> {
+    prop_oneof![
+        text().prop_map(Action::Shell),
+        Just(Action::Pop),
+        Just(Action::Exit),
+        (text(), text()).prop_map(|(on, off)| Action::Toggle { on, off }),
+        text().prop_map(Action::Message),
+    ]
+}
+
+/// Modes nested up to `depth` levels below the returned one
+fn nested_mode(depth: u32) -> impl Strategy<Value = Mode> {
+    let leaf = bindings(leaf_action().boxed());
+    leaf.prop_recursive(depth, 64, 8, |inner| {
+        bindings(prop_oneof![leaf_action(), inner.prop_map(Action::Mode)].boxed())
+    })
+}
+
+/// Modes made of bindings that run the given actions
+fn bindings(action: BoxedStrategy<Action>) -> impl Strategy<Value = Mode> {
+    proptest::collection::vec((key(), text(), action, attrs()), 0..6)
+        .prop_map(Mode::from_entries)
+        .boxed()
+}
+
+/// Actions, including entering modes up to three levels deep
+pub fn action() -> impl Strategy<Value = Action> {
+    prop_oneof![leaf_action(), nested_mode(2).prop_map(Action::Mode)]
+}
+
+/// Modes with up to three levels of nested modes
+pub fn mode() -> impl Strategy<Value = Mode> {
+    nested_mode(3)
+}
+
+impl Arbitrary for Mode {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Mode>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        mode().boxed()
+    }
+}
+
+impl Arbitrary for Action {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Action>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        action().boxed()
+    }
+}
+
+impl Arbitrary for Attrs {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Attrs>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        attrs().boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::Format;
+    use crate::mode::ron_options;
+
+    proptest! {
+        #[test]
+        fn test_mode_round_trip(mode in any::<Mode>()) {
+            let ron = ron_options().to_string(&mode).unwrap();
+            prop_assert_eq!(Mode::parse(&ron, Format::Ron).unwrap(), mode.clone());
+            let json = serde_json::to_string(&mode).unwrap();
+            prop_assert_eq!(Mode::parse(&json, Format::Json).unwrap(), mode);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Placeholder expansion for shell commands.
+//!
+//! Placeholders are identifiers in braces, such as `{key}`. Anything else in
+//! braces is left alone, so shell syntax like `${HOME}` or awk's
+//! `'{print $1}'` passes through unless it names a known variable. A doubled
+//! brace (`{{`) produces a literal brace.
+
+/// Expand `{name}` placeholders in a template
+///
+/// `lookup` returns the value of a variable, or `None` to leave the
+/// placeholder unexpanded.
+pub(crate) fn expand<'a>(template: &str, lookup: impl Fn(&str) -> Option<&'a str>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        // Doubled braces are escapes
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            out.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        if rest.starts_with('{')
+            && let Some(end) = rest.find('}')
+        {
+            let name = &rest[1..end];
+            if is_identifier(name)
+                && let Some(value) = lookup(name)
+            {
+                out.push_str(value);
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+        out.push_str(&rest[..1]);
+        rest = &rest[1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Whether a template contains a `{name}` placeholder
+pub(crate) fn references(template: &str, name: &str) -> bool {
+    let found = std::cell::Cell::new(false);
+    expand(template, |n| {
+        if n == name {
+            found.set(true);
+        }
+        None
+    });
+    found.get()
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<&'static str> {
+        match name {
+            "key" => Some("ctrl+a"),
+            "mode" => Some("Windows"),
+            "HOME_DIR" => Some("/home/me"),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(
+            expand("echo {key} in {mode}", lookup),
+            "echo ctrl+a in Windows"
+        );
+        assert_eq!(expand("ls {HOME_DIR}/src", lookup), "ls /home/me/src");
+        assert_eq!(expand("no placeholders", lookup), "no placeholders");
+    }
+
+    #[test]
+    fn test_expand_passthrough() {
+        assert_eq!(
+            expand("echo ${HOME} {unknown}", lookup),
+            "echo ${HOME} {unknown}"
+        );
+        assert_eq!(expand("awk '{print $1}'", lookup), "awk '{print $1}'");
+        assert_eq!(expand("echo {{key}} }}", lookup), "echo {key} }");
+        assert_eq!(expand("unterminated {key", lookup), "unterminated {key");
+        assert_eq!(expand("}{key}{", lookup), "}ctrl+a{");
+    }
+
+    #[test]
+    fn test_references() {
+        assert!(references("seq {count}", "count"));
+        assert!(!references("seq {{count}}", "count"));
+        assert!(!references("seq {counter}", "count"));
+    }
+}
@@ -0,0 +1,288 @@
+//! Validation of mode trees.
+//!
+//! Validation walks the whole tree and collects every problem, so that users
+//! can fix their config in one pass: keys that fail to parse, and bindings
+//! that can never fire as written.
+
+use crate::mode::{Action, Mode};
+use hotkey_manager::Key;
+use std::fmt;
+
+/// A problem found when validating a mode tree
+///
+/// Paths are the descriptions of the bindings leading from the root to the
+/// mode containing the problem, and are empty for the root mode.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    /// A key string could not be parsed
+    InvalidKey {
+        path: Vec<String>,
+        key: String,
+        message: String,
+    },
+    /// The same key is bound more than once in a mode. Keys are compared
+    /// after parsing, so different spellings of one combination, such as
+    /// `ctrl+a` and `control+a`, collide.
+    Duplicate { path: Vec<String>, key: Key },
+    /// A shell command refers to a command alias that isn't defined
+    UnknownAlias { path: Vec<String>, name: String },
+    /// A binding hides a `global` binding of the same key in an ancestor mode
+    Shadowed {
+        path: Vec<String>,
+        key: Key,
+        global_path: Vec<String>,
+    },
+}
+
+fn format_path(path: &[String]) -> String {
+    if path.is_empty() {
+        "root".to_string()
+    } else {
+        path.join(" > ")
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::InvalidKey { path, key, message } => {
+                write!(f, "{}: invalid key '{key}': {message}", format_path(path))
+            }
+            ValidationError::UnknownAlias { path, name } => {
+                write!(f, "{}: unknown command alias '@{name}'", format_path(path))
+            }
+            ValidationError::Duplicate { path, key } => {
+                write!(
+                    f,
+                    "{}: key '{key}' is bound more than once",
+                    format_path(path)
+                )
+            }
+            ValidationError::Shadowed {
+                path,
+                key,
+                global_path,
+            } => write!(
+                f,
+                "{}: key '{key}' shadows the global binding in {}",
+                format_path(path),
+                format_path(global_path)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl ValidationError {
+    /// Whether this is an unparseable key, which prevents the mode from being
+    /// used at all. Other errors are warnings about bindings that won't fire.
+    pub fn is_invalid_key(&self) -> bool {
+        matches!(self, ValidationError::InvalidKey { .. })
+    }
+}
+
+impl Mode {
+    /// Check the mode tree for invalid, duplicate and shadowed bindings
+    ///
+    /// Returns every problem found, or an empty list if the tree is valid.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        validate_mode(self, &mut Vec::new(), &mut Vec::new(), &mut errors);
+        errors
+    }
+}
+
+/// Validate a mode and its children
+///
+/// `globals` holds the global keys of ancestor modes along with the path of
+/// the mode that binds them.
+fn validate_mode(
+    mode: &Mode,
+    path: &mut Vec<String>,
+    globals: &mut Vec<(Key, Vec<String>)>,
+    errors: &mut Vec<ValidationError>,
+) {
+    for entry in mode.invalid_entries() {
+        errors.push(ValidationError::InvalidKey {
+            path: path.clone(),
+            key: entry.key.clone(),
+            message: entry.error.clone(),
+        });
+    }
+
+    let mut seen: Vec<&Key> = Vec::new();
+    for (key, _, _, _) in mode.entries() {
+        if seen.contains(&key) {
+            // Report each duplicated key once
+            let reported = errors.iter().any(|e| {
+                matches!(e, ValidationError::Duplicate { path: p, key: k } if p == path && k == key)
+            });
+            if !reported {
+                errors.push(ValidationError::Duplicate {
+                    path: path.clone(),
+                    key: key.clone(),
+                });
+            }
+        } else {
+            seen.push(key);
+        }
+
+        if let Some((_, global_path)) = globals.iter().find(|(k, _)| k == key) {
+            errors.push(ValidationError::Shadowed {
+                path: path.clone(),
+                key: key.clone(),
+                global_path: global_path.clone(),
+            });
+        }
+    }
+
+    let globals_len = globals.len();
+    for (key, _, _, attrs) in mode.entries() {
+        if attrs.global {
+            globals.push((key.clone(), path.clone()));
+        }
+    }
+    let children = mode
+        .entries()
+        .map(|(_, desc, action, _)| (desc, action))
+        .chain(mode.invalid_entries().map(|e| (&e.desc, &e.action)));
+    for (desc, action) in children {
+        if let Action::Mode(child) = action {
+            path.push(desc.clone());
+            validate_mode(child, path, globals, errors);
+            path.pop();
+        }
+    }
+    globals.truncate(globals_len);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(s: &str) -> Key {
+        Key::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_validate_ok() {
+        let mode = Mode::from_ron(
+            r#"[
+            ("q", "Quit", exit, (global: true)),
+            ("m", "Menu", mode([
+                ("a", "A", shell("a")),
+            ])),
+            ("n", "Other", mode([
+                ("a", "A", shell("a")),
+            ])),
+        ]"#,
+        )
+        .unwrap();
+        assert!(mode.validate().is_empty());
+    }
+
+    #[test]
+    fn test_validate_duplicates() {
+        let mode = Mode::from_ron(
+            r#"[
+            ("ctrl+a", "One", shell("one")),
+            ("control+a", "Two", shell("two")),
+            ("ctrl+a", "Three", shell("three")),
+            ("m", "Menu", mode([
+                ("b", "B", shell("b")),
+                ("b", "B again", shell("b")),
+            ])),
+        ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            mode.validate(),
+            vec![
+                ValidationError::Duplicate {
+                    path: vec![],
+                    key: key("ctrl+a"),
+                },
+                ValidationError::Duplicate {
+                    path: vec!["Menu".to_string()],
+                    key: key("b"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_invalid_keys() {
+        let ron = r#"[
+            ("bogus", "Bad", shell("bad")),
+            ("m", "Menu", mode([
+                ("ctrl+nope", "Also bad", shell("bad")),
+                ("a", "Fine", shell("a")),
+            ])),
+            ("hyper+x", "Bad mode", mode([
+                ("nokey", "Nested", exit),
+            ])),
+        ]"#;
+        let mode: Mode = crate::ron_options().from_str(ron).unwrap();
+        let errors = mode.validate();
+        let keys: Vec<(&[String], &str)> = errors
+            .iter()
+            .map(|e| match e {
+                ValidationError::InvalidKey { path, key, .. } => (path.as_slice(), key.as_str()),
+                _ => panic!("unexpected error: {e}"),
+            })
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                (&[][..], "bogus"),
+                (&[][..], "hyper+x"),
+                (&["Menu".to_string()][..], "ctrl+nope"),
+                (&["Bad mode".to_string()][..], "nokey"),
+            ]
+        );
+
+        // Parsing rejects the mode, listing every invalid key
+        let err = Mode::from_ron(ron).unwrap_err();
+        assert!(err.contains("'bogus'"));
+        assert!(err.contains("Menu: invalid key 'ctrl+nope'"));
+        assert!(err.contains("Bad mode: invalid key 'nokey'"));
+    }
+
+    #[test]
+    fn test_validate_shadowed() {
+        let mode = Mode::from_ron(
+            r#"[
+            ("esc", "Exit", exit, (global: true)),
+            ("m", "Menu", mode([
+                ("p", "Back", pop, (global: true)),
+                ("s", "Sub", mode([
+                    ("esc", "Close", pop),
+                    ("p", "Print", shell("lpr")),
+                ])),
+            ])),
+        ]"#,
+        )
+        .unwrap();
+        let errors = mode.validate();
+        assert_eq!(
+            errors,
+            vec![
+                ValidationError::Shadowed {
+                    path: vec!["Menu".to_string(), "Sub".to_string()],
+                    key: key("esc"),
+                    global_path: vec![],
+                },
+                ValidationError::Shadowed {
+                    path: vec!["Menu".to_string(), "Sub".to_string()],
+                    key: key("p"),
+                    global_path: vec!["Menu".to_string()],
+                },
+            ]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "Menu > Sub: key 'escape' shadows the global binding in root"
+        );
+    }
+}